@@ -0,0 +1,47 @@
+//! Pretty prints a CommonMark document read from stdin, formatting all
+//! Rust code blocks with `rustfmt`.
+//!
+//! ```sh
+//! cargo run --example rustfmt < README.md
+//! ```
+
+extern crate prettify_cmark;
+
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+
+use prettify_cmark::{CodeBlockFormatter, PrettyPrinter};
+
+struct Rustfmt;
+
+impl CodeBlockFormatter for Rustfmt {
+    fn format(&self, lang: &str, code: &str) -> Option<String> {
+        if lang != "rust" {
+            return None;
+        }
+        let mut child = Command::new("rustfmt")
+            .args(["--emit", "stdout", "--quiet"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        child.stdin.take()?.write_all(code.as_bytes()).ok()?;
+        let output = child.wait_with_output().ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        String::from_utf8(output.stdout).ok()
+    }
+}
+
+fn main() {
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source).expect("failed to read stdin");
+
+    let mut printer = PrettyPrinter::default();
+    printer.set_code_block_formatter(Rustfmt);
+    printer.push_source(&source).expect("failed to pretty print");
+
+    println!("{}", printer.into_inner());
+}
@@ -0,0 +1,207 @@
+use pulldown_cmark::{Event, Parser};
+
+use buffer::into_static;
+use minimal::top_blocks;
+use options::{AdmonitionStyle, Options};
+use printer::{Replacement, ReplacementBlock};
+use references;
+
+/// Kinds of common admonitions, which bold labels are recognized for.
+const KINDS: &[&str] = &[
+    "note", "tip", "important", "warning", "caution", "info", "hint", "attention", "danger", "error",
+    "abstract", "example", "question", "quote", "success", "failure", "bug"
+];
+
+struct Admonition<'a> {
+    /// The kind of the admonition, in lowercase.
+    kind: String,
+    title: Option<&'a str>,
+    lines: Vec<&'a str>
+}
+
+/// Find the admonitions at the top level of a document that are written
+/// in the source syntax of `Options::admonitions`, and convert them into
+/// replacements in the target syntax.
+///
+/// Admonitions that are already written in the mkdocs syntax are printed
+/// again if it is the target, as their indented content would otherwise
+/// be read as part of a paragraph.
+pub fn convert(source: &str, options: &Options) -> Vec<Replacement> {
+    let conversion = match options.admonitions {
+        Some(conversion) if conversion.source != conversion.target || conversion.target == AdmonitionStyle::MkDocs => conversion,
+        _ => return Vec::new()
+    };
+    let blocks = top_blocks(source, options);
+    let mut replacements = Vec::new();
+    let mut index = 0;
+    while index < blocks.len() {
+        let block = &blocks[index];
+        let text = &source[block.range.clone()];
+        let parse = |style| match style {
+            AdmonitionStyle::GitHub if block.quote => parse_callout(text).map(|admonition| (admonition, block.range.end)),
+            AdmonitionStyle::BoldLabel if block.quote => parse_bold_label(text).map(|admonition| (admonition, block.range.end)),
+            AdmonitionStyle::MkDocs if text.starts_with("!!!") => parse_mkdocs(source, block.range.start),
+            _ => None
+        };
+        let found = parse(conversion.source).or_else(|| match conversion.target {
+            AdmonitionStyle::MkDocs => parse(AdmonitionStyle::MkDocs),
+            _ => None
+        });
+        let (admonition, end) = match found {
+            Some(found) => found,
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+        let covered = blocks[index..].iter().take_while(|block| block.range.start < end).collect::<Vec<_>>();
+        let last = covered[covered.len() - 1];
+        if last.range.end > end {
+            // The admonition ends in the middle of a block, which is not
+            // converted rather than losing the rest of the block.
+            index += 1;
+            continue;
+        }
+        let block = match conversion.target {
+            AdmonitionStyle::MkDocs => ReplacementBlock::Raw(render_mkdocs(&admonition, source, options)),
+            style => ReplacementBlock::Events(quote_events(&admonition, style, source, options))
+        };
+        replacements.push(Replacement { range: blocks[index].events.start..last.events.end, block });
+        index += covered.len();
+    }
+    replacements
+}
+
+/// Parse a GitHub callout from the source of a block quote.
+fn parse_callout(text: &str) -> Option<Admonition<'_>> {
+    let mut lines = text.lines().map(strip_quote_marker);
+    let marker = lines.next()?.trim();
+    let kind = marker.strip_prefix("[!")?.strip_suffix(']')?;
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(Admonition { kind: kind.to_ascii_lowercase(), title: None, lines: lines.collect() })
+}
+
+/// Parse an admonition with a bold label from the source of a block
+/// quote.
+fn parse_bold_label(text: &str) -> Option<Admonition<'_>> {
+    let mut lines = text.lines().map(strip_quote_marker);
+    let first = lines.next()?.trim();
+    let rest = first.strip_prefix("**")?;
+    let end = rest.find("**")?;
+    let kind = rest[..end].trim().trim_end_matches(':').to_ascii_lowercase();
+    if !KINDS.contains(&kind.as_str()) {
+        return None;
+    }
+    let after = rest[end + 2..].trim_start_matches(':').trim();
+    let lines = Some(after).filter(|after| !after.is_empty()).into_iter().chain(lines).collect();
+    Some(Admonition { kind, title: None, lines })
+}
+
+/// Parse a mkdocs admonition starting at the given offset, returning it
+/// along with the offset of the end of its last line of content.
+fn parse_mkdocs(source: &str, start: usize) -> Option<(Admonition<'_>, usize)> {
+    let line_end = source[start..].find('\n').map_or(source.len(), |newline| start + newline);
+    let marker = source[start..line_end].trim_end().strip_prefix("!!!")?.trim();
+    let kind = marker.split_whitespace().next()?;
+    if !kind.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return None;
+    }
+    // Anything after the kind other than a quoted title is not part of
+    // an admonition.
+    let rest = marker[kind.len()..].trim();
+    let title = match rest {
+        "" => None,
+        rest => Some(rest.strip_prefix('"')?.strip_suffix('"')?).filter(|title| !title.is_empty())
+    };
+    let mut lines = Vec::new();
+    let mut end = line_end;
+    let mut offset = (line_end + 1).min(source.len());
+    for line in source[offset..].split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.trim().is_empty() {
+            lines.push("");
+            continue;
+        }
+        match line.strip_prefix("    ").or_else(|| line.strip_prefix('\t')) {
+            Some(content) => {
+                lines.push(content);
+                end = line_start + line.trim_end().len();
+            },
+            None => break
+        }
+    }
+    while lines.last() == Some(&"") {
+        lines.pop();
+    }
+    Some((Admonition { kind: kind.to_ascii_lowercase(), title, lines }, end))
+}
+
+/// Remove the marker of a block quote from one of its lines, which is
+/// optional on lazy continuation lines.
+fn strip_quote_marker(line: &str) -> &str {
+    match line.trim_start().strip_prefix('>') {
+        Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+        None => line
+    }
+}
+
+/// The lines of an admonition in a block quote, as the marker or label
+/// followed by the title and the content.
+fn quote_lines(admonition: &Admonition, style: AdmonitionStyle) -> Vec<String> {
+    let marker = match style {
+        AdmonitionStyle::BoldLabel => format!("**{}{}**", admonition.kind[..1].to_uppercase(), &admonition.kind[1..]),
+        _ => format!("[!{}]", admonition.kind.to_ascii_uppercase())
+    };
+    let mut lines = vec![marker];
+    if let Some(title) = admonition.title {
+        lines.push(format!("**{}**", title));
+        lines.push(String::new());
+    }
+    lines.extend(admonition.lines.iter().map(|line| line.to_string()));
+    lines.into_iter().map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) }).collect()
+}
+
+/// The events of an admonition printed as a block quote.
+fn quote_events(admonition: &Admonition, style: AdmonitionStyle, source: &str, options: &Options) -> Vec<Event<'static>> {
+    let text = with_definitions(&quote_lines(admonition, style).join("\n"), source);
+    Parser::new_ext(&text, options.parser_options()).map(into_static).collect()
+}
+
+/// Print an admonition in the syntax of mkdocs, with its content pretty
+/// printed and indented.
+///
+/// Reference links in the content are inlined, as their definitions
+/// remain outside of the admonition.
+fn render_mkdocs(admonition: &Admonition, source: &str, options: &Options) -> String {
+    let mut options = options.clone();
+    options.wrap = options.wrap.map(|width| width.saturating_sub(4));
+    options.references = None;
+    let content = ::prettify_with_options(&with_definitions(&admonition.lines.join("\n"), source), &options);
+    let mut text = format!("!!! {}", admonition.kind);
+    if let Some(title) = admonition.title {
+        text.push_str(&format!(" \"{}\"", title));
+    }
+    for line in content.lines() {
+        text.push('\n');
+        if !line.is_empty() {
+            text.push_str("    ");
+            text.push_str(line);
+        }
+    }
+    text
+}
+
+/// Append the link reference definitions of a document to the content of
+/// one of its admonitions, so that reference links in it are resolved.
+fn with_definitions(content: &str, source: &str) -> String {
+    let mut text = format!("{}\n\n", content);
+    for line in source.lines().filter(|line| references::is_definition(line)) {
+        text.push_str(line);
+        text.push('\n');
+    }
+    text
+}
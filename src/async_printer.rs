@@ -0,0 +1,159 @@
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use pulldown_cmark::{Event, Tag};
+use tokio::io::AsyncWrite;
+
+use options::Options;
+use printer::PrettyPrinter;
+
+/// Event-driven pretty printer that writes to an `AsyncWrite`.
+///
+/// Events are formatted into an internal buffer, which is written out
+/// whenever a top-level block has been completed. This allows streaming
+/// formatted documents (for example as the body of an HTTP response)
+/// without blocking.
+///
+/// Only available with the `tokio` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate pulldown_cmark;
+/// # extern crate prettify_cmark;
+/// # extern crate tokio;
+/// use pulldown_cmark::Parser;
+/// use prettify_cmark::AsyncPrettyPrinter;
+///
+/// # fn main() {
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let mut printer = AsyncPrettyPrinter::new(Vec::new());
+/// for event in Parser::new("Lorem _ipsum_\n\nDolor") {
+///     runtime.block_on(printer.push_event(event)).unwrap();
+/// }
+/// runtime.block_on(printer.finish()).unwrap();
+///
+/// assert_eq!(printer.into_inner(), b"Lorem *ipsum*\n\nDolor");
+/// # }
+/// ```
+pub struct AsyncPrettyPrinter<W> {
+    printer: PrettyPrinter<String>,
+    writer: W,
+    depth: usize
+}
+
+impl<W: AsyncWrite + Unpin> AsyncPrettyPrinter<W> {
+    /// Create a new pretty printer that wraps around an async writer.
+    pub fn new(writer: W) -> AsyncPrettyPrinter<W> {
+        AsyncPrettyPrinter::new_with_options(writer, "", Options::default())
+    }
+
+    /// Create a new pretty printer with a prefix and custom options
+    /// that wraps around an async writer.
+    pub fn new_with_options(writer: W, prefix: &str, options: Options) -> AsyncPrettyPrinter<W> {
+        AsyncPrettyPrinter {
+            printer: PrettyPrinter::new_with_options(String::new(), prefix, options),
+            writer,
+            depth: 0
+        }
+    }
+
+    /// Push a single event into the printer.
+    ///
+    /// The returned future writes out the formatted output if the event
+    /// completed a top-level block, and otherwise resolves immediately.
+    pub fn push_event<'a, E: Into<Event<'a>>>(&mut self, event: E) -> WriteBlocks<'_, W> {
+        let event = event.into();
+        let boundary = match event {
+            Event::Start(ref tag) if is_block(tag) => {
+                self.depth += 1;
+                false
+            },
+            Event::End(ref tag) if is_block(tag) => {
+                self.depth = self.depth.saturating_sub(1);
+                self.depth == 0
+            },
+            Event::Html(_) => self.depth == 0,
+            _ => false
+        };
+        let result = self.printer.push_event(event);
+        let buf = if boundary { self.take_buffer() } else { Vec::new() };
+        WriteBlocks::new(&mut self.writer, buf, result, false)
+    }
+
+    /// Print the content that is held back until the end of the document
+    /// (see `PrettyPrinter::finish`), write out all remaining output and
+    /// flush the underlying writer.
+    pub fn finish(&mut self) -> WriteBlocks<'_, W> {
+        let result = self.printer.finish();
+        let buf = self.take_buffer();
+        WriteBlocks::new(&mut self.writer, buf, result, true)
+    }
+
+    /// Borrow the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Unwrap the printer, returning the underlying writer.
+    ///
+    /// Output that has not been written out via `finish` is discarded.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn take_buffer(&mut self) -> Vec<u8> {
+        mem::take(self.printer.get_mut()).into_bytes()
+    }
+}
+
+fn is_block(tag: &Tag) -> bool {
+    !matches!(*tag, Tag::Emphasis | Tag::Strong | Tag::Code | Tag::Link(..) | Tag::Image(..))
+}
+
+/// Future that writes formatted output to an `AsyncWrite`.
+#[must_use = "futures do nothing unless polled"]
+pub struct WriteBlocks<'w, W: 'w> {
+    writer: &'w mut W,
+    buf: Vec<u8>,
+    pos: usize,
+    error: Option<io::Error>,
+    flush: bool
+}
+
+impl<'w, W> WriteBlocks<'w, W> {
+    fn new(writer: &'w mut W, buf: Vec<u8>, result: fmt::Result, flush: bool) -> WriteBlocks<'w, W> {
+        let error = result.err().map(|_| io::Error::other("failed to format event"));
+        WriteBlocks { writer, buf, pos: 0, error, flush }
+    }
+}
+
+impl<'w, W: AsyncWrite + Unpin> Future for WriteBlocks<'w, W> {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if let Some(err) = this.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        while this.pos < this.buf.len() {
+            match Pin::new(&mut *this.writer).poll_write(cx, &this.buf[this.pos..]) {
+                Poll::Ready(Ok(0)) => {
+                    return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero)));
+                },
+                Poll::Ready(Ok(written)) => this.pos += written,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending
+            }
+        }
+        if this.flush {
+            Pin::new(&mut *this.writer).poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::{Result, Write};
+
+/// A single instruction emitted by the printer to its backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction<'a> {
+    /// Content of the document, such as text or markup.
+    Text(&'a str),
+    /// Indentation markup at the start of a line, such as the prefix of
+    /// the printer or the `>` of a block quote.
+    Indent(&'a str),
+    /// A line break.
+    Break,
+    /// A number of spaces, used for indentation and between words.
+    Space(usize)
+}
+
+/// Backend that receives the output of the printer as a stream of
+/// instructions.
+///
+/// This allows rendering the output in other ways than as plain text,
+/// for example with ANSI colors, as HTML, or only measuring it. The
+/// trait is implemented for all types that implement `fmt::Write`,
+/// which receive the output as plain text.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::fmt::Result;
+/// use prettify_cmark::{Backend, Instruction, PrettyPrinter};
+///
+/// #[derive(Default)]
+/// struct LineCounter(usize);
+///
+/// impl Backend for LineCounter {
+///     fn emit(&mut self, instruction: Instruction) -> Result {
+///         if instruction == Instruction::Break {
+///             self.0 += 1;
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut printer = PrettyPrinter::new(LineCounter::default());
+/// printer.push_source("Lorem\n\n> Ipsum").unwrap();
+/// assert_eq!(printer.into_inner().0, 2);
+/// ```
+pub trait Backend {
+    /// Emit a single instruction.
+    fn emit(&mut self, instruction: Instruction) -> Result;
+}
+
+impl<W: Write> Backend for W {
+    fn emit(&mut self, instruction: Instruction) -> Result {
+        match instruction {
+            Instruction::Text(text) | Instruction::Indent(text) => self.write_str(text),
+            Instruction::Break => self.write_char('\n'),
+            Instruction::Space(count) => {
+                for _ in 0..count {
+                    self.write_char(' ')?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+use printer::{PrettyPrinter, Replacement, ReplacementBlock};
+
+/// A badge (or shield) of a README, such as the status of a build: an
+/// image that usually links to further information.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Badge {
+    /// The alt text of the image.
+    pub alt: String,
+    /// The destination of the image.
+    pub image: String,
+    /// The destination of the link around the image, if any.
+    pub link: Option<String>
+}
+
+impl Badge {
+    /// Create a badge, with a link around its image if given.
+    pub fn new(alt: &str, image: &str, link: Option<&str>) -> Badge {
+        Badge { alt: alt.to_string(), image: image.to_string(), link: link.map(str::to_string) }
+    }
+}
+
+/// The badges of a document, with the range of the events of their
+/// paragraph, or the index of the event before which one is inserted.
+struct BadgeBlock {
+    range: Range<usize>,
+    badges: Vec<Badge>
+}
+
+/// Returns the badges of the leading badge paragraph of a README.
+///
+/// The badge paragraph is the first block of the document after its
+/// leading headings, if it consists of nothing but images (which may be
+/// wrapped in links) separated by whitespace. Reference links and images
+/// are resolved to their destinations.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{find_badges, Badge, Options};
+/// let readme = "# Lorem\n\n[![CI](ci.svg)][ci]\n![License](license.svg)\n\n[ci]: https://ci.example";
+/// assert_eq!(find_badges(readme, &Options::default()), vec![
+///     Badge::new("CI", "ci.svg", Some("https://ci.example")),
+///     Badge::new("License", "license.svg", None)
+/// ]);
+/// ```
+pub fn find_badges(readme: &str, options: &Options) -> Vec<Badge> {
+    locate(readme, options).badges
+}
+
+/// Edits the badges of the leading badge paragraph of a README (as
+/// described for `find_badges`), and returns the reformatted README.
+///
+/// The edited badges are printed on a single line, without wrapping, and
+/// with the titles of their links and images removed. If the README has
+/// no badge paragraph, one is inserted after its leading headings, and if
+/// no badges remain, the paragraph is removed.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{edit_badges, Badge, Options};
+/// let readme = "# Lorem\n\n![Zeta](z.svg)\n[![Alpha](a.svg)](a)\n\nIpsum _dolor_";
+/// let output = edit_badges(readme, &Options { wrap: Some(20), ..Options::default() }, |badges| {
+///     badges.push(Badge::new("Docs", "docs.svg", Some("https://docs.example")));
+///     badges.sort_by(|a, b| a.alt.cmp(&b.alt));
+/// });
+/// assert_eq!(output, "# Lorem\n\n\
+///                     [![Alpha](a.svg)](a) [![Docs](docs.svg)](https://docs.example) ![Zeta](z.svg)\n\n\
+///                     Ipsum *dolor*");
+/// ```
+pub fn edit_badges<F: FnOnce(&mut Vec<Badge>)>(readme: &str, options: &Options, edit: F) -> String {
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.push_source_replacing(readme, |source| {
+        let mut block = locate(source, options);
+        edit(&mut block.badges);
+        vec![Replacement { range: block.range, block: ReplacementBlock::SingleLine(events(&block.badges)) }]
+    }).expect("failed to write to string");
+    printer.into_inner()
+}
+
+/// Find the events of the badge paragraph of a document, or the index of
+/// the first event after its leading headings.
+fn locate(source: &str, options: &Options) -> BadgeBlock {
+    let events = Parser::new_ext(source, options.parser_options()).collect::<Vec<_>>();
+    let mut depth = 0;
+    for (index, event) in events.iter().enumerate() {
+        match *event {
+            Event::Start(Tag::Paragraph) if depth == 0 => {
+                return match parse(&events[index + 1..]) {
+                    Some((badges, length)) => BadgeBlock { range: index..index + 1 + length, badges },
+                    None => BadgeBlock { range: index..index, badges: Vec::new() }
+                };
+            },
+            Event::Start(Tag::Header(_)) => depth += 1,
+            Event::Start(_) if depth > 0 => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ if depth == 0 => return BadgeBlock { range: index..index, badges: Vec::new() },
+            _ => {}
+        }
+    }
+    BadgeBlock { range: events.len()..events.len(), badges: Vec::new() }
+}
+
+/// Parse the badges of a paragraph, along with the number of its events
+/// up to its end, or return `None` if the paragraph contains anything
+/// else.
+fn parse(events: &[Event]) -> Option<(Vec<Badge>, usize)> {
+    let mut badges = Vec::new();
+    // The destination of the open link, the badge of the image that is
+    // open or inside of the open link, and the depth of nested images.
+    let mut link: Option<String> = None;
+    let mut badge: Option<Badge> = None;
+    let mut images = 0;
+    for (index, event) in events.iter().enumerate() {
+        match *event {
+            Event::Start(Tag::Image(..)) if images > 0 => images += 1,
+            Event::End(Tag::Image(..)) if images > 1 => images -= 1,
+            Event::Start(Tag::Image(ref image, _)) if badge.is_none() => {
+                badge = Some(Badge { alt: String::new(), image: image.to_string(), link: link.clone() });
+                images = 1;
+            },
+            Event::End(Tag::Image(..)) if images == 1 => {
+                images = 0;
+                if link.is_none() {
+                    badges.push(badge.take()?);
+                }
+            },
+            Event::Text(ref text) | Event::Html(ref text) | Event::InlineHtml(ref text) if images > 0 => {
+                badge.as_mut()?.alt.push_str(text);
+            },
+            Event::SoftBreak | Event::HardBreak if images > 0 => badge.as_mut()?.alt.push(' '),
+            Event::Start(_) | Event::End(_) if images > 0 => {},
+            Event::Start(Tag::Link(ref destination, _)) if link.is_none() => {
+                link = Some(destination.to_string());
+            },
+            Event::End(Tag::Link(..)) if link.is_some() => {
+                badges.push(badge.take()?);
+                link = None;
+            },
+            Event::End(Tag::Paragraph) if link.is_none() => {
+                return if badges.is_empty() { None } else { Some((badges, index + 1)) };
+            },
+            Event::SoftBreak if link.is_none() => {},
+            Event::Text(ref text) if link.is_none() && text.trim().is_empty() => {},
+            _ => return None
+        }
+    }
+    None
+}
+
+/// The events of a paragraph of badges, or no events if there are none.
+fn events(badges: &[Badge]) -> Vec<Event<'static>> {
+    if badges.is_empty() {
+        return Vec::new();
+    }
+    let mut events = vec![Event::Start(Tag::Paragraph)];
+    for (index, badge) in badges.iter().enumerate() {
+        if index > 0 {
+            events.push(Event::SoftBreak);
+        }
+        let image = Tag::Image(badge.image.clone().into(), "".into());
+        let link = badge.link.as_ref().map(|link| Tag::Link(link.clone().into(), "".into()));
+        events.extend(link.clone().map(Event::Start));
+        events.push(Event::Start(image.clone()));
+        events.push(Event::Text(badge.alt.clone().into()));
+        events.push(Event::End(image));
+        events.extend(link.map(Event::End));
+    }
+    events.push(Event::End(Tag::Paragraph));
+    events
+}
@@ -0,0 +1,136 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use diagnostics::Message;
+
+/// Handle for cancelling printing from another thread.
+///
+/// Clones of a token share their state, so that one clone can be
+/// attached to a printer using `PrettyPrinter::set_cancellation_token`,
+/// while another is used to cancel it. The printer checks the token at
+/// the start of each block, and fails with a `cancelled` error once it
+/// has been cancelled.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{CancellationToken, PrettyPrinter};
+/// let token = CancellationToken::new();
+/// let mut printer = PrettyPrinter::default();
+/// printer.set_cancellation_token(token.clone());
+/// printer.push_source("Lorem").unwrap();
+///
+/// token.cancel();
+/// assert!(printer.push_source("\n\nipsum").is_err());
+/// assert_eq!(printer.diagnostics()[0].rule, "cancelled");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled yet.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancel printing with this token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits on the work the printer may do, so that printing pathological
+/// documents can be aborted.
+///
+/// The time and event limits are checked at the start of each block, and
+/// the buffer limit whenever an event is buffered. When a limit is
+/// exceeded, the printer fails with a `time-budget`, `event-budget` or
+/// `buffer-limit` error.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{Budget, PrettyPrinter};
+/// let mut printer = PrettyPrinter::default();
+/// printer.set_budget(Budget { buffer: Some(1024), ..Budget::default() });
+/// let link = format!("[{}](http://example.com)", "lorem *ipsum* ".repeat(100));
+/// assert!(printer.push_source(&link).is_err());
+/// assert_eq!(printer.diagnostics()[0].message, "more than 1024 bytes of the document were buffered");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Budget {
+    /// Maximum time spent printing, measured from the first event.
+    pub time: Option<Duration>,
+    /// Maximum number of events pushed into the printer.
+    pub events: Option<usize>,
+    /// Maximum size in bytes of the events that are held back at once,
+    /// such as the contents of tables, links or footnote definitions that
+    /// are moved. This protects against untrusted input that makes the
+    /// printer buffer large parts of a document.
+    pub buffer: Option<usize>
+}
+
+/// The work done by a printer, checked against its budget.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Usage {
+    started: Option<Instant>,
+    events: usize,
+    /// The approximate size of the events that are buffered.
+    buffered: usize,
+    /// Whether printing was aborted already.
+    aborted: bool
+}
+
+impl Usage {
+    /// Count an event that is pushed into the printer.
+    pub fn count(&mut self) {
+        self.started.get_or_insert_with(Instant::now);
+        self.events += 1;
+    }
+
+    /// Account for an event of the given size that is buffered, or for
+    /// all buffers being empty if `size` is `None`, and check whether
+    /// printing has to be aborted as a result.
+    pub fn buffer<'a>(&mut self, budget: &Budget, size: Option<usize>) -> Result<(), Option<Message<'a>>> {
+        if self.aborted {
+            return Err(None);
+        }
+        self.buffered = size.map_or(0, |size| self.buffered + size);
+        match budget.buffer {
+            Some(limit) if self.buffered > limit => {
+                self.aborted = true;
+                Err(Some(Message::BufferLimit { limit }))
+            },
+            _ => Ok(())
+        }
+    }
+
+    /// Check whether printing has to be aborted at the start of a block,
+    /// returning the message to report the first time it is.
+    ///
+    /// Returns `Err(None)` if printing was aborted before.
+    pub fn check<'a>(&mut self, budget: &Budget, token: Option<&CancellationToken>) -> Result<(), Option<Message<'a>>> {
+        if self.aborted {
+            return Err(None);
+        }
+        let message = if token.is_some_and(CancellationToken::is_cancelled) {
+            Message::Cancelled
+        } else if let Some(limit) = budget.events.filter(|&limit| self.events > limit) {
+            Message::EventBudget { limit }
+        } else if let Some(limit) = budget.time.filter(|&limit| self.started.is_some_and(|started| started.elapsed() > limit)) {
+            Message::TimeBudget { limit }
+        } else {
+            return Ok(());
+        };
+        self.aborted = true;
+        Err(Some(message))
+    }
+}
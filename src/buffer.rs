@@ -0,0 +1,229 @@
+use std::borrow::Cow;
+use std::fmt::Result;
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use backend::Backend;
+use printer::PrettyPrinter;
+
+/// Buffer that stores owned copies of events.
+///
+/// Events obtained from `pulldown_cmark::Parser` borrow from the source
+/// document. Recording them into a buffer detaches them from the source,
+/// so that they can be inspected, transformed and replayed into a
+/// `PrettyPrinter` later on.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate pulldown_cmark;
+/// # extern crate prettify_cmark;
+/// use pulldown_cmark::{Event, Tag};
+/// use prettify_cmark::{EventBuffer, PrettyPrinter};
+///
+/// # fn main() {
+/// let mut buffer = EventBuffer::from_source("Lorem _ipsum_ dolor");
+/// for event in buffer.events_mut() {
+///     if let Event::Start(Tag::Emphasis) = *event {
+///         *event = Event::Start(Tag::Strong);
+///     } else if let Event::End(Tag::Emphasis) = *event {
+///         *event = Event::End(Tag::Strong);
+///     }
+/// }
+///
+/// let mut printer = PrettyPrinter::default();
+/// buffer.replay(&mut printer).unwrap();
+/// assert_eq!(printer.into_inner(), "Lorem **ipsum** dolor");
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EventBuffer {
+    events: Vec<Event<'static>>
+}
+
+impl EventBuffer {
+    /// Create a new, empty buffer.
+    pub fn new() -> EventBuffer {
+        EventBuffer::default()
+    }
+
+    /// Parse a CommonMark document and record its events.
+    pub fn from_source(source: &str) -> EventBuffer {
+        Parser::new(source).collect()
+    }
+
+    /// Record a single event.
+    pub fn push(&mut self, event: Event) {
+        self.events.push(into_static(event));
+    }
+
+    /// The recorded events.
+    pub fn events(&self) -> &[Event<'static>] {
+        &self.events
+    }
+
+    /// Mutable access to the recorded events, for transforming them.
+    pub fn events_mut(&mut self) -> &mut Vec<Event<'static>> {
+        &mut self.events
+    }
+
+    /// The number of recorded events.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Whether no events have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Iterate over the recorded events.
+    pub fn iter(&self) -> slice::Iter<'_, Event<'static>> {
+        self.events.iter()
+    }
+
+    /// Push copies of the recorded events into a printer.
+    ///
+    /// The buffer is left intact, so that it can be replayed again.
+    pub fn replay<W: Backend>(&self, printer: &mut PrettyPrinter<W>) -> Result {
+        printer.push_events(self.events.iter().cloned())
+    }
+}
+
+impl<'a> Extend<Event<'a>> for EventBuffer {
+    fn extend<I: IntoIterator<Item=Event<'a>>>(&mut self, events: I) {
+        self.events.extend(events.into_iter().map(into_static));
+    }
+}
+
+impl<'a> FromIterator<Event<'a>> for EventBuffer {
+    fn from_iter<I: IntoIterator<Item=Event<'a>>>(events: I) -> EventBuffer {
+        let mut buffer = EventBuffer::new();
+        buffer.extend(events);
+        buffer
+    }
+}
+
+impl IntoIterator for EventBuffer {
+    type Item = Event<'static>;
+    type IntoIter = vec::IntoIter<Event<'static>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
+
+impl<'b> IntoIterator for &'b EventBuffer {
+    type Item = &'b Event<'static>;
+    type IntoIter = slice::Iter<'b, Event<'static>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
+/// An event that owns all of its data.
+///
+/// In contrast to the events produced by `pulldown_cmark::Parser`, owned
+/// events do not borrow from the source document. This makes it possible
+/// to parse a document in one place (such as a task or thread) and print
+/// it in another.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate pulldown_cmark;
+/// # extern crate prettify_cmark;
+/// use std::thread;
+/// use pulldown_cmark::Parser;
+/// use prettify_cmark::{OwnedEvent, PrettyPrinter};
+///
+/// # fn main() {
+/// let source = String::from("Lorem _ipsum_");
+/// let events = Parser::new(&source).map(OwnedEvent::from).collect::<Vec<_>>();
+///
+/// let output = thread::spawn(move || {
+///     let mut printer = PrettyPrinter::default();
+///     printer.push_events(events).unwrap();
+///     printer.into_inner()
+/// }).join().unwrap();
+///
+/// assert_eq!(output, "Lorem *ipsum*");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnedEvent(Event<'static>);
+
+impl OwnedEvent {
+    /// Borrow the underlying event.
+    pub fn as_event(&self) -> &Event<'static> {
+        &self.0
+    }
+
+    /// Unwrap the underlying event.
+    pub fn into_event(self) -> Event<'static> {
+        self.0
+    }
+}
+
+impl<'a> From<Event<'a>> for OwnedEvent {
+    fn from(event: Event<'a>) -> OwnedEvent {
+        OwnedEvent(into_static(event))
+    }
+}
+
+impl From<OwnedEvent> for Event<'static> {
+    fn from(event: OwnedEvent) -> Event<'static> {
+        event.0
+    }
+}
+
+impl Extend<OwnedEvent> for EventBuffer {
+    fn extend<I: IntoIterator<Item=OwnedEvent>>(&mut self, events: I) {
+        self.events.extend(events.into_iter().map(OwnedEvent::into_event));
+    }
+}
+
+/// Convert an event into one that owns all of its data.
+pub fn into_static(event: Event) -> Event<'static> {
+    match event {
+        Event::Start(tag) => Event::Start(owned_tag(tag)),
+        Event::End(tag) => Event::End(owned_tag(tag)),
+        Event::Text(text) => Event::Text(owned(text)),
+        Event::Html(html) => Event::Html(owned(html)),
+        Event::InlineHtml(html) => Event::InlineHtml(owned(html)),
+        Event::FootnoteReference(label) => Event::FootnoteReference(owned(label)),
+        Event::SoftBreak => Event::SoftBreak,
+        Event::HardBreak => Event::HardBreak
+    }
+}
+
+/// Convert a tag into one that owns all of its data.
+pub(crate) fn owned_tag(tag: Tag) -> Tag<'static> {
+    match tag {
+        Tag::Paragraph => Tag::Paragraph,
+        Tag::Rule => Tag::Rule,
+        Tag::Header(level) => Tag::Header(level),
+        Tag::BlockQuote => Tag::BlockQuote,
+        Tag::CodeBlock(info) => Tag::CodeBlock(owned(info)),
+        Tag::List(start) => Tag::List(start),
+        Tag::Item => Tag::Item,
+        Tag::FootnoteDefinition(label) => Tag::FootnoteDefinition(owned(label)),
+        Tag::Table(alignments) => Tag::Table(alignments),
+        Tag::TableHead => Tag::TableHead,
+        Tag::TableRow => Tag::TableRow,
+        Tag::TableCell => Tag::TableCell,
+        Tag::Emphasis => Tag::Emphasis,
+        Tag::Strong => Tag::Strong,
+        Tag::Code => Tag::Code,
+        Tag::Link(url, title) => Tag::Link(owned(url), owned(title)),
+        Tag::Image(url, title) => Tag::Image(owned(url), owned(title))
+    }
+}
+
+fn owned(cow: Cow<str>) -> Cow<'static, str> {
+    Cow::Owned(cow.into_owned())
+}
@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+use pulldown_cmark::{Event, Tag};
+
+use inline;
+use options::HeadingCase;
+
+const SMALL_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "in", "nor",
+    "of", "on", "or", "the", "to", "via", "vs"
+];
+
+impl HeadingCase {
+    /// Apply the case policy to the text events of a buffered heading.
+    ///
+    /// If `keep_attributes` is set, a trailing attribute block such as
+    /// `{#id .class}` is left unchanged.
+    pub(crate) fn apply<S: Default>(&self, events: &mut Vec<(Event<'static>, S)>, keep_attributes: bool) {
+        let transform: fn(&str) -> String = match *self {
+            HeadingCase::Preserve => return,
+            HeadingCase::Sentence => sentence_case,
+            HeadingCase::Title => title_case,
+            HeadingCase::Custom(transform) => transform
+        };
+
+        let mut in_code = false;
+        let mut runs = Vec::new();
+        for (index, (event, _)) in events.iter().enumerate() {
+            match *event {
+                Event::Start(Tag::Code) => in_code = true,
+                Event::End(Tag::Code) => in_code = false,
+                Event::Text(_) if !in_code => runs.push(index),
+                _ => {}
+            }
+        }
+
+        if keep_attributes && runs.last() == Some(&(events.len() - 1)) {
+            let last = events.len() - 1;
+            let split = inline::trailing_attributes(text_of(&events[last].0)).map(|block| {
+                let text = text_of(&events[last].0);
+                (text[..block.start].to_string(), text[block.start..].to_string())
+            });
+            if let Some((text, attributes)) = split {
+                events[last].0 = Event::Text(Cow::Owned(text));
+                events.push((Event::Text(Cow::Owned(attributes)), S::default()));
+            }
+        }
+
+        let text: String = runs.iter().map(|&index| text_of(&events[index].0)).collect();
+        let transformed = transform(&text);
+        if transformed.chars().count() == text.chars().count() {
+            let mut chars = transformed.chars();
+            for &index in &runs {
+                let len = text_of(&events[index].0).chars().count();
+                let run = chars.by_ref().take(len).collect();
+                events[index].0 = Event::Text(Cow::Owned(run));
+            }
+        } else {
+            for &index in &runs {
+                let run = transform(text_of(&events[index].0));
+                events[index].0 = Event::Text(Cow::Owned(run));
+            }
+        }
+    }
+}
+
+fn text_of<'a>(event: &'a Event) -> &'a str {
+    match *event {
+        Event::Text(ref text) => text,
+        _ => ""
+    }
+}
+
+fn sentence_case(text: &str) -> String {
+    map_words(text, |index, word| {
+        if index == 0 {
+            capitalize(word)
+        } else if is_acronym(word) {
+            word.to_string()
+        } else {
+            word.chars().map(lower).collect()
+        }
+    })
+}
+
+fn title_case(text: &str) -> String {
+    let count = text.split_whitespace().count();
+    map_words(text, |index, word| {
+        let small = SMALL_WORDS.iter().any(|small| small.eq_ignore_ascii_case(word));
+        if small && index > 0 && index + 1 < count {
+            word.chars().map(lower).collect()
+        } else {
+            capitalize(word)
+        }
+    })
+}
+
+fn map_words<F>(text: &str, mut f: F) -> String
+    where F: FnMut(usize, &str) -> String
+{
+    let mut output = String::with_capacity(text.len());
+    let mut index = 0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let start = rest.find(|c: char| !c.is_whitespace()).unwrap_or(rest.len());
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if end > 0 {
+            output.push_str(&f(index, &rest[..end]));
+            index += 1;
+        }
+        rest = &rest[end..];
+    }
+    output
+}
+
+fn capitalize(word: &str) -> String {
+    let mut capitalized = false;
+    word.chars().map(|c| {
+        if !capitalized && c.is_alphabetic() {
+            capitalized = true;
+            upper(c)
+        } else {
+            c
+        }
+    }).collect()
+}
+
+fn is_acronym(word: &str) -> bool {
+    word.chars().filter(|c| c.is_alphabetic()).count() > 1 &&
+        !word.chars().any(char::is_lowercase)
+}
+
+// Case mappings that would change the number of characters are skipped,
+// so that transformed text can be mapped back onto the original markup.
+fn upper(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => u,
+        _ => c
+    }
+}
+
+fn lower(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c
+    }
+}
@@ -0,0 +1,159 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+
+/// A top-level heading of a changelog, with the range of its lines.
+struct Heading {
+    level: i32,
+    title: String,
+    start: usize,
+    end: usize
+}
+
+/// Adds entries to a category (such as `Added` or `Fixed`) of the
+/// unreleased changes of a changelog, and returns the reformatted
+/// changelog.
+///
+/// The changelog is expected to follow the structure of [Keep a
+/// Changelog](https://keepachangelog.com/): a section for each version
+/// (a second-level heading), with a third-level heading for each category
+/// of changes, starting with a section for unreleased changes. The
+/// sections for unreleased changes and for the category are created as
+/// needed, and entries are appended to the end of their category.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{add_changelog_entries, Options};
+/// let changelog = "# Changelog\n\n## [1.0.0] - 2024-01-31\n\n### Added\n\n* Lorem\n";
+/// let output = add_changelog_entries(changelog, "Added", &["Ipsum", "Dolor"], &Options::default());
+/// let expected = "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Ipsum\n\n- Dolor\
+///                 \n\n## [1.0.0] - 2024-01-31\n\n### Added\n\n- Lorem";
+/// assert_eq!(output, expected);
+/// ```
+pub fn add_changelog_entries(changelog: &str, category: &str, entries: &[&str], options: &Options) -> String {
+    let source = with_unreleased(changelog, options);
+    let headings = headings(&source, options);
+    let unreleased = headings.iter().position(is_unreleased).expect("unreleased section was added");
+    let end = section_end(&headings, unreleased, 2, source.len());
+    let subsection = headings.iter().enumerate()
+        .skip(unreleased + 1)
+        .take_while(|(_, heading)| heading.start < end)
+        .find(|(_, heading)| heading.level == 3 && heading.title.trim().eq_ignore_ascii_case(category))
+        .map(|(index, _)| index);
+
+    let list = entries.iter().map(|entry| format!("- {}\n", entry.trim().replace('\n', "\n  "))).collect::<String>();
+    let (position, insertion) = match subsection {
+        Some(index) => (section_end(&headings, index, 3, end), list),
+        None => (end, format!("### {}\n\n{}", category, list))
+    };
+    let before = source[..position].trim_end();
+    let edited = format!("{}\n\n{}\n{}", before, insertion, &source[position..]);
+    ::prettify_with_options(&edited, options)
+}
+
+/// Turns the unreleased changes of a changelog into a release, and
+/// returns the reformatted changelog.
+///
+/// A section for the version (and the date of the release, if given) is
+/// inserted after the heading of the unreleased changes, which is kept
+/// for future changes. Versions are written in brackets if the heading of
+/// the unreleased changes is, as is usual to link them to their changes.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{release_changelog, Options};
+/// let changelog = "# Changelog\n\n## Unreleased\n\n### Fixed\n\n- Lorem\n\n## 1.0.0\n\n- Ipsum\n";
+/// let output = release_changelog(changelog, "1.0.1", Some("2024-02-29"), &Options::default());
+/// let expected = "# Changelog\n\n## Unreleased\n\n## 1.0.1 - 2024-02-29\n\n### Fixed\n\n- Lorem\
+///                 \n\n## 1.0.0\n\n- Ipsum";
+/// assert_eq!(output, expected);
+/// ```
+pub fn release_changelog(changelog: &str, version: &str, date: Option<&str>, options: &Options) -> String {
+    let source = with_unreleased(changelog, options);
+    let headings = headings(&source, options);
+    let unreleased = headings.iter().find(|heading| is_unreleased(heading)).expect("unreleased section was added");
+    let bracketed = source[unreleased.start..unreleased.end].contains('[');
+    let mut title = if bracketed { format!("[{}]", version) } else { version.to_string() };
+    if let Some(date) = date {
+        title.push_str(" - ");
+        title.push_str(date);
+    }
+    let position = unreleased.end;
+    let edited = format!("{}\n## {}\n\n{}", source[..position].trim_end(), title, &source[position..]);
+    ::prettify_with_options(&edited, options)
+}
+
+/// Add a section for unreleased changes before the first version of a
+/// changelog, unless it already has one.
+fn with_unreleased(changelog: &str, options: &Options) -> String {
+    let headings = headings(changelog, options);
+    if headings.iter().any(is_unreleased) {
+        return changelog.to_string();
+    }
+    match headings.iter().find(|heading| heading.level == 2) {
+        Some(version) => {
+            let bracketed = changelog[version.start..version.end].contains('[');
+            let title = if bracketed { "[Unreleased]" } else { "Unreleased" };
+            format!("{}## {}\n\n{}", &changelog[..version.start], title, &changelog[version.start..])
+        },
+        None => format!("{}\n\n## [Unreleased]\n", changelog.trim_end())
+    }
+}
+
+fn is_unreleased(heading: &Heading) -> bool {
+    heading.level == 2 && heading.title.trim().trim_matches(['[', ']']).eq_ignore_ascii_case("unreleased")
+}
+
+/// The end of the section of a heading, where the next heading of at most
+/// the given level starts.
+fn section_end(headings: &[Heading], index: usize, level: i32, limit: usize) -> usize {
+    headings[index + 1..].iter()
+        .find(|heading| heading.level <= level)
+        .map_or(limit, |heading| heading.start.min(limit))
+}
+
+/// Collect the top-level headings of a document, along with the ranges
+/// of their lines.
+fn headings(source: &str, options: &Options) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut current: Option<Heading> = None;
+    let mut depth = 0;
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    loop {
+        let offset = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        match event {
+            Event::Start(Tag::Header(level)) if depth == 0 => {
+                let start = offset + source[offset..].len() - source[offset..].trim_start().len();
+                current = Some(Heading { level, title: String::new(), start, end: start });
+            },
+            Event::Text(ref text) => {
+                if let Some(ref mut heading) = current {
+                    heading.title.push_str(text);
+                }
+            },
+            Event::End(Tag::Header(_)) if depth == 1 => {
+                if let Some(mut heading) = current.take() {
+                    let end = parser.get_offset();
+                    heading.end = match source[..end].ends_with('\n') {
+                        true => end,
+                        false => source[end..].find('\n').map_or(source.len(), |newline| end + newline + 1)
+                    };
+                    headings.push(heading);
+                }
+            },
+            _ => {}
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    headings
+}
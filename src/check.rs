@@ -0,0 +1,90 @@
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use diagnostics::{Diagnostic, Message};
+use options::Options;
+use printer::PrettyPrinter;
+
+/// Checks a CommonMark document against the options, without formatting
+/// it, and returns the diagnostics found.
+///
+/// This reports everything the pretty printer would report when printing
+/// the document, such as headings deeper than `Options::heading_limit` or
+/// code blocks without a language if `InfoStringOptions::require_language`
+/// is set, as well as a `line-width` warning for each line of the source
+/// that is wider than `Options::wrap`. Lines that are never wrapped, such
+/// as those of headings, code blocks, HTML blocks and tables, and lines
+/// without a space to break them at, are not reported. The diagnostics
+/// are ordered by their offset in the source.
+///
+/// This allows linting documents before enabling automatic formatting.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{check, HeadingLimit, InfoStringOptions, Options};
+/// let options = Options {
+///     wrap: Some(20),
+///     heading_limit: Some(HeadingLimit { max_level: 2, demote: false }),
+///     info_string: InfoStringOptions { require_language: true, ..InfoStringOptions::default() },
+///     ..Options::default()
+/// };
+/// let source = "### Lorem\n\nLorem ipsum dolor sit amet\n\n```\ncode\n```";
+/// let rules = check(source, &options).into_iter().map(|diagnostic| diagnostic.rule).collect::<Vec<_>>();
+/// assert_eq!(rules, vec!["heading-depth", "line-width", "code-language"]);
+/// ```
+pub fn check(source: &str, options: &Options) -> Vec<Diagnostic> {
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    // Errors are reported as diagnostics as well.
+    let _ = printer.push_source(source);
+    let mut diagnostics = printer.take_diagnostics();
+    if let Some(max_width) = options.wrap {
+        diagnostics.extend(check_line_width(source, max_width, options));
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.offset.unwrap_or(usize::MAX));
+    diagnostics
+}
+
+/// Report the lines of prose that are wider than the maximum width.
+fn check_line_width(source: &str, max_width: usize, options: &Options) -> Vec<Diagnostic> {
+    let unwrapped = unwrapped_ranges(source, options);
+    let mut diagnostics = Vec::new();
+    let mut offset = 0;
+    for line in source.split_inclusive('\n') {
+        let content = line.trim_end();
+        let width = content.chars().count();
+        let breakable = content.trim_start().contains(' ');
+        if width > max_width && breakable && !unwrapped.iter().any(|range| range.contains(&offset)) {
+            diagnostics.push(Diagnostic::warning(Message::LineWidth { width, max_width }, None, Some(offset)));
+        }
+        offset += line.len();
+    }
+    diagnostics
+}
+
+/// Find the source ranges of blocks whose lines are never wrapped.
+fn unwrapped_ranges(source: &str, options: &Options) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    loop {
+        let offset = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => return ranges
+        };
+        match event {
+            Event::Start(Tag::Header(_)) | Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Table(_)) if start.is_none() => {
+                start = Some(offset);
+            },
+            Event::End(Tag::Header(_)) | Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Table(_)) => {
+                if let Some(start) = start.take() {
+                    ranges.push(start..parser.get_offset());
+                }
+            },
+            Event::Html(_) if start.is_none() => ranges.push(offset..parser.get_offset()),
+            _ => {}
+        }
+    }
+}
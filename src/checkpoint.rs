@@ -0,0 +1,286 @@
+use pulldown_cmark::Tag;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use directives::Overrides;
+use footnotes::Footnotes;
+use hooks::Block;
+use outline::{HeadingLevels, HeadingPath, Slugger};
+use printer::{BufferedEvent, OwnedSpan};
+use references::References;
+use writer::WriterState;
+
+/// Snapshot of the internal state of a `PrettyPrinter`.
+///
+/// A checkpoint captures everything the printer needs to continue where
+/// it stopped, including open blocks, pending line breaks and partially
+/// buffered content. This allows formatting a document incrementally,
+/// for example across multiple requests to a long-running service.
+///
+/// Checkpoints can be sent across threads, and with the `serde` feature
+/// enabled they can be serialized as well.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Checkpoint {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub(crate) writer: WriterState,
+    pub(crate) needs_break: bool,
+    pub(crate) in_code: bool,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) inline_buffer: Option<Vec<BufferedEvent>>,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) link_text: Option<Vec<BufferedEvent>>,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) table: Option<Vec<BufferedEvent>>,
+    pub(crate) blocks: Vec<Block>,
+    pub(crate) text_run: Option<(String, Option<usize>)>,
+    pub(crate) code_block: Option<String>,
+    pub(crate) pending_text: Vec<(String, Option<OwnedSpan>)>,
+    pub(crate) quote_start: bool,
+    pub(crate) callout_line: Option<Vec<(String, Option<OwnedSpan>)>>,
+    pub(crate) pending_break: Option<String>,
+    pub(crate) single_line: bool,
+    #[cfg_attr(feature = "serde", serde(with = "event"))]
+    pub(crate) deferred: Option<BufferedEvent>,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) demoted_heading: Option<Vec<BufferedEvent>>,
+    pub(crate) list_depth: usize,
+    pub(crate) references: References,
+    pub(crate) footnotes: Footnotes,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) footnote_buffer: Option<Vec<BufferedEvent>>,
+    #[cfg_attr(feature = "serde", serde(with = "held"))]
+    pub(crate) held_footnotes: Vec<(usize, Vec<BufferedEvent>)>,
+    pub(crate) sort_next: bool,
+    #[cfg_attr(feature = "serde", serde(with = "events"))]
+    pub(crate) sorted_list: Option<Vec<BufferedEvent>>,
+    pub(crate) sort_depth: usize,
+    pub(crate) overrides: Overrides,
+    pub(crate) headings: HeadingPath,
+    pub(crate) heading_levels: HeadingLevels,
+    pub(crate) profile: Option<usize>,
+    #[cfg_attr(feature = "serde", serde(with = "tags"))]
+    pub(crate) open_tags: Vec<Tag<'static>>,
+    pub(crate) section: usize,
+    pub(crate) empty_image: Option<Option<usize>>,
+    #[cfg_attr(feature = "serde", serde(with = "event"))]
+    pub(crate) empty_start: Option<BufferedEvent>,
+    pub(crate) verbatim: Option<String>,
+    pub(crate) html_skip: Option<String>,
+    pub(crate) heading_text: Option<(String, Option<usize>)>,
+    pub(crate) slugger: Slugger,
+    pub(crate) html_anchors: Vec<String>,
+    pub(crate) fragment_links: Vec<(String, Option<usize>, Vec<String>)>
+}
+
+#[cfg(feature = "serde")]
+mod event {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use printer::{BufferedEvent, OwnedSpan};
+    use super::events::{restore_event, store_event, StoredEvent};
+
+    pub fn serialize<S: Serializer>(event: &Option<BufferedEvent>, serializer: S) -> Result<S::Ok, S::Error> {
+        let stored = event.as_ref().map(|(event, span)| (store_event(event), span.clone()));
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<BufferedEvent>, D::Error> {
+        let stored = Option::<(StoredEvent, Option<OwnedSpan>)>::deserialize(deserializer)?;
+        Ok(stored.map(|(event, span)| (restore_event(event), span)))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod tags {
+    use pulldown_cmark::Tag;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::events::{restore_tag, store_tag, StoredTag};
+
+    pub fn serialize<S: Serializer>(tags: &[Tag<'static>], serializer: S) -> Result<S::Ok, S::Error> {
+        tags.iter().map(store_tag).collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Tag<'static>>, D::Error> {
+        let stored = Vec::<StoredTag>::deserialize(deserializer)?;
+        Ok(stored.into_iter().map(restore_tag).collect())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod held {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use printer::{BufferedEvent, OwnedSpan};
+    use super::events::{restore_event, store_event, StoredEvent};
+
+    type StoredBuffer = Vec<(StoredEvent, Option<OwnedSpan>)>;
+
+    pub fn serialize<S: Serializer>(held: &[(usize, Vec<BufferedEvent>)], serializer: S) -> Result<S::Ok, S::Error> {
+        let stored = held.iter().map(|(section, buffer)| {
+            (*section, buffer.iter().map(|(event, span)| (store_event(event), span.clone())).collect::<StoredBuffer>())
+        }).collect::<Vec<_>>();
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(usize, Vec<BufferedEvent>)>, D::Error> {
+        let stored = Vec::<(usize, StoredBuffer)>::deserialize(deserializer)?;
+        Ok(stored.into_iter().map(|(section, buffer)| {
+            (section, buffer.into_iter().map(|(event, span)| (restore_event(event), span)).collect())
+        }).collect())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod events {
+    use std::borrow::Cow;
+
+    use pulldown_cmark::{Alignment, Event, Tag};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use printer::{BufferedEvent, OwnedSpan};
+
+    #[derive(Serialize, Deserialize)]
+    pub enum StoredEvent {
+        Start(StoredTag),
+        End(StoredTag),
+        Text(String),
+        Html(String),
+        InlineHtml(String),
+        FootnoteReference(String),
+        SoftBreak,
+        HardBreak
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum StoredTag {
+        Paragraph,
+        Rule,
+        Header(i32),
+        BlockQuote,
+        CodeBlock(String),
+        List(Option<usize>),
+        Item,
+        FootnoteDefinition(String),
+        Table(Vec<StoredAlignment>),
+        TableHead,
+        TableRow,
+        TableCell,
+        Emphasis,
+        Strong,
+        Code,
+        Link(String, String),
+        Image(String, String)
+    }
+
+    #[derive(Serialize, Deserialize)]
+    pub enum StoredAlignment {
+        None,
+        Left,
+        Center,
+        Right
+    }
+
+    pub fn serialize<S: Serializer>(buffer: &Option<Vec<BufferedEvent>>, serializer: S) -> Result<S::Ok, S::Error> {
+        let stored = buffer.as_ref().map(|buffer| {
+            buffer.iter()
+                .map(|(event, span)| (store_event(event), span.clone()))
+                .collect::<Vec<_>>()
+        });
+        stored.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<BufferedEvent>>, D::Error> {
+        let stored = Option::<Vec<(StoredEvent, Option<OwnedSpan>)>>::deserialize(deserializer)?;
+        Ok(stored.map(|stored| {
+            stored.into_iter()
+                .map(|(event, span)| (restore_event(event), span))
+                .collect()
+        }))
+    }
+
+    pub fn store_event(event: &Event) -> StoredEvent {
+        match *event {
+            Event::Start(ref tag) => StoredEvent::Start(store_tag(tag)),
+            Event::End(ref tag) => StoredEvent::End(store_tag(tag)),
+            Event::Text(ref text) => StoredEvent::Text(text.to_string()),
+            Event::Html(ref html) => StoredEvent::Html(html.to_string()),
+            Event::InlineHtml(ref html) => StoredEvent::InlineHtml(html.to_string()),
+            Event::FootnoteReference(ref label) => StoredEvent::FootnoteReference(label.to_string()),
+            Event::SoftBreak => StoredEvent::SoftBreak,
+            Event::HardBreak => StoredEvent::HardBreak
+        }
+    }
+
+    pub fn store_tag(tag: &Tag) -> StoredTag {
+        match *tag {
+            Tag::Paragraph => StoredTag::Paragraph,
+            Tag::Rule => StoredTag::Rule,
+            Tag::Header(level) => StoredTag::Header(level),
+            Tag::BlockQuote => StoredTag::BlockQuote,
+            Tag::CodeBlock(ref info) => StoredTag::CodeBlock(info.to_string()),
+            Tag::List(start) => StoredTag::List(start),
+            Tag::Item => StoredTag::Item,
+            Tag::FootnoteDefinition(ref label) => StoredTag::FootnoteDefinition(label.to_string()),
+            Tag::Table(ref alignments) => {
+                StoredTag::Table(alignments.iter().map(|alignment| match *alignment {
+                    Alignment::None => StoredAlignment::None,
+                    Alignment::Left => StoredAlignment::Left,
+                    Alignment::Center => StoredAlignment::Center,
+                    Alignment::Right => StoredAlignment::Right
+                }).collect())
+            },
+            Tag::TableHead => StoredTag::TableHead,
+            Tag::TableRow => StoredTag::TableRow,
+            Tag::TableCell => StoredTag::TableCell,
+            Tag::Emphasis => StoredTag::Emphasis,
+            Tag::Strong => StoredTag::Strong,
+            Tag::Code => StoredTag::Code,
+            Tag::Link(ref url, ref title) => StoredTag::Link(url.to_string(), title.to_string()),
+            Tag::Image(ref url, ref title) => StoredTag::Image(url.to_string(), title.to_string())
+        }
+    }
+
+    pub fn restore_event(event: StoredEvent) -> Event<'static> {
+        match event {
+            StoredEvent::Start(tag) => Event::Start(restore_tag(tag)),
+            StoredEvent::End(tag) => Event::End(restore_tag(tag)),
+            StoredEvent::Text(text) => Event::Text(Cow::Owned(text)),
+            StoredEvent::Html(html) => Event::Html(Cow::Owned(html)),
+            StoredEvent::InlineHtml(html) => Event::InlineHtml(Cow::Owned(html)),
+            StoredEvent::FootnoteReference(label) => Event::FootnoteReference(Cow::Owned(label)),
+            StoredEvent::SoftBreak => Event::SoftBreak,
+            StoredEvent::HardBreak => Event::HardBreak
+        }
+    }
+
+    pub fn restore_tag(tag: StoredTag) -> Tag<'static> {
+        match tag {
+            StoredTag::Paragraph => Tag::Paragraph,
+            StoredTag::Rule => Tag::Rule,
+            StoredTag::Header(level) => Tag::Header(level),
+            StoredTag::BlockQuote => Tag::BlockQuote,
+            StoredTag::CodeBlock(info) => Tag::CodeBlock(Cow::Owned(info)),
+            StoredTag::List(start) => Tag::List(start),
+            StoredTag::Item => Tag::Item,
+            StoredTag::FootnoteDefinition(label) => Tag::FootnoteDefinition(Cow::Owned(label)),
+            StoredTag::Table(alignments) => {
+                Tag::Table(alignments.into_iter().map(|alignment| match alignment {
+                    StoredAlignment::None => Alignment::None,
+                    StoredAlignment::Left => Alignment::Left,
+                    StoredAlignment::Center => Alignment::Center,
+                    StoredAlignment::Right => Alignment::Right
+                }).collect())
+            },
+            StoredTag::TableHead => Tag::TableHead,
+            StoredTag::TableRow => Tag::TableRow,
+            StoredTag::TableCell => Tag::TableCell,
+            StoredTag::Emphasis => Tag::Emphasis,
+            StoredTag::Strong => Tag::Strong,
+            StoredTag::Code => Tag::Code,
+            StoredTag::Link(url, title) => Tag::Link(Cow::Owned(url), Cow::Owned(title)),
+            StoredTag::Image(url, title) => Tag::Image(Cow::Owned(url), Cow::Owned(title))
+        }
+    }
+}
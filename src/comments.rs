@@ -0,0 +1,88 @@
+use options::Options;
+
+/// Markers of comment lines that contain Markdown, in the order in which
+/// they are detected.
+const MARKERS: &[&str] = &["///", "//!", "#"];
+
+/// The prefix shared by the lines of a comment, as detected by
+/// `PrettyPrinter::detect_prefix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentPrefix {
+    /// The marker at the start of each line, such as `///`, `//!` or `#`,
+    /// including the indentation before it.
+    pub marker: String,
+    /// Whether text is separated from the marker by a space.
+    pub space: bool,
+    /// Whether empty lines end in a space after the marker.
+    pub trailing_space: bool
+}
+
+impl CommentPrefix {
+    /// Remove the prefix from every line of a comment, or return `None`
+    /// if a line does not start with the marker.
+    pub fn strip(&self, comment: &str) -> Option<String> {
+        let lines = comment.lines().map(|line| {
+            let rest = line.strip_prefix(self.marker.as_str())?;
+            Some(if self.space { rest.strip_prefix(' ').unwrap_or(rest) } else { rest })
+        }).collect::<Option<Vec<_>>>()?;
+        Some(lines.join("\n"))
+    }
+
+    /// Add the prefix to every line of text.
+    pub fn apply(&self, text: &str) -> String {
+        let lines = text.split('\n').map(|line| {
+            let space = if line.is_empty() { self.trailing_space } else { self.space };
+            format!("{}{}{}", self.marker, if space { " " } else { "" }, line)
+        }).collect::<Vec<_>>();
+        lines.join("\n")
+    }
+
+    /// The number of columns taken up by the prefix of a line of text.
+    fn width(&self) -> usize {
+        self.marker.chars().count() + self.space as usize
+    }
+}
+
+/// Detect the prefix shared by all lines of a comment.
+pub(crate) fn detect<'a, I: IntoIterator<Item=&'a str>>(lines: I) -> Option<CommentPrefix> {
+    let lines = lines.into_iter().collect::<Vec<_>>();
+    let first = lines.first()?.trim_start();
+    let indent = &lines[0][..lines[0].len() - first.len()];
+    let marker = format!("{}{}", indent, MARKERS.iter().find(|&marker| first.starts_with(marker))?);
+    let rests = lines.iter()
+        .map(|line| line.trim_end_matches('\r').strip_prefix(marker.as_str()))
+        .collect::<Option<Vec<_>>>()?;
+    let space = rests.iter().filter(|rest| !rest.trim().is_empty()).all(|rest| rest.starts_with(' '));
+    let trailing_space = rests.iter().any(|rest| !rest.is_empty() && rest.trim().is_empty());
+    Some(CommentPrefix { marker, space, trailing_space })
+}
+
+/// Formats the Markdown in a comment whose lines all start with the same
+/// prefix, such as a Rust doc comment (`///` or `//!`) or a shell comment
+/// (`#`), and returns it with exactly the same prefix applied again.
+///
+/// The prefix is detected using `PrettyPrinter::detect_prefix`, keeping
+/// the indentation of the comment, whether text is separated from the
+/// marker by a space and whether empty lines end in one. Lines are
+/// wrapped within `Options::wrap`, including the prefix. Returns `None`
+/// if the lines do not share a prefix.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_comment, Options};
+/// let comment = "    /// Lorem __ipsum__\n    /// \n    /// * dolor\n";
+/// let output = prettify_comment(comment, &Options::default()).unwrap();
+/// assert_eq!(output, "    /// Lorem **ipsum**\n    /// \n    /// - dolor\n");
+/// ```
+pub fn prettify_comment(comment: &str, options: &Options) -> Option<String> {
+    let prefix = detect(comment.lines())?;
+    let source = prefix.strip(comment)?;
+    let mut options = options.clone();
+    options.wrap = options.wrap.map(|width| width.saturating_sub(prefix.width()));
+    let mut output = prefix.apply(&::prettify_with_options(&source, &options));
+    if comment.ends_with('\n') {
+        output.push('\n');
+    }
+    Some(output)
+}
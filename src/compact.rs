@@ -0,0 +1,115 @@
+use std::fmt::Result;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use backend::Backend;
+use options::Options;
+use printer::PrettyPrinter;
+
+/// Parse a CommonMark document and push it into a printer as a single
+/// paragraph.
+///
+/// Blocks are joined by spaces, dropping the markup of headings, lists,
+/// block quotes, tables and thematic breaks. Code blocks become code
+/// spans, and HTML blocks are left out.
+pub(crate) fn push_compact<W: Backend>(printer: &mut PrettyPrinter<W>, source: &str, options: &Options) -> Result {
+    printer.push_event(Event::Start(Tag::Paragraph))?;
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    // Whether a space is due before the next inline content, and whether
+    // any content has been pushed yet.
+    let mut separate = false;
+    let mut started = false;
+    // Whitespace at the end of text, which is dropped if a block ends.
+    let mut trailing = String::new();
+    // The text of the code block that is being collected.
+    let mut code_block: Option<String> = None;
+    let mut in_code = false;
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let end = parser.get_offset();
+        let event = match event {
+            Event::Start(Tag::CodeBlock(_)) => {
+                push_separator(printer, &mut separate, &mut trailing, started)?;
+                started = true;
+                code_block = Some(String::new());
+                Event::Start(Tag::Code)
+            },
+            Event::End(Tag::CodeBlock(_)) => {
+                trailing.clear();
+                let code = code_block.take().unwrap_or_default();
+                printer.push_event(Event::Text(code.split_whitespace().collect::<Vec<_>>().join(" ").into()))?;
+                printer.push_event(Event::End(Tag::Code))?;
+                in_code = false;
+                separate = true;
+                continue;
+            },
+            Event::Text(text) if code_block.is_some() => {
+                if let Some(ref mut code) = code_block {
+                    code.push_str(&text);
+                }
+                continue;
+            },
+            Event::Start(ref tag) | Event::End(ref tag) if is_block(tag) => {
+                separate = true;
+                trailing.clear();
+                continue;
+            },
+            Event::Html(_) | Event::SoftBreak | Event::HardBreak => {
+                separate = true;
+                trailing.clear();
+                continue;
+            },
+            event => event
+        };
+        match event {
+            Event::Start(Tag::Code) => in_code = true,
+            Event::End(Tag::Code) => in_code = false,
+            _ => {}
+        }
+        // Whitespace around blocks, such as the padding of table cells,
+        // is replaced by a single space.
+        if let (Event::Text(ref text), false) = (&event, in_code) {
+            let trimmed = if separate { text.trim_start() } else { text };
+            let content = trimmed.trim_end();
+            if content.len() != text.len() {
+                if !content.is_empty() {
+                    push_separator(printer, &mut separate, &mut trailing, started)?;
+                    printer.push_event(Event::Text(content.to_string().into()))?;
+                    started = true;
+                }
+                trailing.push_str(&trimmed[content.len()..]);
+                continue;
+            }
+        }
+        push_separator(printer, &mut separate, &mut trailing, started)?;
+        started = true;
+        printer.push_event_in_source(event, source, start..end)?;
+    }
+    printer.push_event(Event::End(Tag::Paragraph))
+}
+
+/// Push the space between blocks, or the whitespace at the end of the
+/// previous text, before more inline content.
+fn push_separator<W: Backend>(printer: &mut PrettyPrinter<W>, separate: &mut bool, trailing: &mut String,
+                              started: bool) -> Result {
+    if *separate && started {
+        printer.push_event(Event::Text(" ".into()))?;
+    } else if !trailing.is_empty() {
+        printer.push_event(Event::Text(trailing.clone().into()))?;
+    }
+    *separate = false;
+    trailing.clear();
+    Ok(())
+}
+
+fn is_block(tag: &Tag) -> bool {
+    match *tag {
+        Tag::Paragraph | Tag::Rule | Tag::Header(_) | Tag::BlockQuote | Tag::List(_) | Tag::Item |
+        Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => true,
+        Tag::CodeBlock(_) | Tag::Emphasis | Tag::Strong | Tag::Code | Tag::Link(_, _) | Tag::Image(_, _) => false
+    }
+}
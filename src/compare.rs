@@ -0,0 +1,293 @@
+use std::fmt::{self, Display, Formatter};
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use buffer::{into_static, OwnedEvent};
+use options::Options;
+use validate::name;
+
+/// The first difference between the normalized events of two documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDifference {
+    /// The position of the differing event in the normalized events.
+    pub index: usize,
+    /// The event of the first document, or `None` if it ended early.
+    pub left: Option<OwnedEvent>,
+    /// The event of the second document, or `None` if it ended early.
+    pub right: Option<OwnedEvent>,
+    /// The byte offset of the event in the first document.
+    pub left_offset: usize,
+    /// The byte offset of the event in the second document.
+    pub right_offset: usize
+}
+
+impl Display for EventDifference {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "event {} differs: ", self.index)?;
+        describe(fmt, self.left.as_ref())?;
+        write!(fmt, " at byte {} != ", self.left_offset)?;
+        describe(fmt, self.right.as_ref())?;
+        write!(fmt, " at byte {}", self.right_offset)
+    }
+}
+
+/// Describe an event in words, such as `start of emphasis` or
+/// `text "Lorem"`.
+pub(crate) fn describe(fmt: &mut Formatter, event: Option<&OwnedEvent>) -> fmt::Result {
+    match event.map(OwnedEvent::as_event) {
+        Some(Event::Start(tag)) => write!(fmt, "start of {}", name(tag)),
+        Some(Event::End(tag)) => write!(fmt, "end of {}", name(tag)),
+        Some(Event::Text(text)) => write!(fmt, "text {:?}", text.as_ref()),
+        Some(Event::Html(html)) => write!(fmt, "HTML block {:?}", html.as_ref()),
+        Some(Event::InlineHtml(html)) => write!(fmt, "inline HTML {:?}", html.as_ref()),
+        Some(Event::FootnoteReference(label)) => write!(fmt, "footnote reference {:?}", label.as_ref()),
+        Some(Event::SoftBreak) => fmt.write_str("soft break"),
+        Some(Event::HardBreak) => fmt.write_str("hard break"),
+        None => fmt.write_str("end of document")
+    }
+}
+
+/// Checks whether two CommonMark documents have the same meaning, even
+/// if their source text differs.
+///
+/// Both documents are parsed with the extensions enabled by the options,
+/// and their events are normalized before being compared: adjacent text
+/// is merged, runs of whitespace and soft line breaks in text are treated
+/// as a single space, trailing whitespace of HTML blocks is ignored, and
+/// tight and loose lists are considered equal. Code is always compared
+/// exactly.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{semantically_equal, Options};
+/// let options = Options::default();
+/// assert!(semantically_equal("* Lorem\n* __ipsum__\n  dolor", "- Lorem\n\n- **ipsum** dolor", &options));
+/// assert!(!semantically_equal("Lorem *ipsum*", "Lorem ipsum", &options));
+/// ```
+pub fn semantically_equal(a: &str, b: &str, options: &Options) -> bool {
+    first_difference(a, b, options).is_none()
+}
+
+/// Compares two CommonMark documents like `semantically_equal`, and
+/// returns the first difference between them, if any.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{first_difference, Options};
+/// let difference = first_difference("Lorem *ipsum*", "Lorem **ipsum**", &Options::default()).unwrap();
+/// assert_eq!(difference.index, 2);
+/// assert_eq!(difference.to_string(), "event 2 differs: start of emphasis at byte 6 != start of strong emphasis at byte 6");
+/// ```
+pub fn first_difference(a: &str, b: &str, options: &Options) -> Option<EventDifference> {
+    let left = normalized_events(a, options);
+    let right = normalized_events(b, options);
+    for index in 0..left.len().max(right.len()) {
+        let (left, right) = (left.get(index), right.get(index));
+        if left.map(|(event, _)| event) != right.map(|(event, _)| event) {
+            return Some(EventDifference {
+                index,
+                left: left.map(|(event, _)| OwnedEvent::from(event.clone())),
+                right: right.map(|(event, _)| OwnedEvent::from(event.clone())),
+                left_offset: left.map_or(a.len(), |&(_, offset)| offset),
+                right_offset: right.map_or(b.len(), |&(_, offset)| offset)
+            });
+        }
+    }
+    None
+}
+
+/// Parse a document into normalized events, along with the byte offsets
+/// of the source they were parsed from.
+pub(crate) fn normalized_events(source: &str, options: &Options) -> Vec<(Event<'static>, usize)> {
+    // Along with each event, whether it is text outside of code.
+    let mut events: Vec<(Event<'static>, usize, bool)> = Vec::new();
+    let mut in_code = false;
+    let mut item_paragraphs: Vec<bool> = Vec::new();
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    loop {
+        let offset = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let offset = offset + source[offset..].len() - source[offset..].trim_start().len();
+        let event = match event {
+            Event::SoftBreak => Event::Text(" ".into()),
+            Event::Html(html) => Event::Html(html.trim_end().to_string().into()),
+            event => into_static(event)
+        };
+        match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Start(Tag::Code) => in_code = true,
+            Event::End(Tag::CodeBlock(_)) | Event::End(Tag::Code) => in_code = false,
+            // Paragraphs directly inside of list items only distinguish
+            // loose from tight lists.
+            Event::Start(Tag::Paragraph) => {
+                let in_item = matches!(events.last(), Some(&(Event::Start(Tag::Item), _, _)));
+                item_paragraphs.push(in_item);
+                if in_item {
+                    continue;
+                }
+            },
+            Event::End(Tag::Paragraph) if item_paragraphs.last() == Some(&true) => {
+                item_paragraphs.pop();
+                continue;
+            },
+            Event::End(Tag::Paragraph) => {
+                item_paragraphs.pop();
+            },
+            _ => {}
+        }
+        if let Event::Text(ref text) = event {
+            if let Some(&mut (Event::Text(ref mut previous), _, prose)) = events.last_mut() {
+                if prose != in_code {
+                    previous.to_mut().push_str(text);
+                    continue;
+                }
+            }
+        }
+        let prose = matches!(event, Event::Text(_)) && !in_code;
+        events.push((event, offset, prose));
+    }
+    events.into_iter().map(|(event, offset, prose)| match event {
+        Event::Text(text) if prose => (Event::Text(collapse_whitespace(&text).into()), offset),
+        event => (event, offset)
+    }).collect()
+}
+
+/// Collapse runs of whitespace into a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !c.is_whitespace() {
+            collapsed.push(c);
+        } else if !collapsed.ends_with(' ') {
+            collapsed.push(' ');
+        }
+    }
+    collapsed
+}
+
+/// Whether an event was inserted or deleted in an `EventDiff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The event only occurs in the second document.
+    Inserted,
+    /// The event only occurs in the first document.
+    Deleted
+}
+
+/// An event that only occurs in one of two compared documents.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventChange {
+    /// Whether the event was inserted or deleted.
+    pub kind: ChangeKind,
+    /// The normalized event.
+    pub event: OwnedEvent,
+    /// The byte offset of the event in the document it occurs in.
+    pub offset: usize
+}
+
+/// The differences between the normalized events of two documents.
+///
+/// When displayed, each change is printed on a line of its own, prefixed
+/// with `-` for deletions and `+` for insertions.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EventDiff {
+    /// The changes in document order, with deletions before insertions
+    /// at the same position.
+    pub changes: Vec<EventChange>
+}
+
+impl EventDiff {
+    /// Whether the documents have the same meaning.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl Display for EventDiff {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        for (index, change) in self.changes.iter().enumerate() {
+            if index > 0 {
+                fmt.write_str("\n")?;
+            }
+            fmt.write_str(match change.kind {
+                ChangeKind::Inserted => "+ ",
+                ChangeKind::Deleted => "- "
+            })?;
+            describe(fmt, Some(&change.event))?;
+            write!(fmt, " at byte {}", change.offset)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two CommonMark documents like `semantically_equal`, and
+/// returns all events that were inserted or deleted between them.
+///
+/// The diff is minimal in the number of changed events. Its cost grows
+/// with the product of the number of events that differ in both
+/// documents, after skipping the events they start and end with in
+/// common.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{diff_events, Options};
+/// let diff = diff_events("Lorem *ipsum*", "Lorem **ipsum**", &Options::default());
+/// assert_eq!(diff.to_string(), "- start of emphasis at byte 6\n\
+///                               + start of strong emphasis at byte 6\n\
+///                               - end of emphasis at byte 12\n\
+///                               + end of strong emphasis at byte 13");
+/// ```
+pub fn diff_events(a: &str, b: &str, options: &Options) -> EventDiff {
+    let left = normalized_events(a, options);
+    let right = normalized_events(b, options);
+    let prefix = left.iter().zip(&right).take_while(|(left, right)| left.0 == right.0).count();
+    let suffix = left[prefix..].iter().rev().zip(right[prefix..].iter().rev())
+        .take_while(|(left, right)| left.0 == right.0)
+        .count();
+    let left = &left[prefix..left.len() - suffix];
+    let right = &right[prefix..right.len() - suffix];
+
+    // The length of the longest common subsequence of the events from
+    // each position onwards.
+    let width = right.len() + 1;
+    let mut common = vec![0u32; (left.len() + 1) * width];
+    for i in (0..left.len()).rev() {
+        for j in (0..right.len()).rev() {
+            common[i * width + j] = if left[i].0 == right[j].0 {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    let change = |kind, &(ref event, offset): &(Event<'static>, usize)| {
+        EventChange { kind, event: OwnedEvent::from(event.clone()), offset }
+    };
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < left.len() || j < right.len() {
+        if i < left.len() && j < right.len() && left[i].0 == right[j].0 {
+            changes.append(&mut deleted);
+            changes.append(&mut inserted);
+            i += 1;
+            j += 1;
+        } else if j == right.len() || i < left.len() && common[(i + 1) * width + j] >= common[i * width + j + 1] {
+            deleted.push(change(ChangeKind::Deleted, &left[i]));
+            i += 1;
+        } else {
+            inserted.push(change(ChangeKind::Inserted, &right[j]));
+            j += 1;
+        }
+    }
+    changes.append(&mut deleted);
+    changes.append(&mut inserted);
+    EventDiff { changes }
+}
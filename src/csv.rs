@@ -0,0 +1,128 @@
+//! Conversion of CSV and TSV data into tables.
+//!
+//! This module is only available with the `csv` feature enabled.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+use pulldown_cmark::{Alignment, Event, Tag};
+
+/// An error in CSV or TSV input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvError {
+    /// The line on which the offending quoted field starts, counting
+    /// from one.
+    pub line: usize
+}
+
+impl Display for CsvError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "unterminated quoted field starting on line {}", self.line)
+    }
+}
+
+impl Error for CsvError {}
+
+/// Converts CSV or TSV data into the events of a table, which can be
+/// pushed into a `PrettyPrinter` to embed the data in a document.
+///
+/// The first record is the header of the table. Fields are separated by
+/// `delimiter`, usually `,` or `\t`, and may be quoted with `"` to contain
+/// delimiters, line breaks or doubled quotes. Records with fewer fields
+/// than the widest record are filled up with empty cells. The contents of
+/// every field are plain text, which the printer escapes as needed.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{csv_table, PrettyPrinter};
+/// let events = csv_table("name,value\nLorem,1\n\"ipsum, *dolor*\",23\n", ',').unwrap();
+/// let mut printer = PrettyPrinter::default();
+/// printer.push_events(events).unwrap();
+/// assert_eq!(printer.into_inner(), "| name             | value |\n\
+///                                   | ---------------- | ----- |\n\
+///                                   | Lorem            | 1     |\n\
+///                                   | ipsum, \\*dolor\\* | 23    |");
+/// ```
+pub fn csv_table(input: &str, delimiter: char) -> Result<Vec<Event<'static>>, CsvError> {
+    let records = parse(input, delimiter)?;
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+    let columns = records.iter().map(Vec::len).max().unwrap_or(0);
+    let alignments = vec![Alignment::None; columns];
+    let mut events = vec![Event::Start(Tag::Table(alignments.clone()))];
+    for (index, record) in records.into_iter().enumerate() {
+        let row = if index == 0 { Tag::TableHead } else { Tag::TableRow };
+        events.push(Event::Start(row.clone()));
+        let filled = record.len();
+        for field in record.into_iter().chain((filled..columns).map(|_| String::new())) {
+            events.push(Event::Start(Tag::TableCell));
+            if !field.is_empty() {
+                events.push(Event::Text(field.into()));
+            }
+            events.push(Event::End(Tag::TableCell));
+        }
+        events.push(Event::End(row));
+    }
+    events.push(Event::End(Tag::Table(alignments)));
+    Ok(events)
+}
+
+/// Split the input into records of fields.
+fn parse(input: &str, delimiter: char) -> Result<Vec<Vec<String>>, CsvError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut chars = input.chars().peekable();
+    // Whether the current record has any content, so that blank lines
+    // are skipped.
+    let mut started = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if field.is_empty() => {
+                let start = line;
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        },
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            field.push(c);
+                        },
+                        None => return Err(CsvError { line: start })
+                    }
+                }
+                started = true;
+            },
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => {
+                line += 1;
+                if started {
+                    record.push(field.split_off(0));
+                    records.push(record.split_off(0));
+                    started = false;
+                }
+            },
+            c if c == delimiter => {
+                record.push(field.split_off(0));
+                started = true;
+            },
+            c => {
+                field.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::Duration;
+
+use hooks::Localizer;
+
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+/// Severity of a diagnostic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(rename_all = "lowercase"))]
+pub enum Severity {
+    /// The document was printed, but may not look as intended.
+    Warning,
+    /// The document violates a requirement set in the options.
+    Error
+}
+
+/// A message about the document, reported by the printer while printing.
+///
+/// With the `serde` feature enabled, diagnostics can be serialized, such
+/// as into JSON for other tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+pub struct Diagnostic {
+    /// How severe the reported issue is.
+    pub severity: Severity,
+    /// A short identifier for the kind of issue, such as `list-depth`.
+    pub rule: &'static str,
+    /// A human-readable description of the issue.
+    pub message: String,
+    /// The byte offset in the source document the issue refers to, if
+    /// the printer had access to the source.
+    pub offset: Option<usize>,
+    /// The headings of the section the issue was found in, from the
+    /// outermost one, as returned by `PrettyPrinter::heading_path`.
+    pub section: Vec<String>
+}
+
+impl Diagnostic {
+    pub(crate) fn warning(message: Message, localizer: Option<&dyn Localizer>, offset: Option<usize>) -> Diagnostic {
+        Diagnostic { severity: Severity::Warning, rule: message.rule(), message: message.localize(localizer), offset, section: Vec::new() }
+    }
+
+    pub(crate) fn error(message: Message, localizer: Option<&dyn Localizer>, offset: Option<usize>) -> Diagnostic {
+        Diagnostic { severity: Severity::Error, rule: message.rule(), message: message.localize(localizer), offset, section: Vec::new() }
+    }
+}
+
+/// Text generated by the printer, along with the values it refers to.
+///
+/// Messages are displayed in English, unless a `Localizer` supplies
+/// other text for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Message<'a> {
+    /// A footnote definition that is never referenced was removed.
+    UnusedFootnote { label: &'a str },
+    /// A footnote definition conflicts with an earlier one.
+    DuplicateFootnote { label: &'a str },
+    /// A link reference definition conflicts with an earlier one.
+    DuplicateDefinition { label: &'a str },
+    /// A list nested too deeply was flattened.
+    ListDepth { max_depth: usize },
+    /// A heading is deeper than the maximum level.
+    HeadingDepth { level: i32, max_level: i32 },
+    /// A code block has no language.
+    CodeLanguage,
+    /// A heading has the same anchor as a previous one.
+    DuplicateAnchor { heading: &'a str, anchor: &'a str },
+    /// A link points to an anchor that does not exist in the document.
+    BrokenAnchor { anchor: &'a str },
+    /// An image has no alt text.
+    ImageAlt { url: &'a str },
+    /// Printing was cancelled using a `CancellationToken`.
+    Cancelled,
+    /// Printing took longer than the time budget.
+    TimeBudget { limit: Duration },
+    /// More events were pushed than the event budget allows.
+    EventBudget { limit: usize },
+    /// More bytes were buffered than the buffer limit allows.
+    BufferLimit { limit: usize },
+    /// A construct, such as emphasis or a list item, has no content.
+    EmptyConstruct { construct: &'a str },
+    /// Front matter could not be parsed as YAML.
+    FrontMatter { error: &'a str },
+    /// Front matter cannot be canonicalized without the `yaml` feature.
+    FrontMatterFeature,
+    /// Text contains a zero-width or bidirectional control character.
+    InvisibleCharacter { character: char },
+    /// A line of the source is wider than the maximum width.
+    LineWidth { width: usize, max_width: usize },
+    /// Text contains a URL that is not a link.
+    BareUrl { url: &'a str },
+    /// A heading is more than one level deeper than the previous heading.
+    SkippedHeadingLevel { level: i32, previous: i32 },
+    /// A level 1 heading follows another one.
+    MultipleH1,
+    /// A level 1 heading follows a heading of another level.
+    H1NotFirst
+}
+
+impl<'a> Message<'a> {
+    /// The rule of diagnostics with this message, such as `list-depth`.
+    pub fn rule(&self) -> &'static str {
+        match *self {
+            Message::UnusedFootnote { .. } => "unused-footnote",
+            Message::DuplicateFootnote { .. } => "duplicate-footnote",
+            Message::DuplicateDefinition { .. } => "duplicate-definition",
+            Message::ListDepth { .. } => "list-depth",
+            Message::HeadingDepth { .. } => "heading-depth",
+            Message::CodeLanguage => "code-language",
+            Message::DuplicateAnchor { .. } => "duplicate-anchor",
+            Message::BrokenAnchor { .. } => "broken-anchor",
+            Message::ImageAlt { .. } => "image-alt",
+            Message::Cancelled => "cancelled",
+            Message::TimeBudget { .. } => "time-budget",
+            Message::EventBudget { .. } => "event-budget",
+            Message::BufferLimit { .. } => "buffer-limit",
+            Message::EmptyConstruct { .. } => "empty-construct",
+            Message::FrontMatter { .. } | Message::FrontMatterFeature => "front-matter",
+            Message::InvisibleCharacter { .. } => "invisible-character",
+            Message::LineWidth { .. } => "line-width",
+            Message::BareUrl { .. } => "no-bare-urls",
+            Message::SkippedHeadingLevel { .. } => "no-skipped-heading-levels",
+            Message::MultipleH1 => "single-h1",
+            Message::H1NotFirst => "first-h1"
+        }
+    }
+
+    /// The text of the message, as supplied by the localizer if any.
+    pub(crate) fn localize(&self, localizer: Option<&dyn Localizer>) -> String {
+        localizer.and_then(|localizer| localizer.localize(self)).unwrap_or_else(|| self.to_string())
+    }
+}
+
+impl<'a> fmt::Display for Message<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Message::UnusedFootnote { label } => write!(f, "unreferenced footnote `{}` was removed", label),
+            Message::DuplicateFootnote { label } => write!(f, "conflicting definition of footnote `{}`", label),
+            Message::DuplicateDefinition { label } => write!(f, "conflicting definition of `{}` was removed", label),
+            Message::ListDepth { max_depth } => write!(f, "list nested deeper than {} levels was flattened", max_depth),
+            Message::HeadingDepth { level, max_level } => {
+                write!(f, "heading of level {} is deeper than the maximum of {}", level, max_level)
+            },
+            Message::CodeLanguage => f.write_str("code block has no language"),
+            Message::DuplicateAnchor { heading, anchor } => {
+                write!(f, "heading `{}` has the same anchor `#{}` as a previous heading", heading, anchor)
+            },
+            Message::BrokenAnchor { anchor } => write!(f, "link to `#{}` matches no heading or anchor", anchor),
+            Message::ImageAlt { url } => write!(f, "image `{}` has no alt text", url),
+            Message::Cancelled => f.write_str("printing was cancelled"),
+            Message::TimeBudget { limit } => write!(f, "printing took longer than {:?}", limit),
+            Message::EventBudget { limit } => write!(f, "document has more than {} events", limit),
+            Message::BufferLimit { limit } => write!(f, "more than {} bytes of the document were buffered", limit),
+            Message::EmptyConstruct { construct } => write!(f, "{} has no content", construct),
+            Message::FrontMatter { error } => write!(f, "front matter is not valid YAML: {}", error),
+            Message::FrontMatterFeature => f.write_str("front matter can only be canonicalized with the `yaml` feature"),
+            Message::InvisibleCharacter { character } => {
+                write!(f, "text contains invisible character U+{:04X}", character as u32)
+            },
+            Message::LineWidth { width, max_width } => {
+                write!(f, "line is {} columns wide, more than the maximum of {}", width, max_width)
+            },
+            Message::BareUrl { url } => write!(f, "bare URL `{}` is not a link", url),
+            Message::SkippedHeadingLevel { level, previous } => {
+                write!(f, "heading of level {} follows a heading of level {}", level, previous)
+            },
+            Message::MultipleH1 => f.write_str("document has more than one level 1 heading"),
+            Message::H1NotFirst => f.write_str("level 1 heading is not the first heading of the document")
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error"
+        };
+        write!(f, "{}[{}]", severity, self.rule)?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {}", offset)?;
+        }
+        if !self.section.is_empty() {
+            write!(f, " in {}", self.section.join(" > "))?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
@@ -0,0 +1,102 @@
+use pulldown_cmark::Event;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use printer::BufferedEvent;
+
+/// An instruction to the printer in an HTML comment, such as
+/// `<!-- prettify: sort -->`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    /// Sort the items of the following list.
+    Sort,
+    /// Override options for the rest of the document, as in
+    /// `<!-- prettify: wrap=off max-width=100 -->`.
+    Set(Vec<Setting>),
+    /// Restore the options that were overridden.
+    Reset
+}
+
+/// An option that is overridden by a directive, with `off` for `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Setting {
+    /// `wrap`, for `Options::wrap`.
+    Wrap(Option<usize>),
+    /// `max-width`, for `TableOptions::max_width`.
+    MaxWidth(Option<usize>)
+}
+
+/// The options that are overridden by directives, if any.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Overrides {
+    pub wrap: Option<Option<usize>>,
+    pub max_width: Option<Option<usize>>
+}
+
+impl Overrides {
+    pub fn apply(&mut self, settings: &[Setting]) {
+        for setting in settings {
+            match *setting {
+                Setting::Wrap(wrap) => self.wrap = Some(wrap),
+                Setting::MaxWidth(max_width) => self.max_width = Some(max_width)
+            }
+        }
+    }
+}
+
+/// Parse the directive of an HTML block that consists of a single
+/// comment starting with `prettify:`.
+pub fn parse(html: &str) -> Option<Directive> {
+    let comment = html.trim().strip_prefix("<!--")?.strip_suffix("-->")?;
+    let directive = comment.trim().strip_prefix("prettify:")?;
+    match directive.trim() {
+        "sort" => Some(Directive::Sort),
+        "reset" => Some(Directive::Reset),
+        directive => directive.split_whitespace().map(parse_setting).collect::<Option<_>>().map(Directive::Set)
+    }
+}
+
+/// Parse a setting such as `wrap=80`.
+fn parse_setting(setting: &str) -> Option<Setting> {
+    let (name, value) = setting.split_once('=')?;
+    let value = match value {
+        "off" => None,
+        value => Some(value.parse().ok()?)
+    };
+    match name {
+        "wrap" => Some(Setting::Wrap(value)),
+        "max-width" => Some(Setting::MaxWidth(value)),
+        _ => None
+    }
+}
+
+/// Sort the items of a buffered list alphabetically (ignoring case) by
+/// their first text, keeping the nested content of each item with it.
+pub fn sort_items(list: Vec<BufferedEvent>) -> Vec<BufferedEvent> {
+    let mut events = list.into_iter();
+    let start = events.next();
+    let end = events.next_back();
+    let mut items: Vec<Vec<BufferedEvent>> = Vec::new();
+    let mut depth = 0;
+    for event in events {
+        if depth == 0 {
+            items.push(Vec::new());
+        }
+        match event.0 {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        if let Some(item) = items.last_mut() {
+            item.push(event);
+        }
+    }
+    items.sort_by_cached_key(|item| item.iter().find_map(|event| match event.0 {
+        Event::Text(ref text) => Some(text.to_lowercase()),
+        _ => None
+    }).unwrap_or_default());
+    start.into_iter().chain(items.into_iter().flatten()).chain(end).collect()
+}
+
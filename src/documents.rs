@@ -0,0 +1,347 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use front_matter;
+use options::{FrontMatterPolicy, Options};
+use printer::{PrettyPrinter, Replacement, ReplacementBlock};
+use truncate::with_definitions;
+
+/// A document fragment that can be concatenated with others using
+/// `concat_documents`.
+#[derive(Debug, Clone, Copy)]
+pub struct Document<'a> {
+    /// The title of the document, used for heading separators.
+    pub title: Option<&'a str>,
+    /// The CommonMark source of the document.
+    pub source: &'a str
+}
+
+impl<'a> From<&'a str> for Document<'a> {
+    fn from(source: &'a str) -> Document<'a> {
+        Document { title: None, source }
+    }
+}
+
+impl<'a> From<(&'a str, &'a str)> for Document<'a> {
+    fn from((title, source): (&'a str, &'a str)) -> Document<'a> {
+        Document { title: Some(title), source }
+    }
+}
+
+/// Separator placed between concatenated documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentSeparator {
+    /// Separate documents by a thematic break.
+    Rule,
+    /// Start each document that has a title with a heading of the
+    /// given level.
+    Heading(i32)
+}
+
+/// Parses multiple CommonMark documents and returns them pretty printed
+/// as a single document.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{concat_documents, DocumentSeparator, Options};
+/// let output = concat_documents(vec!["Lorem _ipsum_", "Dolor sit"],
+///                               DocumentSeparator::Rule, &Options::default());
+/// assert_eq!(output, "Lorem *ipsum*\n\n---\n\nDolor sit");
+///
+/// let output = concat_documents(vec![("Intro", "Lorem"), ("Usage", "Ipsum")],
+///                               DocumentSeparator::Heading(2), &Options::default());
+/// assert_eq!(output, "## Intro\n\nLorem\n\n## Usage\n\nIpsum");
+/// ```
+pub fn concat_documents<'a, I>(documents: I, separator: DocumentSeparator, options: &Options) -> String
+    where I: IntoIterator, I::Item: Into<Document<'a>>
+{
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    for (index, document) in documents.into_iter().enumerate() {
+        let document = document.into();
+        match separator {
+            DocumentSeparator::Rule => {
+                if index > 0 {
+                    printer.push_document_break().expect("failed to write to string");
+                }
+            },
+            DocumentSeparator::Heading(level) => {
+                if let Some(title) = document.title {
+                    printer.push_events(vec![
+                        Event::Start(Tag::Header(level)),
+                        Event::Text(Cow::Borrowed(title)),
+                        Event::End(Tag::Header(level))
+                    ]).expect("failed to write to string");
+                }
+            }
+        }
+        printer.push_source(document.source).expect("failed to write to string");
+    }
+    printer.into_inner()
+}
+
+/// A section of a document, as returned by `split_by_heading`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// The text of the heading that starts the section, with all markup
+    /// removed, or `None` for content preceding the first heading.
+    pub title: Option<String>,
+    /// The pretty printed CommonMark source of the section, including
+    /// its heading.
+    pub source: String
+}
+
+/// Parses a CommonMark document and splits it into pretty printed
+/// sections at each heading of the given level or above.
+///
+/// Only headings at the top level of the document start a new section,
+/// headings nested within block quotes or lists do not. Content before
+/// the first heading forms a section without a title. Each section keeps
+/// the link reference definitions of the whole document, so that its
+/// links still resolve.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{split_by_heading, Options};
+/// let sections = split_by_heading("Intro\n\n# One\n\n## _Sub_\n\n# Two", 1, &Options::default());
+/// assert_eq!(sections.len(), 3);
+/// assert_eq!(sections[0].title, None);
+/// assert_eq!(sections[1].title.as_deref(), Some("One"));
+/// assert_eq!(sections[1].source, "# One\n\n## *Sub*");
+/// assert_eq!(sections[2].source, "# Two");
+/// ```
+pub fn split_by_heading(source: &str, level: i32, options: &Options) -> Vec<Section> {
+    sections(source, level, options).into_iter().map(|(title, range)| {
+        Section { title, source: print_section(source, range, 0, options) }
+    }).collect()
+}
+
+/// The headings of a document that `adjust_headings` applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadingRange {
+    /// All headings of the document.
+    All,
+    /// The headings that start within a range of bytes of the source.
+    Bytes(Range<usize>),
+    /// The headings at a range of positions among all headings of the
+    /// document, counting from zero.
+    Headings(Range<usize>)
+}
+
+/// Parses a CommonMark document and returns it pretty printed, with the
+/// level of the headings within a range shifted by `delta`.
+///
+/// A negative `delta` promotes headings, and a positive one demotes
+/// them. Levels are kept between 1 and 6. This is useful when merging a
+/// document into another, or when extracting a section into a document
+/// of its own.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{adjust_headings, HeadingRange, Options};
+/// let source = "# Title\n\n## Usage\n\n### Details";
+/// assert_eq!(adjust_headings(source, 1, HeadingRange::All, &Options::default()),
+///            "## Title\n\n### Usage\n\n#### Details");
+/// assert_eq!(adjust_headings(source, -1, HeadingRange::Headings(1..3), &Options::default()),
+///            "# Title\n\n# Usage\n\n## Details");
+/// assert_eq!(adjust_headings(source, 5, HeadingRange::Bytes(9..source.len()), &Options::default()),
+///            "# Title\n\n###### Usage\n\n###### Details");
+/// ```
+pub fn adjust_headings(source: &str, delta: i32, range: HeadingRange, options: &Options) -> String {
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.push_source_replacing(source, |source| {
+        let mut replacements = Vec::new();
+        // The position of the current event among the headings and among
+        // all events of the document.
+        let mut index = 0;
+        let mut position = 0;
+        // The adjusted levels of the headings that are currently open.
+        let mut levels = Vec::new();
+        let mut parser = Parser::new_ext(source, options.parser_options());
+        loop {
+            let start = parser.get_offset();
+            let event = match parser.next() {
+                Some(event) => event,
+                None => return replacements
+            };
+            let end = parser.get_offset();
+            let adjusted = match event {
+                Event::Start(Tag::Header(level)) => {
+                    let offset = start + source[start..end].len() - source[start..end].trim_start().len();
+                    let adjust = match range {
+                        HeadingRange::All => true,
+                        HeadingRange::Bytes(ref bytes) => bytes.contains(&offset),
+                        HeadingRange::Headings(ref headings) => headings.contains(&index)
+                    };
+                    index += 1;
+                    let adjusted = if adjust { level.saturating_add(delta).clamp(1, 6) } else { level };
+                    levels.push(adjusted);
+                    Some(Event::Start(Tag::Header(adjusted))).filter(|_| adjusted != level)
+                },
+                Event::End(Tag::Header(level)) => {
+                    let adjusted = levels.pop().unwrap_or(level);
+                    Some(Event::End(Tag::Header(adjusted))).filter(|_| adjusted != level)
+                },
+                _ => None
+            };
+            if let Some(event) = adjusted {
+                replacements.push(Replacement { range: position..position + 1, block: ReplacementBlock::Events(vec![event]) });
+            }
+            position += 1;
+        }
+    }).expect("failed to write to string");
+    printer.into_inner()
+}
+
+/// Parses a CommonMark document and returns the pretty printed section
+/// at a path of headings, or `None` if there is no such section.
+///
+/// The first title of the path names the first heading with that title,
+/// and each following title names the first deeper heading with that
+/// title within the section of the previous one. Headings nested within
+/// block quotes or lists are not considered. Titles are compared against
+/// the text of the headings with all markup removed, as in the titles of
+/// the sections returned by `split_by_heading`. The section includes its
+/// heading, and ends at the next heading of the same level or above. If
+/// `rebase` is set, the headings of the section are shifted so that it
+/// starts with a heading of level 1, to be used as a document on its own.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{extract_section, Options};
+/// let source = "# Guide\n\n## Installation\n\nRun __it__.\n\n### Linux\n\n## Usage\n\n# Installation";
+/// assert_eq!(extract_section(source, &["Guide", "Installation"], false, &Options::default()).as_deref(),
+///            Some("## Installation\n\nRun **it**.\n\n### Linux"));
+/// assert_eq!(extract_section(source, &["Guide", "Installation"], true, &Options::default()).as_deref(),
+///            Some("# Installation\n\nRun **it**.\n\n## Linux"));
+/// assert_eq!(extract_section(source, &["Guide", "Linux"], false, &Options::default()).as_deref(),
+///            Some("### Linux"));
+/// assert_eq!(extract_section(source, &["Usage", "Linux"], false, &Options::default()), None);
+/// ```
+pub fn extract_section(source: &str, path: &[&str], rebase: bool, options: &Options) -> Option<String> {
+    if path.is_empty() {
+        return None;
+    }
+    let headings = headings(source, options);
+
+    // Narrow down the headings to the section at each step of the path.
+    let mut section = &headings[..];
+    let mut level = 0;
+    for title in path {
+        let position = section.iter().position(|heading| heading.level > level && heading.title.trim() == title.trim())?;
+        level = section[position].level;
+        let rest = &section[position + 1..];
+        let end = rest.iter().position(|heading| heading.level <= level).unwrap_or(rest.len());
+        section = &section[position..position + 1 + end];
+    }
+    let start = section.first()?.offset;
+    let end = headings.iter()
+        .find(|heading| heading.offset > start && heading.level <= level)
+        .map_or(source.len(), |heading| heading.offset);
+    let delta = if rebase { 1 - level } else { 0 };
+    Some(print_section(source, start..end, delta, options))
+}
+
+/// A heading at the top level of a document.
+struct Heading {
+    /// The offset of the heading in the source.
+    offset: usize,
+    level: i32,
+    /// The text of the heading, with all markup removed.
+    title: String
+}
+
+/// Find the headings at the top level of a document.
+fn headings(source: &str, options: &Options) -> Vec<Heading> {
+    // front matter that is not parsed as Markdown holds no headings
+    let source = blank_front_matter(source, options);
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut depth = 0usize;
+    let mut in_heading = false;
+    let mut parser = Parser::new_ext(&source, options.parser_options());
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => return headings
+        };
+        match event {
+            Event::Start(Tag::Header(level)) if depth == 0 => {
+                let text = &source[start..parser.get_offset()];
+                let offset = start + text.len() - text.trim_start().len();
+                headings.push(Heading { offset, level, title: String::new() });
+                in_heading = true;
+            },
+            Event::End(Tag::Header(_)) if depth == 1 => in_heading = false,
+            ref event if in_heading => {
+                if let Some(heading) = headings.last_mut() {
+                    push_title(&mut heading.title, event);
+                }
+            },
+            _ => {}
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+}
+
+/// Find the title and the source range of each section of a document,
+/// when it is split at the headings of the given level or above.
+fn sections(source: &str, level: i32, options: &Options) -> Vec<(Option<String>, Range<usize>)> {
+    let mut sections: Vec<(Option<String>, Range<usize>)> = Vec::new();
+    for heading in headings(source, options).into_iter().filter(|heading| heading.level <= level) {
+        match sections.last_mut() {
+            Some(&mut (_, ref mut range)) => range.end = heading.offset,
+            None if !source[..heading.offset].trim().is_empty() => sections.push((None, 0..heading.offset)),
+            None => {}
+        }
+        sections.push((Some(heading.title), heading.offset..source.len()));
+    }
+    if sections.is_empty() && !source.trim().is_empty() {
+        sections.push((None, 0..source.len()));
+    }
+    sections
+}
+
+/// Pretty print a range of a document, with the levels of its headings
+/// shifted by `delta`.
+///
+/// The link reference definitions in the rest of the document are kept,
+/// so that the links in the range still resolve.
+fn print_section(source: &str, range: Range<usize>, delta: i32, options: &Options) -> String {
+    let rest = format!("{}\n{}", &source[..range.start], &source[range.end..]);
+    let section = with_definitions(&source[range], &rest);
+    if delta == 0 {
+        ::prettify_with_options(&section, options)
+    } else {
+        adjust_headings(&section, delta, HeadingRange::All, options)
+    }
+}
+
+/// Blank the front matter of a document, unless it is parsed as Markdown.
+fn blank_front_matter<'a>(source: &'a str, options: &Options) -> Cow<'a, str> {
+    match front_matter::find(source) {
+        Some(ref front_matter) if options.front_matter != FrontMatterPolicy::Markdown => {
+            Cow::Owned(front_matter::blank(source, front_matter))
+        },
+        _ => Cow::Borrowed(source)
+    }
+}
+
+/// Add the text of an event within a heading to its title, leaving out
+/// markup such as inline HTML.
+fn push_title(title: &mut String, event: &Event) {
+    match *event {
+        Event::Text(ref text) => title.push_str(text),
+        Event::SoftBreak | Event::HardBreak => title.push(' '),
+        _ => {}
+    }
+}
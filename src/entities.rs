@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+const NAMED_ENTITIES: &[(char, &str)] = &[
+    ('&', "amp"),
+    ('<', "lt"),
+    ('>', "gt"),
+    ('\u{a0}', "nbsp"),
+    ('\u{a9}', "copy"),
+    ('\u{ae}', "reg"),
+    ('\u{b0}', "deg"),
+    ('\u{b7}', "middot"),
+    ('\u{c4}', "Auml"),
+    ('\u{d6}', "Ouml"),
+    ('\u{dc}', "Uuml"),
+    ('\u{df}', "szlig"),
+    ('\u{e4}', "auml"),
+    ('\u{e9}', "eacute"),
+    ('\u{f6}', "ouml"),
+    ('\u{fc}', "uuml"),
+    ('\u{2013}', "ndash"),
+    ('\u{2014}', "mdash"),
+    ('\u{2018}', "lsquo"),
+    ('\u{2019}', "rsquo"),
+    ('\u{201c}', "ldquo"),
+    ('\u{201d}', "rdquo"),
+    ('\u{2026}', "hellip"),
+    ('\u{2122}', "trade")
+];
+
+/// Encode special and non-ASCII characters as entity references.
+///
+/// Well-known characters use their named entity, all others fall back
+/// to a numeric character reference. Non-breaking spaces are only
+/// encoded if `nbsp` is set.
+pub fn encode(text: &str, nbsp: bool) -> Cow<'_, str> {
+    let needs_encoding = |c: char| needs_encoding(c) && (nbsp || c != '\u{a0}');
+    if !text.chars().any(needs_encoding) {
+        return Cow::Borrowed(text);
+    }
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if !needs_encoding(c) {
+            output.push(c);
+            continue;
+        }
+        match NAMED_ENTITIES.iter().find(|&&(named, _)| named == c) {
+            Some(&(_, name)) => write!(output, "&{};", name).unwrap(),
+            None => write!(output, "&#{};", c as u32).unwrap()
+        }
+    }
+    Cow::Owned(output)
+}
+
+/// Check whether a slice of source text is a single entity reference.
+pub fn is_reference(source: &str) -> bool {
+    source.len() > 2 && source.starts_with('&') && source.ends_with(';') &&
+        !source[1..source.len() - 1].contains(|c: char| !(c == '#' || c.is_ascii_alphanumeric()))
+}
+
+fn needs_encoding(c: char) -> bool {
+    c == '&' || c == '<' || c == '>' || !c.is_ascii()
+}
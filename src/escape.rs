@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use pulldown_cmark::{Event, Tag};
+
+use options::{EscapeMethod, EscapeStyle};
+use printer::BufferedEvent;
+
+/// Escape characters in text that would otherwise be parsed as inline
+/// markup, such as emphasis, code spans, HTML tags or entity references.
+///
+/// Brackets are only escaped inside of link text, where `brackets` holds
+/// the decision for each bracket in turn (see `plan_link_text`). The
+/// characters `&` and `<` are left alone if `markup` is false, for text
+/// whose special characters are encoded as entity references anyway.
+pub fn escape_inline<'a>(text: Cow<'a, str>, markup: bool, mut brackets: Option<&mut VecDeque<bool>>) -> Cow<'a, str> {
+    let chars = text.char_indices().collect::<Vec<_>>();
+    let mut escapes = vec![];
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        let prev = if i > 0 { Some(chars[i - 1].1) } else { None };
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let escape = match c {
+            '\\' => next.is_none_or(|next| next.is_ascii_punctuation()),
+            '`' => true,
+            '*' => !(is_space(prev) && is_space(next)),
+            '_' => !(is_space(prev) && is_space(next) || is_alphanumeric(prev) && is_alphanumeric(next)),
+            '[' | ']' => brackets.as_mut().and_then(|brackets| brackets.pop_front()).unwrap_or(false),
+            '<' => markup && next.is_some_and(|next| next.is_ascii_alphabetic() || "/!?".contains(next)),
+            '&' => markup && is_entity_like(&text[pos + 1..]),
+            _ => false
+        };
+        if escape {
+            escapes.push(pos);
+        }
+    }
+    if escapes.is_empty() {
+        return text;
+    }
+    let mut escaped = String::with_capacity(text.len() + escapes.len());
+    let mut last = 0;
+    for pos in escapes {
+        escaped.push_str(&text[last..pos]);
+        escaped.push('\\');
+        last = pos;
+    }
+    escaped.push_str(&text[last..]);
+    Cow::Owned(escaped)
+}
+
+/// Replace the backslash escapes in text escaped by `escape_inline` with
+/// numeric character references, for the characters that the style
+/// escapes that way.
+///
+/// Every backslash that is followed by punctuation in escaped text starts
+/// an escape, because such backslashes are escaped themselves otherwise.
+pub fn apply_style<'a>(text: Cow<'a, str>, style: &EscapeStyle) -> Cow<'a, str> {
+    if *style == EscapeStyle::default() || !text.contains('\\') {
+        return text;
+    }
+    let mut styled = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&next) if c == '\\' && next.is_ascii_punctuation() => {
+                chars.next();
+                push_escaped(&mut styled, next, style.method(next));
+            },
+            _ => styled.push(c)
+        }
+    }
+    Cow::Owned(styled)
+}
+
+/// Write an escaped character using the given method.
+pub fn push_escaped(output: &mut String, c: char, method: EscapeMethod) {
+    match method {
+        EscapeMethod::Backslash => {
+            output.push('\\');
+            output.push(c);
+        },
+        EscapeMethod::Entity => {
+            output.push_str(&format!("&#{};", c as u32));
+        }
+    }
+}
+
+/// Decide for each bracket in the text of a link or image, in order,
+/// whether it has to be escaped so that it does not end the link text
+/// early or start a new link within it.
+///
+/// Only text that belongs to the link itself is considered. Code spans
+/// bind more tightly than link brackets and are left alone, and the text
+/// of nested images is planned separately when the image is printed.
+pub fn plan_link_text(buffer: &[BufferedEvent]) -> VecDeque<bool> {
+    let mut brackets = vec![];
+    let mut open = vec![];
+    let mut nesting = 0;
+    for (event, _) in buffer {
+        match *event {
+            Event::Start(Tag::Code) | Event::Start(Tag::Link(_, _)) | Event::Start(Tag::Image(_, _)) => nesting += 1,
+            Event::End(Tag::Code) | Event::End(Tag::Link(_, _)) | Event::End(Tag::Image(_, _)) => nesting -= 1,
+            Event::Text(ref text) if nesting == 0 => {
+                for c in text.chars() {
+                    match c {
+                        '[' => {
+                            open.push(brackets.len());
+                            brackets.push(true);
+                        },
+                        ']' => match open.pop() {
+                            Some(opening) => {
+                                brackets[opening] = false;
+                                brackets.push(false);
+                            },
+                            None => brackets.push(true)
+                        },
+                        _ => {}
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+    brackets.into()
+}
+
+/// Skip the decisions for the brackets in text that is not escaped.
+pub fn skip_brackets(brackets: &mut VecDeque<bool>, text: &str) {
+    let count = text.matches(['[', ']']).count();
+    brackets.drain(..count.min(brackets.len()));
+}
+
+/// Check whether a slice of source text is a single backslash escape.
+pub fn is_escape(source: &str) -> bool {
+    let mut chars = source.chars();
+    chars.next() == Some('\\') && chars.next().is_some_and(|c| c.is_ascii_punctuation()) && chars.next().is_none()
+}
+
+fn is_space(c: Option<char>) -> bool {
+    c.is_some_and(char::is_whitespace)
+}
+
+fn is_alphanumeric(c: Option<char>) -> bool {
+    c.is_some_and(char::is_alphanumeric)
+}
+
+/// Check whether text starts like the rest of an entity reference, such
+/// as `amp;` or `#123;`.
+fn is_entity_like(text: &str) -> bool {
+    let name = match text.find(';') {
+        Some(end) => &text[..end],
+        None => return false
+    };
+    if let Some(number) = name.strip_prefix('#') {
+        match number.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        }
+    } else {
+        name.starts_with(|c: char| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+}
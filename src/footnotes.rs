@@ -0,0 +1,310 @@
+use pulldown_cmark::{Event, Parser, Tag, OPTION_ENABLE_FOOTNOTES};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use diagnostics::{Diagnostic, Message};
+use hooks::Localizer;
+use options::{FootnoteOptions, FootnoteStyle};
+
+/// Footnote references and definitions found in a source document.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct Footnotes {
+    /// Whether the source document has been scanned.
+    scanned: bool,
+    /// Referenced labels in order of first reference, along with the
+    /// index of the section the first reference appears in.
+    referenced: Vec<(String, usize)>,
+    /// Replacement labels, if footnotes are renumbered.
+    labels: Vec<(String, String)>,
+    /// The offsets of definitions whose label has been defined before.
+    duplicates: Vec<usize>,
+    /// The labels of footnotes that are written inline, along with their
+    /// printed contents.
+    inline: Vec<(String, String)>
+}
+
+impl Footnotes {
+    /// Check whether a definition should be removed, because it is never
+    /// referenced.
+    pub fn is_unreferenced(&self, label: &str) -> bool {
+        self.scanned && !self.referenced.iter().any(|(referenced, _)| referenced == label)
+    }
+
+    /// Check whether the definition at an offset repeats the label of an
+    /// earlier definition.
+    pub fn is_duplicate(&self, offset: usize) -> bool {
+        self.duplicates.contains(&offset)
+    }
+
+    /// The label to print for a footnote.
+    pub fn label<'a>(&'a self, label: &'a str) -> &'a str {
+        self.labels.iter()
+            .find(|(original, _)| original == label)
+            .map_or(label, |(_, replacement)| replacement.as_str())
+    }
+
+    /// Check whether a footnote is written inline, in which case its
+    /// definition is removed.
+    pub fn is_inline(&self, label: &str) -> bool {
+        self.inline.iter().any(|(inline, _)| inline == label)
+    }
+
+    /// The printed contents of a footnote that is written inline.
+    pub fn inline(&self, label: &str) -> Option<&str> {
+        self.inline.iter().find(|(inline, _)| inline == label).map(|(_, text)| text.as_str())
+    }
+
+    /// Set the printed contents of a footnote that is written inline.
+    pub fn set_inline(&mut self, label: &str, text: String) {
+        if let Some(entry) = self.inline.iter_mut().find(|(inline, _)| inline == label) {
+            entry.1 = text;
+        }
+    }
+
+    /// The position of a footnote in order of first reference.
+    pub fn order(&self, label: &str) -> usize {
+        self.referenced.iter().position(|(referenced, _)| referenced == label).unwrap_or(usize::MAX)
+    }
+
+    /// The index of the section the footnote is first referenced in.
+    pub fn section(&self, label: &str) -> Option<usize> {
+        self.referenced.iter().find(|(referenced, _)| referenced == label).map(|&(_, section)| section)
+    }
+}
+
+/// A footnote definition found while scanning a document.
+struct Definition {
+    label: String,
+    /// The offset of the definition, without leading whitespace.
+    offset: usize,
+    /// The content, with runs of whitespace collapsed.
+    content: String,
+    /// The number of blocks the definition contains.
+    blocks: usize,
+    /// Whether the definition consists of a single paragraph without hard
+    /// line breaks.
+    simple: bool
+}
+
+/// Scan a source document for footnote references and definitions.
+///
+/// Definitions that repeat the label of an earlier definition with
+/// different content are reported as `duplicate-footnote` diagnostics.
+/// With `FootnoteStyle::Inline`, the footnotes that can be written inline
+/// are determined as well, but their contents are left to be printed.
+pub(crate) fn scan(source: &str, options: &FootnoteOptions, diagnostics: &mut Vec<Diagnostic>,
+                   localizer: Option<&dyn Localizer>) -> Footnotes {
+    let mut referenced: Vec<(String, usize)> = Vec::new();
+    // The number of references to each label, and whether any of them is
+    // within a footnote definition.
+    let mut references: Vec<(String, usize, bool)> = Vec::new();
+    let mut defined: Vec<Definition> = Vec::new();
+    // The index, content offset and depth of the open definitions.
+    let mut open: Vec<(usize, usize, usize)> = Vec::new();
+    let mut depth = 0usize;
+    let mut section = 0;
+    let mut parser = Parser::new_ext(source, OPTION_ENABLE_FOOTNOTES);
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let end = parser.get_offset();
+        if let Some(&(index, _, open_depth)) = open.last() {
+            let definition = &mut defined[index];
+            match event {
+                Event::Start(ref tag) if depth == open_depth + 1 => {
+                    definition.blocks += 1;
+                    definition.simple &= *tag == Tag::Paragraph;
+                },
+                Event::HardBreak => definition.simple = false,
+                _ => {}
+            }
+        }
+        match event {
+            Event::Start(Tag::Header(_)) if depth == 0 => section += 1,
+            Event::Start(Tag::FootnoteDefinition(ref label)) => {
+                let offset = start + source[start..end].len() - source[start..end].trim_start().len();
+                open.push((defined.len(), end, depth));
+                defined.push(Definition { label: label.to_string(), offset, content: String::new(), blocks: 0, simple: true });
+            },
+            Event::End(Tag::FootnoteDefinition(_)) => {
+                if let Some((index, content_start, _)) = open.pop() {
+                    defined[index].content = source[content_start..start].split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+                }
+            },
+            Event::FootnoteReference(ref label) => {
+                if !referenced.iter().any(|(referenced, _)| referenced == label) {
+                    referenced.push((label.to_string(), section));
+                    references.push((label.to_string(), 0, false));
+                }
+                if let Some(entry) = references.iter_mut().find(|(referenced, _, _)| referenced == label) {
+                    entry.1 += 1;
+                    entry.2 |= !open.is_empty();
+                }
+            },
+            _ => {}
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+
+    let mut duplicates = Vec::new();
+    for (index, definition) in defined.iter().enumerate() {
+        if let Some(first) = defined[..index].iter().find(|other| other.label == definition.label) {
+            duplicates.push(definition.offset);
+            if first.content != definition.content {
+                diagnostics.push(Diagnostic::warning(
+                    Message::DuplicateFootnote { label: &definition.label },
+                    localizer,
+                    Some(definition.offset)));
+            }
+        }
+    }
+
+    let mut inline = Vec::new();
+    if options.style == FootnoteStyle::Inline {
+        for definition in &defined {
+            let once = references.iter().any(|(label, count, nested)| *label == definition.label && *count == 1 && !nested);
+            let unique = defined.iter().filter(|other| other.label == definition.label).count() == 1;
+            if once && unique && definition.blocks == 1 && definition.simple && balanced_brackets(&definition.content) {
+                inline.push((definition.label.clone(), String::new()));
+            }
+        }
+    }
+
+    let mut labels = Vec::new();
+    if options.renumber {
+        let mut unreferenced = Vec::new();
+        for definition in &defined {
+            let label = &definition.label;
+            if !referenced.iter().any(|(referenced, _)| referenced == label) && !unreferenced.contains(&label) {
+                unreferenced.push(label);
+            }
+        }
+        let all = referenced.iter().map(|(label, _)| label).chain(unreferenced)
+            .filter(|label| !inline.iter().any(|(inline, _)| inline == *label));
+        for (number, label) in all.enumerate() {
+            labels.push((label.clone(), (number + 1).to_string()));
+        }
+    }
+    Footnotes { scanned: true, referenced, labels, duplicates, inline }
+}
+
+/// Check whether the brackets in text that are not escaped by a backslash
+/// are balanced.
+fn balanced_brackets(text: &str) -> bool {
+    let mut depth = 0usize;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            },
+            '[' => depth += 1,
+            ']' if depth == 0 => return false,
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    depth == 0
+}
+
+/// Convert the inline footnotes (`^[text]`) of a source document into
+/// references to numbered definitions, which are added at the end of the
+/// document. Returns `None` if the document has no inline footnotes.
+///
+/// Inline footnotes are looked for in text outside of code and HTML. The
+/// numbers start after the highest number used as a label already.
+pub(crate) fn expand_inline(source: &str) -> Option<String> {
+    // The ranges of text in which inline footnotes may start, and the
+    // labels that are in use.
+    let mut text = Vec::new();
+    let mut labels = Vec::new();
+    let mut code = 0usize;
+    let mut parser = Parser::new_ext(source, OPTION_ENABLE_FOOTNOTES);
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let end = parser.get_offset();
+        match event {
+            Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => code += 1,
+            Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => code -= 1,
+            Event::Text(_) if code == 0 => text.push(start..end),
+            Event::FootnoteReference(label) | Event::Start(Tag::FootnoteDefinition(label)) => labels.push(label),
+            _ => {}
+        }
+    }
+
+    let mut notes = Vec::new();
+    let mut position = 0;
+    for range in text {
+        for (index, _) in source[range.clone()].match_indices('^') {
+            let caret = range.start + index;
+            if caret < position || !source[caret + 1..].starts_with('[') {
+                continue;
+            }
+            if source[..caret].chars().rev().take_while(|&c| c == '\\').count() % 2 == 1 {
+                continue;
+            }
+            if let Some(length) = closing_bracket(&source[caret + 2..]) {
+                position = caret + 2 + length + 1;
+                notes.push(caret..position);
+            }
+        }
+    }
+    if notes.is_empty() {
+        return None;
+    }
+
+    let mut number = labels.iter().filter_map(|label| label.parse::<usize>().ok()).max().unwrap_or(0);
+    let mut expanded = String::with_capacity(source.len());
+    let mut definitions = String::new();
+    let mut last = 0;
+    for note in notes {
+        number += 1;
+        expanded.push_str(&source[last..note.start]);
+        expanded.push_str(&format!("[^{}]", number));
+        let content = source[note.start + 2..note.end - 1].split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+        definitions.push_str(&format!("\n\n[^{}]: {}", number, content));
+        last = note.end;
+    }
+    expanded.push_str(&source[last..]);
+    Some(expanded.trim_end().to_string() + &definitions)
+}
+
+/// Find the length of the text before the bracket that closes an inline
+/// footnote, skipping nested brackets and backslash escapes. The footnote
+/// may not contain a blank line.
+fn closing_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut escaped = false;
+    let mut newline = false;
+    for (index, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '[' => depth += 1,
+            ']' if depth == 0 => return Some(index),
+            ']' => depth -= 1,
+            '\n' if newline => return None,
+            '\n' => {
+                newline = true;
+                continue;
+            },
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            newline = false;
+        }
+    }
+    None
+}
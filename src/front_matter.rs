@@ -0,0 +1,88 @@
+#[cfg(feature = "yaml")]
+use serde_yaml::{self, Value};
+
+/// Front matter at the start of a document.
+#[cfg_attr(not(feature = "yaml"), allow(dead_code))]
+pub struct FrontMatter<'a> {
+    /// The source of the front matter, including its delimiters.
+    pub source: &'a str,
+    /// The contents between the delimiters.
+    pub content: &'a str,
+    /// The offset of the contents in the document.
+    pub offset: usize,
+    /// Whether the front matter is YAML, between `---` lines, rather than
+    /// TOML, between `+++` lines.
+    pub yaml: bool
+}
+
+/// Find the front matter at the start of a document, which is YAML
+/// between `---` lines (closed by `---` or `...`) or TOML between `+++`
+/// lines.
+pub fn find(source: &str) -> Option<FrontMatter<'_>> {
+    let mut lines = source.split_inclusive('\n');
+    let opening = lines.next()?;
+    let yaml = match opening.trim_end() {
+        "---" => true,
+        "+++" => false,
+        _ => return None
+    };
+    let offset = opening.len();
+    let mut end = offset;
+    for line in lines {
+        let closes = match line.trim_end() {
+            "---" | "..." => yaml,
+            "+++" => !yaml,
+            _ => false
+        };
+        if closes {
+            let source = &source[..end + line.trim_end().len()];
+            return Some(FrontMatter { source, content: &source[offset..end], offset, yaml });
+        }
+        end += line.len();
+    }
+    None
+}
+
+/// Replace the front matter of a document by spaces, keeping its line
+/// breaks, so that the rest of the document is parsed at the same
+/// offsets.
+pub fn blank(source: &str, front_matter: &FrontMatter) -> String {
+    let length = front_matter.source.len();
+    let blanked = front_matter.source.split('\n').map(|line| " ".repeat(line.len())).collect::<Vec<_>>();
+    format!("{}{}", blanked.join("\n"), &source[length..])
+}
+
+/// Print YAML front matter with the keys of mappings sorted, and with
+/// canonical quoting of strings.
+///
+/// Comments in the front matter are removed.
+#[cfg(feature = "yaml")]
+pub fn canonicalize(front_matter: &FrontMatter) -> Result<String, serde_yaml::Error> {
+    if front_matter.content.trim().is_empty() {
+        return Ok(front_matter.source.to_string());
+    }
+    let value = serde_yaml::from_str::<Value>(front_matter.content)?;
+    let yaml = serde_yaml::to_string(&sorted(value))?;
+    Ok(format!("---\n{}\n---", yaml.trim_end()))
+}
+
+/// Sort the keys of all mappings within a value.
+#[cfg(feature = "yaml")]
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Mapping(mapping) => {
+            let mut entries = mapping.into_iter().map(|(key, value)| (key, sorted(value))).collect::<Vec<_>>();
+            entries.sort_by_cached_key(|(key, _)| match key.as_str() {
+                Some(key) => key.to_string(),
+                None => serde_yaml::to_string(key).unwrap_or_default()
+            });
+            Value::Mapping(entries.into_iter().collect())
+        },
+        Value::Sequence(items) => Value::Sequence(items.into_iter().map(sorted).collect()),
+        Value::Tagged(mut tagged) => {
+            tagged.value = sorted(tagged.value);
+            Value::Tagged(tagged)
+        },
+        value => value
+    }
+}
@@ -0,0 +1,208 @@
+use pulldown_cmark::Tag;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use diagnostics::Message;
+
+/// Kinds of blocks that can enclose a run of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Block {
+    Paragraph,
+    /// A heading with the given level.
+    Heading(i32),
+    BlockQuote,
+    /// A list, with the number of the first item if it is ordered.
+    List(Option<usize>),
+    Item,
+    FootnoteDefinition,
+    Table,
+    TableCell
+}
+
+impl Block {
+    pub(crate) fn from_tag(tag: &Tag) -> Option<Block> {
+        match *tag {
+            Tag::Paragraph => Some(Block::Paragraph),
+            Tag::Header(level) => Some(Block::Heading(level)),
+            Tag::BlockQuote => Some(Block::BlockQuote),
+            Tag::List(start) => Some(Block::List(start)),
+            Tag::Item => Some(Block::Item),
+            Tag::FootnoteDefinition(_) => Some(Block::FootnoteDefinition),
+            Tag::Table(_) => Some(Block::Table),
+            Tag::TableCell => Some(Block::TableCell),
+            _ => None
+        }
+    }
+}
+
+/// A contiguous run of prose text, as seen by the printer.
+///
+/// Text is split into runs at inline markup (such as emphasis or code
+/// spans). Soft breaks within a run are represented as `\n`.
+#[derive(Debug)]
+pub struct TextRun<'a> {
+    /// The text content of the run, with entity references decoded.
+    pub text: &'a str,
+    /// The byte offset of the run in the source document, if known.
+    ///
+    /// Offsets are only available when the printer is driven via
+    /// `PrettyPrinter::push_source`.
+    pub offset: Option<usize>,
+    /// The blocks enclosing the run, outermost first.
+    pub blocks: &'a [Block],
+    /// The headings of the section the run is in, outermost first, as
+    /// returned by `PrettyPrinter::heading_path`.
+    pub headings: &'a [&'a str]
+}
+
+/// Hook that receives every run of prose text that is printed.
+///
+/// This allows external tools such as spell checkers or prose linters
+/// to piggyback on the printer instead of parsing the document again.
+///
+/// The trait is implemented for all closures that take a `&TextRun`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+/// use prettify_cmark::PrettyPrinter;
+///
+/// let runs = Rc::new(RefCell::new(Vec::new()));
+/// let mut printer = PrettyPrinter::default();
+/// let collected = runs.clone();
+/// printer.set_text_inspector(move |run: &prettify_cmark::TextRun| {
+///     collected.borrow_mut().push((run.text.to_string(), run.offset));
+/// });
+/// printer.push_source("Lorem *ipsum* dolor").unwrap();
+///
+/// assert_eq!(*runs.borrow(), vec![
+///     ("Lorem ".to_string(), Some(0)),
+///     ("ipsum".to_string(), Some(7)),
+///     (" dolor".to_string(), Some(13))
+/// ]);
+/// ```
+pub trait TextInspector {
+    /// Inspect a run of text.
+    fn inspect(&mut self, run: &TextRun);
+}
+
+impl<F: FnMut(&TextRun)> TextInspector for F {
+    fn inspect(&mut self, run: &TextRun) {
+        self(run)
+    }
+}
+
+/// Hook that supplies alt text for images that have none.
+///
+/// The trait is implemented for all closures that take the destination
+/// and title of an image.
+///
+/// # Examples
+///
+/// ```rust
+/// use prettify_cmark::PrettyPrinter;
+///
+/// let mut printer = PrettyPrinter::default();
+/// printer.set_alt_text_provider(|url: &str, _title: &str| {
+///     url.rsplit('/').next().map(|name| format!("Image: {}", name))
+/// });
+/// printer.push_source("![](img/logo.png)").unwrap();
+///
+/// assert_eq!(printer.into_inner(), "![Image: logo.png](img/logo.png)");
+/// ```
+pub trait AltTextProvider {
+    /// Generate alt text for an image, given its destination and title.
+    ///
+    /// Returning `None` leaves the alt text empty.
+    fn alt_text(&mut self, url: &str, title: &str) -> Option<String>;
+}
+
+impl<F: FnMut(&str, &str) -> Option<String>> AltTextProvider for F {
+    fn alt_text(&mut self, url: &str, title: &str) -> Option<String> {
+        self(url, title)
+    }
+}
+
+/// Hook that supplies the text generated by the printer, such as the
+/// messages of diagnostics, so that it can be translated or reworded.
+///
+/// The trait is implemented for all closures that take a `&Message`.
+///
+/// # Examples
+///
+/// ```rust
+/// use prettify_cmark::{Message, Options, PrettyPrinter};
+///
+/// let options = Options { max_list_depth: Some(1), ..Options::default() };
+/// let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+/// printer.set_localizer(|message: &Message| match *message {
+///     Message::ListDepth { max_depth } => Some(format!("Liste tiefer als {} Ebenen verschachtelt", max_depth)),
+///     _ => None
+/// });
+/// printer.push_source("- Lorem\n  - ipsum").unwrap();
+///
+/// assert_eq!(printer.diagnostics()[0].rule, "list-depth");
+/// assert_eq!(printer.diagnostics()[0].message, "Liste tiefer als 1 Ebenen verschachtelt");
+/// ```
+pub trait Localizer {
+    /// Supply the text for a message.
+    ///
+    /// Returning `None` keeps the English text of the message.
+    fn localize(&self, message: &Message) -> Option<String>;
+}
+
+impl<F: Fn(&Message) -> Option<String>> Localizer for F {
+    fn localize(&self, message: &Message) -> Option<String> {
+        self(message)
+    }
+}
+
+/// Formatter for the contents of fenced code blocks.
+///
+/// This allows plugging in language-specific formatters (such as `rustfmt`
+/// or `prettier`) that will be applied to embedded code while the document
+/// is being pretty printed.
+///
+/// # Examples
+///
+/// ```rust
+/// use prettify_cmark::{CodeBlockFormatter, PrettyPrinter};
+///
+/// struct Uppercase;
+///
+/// impl CodeBlockFormatter for Uppercase {
+///     fn format(&self, lang: &str, code: &str) -> Option<String> {
+///         if lang == "sql" {
+///             Some(code.to_uppercase())
+///         } else {
+///             None
+///         }
+///     }
+/// }
+///
+/// let mut printer = PrettyPrinter::default();
+/// printer.set_code_block_formatter(Uppercase);
+/// printer.push_source("```sql\nselect 1;\n```").unwrap();
+///
+/// assert_eq!(printer.into_inner(), "```sql\nSELECT 1;\n```");
+/// ```
+pub trait CodeBlockFormatter {
+    /// Format a code block, given the language from its info string.
+    ///
+    /// Returning `None` leaves the code block unchanged.
+    fn format(&self, lang: &str, code: &str) -> Option<String>;
+}
+
+/// Code block formatter that leaves all code blocks unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopFormatter;
+
+impl CodeBlockFormatter for NoopFormatter {
+    fn format(&self, _lang: &str, _code: &str) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,275 @@
+use options::HtmlPolicy;
+
+/// Elements whose content is removed along with the element itself.
+const SCRIPT_ELEMENTS: &[&str] = &["script", "style"];
+
+/// Attributes that may contain URLs.
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "action", "formaction", "xlink:href"];
+
+/// Schemes that URLs in attributes may have. Relative URLs have none.
+const URL_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// Named character references that can hide the scheme of a URL.
+const URL_REFERENCES: &[(&str, char)] = &[("colon", ':'), ("tab", '\t'), ("newline", '\n')];
+
+/// Sanitize a piece of HTML according to a policy.
+///
+/// `skipping` holds the name of a script element whose content is being
+/// removed, which can span multiple pieces of inline HTML.
+pub fn sanitize(html: &str, policy: &HtmlPolicy, skipping: &mut Option<String>) -> String {
+    let mut output = String::with_capacity(html.len());
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (token, remainder) = next_token(rest);
+        rest = remainder;
+        let kept = match parse_tag(token) {
+            Some(tag) => sanitize_tag(tag, policy, skipping, &mut output),
+            None => {
+                if skipping.is_none() {
+                    output.push_str(token);
+                }
+                skipping.is_none()
+            }
+        };
+        // Avoid leaving blank lines where whole lines have been removed.
+        if !kept && (output.is_empty() || output.ends_with('\n')) {
+            rest = rest.strip_prefix('\n').unwrap_or(rest);
+        }
+    }
+    output
+}
+
+/// Write a tag to the output if it is allowed, returning whether it was
+/// kept.
+fn sanitize_tag(tag: Tag, policy: &HtmlPolicy, skipping: &mut Option<String>, output: &mut String) -> bool {
+    if let Some(ref name) = *skipping {
+        if tag.closing && tag.name.eq_ignore_ascii_case(name) {
+            *skipping = None;
+        }
+        return false;
+    }
+    let name = tag.name.to_ascii_lowercase();
+    if SCRIPT_ELEMENTS.contains(&name.as_str()) {
+        if !tag.closing && !tag.self_closing {
+            *skipping = Some(name);
+        }
+        return false;
+    }
+    if !is_allowed_tag(&name, policy) {
+        return false;
+    }
+    output.push('<');
+    if tag.closing {
+        output.push('/');
+    }
+    output.push_str(tag.name);
+    for (attribute, value) in tag.attributes {
+        if is_allowed_attribute(attribute, value, policy) {
+            output.push(' ');
+            output.push_str(attribute);
+            if let Some(value) = value {
+                output.push('=');
+                output.push_str(value);
+            }
+        }
+    }
+    if tag.self_closing {
+        output.push_str(" /");
+    }
+    output.push('>');
+    true
+}
+
+/// Escape text for use as the value of an attribute in double quotes.
+pub fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
+
+/// Check whether a piece of inline HTML is a single `<br>` element, and
+/// if so, whether it has any attributes.
+pub fn line_break(html: &str) -> Option<bool> {
+    let (token, rest) = next_token(html);
+    match parse_tag(token) {
+        Some(tag) if rest.is_empty() && !tag.closing && tag.name.eq_ignore_ascii_case("br") => {
+            Some(!tag.attributes.is_empty())
+        },
+        _ => None
+    }
+}
+
+/// The values of the `id` and `name` attributes of the tags in a piece
+/// of HTML, which links can point to as anchors.
+pub fn anchors(html: &str) -> Vec<String> {
+    let mut anchors = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let (token, remainder) = next_token(rest);
+        rest = remainder;
+        let tag = match parse_tag(token) {
+            Some(tag) => tag,
+            None => continue
+        };
+        for (attribute, value) in tag.attributes {
+            if attribute.eq_ignore_ascii_case("id") || attribute.eq_ignore_ascii_case("name") {
+                anchors.extend(value.map(|value| value.trim_matches(['"', '\'']).to_string()));
+            }
+        }
+    }
+    anchors
+}
+
+/// Split off the next tag, comment or run of text.
+fn next_token(html: &str) -> (&str, &str) {
+    if let Some(comment) = html.strip_prefix("<!--") {
+        let end = comment.find("-->").map_or(html.len(), |end| end + 7);
+        return html.split_at(end);
+    }
+    if html.starts_with('<') {
+        let mut quote = None;
+        for (index, c) in html.char_indices().skip(1) {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {},
+                (None, '"') | (None, '\'') => quote = Some(c),
+                (None, '>') => return html.split_at(index + 1),
+                (None, '<') => return html.split_at(index),
+                _ => {}
+            }
+        }
+        return (html, "");
+    }
+    let end = html[1..].find('<').map_or(html.len(), |end| end + 1);
+    html.split_at(end)
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    closing: bool,
+    self_closing: bool,
+    attributes: Vec<(&'a str, Option<&'a str>)>
+}
+
+/// Parse an opening or closing tag, such as `<a href="x">` or `</a>`.
+fn parse_tag(token: &str) -> Option<Tag<'_>> {
+    let inner = token.strip_prefix('<')?.strip_suffix('>')?;
+    let (closing, inner) = match inner.strip_prefix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner)
+    };
+    let (self_closing, inner) = match inner.strip_suffix('/') {
+        Some(inner) => (true, inner),
+        None => (false, inner)
+    };
+    let name_end = inner.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-')).unwrap_or(inner.len());
+    if name_end == 0 || !inner.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = &inner[..name_end];
+    let mut attributes = Vec::new();
+    // Browsers separate attributes by slashes as well as by whitespace.
+    let separator = |c: char| c == '/' || c.is_whitespace();
+    let mut rest = inner[name_end..].trim_start_matches(separator);
+    while !rest.is_empty() {
+        let end = rest.find(|c: char| c == '=' || separator(c)).unwrap_or(rest.len());
+        let attribute = &rest[..end];
+        rest = rest[end..].trim_start();
+        let mut value = None;
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let len = match after.chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    after[1..].find(quote).map_or(after.len(), |end| end + 2)
+                },
+                _ => after.find(char::is_whitespace).unwrap_or(after.len())
+            };
+            value = Some(&after[..len]);
+            rest = &after[len..];
+        }
+        rest = rest.trim_start_matches(separator);
+        if attribute.is_empty() {
+            break;
+        }
+        attributes.push((attribute, value));
+    }
+    Some(Tag { name, closing, self_closing, attributes })
+}
+
+fn is_allowed_tag(name: &str, policy: &HtmlPolicy) -> bool {
+    match *policy {
+        HtmlPolicy::Allowlist { ref tags, .. } => tags.iter().any(|tag| tag.eq_ignore_ascii_case(name)),
+        _ => true
+    }
+}
+
+fn is_allowed_attribute(attribute: &str, value: Option<&str>, policy: &HtmlPolicy) -> bool {
+    let attribute = attribute.to_ascii_lowercase();
+    if attribute.starts_with("on") {
+        return false;
+    }
+    if URL_ATTRIBUTES.contains(&attribute.as_str()) && !is_allowed_url(value.unwrap_or("")) {
+        return false;
+    }
+    match *policy {
+        HtmlPolicy::Allowlist { ref attributes, .. } => {
+            attributes.iter().any(|allowed| allowed.eq_ignore_ascii_case(&attribute))
+        },
+        _ => true
+    }
+}
+
+/// Check whether the value of an attribute is a relative URL or has an
+/// allowed scheme, the way browsers read it: after decoding character
+/// references and removing whitespace and control characters.
+fn is_allowed_url(value: &str) -> bool {
+    let url = decode_references(value.trim_matches(['"', '\'']));
+    let url = url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control()).collect::<String>();
+    match url.find([':', '/', '?', '#']) {
+        Some(end) if url[end..].starts_with(':') => {
+            URL_SCHEMES.iter().any(|scheme| scheme.eq_ignore_ascii_case(&url[..end]))
+        },
+        _ => true
+    }
+}
+
+/// Decode numeric character references, as well as the named ones that
+/// can hide the scheme of a URL.
+fn decode_references(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let (decoded, len) = match rest[1..].strip_prefix('#') {
+            Some(number) => {
+                let (digits, radix, prefix) = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => (hex, 16, 3),
+                    None => (number, 10, 2)
+                };
+                let len = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+                let decoded = u32::from_str_radix(&digits[..len], radix).ok()
+                    .map(|code| char::from_u32(code).unwrap_or('\u{fffd}'));
+                let semicolon = digits[len..].starts_with(';') as usize;
+                (decoded, prefix + len + semicolon)
+            },
+            None => {
+                let name = rest[1..].find(';').map_or("", |end| &rest[1..end + 1]);
+                let decoded = URL_REFERENCES.iter()
+                    .find(|&&(reference, _)| reference.eq_ignore_ascii_case(name))
+                    .map(|&(_, c)| c);
+                (decoded, name.len() + 2)
+            }
+        };
+        match decoded {
+            Some(c) => {
+                output.push(c);
+                rest = &rest[len..];
+            },
+            None => {
+                output.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
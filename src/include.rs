@@ -0,0 +1,201 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use options::Options;
+
+/// Loader for the files referenced by include directives.
+pub trait IncludeLoader {
+    /// Load the contents of the file at the given path.
+    fn load(&self, path: &Path) -> io::Result<String>;
+}
+
+/// Include loader that reads files from the file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileLoader;
+
+impl IncludeLoader for FileLoader {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Error that occurred while expanding include directives.
+#[derive(Debug)]
+pub enum IncludeError {
+    /// An included file could not be loaded.
+    Io(PathBuf, io::Error),
+    /// A file includes itself, directly or indirectly. The chain of
+    /// includes that forms the cycle is attached.
+    Cycle(Vec<PathBuf>),
+    /// Includes are nested more than 32 levels deep. The chain of
+    /// includes is attached.
+    TooDeep(Vec<PathBuf>)
+}
+
+/// The maximum number of nested includes.
+const MAX_DEPTH: usize = 32;
+
+impl Display for IncludeError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            IncludeError::Io(ref path, ref err) => {
+                write!(fmt, "failed to include {}: {}", path.display(), err)
+            },
+            IncludeError::Cycle(ref chain) => {
+                let chain = chain.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+                write!(fmt, "include cycle: {}", chain.join(" -> "))
+            },
+            IncludeError::TooDeep(ref chain) => {
+                let chain = chain.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+                write!(fmt, "includes nested more than {} levels deep: {}", MAX_DEPTH, chain.join(" -> "))
+            }
+        }
+    }
+}
+
+impl Error for IncludeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            IncludeError::Io(_, ref err) => Some(err),
+            IncludeError::Cycle(_) | IncludeError::TooDeep(_) => None
+        }
+    }
+}
+
+/// Expands include directives in a CommonMark document.
+///
+/// Two kinds of directives are recognized, each of which has to be on
+/// a line of its own:
+///
+/// - HTML comments such as `<!-- include: path/to/file.md -->`
+/// - mdBook-style includes such as `{{#include path/to/file.md}}`
+///
+/// Paths are resolved relative to the directory of the including file,
+/// which is `path` for the top-level document, and `.` and `..`
+/// components are removed from them. Any indentation or block quote
+/// markers in front of a directive are repeated on every line of the
+/// included content. Included files are expanded recursively, up to 32
+/// levels deep. Directives inside fenced code blocks are
+/// left as they are.
+///
+/// Without expansion, directives are preserved verbatim by the printer.
+pub fn expand_includes<L: IncludeLoader>(source: &str, path: &Path, loader: &L) -> Result<String, IncludeError> {
+    let mut stack = vec![normalize(path)];
+    expand(source, &mut stack, loader)
+}
+
+/// Parses a CommonMark document, expands its include directives using
+/// the file system, and returns it as a pretty printed string.
+pub fn prettify_with_includes(source: &str, path: &Path, options: &Options) -> Result<String, IncludeError> {
+    let expanded = expand_includes(source, path, &FileLoader)?;
+    Ok(::prettify_with_options(&expanded, options))
+}
+
+fn expand<L: IncludeLoader>(source: &str, stack: &mut Vec<PathBuf>, loader: &L) -> Result<String, IncludeError> {
+    let mut output = String::with_capacity(source.len());
+    let mut fence = None;
+    for line in source.split_inclusive('\n') {
+        let directive = match fence {
+            None => directive(line),
+            Some(_) => None
+        };
+        update_fence(&mut fence, line);
+        let (prefix, target) = match directive {
+            Some(directive) => directive,
+            None => {
+                output.push_str(line);
+                continue;
+            }
+        };
+
+        let base = stack.last().and_then(|path| path.parent()).unwrap_or_else(|| Path::new(""));
+        let path = normalize(&base.join(target));
+        if stack.contains(&path) {
+            let mut chain = stack.clone();
+            chain.push(path);
+            return Err(IncludeError::Cycle(chain));
+        }
+        if stack.len() > MAX_DEPTH {
+            let mut chain = stack.clone();
+            chain.push(path);
+            return Err(IncludeError::TooDeep(chain));
+        }
+        let included = loader.load(&path).map_err(|err| IncludeError::Io(path.clone(), err))?;
+        stack.push(path);
+        let included = expand(&included, stack, loader)?;
+        stack.pop();
+
+        for included_line in included.lines() {
+            output.push_str(prefix);
+            output.push_str(included_line);
+            output.push('\n');
+        }
+        if !line.ends_with('\n') && output.ends_with('\n') {
+            output.pop();
+        }
+    }
+    Ok(output)
+}
+
+/// Recognize an include directive, returning the prefix in front of it
+/// and the path of the included file.
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let content = line.trim_start_matches(['>', ' ', '\t']);
+    let prefix = &line[..line.len() - content.len()];
+    let content = content.trim_end();
+    let target = if content.starts_with("<!--") && content.ends_with("-->") {
+        content[4..content.len() - 3].trim().strip_prefix("include:")?
+    } else if content.starts_with("{{#include") && content.ends_with("}}") {
+        content["{{#include".len()..content.len() - 2].strip_prefix(char::is_whitespace)?
+    } else {
+        return None;
+    };
+    let target = target.trim();
+    if target.is_empty() {
+        None
+    } else {
+        Some((prefix, target))
+    }
+}
+
+/// Track whether a line opens or closes a fenced code block, given the
+/// marker character and length of the open fence, if any.
+fn update_fence(fence: &mut Option<(char, usize)>, line: &str) {
+    let content = line.trim_start_matches(['>', ' ', '\t']).trim_end();
+    let marker = match content.chars().next() {
+        Some(marker) if marker == '`' || marker == '~' => marker,
+        _ => return
+    };
+    let len = content.chars().take_while(|&c| c == marker).count();
+    if len < 3 {
+        return;
+    }
+    match *fence {
+        None => *fence = Some((marker, len)),
+        Some((open, open_len)) if open == marker && len >= open_len && content[len..].trim().is_empty() => *fence = None,
+        Some(_) => {}
+    }
+}
+
+/// Remove `.` and `..` components from a path, without accessing the
+/// file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                },
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component.as_os_str())
+        }
+    }
+    normalized
+}
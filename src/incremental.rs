@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use minimal::{parse_blocks, ParsedBlock};
+use options::{FrontMatterPolicy, Options};
+use printer::PrettyPrinter;
+
+/// A replacement of a range of text, given in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// The range of text that is replaced.
+    pub range: Range<usize>,
+    /// The text that the range is replaced with.
+    pub replacement: String
+}
+
+/// Pretty printer that keeps a document and its pretty printed output
+/// up to date as the document is edited, reformatting only the top-level
+/// blocks that are affected by each edit.
+///
+/// This is meant for editor integrations, where a large document should
+/// be kept formatted while it is being typed. The whole document is
+/// reformatted instead if an edit may affect blocks elsewhere, such as
+/// when it touches a link reference definition, and if reference links,
+/// footnotes, tables, `Options::normalize_structure`,
+/// `Options::sort_lists`, `Options::admonitions`, `Options::front_matter`,
+/// `Options::shortcodes`, `Options::profiles`, `Options::heading_limit`,
+/// `Options::heading_hierarchy` or `Options::duplicate_anchors` are
+/// enabled, or if the document contains directives that override
+/// options.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{IncrementalFormatter, Options, TextEdit};
+/// let mut formatter = IncrementalFormatter::new("# One\n\nLorem __ipsum__\n\n* dolor", Options::default());
+/// assert_eq!(formatter.output(), "# One\n\nLorem **ipsum**\n\n- dolor");
+///
+/// let edit = formatter.apply(TextEdit { range: 7..12, replacement: "_Sit_".to_string() });
+/// assert_eq!(formatter.source(), "# One\n\n_Sit_ __ipsum__\n\n* dolor");
+/// assert_eq!(formatter.output(), "# One\n\n*Sit* **ipsum**\n\n- dolor");
+/// assert_eq!(edit, TextEdit { range: 5..24, replacement: "\n\n*Sit* **ipsum**\n\n".to_string() });
+/// ```
+#[derive(Debug, Clone)]
+pub struct IncrementalFormatter {
+    options: Options,
+    source: String,
+    output: String,
+    blocks: Vec<FormattedBlock>
+}
+
+/// The ranges of a top-level block in the source and in the output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FormattedBlock {
+    source: Range<usize>,
+    output: Range<usize>
+}
+
+impl IncrementalFormatter {
+    /// Pretty print a document, keeping the state required to reformat
+    /// it incrementally.
+    pub fn new(source: &str, options: Options) -> IncrementalFormatter {
+        let mut formatter = IncrementalFormatter {
+            options,
+            source: String::new(),
+            output: String::new(),
+            blocks: Vec::new()
+        };
+        formatter.apply(TextEdit { range: 0..0, replacement: source.to_string() });
+        formatter
+    }
+
+    /// The current source document.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The pretty printed output for the current source document.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// Apply an edit to the source document, and return the edit that
+    /// was applied to the output as a result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of bounds, or does not lie
+    /// on character boundaries of the source.
+    pub fn apply(&mut self, edit: TextEdit) -> TextEdit {
+        let mut source = String::with_capacity(self.source.len() + edit.replacement.len());
+        source.push_str(&self.source[..edit.range.start]);
+        source.push_str(&edit.replacement);
+        source.push_str(&self.source[edit.range.end..]);
+        let inserted = edit.range.start..edit.range.start + edit.replacement.len();
+
+        if self.blocks.is_empty() || !self.is_local(&edit, &source, &inserted) {
+            return self.format_all(source);
+        }
+        let blocks = parse_blocks(&source, &self.options);
+        let (prefix, suffix) = self.unchanged_blocks(&blocks, &edit, &inserted);
+        let replaced_start = if prefix > 0 { self.blocks[prefix - 1].output.end } else { 0 };
+        let replaced_end = if suffix > 0 { self.blocks[self.blocks.len() - suffix].output.start } else { self.output.len() };
+
+        // Blocks are separated by a blank line, including the unchanged
+        // blocks on either side of the reformatted ones.
+        let mut replacement = String::new();
+        let mut formatted = Vec::new();
+        let mut separate = prefix > 0;
+        for block in &blocks[prefix..blocks.len() - suffix] {
+            let output = self.format_block(&source, block);
+            if output.is_empty() {
+                continue;
+            }
+            if separate {
+                replacement.push_str("\n\n");
+            }
+            let start = replaced_start + replacement.len();
+            replacement.push_str(&output);
+            formatted.push(FormattedBlock { source: block.range.clone(), output: start..replaced_start + replacement.len() });
+            separate = true;
+        }
+        if separate && suffix > 0 {
+            replacement.push_str("\n\n");
+        }
+
+        let source_shift = |offset: usize| offset + edit.replacement.len() - edit.range.len();
+        let output_shift = |offset: usize| offset + replaced_start + replacement.len() - replaced_end;
+        let suffix_blocks = self.blocks.split_off(self.blocks.len() - suffix);
+        self.blocks.truncate(prefix);
+        self.blocks.extend(formatted);
+        self.blocks.extend(suffix_blocks.into_iter().map(|block| FormattedBlock {
+            source: source_shift(block.source.start)..source_shift(block.source.end),
+            output: output_shift(block.output.start)..output_shift(block.output.end)
+        }));
+        self.output.replace_range(replaced_start..replaced_end, &replacement);
+        self.source = source;
+        TextEdit { range: replaced_start..replaced_end, replacement }
+    }
+
+    /// Pretty print the whole document, and find the blocks of the output
+    /// for later edits.
+    ///
+    /// If the output has a different number of top-level blocks than the
+    /// source, no blocks are kept, so that the next edit reformats the
+    /// whole document again.
+    fn format_all(&mut self, source: String) -> TextEdit {
+        let output = ::prettify_with_options(&source, &self.options);
+        let blocks = parse_blocks(&source, &self.options);
+        let formatted = parse_blocks(&output, &self.options);
+        self.blocks = if formatted.len() == blocks.len() {
+            blocks.iter().zip(formatted)
+                .map(|(block, pretty)| FormattedBlock { source: block.range.clone(), output: pretty.range })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let range = 0..self.output.len();
+        self.output = output.clone();
+        self.source = source;
+        TextEdit { range, replacement: output }
+    }
+
+    /// Check whether an edit can only affect the blocks that surround it.
+    fn is_local(&self, edit: &TextEdit, source: &str, inserted: &Range<usize>) -> bool {
+        let options = &self.options;
+        if options.references.is_some() || options.footnotes.is_some() || options.normalize_structure ||
+            options.sort_lists || options.admonitions.is_some() || options.front_matter != FrontMatterPolicy::Markdown ||
+            !options.shortcodes.is_empty() || !options.profiles.is_empty() || options.tables.is_some() ||
+            options.heading_hierarchy.is_some() || options.heading_limit.is_some() || options.duplicate_anchors {
+            return false;
+        }
+        // Directives such as `<!-- prettify: wrap=off -->` override options
+        // for the blocks that follow them.
+        if self.source.contains("prettify:") || source.contains("prettify:") {
+            return false;
+        }
+        // Link reference definitions affect links anywhere in the document.
+        let touches_definition = |text: &str, range: &Range<usize>| {
+            let start = text[..range.start].rfind('\n').map_or(0, |newline| newline + 1);
+            let end = text[range.end..].find('\n').map_or(text.len(), |newline| range.end + newline);
+            text[start..end].contains("]:")
+        };
+        !touches_definition(&self.source, &edit.range) && !touches_definition(source, inserted)
+    }
+
+    /// Count the blocks at the start and end of the document that are not
+    /// affected by an edit, and whose output can be kept.
+    fn unchanged_blocks(&self, blocks: &[ParsedBlock], edit: &TextEdit, inserted: &Range<usize>) -> (usize, usize) {
+        let prefix = self.blocks.iter().zip(blocks)
+            .take_while(|&(old, new)| old.source == new.range && old.source.end < edit.range.start)
+            .count();
+        let suffix = self.blocks[prefix..].iter().rev().zip(blocks[prefix..].iter().rev())
+            .take_while(|&(old, new)| {
+                old.source.start > edit.range.end && new.range.start >= inserted.end &&
+                    old.source.start - edit.range.end == new.range.start - inserted.end &&
+                    old.source.len() == new.range.len()
+            })
+            .count();
+        (prefix, suffix)
+    }
+
+    fn format_block(&self, source: &str, block: &ParsedBlock) -> String {
+        let mut printer = PrettyPrinter::new_with_options(String::new(), "", self.options.clone());
+        for (event, range) in &block.events {
+            printer.push_event_in_source(event.clone(), source, range.clone()).expect("failed to write to string");
+        }
+        printer.into_inner()
+    }
+}
@@ -0,0 +1,82 @@
+use std::borrow::Cow;
+
+use inline;
+use options::InfoStringOptions;
+
+/// Normalize the info string of a fenced code block.
+///
+/// The info string is split into a language followed by attributes,
+/// separated by commas (as used by rustdoc) or whitespace. If
+/// `keep_attributes` is set, a trailing attribute block such as
+/// `{.numberLines}` is preserved verbatim.
+pub fn normalize<'a>(info: &'a str, options: &InfoStringOptions, keep_attributes: bool) -> Cow<'a, str> {
+    if !options.sort_attributes && !options.dedupe_attributes && !options.lowercase_language &&
+        options.aliases.is_empty() {
+        return Cow::Borrowed(info);
+    }
+    if keep_attributes {
+        if let Some(block) = inline::trailing_attributes(info) {
+            let normalized = normalize(info[..block.start].trim_end(), options, false);
+            if normalized.is_empty() {
+                return Cow::Owned(info[block].to_string());
+            }
+            return Cow::Owned(format!("{} {}", normalized, &info[block]));
+        }
+    }
+
+    let separator = if info.contains(',') { "," } else { " " };
+    let mut tokens = info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .enumerate()
+        .map(|(index, token)| {
+            let token = if index == 0 && options.lowercase_language {
+                Cow::Owned(token.to_lowercase())
+            } else {
+                Cow::Borrowed(token)
+            };
+            options.aliases.iter()
+                .find(|&(from, _)| *from == token)
+                .map_or(token, |(_, to)| Cow::Borrowed(to.as_str()))
+        })
+        .collect::<Vec<_>>();
+
+    if options.dedupe_attributes {
+        let mut seen = Vec::with_capacity(tokens.len());
+        tokens.retain(|token| {
+            if seen.contains(token) {
+                false
+            } else {
+                seen.push(token.clone());
+                true
+            }
+        });
+    }
+    if options.sort_attributes && tokens.len() > 1 {
+        tokens[1..].sort();
+    }
+
+    Cow::Owned(tokens.join(separator))
+}
+
+const RUSTDOC_ATTRIBUTES: &[&str] = &[
+    "rust", "ignore", "should_panic", "no_run", "compile_fail", "test_harness",
+    "allow_fail", "standalone_crate"
+];
+
+/// Check whether an info string denotes a rustdoc code block (doctest).
+///
+/// In rustdoc, fences without a language are Rust code as well.
+pub fn is_rustdoc(info: &str) -> bool {
+    info.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .all(|token| {
+            RUSTDOC_ATTRIBUTES.contains(&token) ||
+                token.starts_with("edition") ||
+                token.starts_with("ignore-")
+        })
+}
+
+/// Returns the language of an info string.
+pub fn language(info: &str) -> &str {
+    info.split(|c: char| c == ',' || c.is_whitespace()).next().unwrap_or("")
+}
@@ -0,0 +1,174 @@
+use std::ops::Range;
+
+use options::Options;
+
+/// Check whether any recognizers for atomic inline tokens are enabled.
+pub fn has_recognizers(options: &Options) -> bool {
+    options.wiki_links || options.attributes || !options.placeholders.is_empty() ||
+        !options.shortcodes.is_empty() || options.bare_urls.is_some()
+}
+
+/// The kind of an atomic inline token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtomKind {
+    /// A token that is printed verbatim.
+    Verbatim,
+    /// A bare URL that is converted into a link.
+    Url,
+    /// A bare email address that is converted into a link.
+    Email
+}
+
+/// Find the byte ranges of atomic inline tokens in a run of text.
+///
+/// Atomic tokens are never escaped or broken across lines. Bare URLs and
+/// email addresses are only recognized if `linkify` is true, which is
+/// the case outside of links and images. The returned ranges are sorted
+/// and disjoint.
+pub fn atoms(text: &str, options: &Options, heading_end: bool, linkify: bool) -> Vec<(Range<usize>, AtomKind)> {
+    let mut atoms = Vec::new();
+    if options.wiki_links {
+        atoms.extend(wiki_links(text).into_iter().map(|atom| (atom, AtomKind::Verbatim)));
+    }
+    for (open, close) in options.placeholders.iter().chain(&options.shortcodes) {
+        atoms.extend(delimited(text, open, close).into_iter().map(|atom| (atom, AtomKind::Verbatim)));
+    }
+    if options.attributes && heading_end {
+        atoms.extend(trailing_attributes(text).map(|atom| (atom, AtomKind::Verbatim)));
+    }
+    if options.bare_urls.is_some() && linkify {
+        atoms.extend(bare_urls(text).into_iter().map(|atom| (atom, AtomKind::Url)));
+        atoms.extend(emails(text).into_iter().map(|atom| (atom, AtomKind::Email)));
+    }
+    atoms.sort_by_key(|atom| atom.0.start);
+    let mut disjoint: Vec<(Range<usize>, AtomKind)> = Vec::with_capacity(atoms.len());
+    for atom in atoms {
+        if disjoint.last().is_none_or(|last| last.0.end <= atom.0.start) {
+            disjoint.push(atom);
+        }
+    }
+    disjoint
+}
+
+/// Recognize `[[Wiki Links]]` and `![[embeds]]`.
+fn wiki_links(text: &str) -> Vec<Range<usize>> {
+    let mut links = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find("[[") {
+        let open = pos + found;
+        let inner = open + 2;
+        let close = match text[inner..].find("]]") {
+            Some(close) => inner + close,
+            None => break
+        };
+        let content = &text[inner..close];
+        if content.is_empty() || content.contains('\n') || content.contains('[') {
+            pos = inner;
+            continue;
+        }
+        let start = if text[..open].ends_with('!') { open - 1 } else { open };
+        links.push(start..close + 2);
+        pos = close + 2;
+    }
+    links
+}
+
+/// Recognize tokens enclosed by the given delimiters, such as
+/// `{{ mustache }}` placeholders.
+fn delimited(text: &str, open: &str, close: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    if open.is_empty() || close.is_empty() {
+        return tokens;
+    }
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find(open) {
+        let start = pos + found;
+        let inner = start + open.len();
+        match text[inner..].find(close) {
+            Some(end) if !text[inner..inner + end].contains('\n') => {
+                pos = inner + end + close.len();
+                tokens.push(start..pos);
+            },
+            _ => pos = inner
+        }
+    }
+    tokens
+}
+
+/// Recognize a trailing attribute block such as `{#id .class}`.
+pub fn trailing_attributes(text: &str) -> Option<Range<usize>> {
+    let trimmed = text.trim_end();
+    if !trimmed.ends_with('}') {
+        return None;
+    }
+    let open = trimmed.rfind('{')?;
+    let content = &trimmed[open + 1..trimmed.len() - 1];
+    let preceded_by_space = open == 0 || text[..open].ends_with(char::is_whitespace);
+    if content.trim().is_empty() || content.contains('}') || !preceded_by_space {
+        return None;
+    }
+    Some(open..trimmed.len())
+}
+
+/// Recognize bare URLs with an `http`, `https` or `ftp` scheme.
+///
+/// A URL extends up to the next whitespace or angle bracket, excluding
+/// trailing punctuation and unbalanced closing parentheses, so that a
+/// URL at the end of a sentence or in parentheses is recognized as
+/// expected.
+pub fn bare_urls(text: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find("://") {
+        let colon = pos + found;
+        let start = text[..colon].trim_end_matches(|c: char| c.is_ascii_alphabetic()).len();
+        let scheme = text[start..colon].to_ascii_lowercase();
+        let rest = &text[colon + 3..];
+        let end = colon + 3 + rest.find(|c: char| c.is_whitespace() || c == '<' || c == '>').unwrap_or(rest.len());
+        let end = start + trim_url(&text[start..end]).len();
+        let preceded_by_word = text[..start].ends_with(|c: char| c.is_alphanumeric());
+        if ["http", "https", "ftp"].contains(&scheme.as_str()) && !preceded_by_word && end > colon + 3 {
+            urls.push(start..end);
+            pos = end;
+        } else {
+            pos = colon + 3;
+        }
+    }
+    urls
+}
+
+fn trim_url(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ':', ';', '!', '?', '"', '\'', '*', '_', '~']);
+        let unbalanced = trimmed.ends_with(')') && trimmed.matches(')').count() > trimmed.matches('(').count();
+        let trimmed = if unbalanced { &trimmed[..trimmed.len() - 1] } else { trimmed };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+/// Recognize bare email addresses such as `user@example.com`.
+fn emails(text: &str) -> Vec<Range<usize>> {
+    let is_local = |c: char| c.is_ascii_alphanumeric() || ".+-_".contains(c);
+    let is_domain = |c: char| c.is_ascii_alphanumeric() || c == '.' || c == '-';
+    let mut emails = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = text[pos..].find('@') {
+        let at = pos + found;
+        pos = at + 1;
+        let start = text[..at].trim_end_matches(is_local).len();
+        let end = at + 1 + text[at + 1..].find(|c: char| !is_domain(c)).unwrap_or(text.len() - at - 1);
+        let domain = text[at + 1..end].trim_end_matches(['.', '-']);
+        let end = at + 1 + domain.len();
+        let valid_domain = domain.contains('.') && !domain.starts_with(['.', '-']) && !domain.contains("..");
+        let preceded_by_word = text[..start].ends_with(|c: char| c.is_alphanumeric() || c == '/' || c == ':');
+        if start < at && valid_domain && !preceded_by_word && emails.last().is_none_or(|last: &Range<usize>| last.end <= start) {
+            emails.push(start..end);
+            pos = end;
+        }
+    }
+    emails
+}
@@ -0,0 +1,38 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+
+/// Check whether a character is a zero-width or bidirectional control
+/// character, which is invisible but can change how text reads.
+pub fn is_invisible(c: char) -> bool {
+    matches!(c,
+        // zero width space, non-joiner and joiner, word joiner and
+        // zero width no-break space
+        '\u{200b}'..='\u{200d}' | '\u{2060}' | '\u{feff}' |
+        // bidirectional marks, embeddings, overrides and isolates
+        '\u{061c}' | '\u{200e}' | '\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Remove zero-width and bidirectional control characters from text.
+pub fn strip(text: Cow<'_, str>) -> Cow<'_, str> {
+    if !text.contains(is_invisible) {
+        return text;
+    }
+    Cow::Owned(text.chars().filter(|&c| !is_invisible(c)).collect())
+}
+
+/// Replace zero-width and bidirectional control characters in text by
+/// numeric character references.
+pub fn escape(text: Cow<'_, str>) -> Cow<'_, str> {
+    if !text.contains(is_invisible) {
+        return text;
+    }
+    let mut output = String::with_capacity(text.len());
+    for c in text.chars() {
+        if is_invisible(c) {
+            write!(output, "&#{};", c as u32).unwrap();
+        } else {
+            output.push(c);
+        }
+    }
+    Cow::Owned(output)
+}
@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde_json::{self, json, Value};
+
+use diagnostics::{Diagnostic, Severity};
+
+/// The diagnostics reported for a single file, as written by
+/// `diagnostics_to_json` and `diagnostics_to_sarif`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiagnostics {
+    /// The path of the file, relative to the root of the repository for
+    /// tools that display findings in place.
+    pub path: PathBuf,
+    /// The source of the file, which the lines and columns of the
+    /// diagnostics are computed from. It is not serialized.
+    #[serde(skip)]
+    pub source: String,
+    /// The diagnostics reported for the file.
+    pub diagnostics: Vec<Diagnostic>
+}
+
+/// Serializes diagnostics into JSON, for CI systems and other tools.
+///
+/// The output is an array with an object for each file, holding its
+/// `path` and its `diagnostics`, each of which has a `severity`, a
+/// `rule`, a `message`, the byte `offset` in the source (or `null`) and
+/// the headings of the `section` it was found in.
+///
+/// Requires the `json` feature.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{check, diagnostics_to_json, FileDiagnostics, Options};
+/// let options = Options { wrap: Some(10), ..Options::default() };
+/// let source = "Lorem ipsum dolor";
+/// let files = vec![FileDiagnostics { path: "README.md".into(), source: source.into(), diagnostics: check(source, &options) }];
+/// assert_eq!(diagnostics_to_json(&files),
+///            "[{\"path\":\"README.md\",\"diagnostics\":[{\"severity\":\"warning\",\"rule\":\"line-width\",\
+///             \"message\":\"line is 17 columns wide, more than the maximum of 10\",\"offset\":0,\"section\":[]}]}]");
+/// ```
+pub fn diagnostics_to_json(files: &[FileDiagnostics]) -> String {
+    serde_json::to_string(files).expect("failed to serialize diagnostics")
+}
+
+/// Serializes diagnostics into a [SARIF](https://sarifweb.azurewebsites.net/)
+/// 2.1.0 log, which code scanning services and code review bots can
+/// display on the lines they refer to.
+///
+/// Each diagnostic becomes a result of a single run, with its rule as the
+/// rule ID, its severity as the level, and its file as the location,
+/// along with its byte offset and the line and column it starts at. The
+/// headings of the section it was found in are stored in the `section`
+/// property.
+///
+/// Requires the `json` feature.
+pub fn diagnostics_to_sarif(files: &[FileDiagnostics]) -> String {
+    let mut rules = Vec::new();
+    let mut results = Vec::new();
+    for file in files {
+        let uri = file.path.to_string_lossy().replace('\\', "/");
+        for diagnostic in &file.diagnostics {
+            if !rules.contains(&diagnostic.rule) {
+                rules.push(diagnostic.rule);
+            }
+            let mut location = json!({ "artifactLocation": { "uri": uri } });
+            if let Some(offset) = diagnostic.offset {
+                let (line, column) = position(&file.source, offset);
+                location["region"] = json!({ "byteOffset": offset, "startLine": line, "startColumn": column });
+            }
+            results.push(json!({
+                "ruleId": diagnostic.rule,
+                "level": level(diagnostic.severity),
+                "message": { "text": diagnostic.message },
+                "locations": [{ "physicalLocation": location }],
+                "properties": { "section": diagnostic.section }
+            }));
+        }
+    }
+    let rules = rules.into_iter().map(|rule| json!({ "id": rule })).collect::<Vec<Value>>();
+    let log = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results
+        }]
+    });
+    serde_json::to_string_pretty(&log).expect("failed to serialize diagnostics")
+}
+
+/// The line and column, both counted from one, at an offset in a source.
+fn position(source: &str, offset: usize) -> (usize, usize) {
+    let before = source.get(..offset).unwrap_or(source);
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (before.matches('\n').count() + 1, before[line_start..].chars().count() + 1)
+}
+
+/// The SARIF level of a severity.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error"
+    }
+}
@@ -36,20 +36,171 @@
 //! assert_eq!(printer.into_inner(), "Lorem *ipsum* dolor `sit`.")
 //! # }
 //! ```
+//!
+//! ## Parser version
+//!
+//! The APIs that take CommonMark source text, such as `prettify`,
+//! `prettify_with_options` or `semantically_equal`, do not expose any
+//! types of the parser, and remain stable when this crate moves to a new
+//! major version of `pulldown-cmark`. The exceptions are
+//! `first_difference` and `diff_events`, whose results hold the events
+//! that differ (`EventDifference::left` and `right`, and
+//! `EventChange::event`) in the event model of the parser.
+//!
+//! The APIs that take or return events, such as
+//! `PrettyPrinter::push_events` or `EventBuffer`, use the types of
+//! `pulldown-cmark` directly, and change with its major versions. The
+//! parser is re-exported as `prettify_cmark::pulldown_cmark` by the
+//! default `parser-reexport` feature. Crates that only use the source
+//! based APIs can disable it, so that the version of the parser is not
+//! part of the API they depend on.
+//!
+//! ## Deterministic output
+//!
+//! The output only depends on the input and the options. State that is
+//! collected while printing, such as link reference definitions and
+//! footnotes, is kept in insertion order and only reordered by stable
+//! sorts, so formatting the same document always produces the same bytes,
+//! regardless of the process, thread or platform it runs on.
+//!
+//! ## Directives
+//!
+//! HTML comments starting with `prettify:` override options for the rest
+//! of the document, or until a `<!-- prettify: reset -->` comment. The
+//! overrides are `wrap` (for `Options::wrap`) and `max-width` (for
+//! `TableOptions::max_width`), which take a width or `off`.
+//!
+//! ```rust
+//! use prettify_cmark::{prettify_with_options, Options};
+//!
+//! let source = "Lorem ipsum dolor\n\n<!-- prettify: wrap=off -->\n\nLorem ipsum dolor\n\n\
+//!               <!-- prettify: reset -->\n\nLorem ipsum dolor";
+//! let output = prettify_with_options(source, &Options { wrap: Some(12), ..Options::default() });
+//! assert_eq!(output, "Lorem ipsum\ndolor\n\n<!-- prettify: wrap=off -->\n\nLorem ipsum dolor\n\n\
+//!                     <!-- prettify: reset -->\n\nLorem ipsum\ndolor");
+//! ```
 
+#[cfg(feature = "parser-reexport")]
 pub extern crate pulldown_cmark;
+#[cfg(not(feature = "parser-reexport"))]
+extern crate pulldown_cmark;
+extern crate unicode_normalization;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+#[cfg(any(test, feature = "json"))]
+extern crate serde_json;
 
 use std::fmt::{Display, Formatter, Result};
 
-use pulldown_cmark::Parser;
-
+mod admonitions;
+#[cfg(feature = "tokio")]
+mod async_printer;
+mod backend;
+mod badges;
+mod buffer;
+mod budget;
+mod case;
+mod check;
+mod changelog;
+mod checkpoint;
+mod comments;
+mod compact;
+mod compare;
+#[cfg(feature = "csv")]
+mod csv;
+mod diagnostics;
+mod directives;
+mod documents;
+mod entities;
+mod escape;
+mod footnotes;
+mod front_matter;
+mod hooks;
+mod html;
+mod include;
+mod incremental;
+mod info;
+mod invisible;
+mod inline;
+#[cfg(feature = "json")]
+mod json;
+mod links;
+mod lints;
+mod minimal;
+mod options;
+mod outline;
+mod plain;
+mod references;
+mod render;
+mod report;
+mod shortcodes;
+mod staged;
+mod summary;
+mod table;
+mod tables;
+#[cfg(feature = "tracing")]
+mod trace;
+#[cfg(feature = "testing")]
+#[macro_use]
+pub mod testing;
+#[cfg(feature = "fs")]
+mod tree;
+mod truncate;
+mod validate;
 mod writer;
 mod printer;
 
 #[cfg(test)]
 mod tests;
 
+#[cfg(feature = "tokio")]
+pub use async_printer::{AsyncPrettyPrinter, WriteBlocks};
+pub use backend::{Backend, Instruction};
+pub use badges::{edit_badges, find_badges, Badge};
+pub use buffer::{EventBuffer, OwnedEvent};
+pub use budget::{Budget, CancellationToken};
+pub use changelog::{add_changelog_entries, release_changelog};
+pub use check::check;
+pub use checkpoint::Checkpoint;
+pub use comments::{prettify_comment, CommentPrefix};
+#[cfg(feature = "csv")]
+pub use csv::{csv_table, CsvError};
+pub use compare::{diff_events, first_difference, semantically_equal, ChangeKind, EventChange, EventDiff, EventDifference};
+pub use diagnostics::{Diagnostic, Message, Severity};
+pub use documents::{adjust_headings, concat_documents, extract_section, split_by_heading, Document, DocumentSeparator, HeadingRange, Section};
+pub use hooks::{AltTextProvider, Block, CodeBlockFormatter, Localizer, NoopFormatter, TextInspector, TextRun};
+pub use incremental::{IncrementalFormatter, TextEdit};
+#[cfg(feature = "json")]
+pub use json::{diagnostics_to_json, diagnostics_to_sarif, FileDiagnostics};
+pub use include::{expand_includes, prettify_with_includes, FileLoader, IncludeError, IncludeLoader};
+pub use options::{AdmonitionOptions, AdmonitionStyle, AltTextPolicy, AutolinkStyle, BlockQuoteStyle, CalloutCase, EmptyConstructPolicy, EntityPolicy, EscapeMethod, EscapeStyle, FootnoteOptions, FootnotePlacement, FootnoteStyle, HeadingCase, HeadingLimit};
+pub use options::{FrontMatterPolicy, HeadingBreakStyle, HeadingHierarchy, HtmlBreakPolicy, HtmlPolicy, InfoStringOptions, InvisibleCharacterPolicy};
+pub use options::{NonBreakingSpaces, Normalization, Options, SectionProfile, VerbatimBlocks};
+pub use options::{ReferenceLabels, ReferenceOptions, ReferencePlacement, RuleMarker, RuleStyle, TableOptions, TableOverflow, TableSeparator};
+pub use options::{TitleOptions, TitleQuote};
+pub use links::{FileLink, FileNode, LinkGraph};
+pub use lints::{FirstH1, Lint, LintContext, Lints, NoBareUrls, NoSkippedHeadingLevels, SingleH1};
+pub use minimal::prettify_minimal;
+pub use outline::{outline, OutlineEntry, Slugger};
+pub use plain::{to_plain_text, PlainTextLinks};
 pub use printer::PrettyPrinter;
+pub use render::render_html;
+pub use report::{list_from_iter, table_from_iter, Report};
+pub use tables::{edit_tables, Cell, Table};
+pub use staged::{format_staged, StagedFile, StagedReplacement, StagedSummary};
+pub use summary::{summary, Summary};
+#[cfg(feature = "fs")]
+pub use tree::{format_file_in_place, format_tree, format_tree_with_links, FileOutcome, FileReport, TreeOptions};
+pub use truncate::{prettify_truncated, Truncation, TruncationLimit};
+pub use validate::{validate_events, NestingError};
+#[cfg(feature = "unstable-writer")]
+pub use writer::{Frame, Writer};
 
 /// Parses a CommonMark document and returns it as a pretty printed string.
 ///
@@ -64,9 +215,31 @@ pub fn prettify(source: &str) -> String {
     PrettyDisplay(source).to_string()
 }
 
+/// Parses a CommonMark document and returns it as a string that has been
+/// pretty printed using custom options.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, EntityPolicy, Options};
+/// let options = Options { entities: EntityPolicy::Preserve, ..Options::default() };
+/// let output = prettify_with_options("Lorem&nbsp;ipsum", &options);
+/// assert_eq!(output,  "Lorem&nbsp;ipsum");
+/// ```
+pub fn prettify_with_options(source: &str, options: &Options) -> String {
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.push_source(source).expect("failed to write to string");
+    printer.into_inner()
+}
+
 /// Wrapper that will pretty print the wrapped document when formatted
 /// via `Display`.
 ///
+/// With the alternate flag (`{:#}`), the document is printed compactly as
+/// a single paragraph, joining its blocks by spaces. This is meant for
+/// embedding documents in single-line contexts such as log messages. A
+/// width (`{:80}`) is used to wrap lines at, as by `Options::wrap`.
+///
 /// # Examples
 ///
 /// Via `to_string`:
@@ -83,13 +256,73 @@ pub fn prettify(source: &str) -> String {
 /// # use prettify_cmark::PrettyDisplay;
 /// let output = format!("My document: {}", PrettyDisplay("Lorem __ipsum__ dolor `sit` amet!"));
 /// assert_eq!(output,  "My document: Lorem **ipsum** dolor `sit` amet!");
+///
+/// let output = format!("My document: {:#}", PrettyDisplay("# Lorem\n\n* __ipsum__\n* dolor"));
+/// assert_eq!(output,  "My document: Lorem **ipsum** dolor");
+/// ```
+///
+/// With a width (such as `{:20}`), paragraphs are wrapped at that width:
+///
+/// ```rust
+/// # use prettify_cmark::PrettyDisplay;
+/// let output = format!("{:20}", PrettyDisplay("* Lorem ipsum dolor sit amet, consectetur adipiscing"));
+/// assert_eq!(output,  "- Lorem ipsum dolor\n  sit amet,\n  consectetur\n  adipiscing");
 /// ```
 pub struct PrettyDisplay<T>(pub T);
 
+impl<T> PrettyDisplay<T> {
+    /// Wrap a document to be pretty printed using custom options.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use prettify_cmark::{Options, PrettyDisplay, RuleMarker, RuleStyle};
+    /// let options = Options { rule: RuleStyle { marker: RuleMarker::Asterisk, ..RuleStyle::default() }, ..Options::default() };
+    /// let output = format!("{}", PrettyDisplay::with("Lorem\n\n---\n\nipsum", options));
+    /// assert_eq!(output,  "Lorem\n\n***\n\nipsum");
+    /// ```
+    pub fn with(source: T, options: Options) -> PrettyDisplayWith<T> {
+        PrettyDisplayWith { source, options }
+    }
+}
+
 impl<T: AsRef<str>> Display for PrettyDisplay<T> {
     fn fmt(&self, fmt: &mut Formatter) -> Result {
-        let events = Parser::new(self.0.as_ref());
-        let mut pretty_printer = PrettyPrinter::new(fmt);
-        pretty_printer.push_events(events)
+        display(fmt, self.0.as_ref(), &Options::default())
+    }
+}
+
+/// Wrapper that will pretty print the wrapped document using custom
+/// options when formatted via `Display`, as created by
+/// `PrettyDisplay::with`.
+///
+/// Like `PrettyDisplay`, it honors the alternate flag (`{:#}`).
+#[derive(Debug, Clone)]
+pub struct PrettyDisplayWith<T> {
+    /// The wrapped document.
+    pub source: T,
+    /// The options to print the document with.
+    pub options: Options
+}
+
+impl<T: AsRef<str>> Display for PrettyDisplayWith<T> {
+    fn fmt(&self, fmt: &mut Formatter) -> Result {
+        display(fmt, self.source.as_ref(), &self.options)
+    }
+}
+
+/// Pretty print a document into a formatter, honoring its alternate flag
+/// and using its width as the width to wrap lines at.
+fn display(fmt: &mut Formatter, source: &str, options: &Options) -> Result {
+    let compact = fmt.alternate();
+    let mut options = options.clone();
+    if let Some(width) = fmt.width() {
+        options.wrap = Some(width);
+    }
+    let mut pretty_printer = PrettyPrinter::new_with_options(fmt, "", options.clone());
+    if compact {
+        compact::push_compact(&mut pretty_printer, source, &options)
+    } else {
+        pretty_printer.push_source(source)
     }
 }
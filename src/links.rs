@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use pulldown_cmark::{Event, Parser, Tag};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use options::Options;
+use outline::Slugger;
+
+/// A relative link (or image) from one file to another, as collected in a
+/// `LinkGraph`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileLink {
+    /// The destination of the link as written, such as `../guide.md#setup`.
+    pub destination: String,
+    /// The path of the linked file relative to the root of the graph,
+    /// with `/` as separator. Paths that lead outside of the root start
+    /// with `..`.
+    pub target: String,
+    /// The fragment of the destination, without the `#`, if any.
+    pub fragment: Option<String>,
+    /// The byte offset of the link in the source of the file.
+    pub offset: usize
+}
+
+/// The anchors and outgoing links of a file in a `LinkGraph`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FileNode {
+    /// The anchors of the headings of the file, as generated by `Slugger`.
+    pub anchors: Vec<String>,
+    /// The relative links of the file, in the order of the document.
+    pub links: Vec<FileLink>
+}
+
+/// The graph of relative links between the files of a documentation tree,
+/// for detecting dead links across files.
+///
+/// Links with a scheme (such as `https:` or `mailto:`), absolute paths and
+/// links within the same file are not part of the graph. With the `fs`
+/// feature enabled, `format_tree_with_links` collects the graph while
+/// formatting a directory tree.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{LinkGraph, Options};
+/// let mut graph = LinkGraph::new();
+/// graph.add_file("README.md", "[Guide](docs/guide.md#setup) [API](docs/api.md)", &Options::default());
+/// graph.add_file("docs/guide.md", "# Setup\n\n[Home](../README.md) ![Logo](logo.png)", &Options::default());
+/// graph.assets.insert("docs/logo.png".to_string());
+///
+/// let dead = graph.dead_links();
+/// assert_eq!(dead.len(), 1);
+/// assert_eq!(dead[0].0, "README.md");
+/// assert_eq!(dead[0].1.target, "docs/api.md");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinkGraph {
+    /// The Markdown files of the graph, by their path relative to the
+    /// root, with `/` as separator.
+    pub files: BTreeMap<String, FileNode>,
+    /// The paths of other files that links may point to, such as images.
+    pub assets: BTreeSet<String>
+}
+
+impl LinkGraph {
+    /// Create an empty graph.
+    pub fn new() -> LinkGraph {
+        LinkGraph::default()
+    }
+
+    /// Parse a Markdown file and add its anchors and relative links to
+    /// the graph, given its path relative to the root.
+    pub fn add_file(&mut self, path: &str, source: &str, options: &Options) {
+        let mut node = FileNode::default();
+        let mut slugger = Slugger::new();
+        let mut heading: Option<String> = None;
+        let mut parser = Parser::new_ext(source, options.parser_options());
+        loop {
+            let start = parser.get_offset();
+            let event = match parser.next() {
+                Some(event) => event,
+                None => break
+            };
+            match event {
+                Event::Start(Tag::Header(_)) => heading = Some(String::new()),
+                Event::End(Tag::Header(_)) => node.anchors.extend(heading.take().map(|text| slugger.slug(&text))),
+                Event::Text(ref text) | Event::InlineHtml(ref text) => {
+                    if let Some(ref mut heading) = heading {
+                        heading.push_str(text);
+                    }
+                },
+                Event::Start(Tag::Link(ref destination, _)) | Event::Start(Tag::Image(ref destination, _)) => {
+                    if let Some((target, fragment)) = resolve(path, destination) {
+                        let offset = start + source[start..].len() - source[start..].trim_start().len();
+                        node.links.push(FileLink { destination: destination.to_string(), target, fragment, offset });
+                    }
+                },
+                _ => {}
+            }
+        }
+        self.files.insert(path.to_string(), node);
+    }
+
+    /// Returns the links whose target is neither a file nor an asset of
+    /// the graph (or a directory containing one), or whose fragment is
+    /// not an anchor of the linked Markdown file, along with the path of
+    /// the file they are in.
+    pub fn dead_links(&self) -> Vec<(&str, &FileLink)> {
+        let mut dead = Vec::new();
+        for (path, node) in &self.files {
+            for link in &node.links {
+                let alive = match self.files.get(&link.target) {
+                    Some(target) => link.fragment.as_ref().is_none_or(|fragment| {
+                        fragment.is_empty() || target.anchors.contains(&fragment.to_lowercase())
+                    }),
+                    None => self.assets.contains(&link.target) || self.contains_directory(&link.target)
+                };
+                if !alive {
+                    dead.push((path.as_str(), link));
+                }
+            }
+        }
+        dead
+    }
+
+    fn contains_directory(&self, directory: &str) -> bool {
+        let prefix = format!("{}/", directory);
+        self.files.keys().chain(&self.assets).any(|path| directory.is_empty() || path.starts_with(&prefix))
+    }
+}
+
+/// Resolve the destination of a link in the file at the given path,
+/// returning the path of its target and its fragment, unless it is not a
+/// relative link to another file.
+fn resolve(from: &str, destination: &str) -> Option<(String, Option<String>)> {
+    let end = destination.find(['/', '?', '#']).unwrap_or(destination.len());
+    if destination.starts_with('/') || destination[..end].contains(':') {
+        return None;
+    }
+    let (path, fragment) = match destination.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment.to_string())),
+        None => (destination, None)
+    };
+    let path = path.split('?').next().unwrap_or_default();
+    if path.is_empty() {
+        return None;
+    }
+    let mut components = from.split('/').collect::<Vec<_>>();
+    components.pop();
+    let decoded = percent_decode(path);
+    for component in decoded.split('/') {
+        match component {
+            "" | "." => {},
+            ".." if components.last().is_some_and(|last| *last != "..") => {
+                components.pop();
+            },
+            component => components.push(component)
+        }
+    }
+    Some((components.join("/"), fragment))
+}
+
+/// Decode the percent-encoded bytes of a path, such as `%20`, leaving it
+/// as it is if it is not valid UTF-8 after decoding.
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = path.get(index + 1..index + 3)
+            .filter(|hex| hex.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) if bytes[index] == b'%' => {
+                decoded.push(byte);
+                index += 3;
+            },
+            _ => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| path.to_string())
+}
@@ -0,0 +1,285 @@
+use pulldown_cmark::{Event, Tag};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use diagnostics::{Diagnostic, Message, Severity};
+use hooks::Localizer;
+use inline;
+
+/// What a lint knows about the event it checks.
+pub struct LintContext<'a> {
+    /// The byte offset of the event in the source document, if known.
+    ///
+    /// Offsets are only available when the printer is driven via
+    /// `PrettyPrinter::push_source`.
+    pub offset: Option<usize>,
+    /// The source text of the event, starting at `offset`, if known.
+    pub source: Option<&'a str>,
+    /// The headings of the section the event is in, outermost first, as
+    /// returned by `PrettyPrinter::heading_path`.
+    pub headings: &'a [&'a str],
+    localizer: Option<&'a dyn Localizer>,
+    reports: Vec<(String, Option<usize>)>
+}
+
+impl<'a> LintContext<'a> {
+    pub(crate) fn new(offset: Option<usize>, source: Option<&'a str>, headings: &'a [&'a str],
+                      localizer: Option<&'a dyn Localizer>) -> LintContext<'a> {
+        LintContext { offset, source, headings, localizer, reports: Vec::new() }
+    }
+
+    /// Run a lint on an event, returning its reports as diagnostics with
+    /// the given severity.
+    pub(crate) fn run<L: Lint + ?Sized>(mut self, lint: &mut L, event: &Event, severity: Severity) -> Vec<Diagnostic> {
+        lint.check(event, &mut self);
+        let rule = lint.rule();
+        self.reports.into_iter()
+            .map(|(message, offset)| Diagnostic { severity, rule, message, offset, section: Vec::new() })
+            .collect()
+    }
+
+    /// Report an issue at the offset of the event.
+    pub fn report<M: Into<String>>(&mut self, message: M) {
+        let offset = self.offset;
+        self.report_at(message, offset);
+    }
+
+    /// Report an issue at the given offset in the source document, such
+    /// as that of a part of the event.
+    pub fn report_at<M: Into<String>>(&mut self, message: M, offset: Option<usize>) {
+        self.reports.push((message.into(), offset));
+    }
+
+    /// Report a message of the printer at the offset of the event, with
+    /// the text supplied by the localizer of the printer if any.
+    pub(crate) fn report_message(&mut self, message: Message) {
+        let offset = self.offset;
+        self.report_message_at(message, offset);
+    }
+
+    /// Report a message of the printer at the given offset.
+    pub(crate) fn report_message_at(&mut self, message: Message, offset: Option<usize>) {
+        let text = message.localize(self.localizer);
+        self.report_at(text, offset);
+    }
+}
+
+/// A check that runs on the events of a document while it is printed,
+/// reporting its findings as diagnostics.
+///
+/// Lints see the events in the order they are printed, after the
+/// printer has resolved references and applied options such as
+/// `Options::heading_limit`. Events of table cells are not passed to
+/// lints.
+///
+/// # Examples
+///
+/// ```rust
+/// extern crate pulldown_cmark;
+/// extern crate prettify_cmark;
+///
+/// use pulldown_cmark::Event;
+/// use prettify_cmark::{Lint, LintContext, Lints, PrettyPrinter, Severity};
+///
+/// struct NoTodo;
+///
+/// impl Lint for NoTodo {
+///     fn rule(&self) -> &'static str {
+///         "no-todo"
+///     }
+///
+///     fn check(&mut self, event: &Event, context: &mut LintContext) {
+///         if let Event::Text(ref text) = *event {
+///             if text.contains("TODO") {
+///                 context.report("text contains a TODO");
+///             }
+///         }
+///     }
+/// }
+///
+/// fn main() {
+///     let mut lints = Lints::new();
+///     lints.register(NoTodo, Severity::Error);
+///     let mut printer = PrettyPrinter::default();
+///     printer.set_lints(lints);
+///     printer.push_source("# Usage\n\nTODO").unwrap();
+///
+///     let diagnostics = printer.take_diagnostics();
+///     assert_eq!(diagnostics[0].to_string(), "error[no-todo] at byte 9 in Usage: text contains a TODO");
+/// }
+/// ```
+pub trait Lint {
+    /// The rule of the diagnostics reported by the lint, such as
+    /// `no-bare-urls`.
+    fn rule(&self) -> &'static str;
+
+    /// Check a single event.
+    fn check(&mut self, event: &Event, context: &mut LintContext);
+}
+
+/// A registry of lints along with the severity of their diagnostics, as
+/// attached to a printer by `PrettyPrinter::set_lints`.
+#[derive(Default)]
+pub struct Lints {
+    lints: Vec<(Box<dyn Lint>, Severity)>
+}
+
+impl Lints {
+    /// Create an empty registry.
+    pub fn new() -> Lints {
+        Lints::default()
+    }
+
+    /// Create a registry with all built-in lints, reporting warnings:
+    /// `NoBareUrls`, `NoSkippedHeadingLevels`, `SingleH1` and `FirstH1`.
+    pub fn builtin() -> Lints {
+        let mut lints = Lints::new();
+        lints.register(NoBareUrls::default(), Severity::Warning);
+        lints.register(NoSkippedHeadingLevels::default(), Severity::Warning);
+        lints.register(SingleH1::default(), Severity::Warning);
+        lints.register(FirstH1::default(), Severity::Warning);
+        lints
+    }
+
+    /// Add a lint, whose diagnostics are reported with the given severity.
+    pub fn register<L: Lint + 'static>(&mut self, lint: L, severity: Severity) {
+        self.lints.push((Box::new(lint), severity));
+    }
+
+    /// Change the severity of the diagnostics of the lints with a rule.
+    pub fn set_severity(&mut self, rule: &str, severity: Severity) {
+        for (lint, current) in &mut self.lints {
+            if lint.rule() == rule {
+                *current = severity;
+            }
+        }
+    }
+
+    /// Remove the lints with a rule.
+    pub fn remove(&mut self, rule: &str) {
+        self.lints.retain(|(lint, _)| lint.rule() != rule);
+    }
+
+    /// The rules of the registered lints, in order of registration.
+    pub fn rules(&self) -> Vec<&'static str> {
+        self.lints.iter().map(|(lint, _)| lint.rule()).collect()
+    }
+
+    /// Run all lints on an event, returning their diagnostics.
+    pub(crate) fn check(&mut self, event: &Event, offset: Option<usize>, source: Option<&str>, headings: &[&str],
+                        localizer: Option<&dyn Localizer>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for (lint, severity) in &mut self.lints {
+            let context = LintContext::new(offset, source, headings, localizer);
+            diagnostics.extend(context.run(&mut **lint, event, *severity));
+        }
+        diagnostics
+    }
+}
+
+/// Lint that reports URLs in text that are not links, which are only
+/// clickable in renderers that detect them (`no-bare-urls`).
+#[derive(Debug, Clone, Default)]
+pub struct NoBareUrls {
+    /// The depth of links, images and code the current event is in.
+    depth: usize
+}
+
+impl Lint for NoBareUrls {
+    fn rule(&self) -> &'static str {
+        "no-bare-urls"
+    }
+
+    fn check(&mut self, event: &Event, context: &mut LintContext) {
+        match *event {
+            Event::Start(Tag::Link(..)) | Event::Start(Tag::Image(..)) | Event::Start(Tag::Code) | Event::Start(Tag::CodeBlock(_)) => {
+                self.depth += 1;
+            },
+            Event::End(Tag::Link(..)) | Event::End(Tag::Image(..)) | Event::End(Tag::Code) | Event::End(Tag::CodeBlock(_)) => {
+                self.depth = self.depth.saturating_sub(1);
+            },
+            Event::Text(ref text) if self.depth == 0 => {
+                let text = context.source.unwrap_or(text).to_string();
+                for url in inline::bare_urls(&text) {
+                    let offset = context.offset.map(|offset| offset + url.start);
+                    context.report_message_at(Message::BareUrl { url: &text[url] }, offset);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Lint that reports headings that are more than one level deeper than
+/// the previous heading, such as a level 3 heading following a level 1
+/// heading (`no-skipped-heading-levels`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct NoSkippedHeadingLevels {
+    last: Option<i32>
+}
+
+impl Lint for NoSkippedHeadingLevels {
+    fn rule(&self) -> &'static str {
+        "no-skipped-heading-levels"
+    }
+
+    fn check(&mut self, event: &Event, context: &mut LintContext) {
+        if let Event::Start(Tag::Header(level)) = *event {
+            if let Some(last) = self.last.filter(|&last| level > last + 1) {
+                context.report_message(Message::SkippedHeadingLevel { level, previous: last });
+            }
+            self.last = Some(level);
+        }
+    }
+}
+
+/// Lint that reports every level 1 heading after the first one
+/// (`single-h1`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SingleH1 {
+    seen: bool
+}
+
+impl Lint for SingleH1 {
+    fn rule(&self) -> &'static str {
+        "single-h1"
+    }
+
+    fn check(&mut self, event: &Event, context: &mut LintContext) {
+        if let Event::Start(Tag::Header(1)) = *event {
+            if self.seen {
+                context.report_message(Message::MultipleH1);
+            }
+            self.seen = true;
+        }
+    }
+}
+
+/// Lint that reports a level 1 heading that follows headings of other
+/// levels, as the title of a document should come first (`first-h1`).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FirstH1 {
+    /// Whether a heading has been checked.
+    seen_heading: bool,
+    /// Whether a level 1 heading has been checked.
+    seen_h1: bool
+}
+
+impl Lint for FirstH1 {
+    fn rule(&self) -> &'static str {
+        "first-h1"
+    }
+
+    fn check(&mut self, event: &Event, context: &mut LintContext) {
+        if let Event::Start(Tag::Header(level)) = *event {
+            if level == 1 && self.seen_heading && !self.seen_h1 {
+                context.report_message(Message::H1NotFirst);
+            }
+            self.seen_heading = true;
+            self.seen_h1 |= level == 1;
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::mem;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+use references;
+
+/// Parses a CommonMark document and pretty prints only those top-level
+/// blocks that are not formatted yet, leaving the rest of the source
+/// byte-for-byte identical.
+///
+/// This keeps diffs small when introducing the pretty printer into an
+/// existing repository: blank lines and link reference definitions
+/// between blocks are preserved as well, and a block is only replaced
+/// if pretty printing it changes its source text. If pretty printing
+/// changes the structure of the document (for example because
+/// `Options::normalize_structure` merges blocks, or footnote definitions
+/// are moved), the whole document is pretty printed instead.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_minimal, Options};
+/// let source = "Lorem  __ipsum__\n\n\n* dolor\n* sit\n\n\n[amet]: http://example.com\n";
+/// let output = prettify_minimal(source, &Options::default());
+/// assert_eq!(output, "Lorem  **ipsum**\n\n\n- dolor\n\n- sit\n\n\n[amet]: http://example.com\n");
+/// ```
+pub fn prettify_minimal(source: &str, options: &Options) -> String {
+    let formatted = ::prettify_with_options(source, options);
+    let original_blocks = blocks(source, options);
+    let formatted_blocks = blocks(&formatted, options);
+    if original_blocks.len() != formatted_blocks.len() {
+        return formatted;
+    }
+    let mut output = String::with_capacity(source.len());
+    let mut last = 0;
+    for (original, pretty) in original_blocks.into_iter().zip(formatted_blocks) {
+        output.push_str(&source[last..original.start]);
+        output.push_str(&formatted[pretty]);
+        last = original.end;
+    }
+    output.push_str(&source[last..]);
+    output
+}
+
+/// A top-level block of a document, along with the events it was parsed
+/// into and their source ranges.
+pub(crate) struct ParsedBlock<'a> {
+    pub range: Range<usize>,
+    pub events: Vec<(Event<'a>, Range<usize>)>
+}
+
+/// Find the source ranges of the top-level blocks of a document.
+///
+/// The ranges exclude blank lines and link reference definitions around
+/// the blocks, as well as the line ending after their last line.
+fn blocks(source: &str, options: &Options) -> Vec<Range<usize>> {
+    parse_blocks(source, options).into_iter().map(|block| block.range).collect()
+}
+
+/// Parse a document into its top-level blocks.
+pub(crate) fn parse_blocks<'a>(source: &'a str, options: &Options) -> Vec<ParsedBlock<'a>> {
+    let mut blocks = Vec::new();
+    let mut events = Vec::new();
+    let mut depth = 0usize;
+    let mut last = 0;
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let end = parser.get_offset();
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        events.push((event, start..end));
+        if depth == 0 {
+            let start = block_start(source, last, end, options);
+            let end = start + source[start..end].trim_end().len();
+            blocks.push(ParsedBlock { range: start..end, events: mem::take(&mut events) });
+            last = end;
+        }
+    }
+    blocks
+}
+
+/// Find the start of the first line in `source[from..to]` that is not
+/// blank and not a link reference definition.
+///
+/// Lines such as `[^a]: Note` start footnote definitions instead if
+/// footnotes are enabled.
+fn block_start(source: &str, from: usize, to: usize, options: &Options) -> usize {
+    let mut start = from;
+    for line in source[from..to].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let footnote = options.footnotes.is_some() && content.trim_start().starts_with("[^");
+        if !content.trim().is_empty() && (footnote || !references::is_definition(content)) {
+            break;
+        }
+        start += line.len();
+    }
+    start
+}
+
+/// A top-level block of a document.
+pub(crate) struct TopBlock {
+    /// The indices of the events of the block.
+    pub events: Range<usize>,
+    /// The source of the block, excluding blank lines around it.
+    pub range: Range<usize>,
+    /// Whether the block is a block quote.
+    pub quote: bool
+}
+
+/// Find the top-level blocks of a document.
+pub(crate) fn top_blocks(source: &str, options: &Options) -> Vec<TopBlock> {
+    let mut blocks = Vec::new();
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    let mut depth = 0usize;
+    let mut index = 0;
+    let mut first = (0, 0, false);
+    loop {
+        let offset = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => return blocks
+        };
+        if depth == 0 {
+            first = (index, offset, event == Event::Start(Tag::BlockQuote));
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+        index += 1;
+        if depth == 0 {
+            let (events, offset, quote) = first;
+            let end = parser.get_offset();
+            let start = block_start(source, offset, end, options);
+            let end = start + source[start..end].trim_end().len();
+            blocks.push(TopBlock { events: events..index, range: start..end, quote });
+        }
+    }
+}
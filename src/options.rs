@@ -0,0 +1,1044 @@
+use std::borrow::Cow;
+
+use pulldown_cmark::{Options as ParserOptions, Tag, OPTION_ENABLE_FOOTNOTES, OPTION_ENABLE_TABLES};
+
+/// Options that control the output of the pretty printer.
+///
+/// # Examples
+///
+/// ```rust
+/// use prettify_cmark::{prettify_with_options, EntityPolicy, Options};
+///
+/// let options = Options {
+///     entities: EntityPolicy::Encode,
+///     ..Options::default()
+/// };
+/// let output = prettify_with_options("Caf&eacute; &amp; bar", &options);
+/// assert_eq!(output, "Caf&eacute; &amp; bar");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// How characters that originate from entity references are printed.
+    pub entities: EntityPolicy,
+    /// How non-breaking spaces (U+00A0) in text are printed, overriding
+    /// the entity policy for them.
+    pub non_breaking_spaces: NonBreakingSpaces,
+    /// Handle zero-width and bidirectional control characters in prose,
+    /// which are invisible but can make text read differently from how
+    /// it is rendered or processed.
+    pub invisible_characters: Option<InvisibleCharacterPolicy>,
+    /// Unicode normalization form applied to text content, if any.
+    ///
+    /// Code blocks and inline code are never normalized.
+    pub normalization: Option<Normalization>,
+    /// Style of thematic breaks.
+    pub rule: RuleStyle,
+    /// Case policy applied to the text of headings.
+    pub heading_case: HeadingCase,
+    /// Normalization of the info strings of fenced code blocks.
+    pub info_string: InfoStringOptions,
+    /// Treat the document as a Rust doc comment.
+    ///
+    /// Code blocks that rustdoc considers doctests (including fences
+    /// without a language) are preserved exactly, including their info
+    /// strings and hidden `# ` lines, so that they remain valid doctests.
+    pub rustdoc: bool,
+    /// Treat `[[Wiki Links]]` and `![[embeds]]` as atomic tokens that
+    /// are preserved verbatim.
+    pub wiki_links: bool,
+    /// Case of the type in callout markers such as `> [!NOTE]`.
+    pub callout_case: CalloutCase,
+    /// Convert admonitions (callouts) at the top level of the document
+    /// from one syntax to another.
+    pub admonitions: Option<AdmonitionOptions>,
+    /// Preserve trailing attribute blocks (such as `{#id .class}`) on
+    /// headings and in the info strings of fenced code blocks verbatim.
+    pub attributes: bool,
+    /// Opening and closing delimiters of template placeholders, such as
+    /// `("{{", "}}")`, `("{%", "%}")` or `("${", "}")`.
+    ///
+    /// Placeholders are treated as atomic tokens that are preserved
+    /// verbatim.
+    pub placeholders: Vec<(String, String)>,
+    /// Opening and closing delimiters of the shortcodes of static site
+    /// generators, such as `("{{<", ">}}")` and `("{{%", "%}}")` for Hugo,
+    /// or `("{%", "%}")` for Jekyll.
+    ///
+    /// Shortcodes are preserved verbatim: within text as atomic tokens,
+    /// and as blocks if a shortcode at the top level of the document
+    /// starts a block and ends a line. A block shortcode may span multiple
+    /// lines, and includes everything up to its closing shortcode (such as
+    /// `{{< /name >}}` or `{% endname %}`), if there is one.
+    pub shortcodes: Vec<(String, String)>,
+    /// Options for the sections of the document whose headings match a
+    /// selector, taking effect after the heading of each section.
+    ///
+    /// The first profile whose selector matches a section is used, and
+    /// its options apply to the subsections as well. Options that affect
+    /// the whole document, such as `references`, `footnotes` and the
+    /// `profiles` themselves, are always taken from the document options.
+    pub profiles: Vec<SectionProfile>,
+    /// Merge adjacent block quotes and consecutive lists of the same
+    /// type (ordered or unordered), and remove empty list items.
+    pub normalize_structure: bool,
+    /// How front matter at the start of the document is printed.
+    pub front_matter: FrontMatterPolicy,
+    /// Sort the items of each list that follows a `<!-- prettify: sort -->`
+    /// comment alphabetically (ignoring case) by their first text, keeping
+    /// the nested content of each item with it.
+    ///
+    /// The comment is kept, so that the list stays sorted when it is
+    /// formatted again.
+    pub sort_lists: bool,
+    /// Indent nested list content by this number of columns, instead of
+    /// the width of the list marker.
+    ///
+    /// Markers are padded with spaces to fill the indent unit.
+    pub list_indent: Option<usize>,
+    /// Maximum nesting depth of lists, at least one.
+    ///
+    /// Items of lists nested any deeper are flattened into the deepest
+    /// allowed list, and a `list-depth` diagnostic is reported.
+    pub max_list_depth: Option<usize>,
+    /// Keep reference links and their definitions, instead of inlining
+    /// the destinations of reference links.
+    ///
+    /// This requires the printer to have access to the source document
+    /// (see `PrettyPrinter::push_source`).
+    pub references: Option<ReferenceOptions>,
+    /// Parse and print footnotes (`[^label]`) when printing from source
+    /// using `PrettyPrinter::push_source`.
+    ///
+    /// Footnote events pushed into the printer directly are printed
+    /// regardless of this option.
+    pub footnotes: Option<FootnoteOptions>,
+    /// How images without alt text are handled.
+    ///
+    /// Images for which an `AltTextProvider` supplies alt text are never
+    /// reported.
+    pub image_alt: AltTextPolicy,
+    /// Types of blocks that are copied verbatim from the source, instead
+    /// of being pretty printed.
+    pub verbatim: VerbatimBlocks,
+    /// Sanitization applied to HTML blocks and inline HTML.
+    pub html: HtmlPolicy,
+    /// Report a `duplicate-anchor` diagnostic for headings whose anchor
+    /// (as generated by `Slugger`) collides with a previous heading.
+    pub duplicate_anchors: bool,
+    /// Report a `broken-anchor` diagnostic for each link to a `#fragment`
+    /// that matches neither the anchor of a heading (as generated by
+    /// `Slugger`) nor the `id` or `name` of an HTML element, once the
+    /// document has been printed using `PrettyPrinter::push_source`.
+    pub broken_anchors: bool,
+    /// Maximum level of headings, reporting a `heading-depth` diagnostic
+    /// for each heading that is deeper.
+    pub heading_limit: Option<HeadingLimit>,
+    /// Check that headings form a hierarchy, reporting a diagnostic for
+    /// each heading that does not.
+    pub heading_hierarchy: Option<HeadingHierarchy>,
+    /// Strip trailing whitespace from every printed line, including lines
+    /// of HTML blocks and raw text. The contents of code blocks and of
+    /// blocks copied verbatim are always preserved exactly.
+    pub trim_trailing_whitespace: bool,
+    /// Expand tabs in prose to spaces, up to the next multiple of this
+    /// width in columns. Tabs in code blocks are always kept, and are
+    /// measured up to the next multiple of four columns when wrapping
+    /// lines.
+    pub expand_tabs: Option<usize>,
+    /// Whether the continuation lines of paragraphs inside block quotes
+    /// repeat the `>` marker.
+    pub block_quote_style: BlockQuoteStyle,
+    /// Normalization of the titles of links and images.
+    pub titles: TitleOptions,
+    /// Convert bare URLs (starting with `http://`, `https://` or
+    /// `ftp://`) and email addresses in text into links of this style.
+    ///
+    /// Text inside of links and images is never converted.
+    pub bare_urls: Option<AutolinkStyle>,
+    /// Parse and print tables when printing from source using
+    /// `PrettyPrinter::push_source`.
+    ///
+    /// Table events pushed into the printer directly are printed
+    /// regardless of this option, using the default table options.
+    pub tables: Option<TableOptions>,
+    /// How characters that would otherwise be mistaken for markup are
+    /// escaped.
+    pub escapes: EscapeStyle,
+    /// How `<br>` elements in inline HTML are printed.
+    pub html_breaks: HtmlBreakPolicy,
+    /// How line breaks inside of headings and table cells are printed.
+    pub heading_breaks: HeadingBreakStyle,
+    /// How emphasis, strong emphasis, code spans, links and list items
+    /// without any content are printed.
+    pub empty_constructs: EmptyConstructPolicy,
+    /// Wrap prose at this width in columns, breaking lines at the spaces
+    /// between words.
+    ///
+    /// Words that are longer than the width are not broken. Headings,
+    /// tables, code and inline HTML are never wrapped.
+    pub wrap: Option<usize>
+}
+
+impl Options {
+    /// The extensions of the parser that documents are parsed with.
+    pub(crate) fn parser_options(&self) -> ParserOptions {
+        let mut parser = ParserOptions::empty();
+        if self.footnotes.is_some() {
+            parser.insert(OPTION_ENABLE_FOOTNOTES);
+        }
+        if self.tables.is_some() {
+            parser.insert(OPTION_ENABLE_TABLES);
+        }
+        parser
+    }
+}
+
+/// Options for the sections of a document that match a selector, as used
+/// by `Options::profiles`.
+///
+/// Selectors are paths of headings separated by `/`, such as
+/// `Guide/Install`, which are compared ignoring case. A `*` matches any
+/// heading and `**` any number of headings, so that `**/API Reference`
+/// matches a section with that heading anywhere in the document. Only
+/// headings at the top level of the document are considered.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, Options, SectionProfile};
+/// let options = Options {
+///     wrap: Some(12),
+///     profiles: vec![SectionProfile {
+///         selector: "**/API Reference".to_string(),
+///         options: Options::default()
+///     }],
+///     ..Options::default()
+/// };
+/// let source = "# Lorem\n\nLorem ipsum dolor\n\n## API Reference\n\nLorem ipsum dolor\
+///               \n\n# Ipsum\n\nLorem ipsum dolor";
+/// assert_eq!(prettify_with_options(source, &options),
+///            "# Lorem\n\nLorem ipsum\ndolor\n\n## API Reference\n\nLorem ipsum dolor\
+///             \n\n# Ipsum\n\nLorem ipsum\ndolor");
+/// ```
+#[derive(Debug, Clone)]
+pub struct SectionProfile {
+    /// The selector for the heading paths of sections.
+    pub selector: String,
+    /// The options that the sections are printed with.
+    pub options: Options
+}
+
+/// Policy for printing characters that may have been written as
+/// HTML entity references (such as `&nbsp;` or `&amp;`) in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntityPolicy {
+    /// Print the decoded characters as they were produced by the parser.
+    #[default]
+    Decode,
+    /// Encode special and non-ASCII characters as entity references.
+    Encode,
+    /// Print entity references exactly as they appeared in the source.
+    ///
+    /// This requires the printer to have access to the source document
+    /// (see `PrettyPrinter::push_source`), and otherwise behaves like
+    /// `Decode`.
+    Preserve
+}
+
+/// Printing of non-breaking spaces (U+00A0) in text, such as between a
+/// number and its unit.
+///
+/// Non-breaking spaces are never used to wrap lines at, nor are they
+/// collapsed or trimmed like other whitespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonBreakingSpaces {
+    /// Print non-breaking spaces according to `Options::entities`.
+    #[default]
+    Entities,
+    /// Print non-breaking spaces as the U+00A0 character, even where
+    /// the source had an entity or character reference.
+    Character,
+    /// Print non-breaking spaces as `&nbsp;`.
+    Entity
+}
+
+/// Handling of zero-width and bidirectional control characters in prose,
+/// such as U+200B (zero width space) or U+202E (right-to-left override).
+///
+/// Code blocks and code spans are left alone. Note that some of these
+/// characters are legitimately used, such as the zero width joiner in
+/// emoji sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvisibleCharacterPolicy {
+    /// Keep the characters, and report an `invisible-character` warning
+    /// for each of them.
+    Report,
+    /// Remove the characters.
+    Strip,
+    /// Print the characters as numeric character references, such as
+    /// `&#8203;`, so that they are visible in the source.
+    Escape
+}
+
+/// Unicode normalization forms that can be applied to text content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition (NFC).
+    Nfc,
+    /// Compatibility composition (NFKC).
+    Nfkc
+}
+
+/// Style of thematic breaks (rules).
+///
+/// The default style is `---`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleStyle {
+    /// The character used to draw the rule.
+    pub marker: RuleMarker,
+    /// The number of marker characters, at least three.
+    pub length: usize,
+    /// Whether marker characters are separated by spaces (`- - -`).
+    pub spaced: bool
+}
+
+impl Default for RuleStyle {
+    fn default() -> RuleStyle {
+        RuleStyle {
+            marker: RuleMarker::Dash,
+            length: 3,
+            spaced: false
+        }
+    }
+}
+
+impl RuleStyle {
+    pub(crate) fn render(&self) -> String {
+        let marker = match self.marker {
+            RuleMarker::Dash => "-",
+            RuleMarker::Asterisk => "*",
+            RuleMarker::Underscore => "_"
+        };
+        let markers = vec![marker; self.length.max(3)];
+        markers.join(if self.spaced { " " } else { "" })
+    }
+}
+
+/// Characters that can be used to draw thematic breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleMarker {
+    /// `-`
+    Dash,
+    /// `*`
+    Asterisk,
+    /// `_`
+    Underscore
+}
+
+/// Case policy applied to the text of headings.
+///
+/// Text within inline code is never changed.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum HeadingCase {
+    /// Leave headings as they are.
+    #[default]
+    Preserve,
+    /// Capitalize the first word and lowercase all others
+    /// (except for words written in all-caps, such as acronyms).
+    Sentence,
+    /// Capitalize all words except for short articles, conjunctions
+    /// and prepositions.
+    Title,
+    /// Transform the text of headings using a custom function.
+    ///
+    /// The function receives the text of the heading with all markup
+    /// removed. If it preserves the number of characters, the result is
+    /// mapped back onto the original markup, otherwise the function is
+    /// applied to each run of text individually.
+    Custom(fn(&str) -> String)
+}
+
+impl HeadingCase {
+    pub(crate) fn is_preserve(&self) -> bool {
+        matches!(*self, HeadingCase::Preserve)
+    }
+}
+
+/// Maximum level of headings.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, HeadingLimit, Options};
+/// let options = Options {
+///     heading_limit: Some(HeadingLimit { max_level: 4, demote: true }),
+///     ..Options::default()
+/// };
+/// let output = prettify_with_options("#### Lorem\n\n##### Ipsum _dolor_", &options);
+/// assert_eq!(output, "#### Lorem\n\n**Ipsum *dolor***");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeadingLimit {
+    /// The deepest allowed heading level, such as `4`.
+    pub max_level: i32,
+    /// Print deeper headings as paragraphs of strong text, instead of
+    /// only reporting them. Empty headings are dropped.
+    pub demote: bool
+}
+
+/// Checks of the hierarchy of headings, as used by
+/// `Options::heading_hierarchy`.
+///
+/// A `no-skipped-heading-levels` warning is reported for a heading that
+/// is more than one level deeper than the previous heading, a `single-h1`
+/// warning for each level 1 heading after the first one, and a `first-h1`
+/// warning for a level 1 heading that follows headings of other levels,
+/// as reported by the `NoSkippedHeadingLevels`, `SingleH1` and `FirstH1`
+/// lints on the levels of the headings in the source. Rules that are also
+/// registered with `PrettyPrinter::set_lints` are left to those lints.
+/// Headings in block quotes and lists are checked as well.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, HeadingHierarchy, Options};
+/// let options = Options { heading_hierarchy: Some(HeadingHierarchy { fix: true }), ..Options::default() };
+/// let output = prettify_with_options("# Lorem\n\n### Ipsum\n\n#### Dolor\n\n# Sit", &options);
+/// assert_eq!(output, "# Lorem\n\n## Ipsum\n\n### Dolor\n\n## Sit");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeadingHierarchy {
+    /// Repair the hierarchy while printing, by demoting level 1 headings
+    /// other than the first heading to level 2, and promoting headings
+    /// that skip levels to the level after that of their parent heading.
+    pub fix: bool
+}
+
+/// Normalization of the info strings of fenced code blocks.
+///
+/// Info strings are treated as a language followed by a list of
+/// attributes, separated by commas (such as `rust,ignore,no_run`)
+/// or whitespace. By default, info strings are printed as-is.
+#[derive(Debug, Clone, Default)]
+pub struct InfoStringOptions {
+    /// Sort the attributes following the language alphabetically.
+    pub sort_attributes: bool,
+    /// Remove duplicate attributes.
+    pub dedupe_attributes: bool,
+    /// Replacements for the language or attributes, such as
+    /// `("rs", "rust")`.
+    pub aliases: Vec<(String, String)>,
+    /// Print the language in lowercase, before applying aliases.
+    pub lowercase_language: bool,
+    /// Report a `code-language` diagnostic for code blocks without a
+    /// language.
+    ///
+    /// Indented code blocks are reported as well, since they are printed
+    /// as fenced code blocks. In rustdoc mode, doctests are exempt.
+    pub require_language: bool
+}
+
+impl InfoStringOptions {
+    /// Commonly used aliases for languages, such as `("sh", "bash")` or
+    /// `("js", "javascript")`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use prettify_cmark::{prettify_with_options, InfoStringOptions, Options};
+    /// let options = Options {
+    ///     info_string: InfoStringOptions {
+    ///         aliases: InfoStringOptions::common_aliases(),
+    ///         lowercase_language: true,
+    ///         ..InfoStringOptions::default()
+    ///     },
+    ///     ..Options::default()
+    /// };
+    /// let output = prettify_with_options("```JS\nfoo();\n```", &options);
+    /// assert_eq!(output, "```javascript\nfoo();\n```");
+    /// ```
+    pub fn common_aliases() -> Vec<(String, String)> {
+        [
+            ("sh", "bash"), ("shell", "bash"), ("zsh", "bash"),
+            ("js", "javascript"), ("jsx", "javascript"),
+            ("ts", "typescript"), ("tsx", "typescript"),
+            ("py", "python"), ("rb", "ruby"), ("rs", "rust"),
+            ("yml", "yaml"), ("md", "markdown")
+        ].iter().map(|&(from, to)| (from.to_string(), to.to_string())).collect()
+    }
+}
+
+/// Case of the type in callout (admonition) markers such as `> [!NOTE]`.
+///
+/// Regardless of this option, callout markers are always kept on a line
+/// of their own, along with their title if any, such as in
+/// `> [!NOTE] Title`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CalloutCase {
+    /// Leave the type as it is.
+    #[default]
+    Preserve,
+    /// Print the type in uppercase, such as `[!NOTE]`.
+    Upper,
+    /// Print the type in lowercase, such as `[!note]`.
+    Lower
+}
+
+impl CalloutCase {
+    pub(crate) fn apply(&self, marker: &str) -> String {
+        match *self {
+            CalloutCase::Preserve => marker.to_string(),
+            CalloutCase::Upper => marker.to_ascii_uppercase(),
+            CalloutCase::Lower => marker.to_ascii_lowercase()
+        }
+    }
+}
+
+/// Conversion of admonitions from one syntax to another, for migrating
+/// documentation between platforms.
+///
+/// Only admonitions in the source syntax are converted. The kind of an
+/// admonition (such as `note` or `warning`) is kept as it is, while its
+/// title, which only mkdocs supports, is kept as a bold paragraph at the
+/// start of its content in the other syntaxes.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, AdmonitionOptions, AdmonitionStyle, Options};
+/// let options = Options {
+///     admonitions: Some(AdmonitionOptions { source: AdmonitionStyle::MkDocs, target: AdmonitionStyle::GitHub }),
+///     ..Options::default()
+/// };
+/// let output = prettify_with_options("!!! warning\n    Lorem _ipsum_", &options);
+/// assert_eq!(output, "> [!WARNING]\n> Lorem *ipsum*");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdmonitionOptions {
+    /// The syntax of the admonitions that are converted.
+    pub source: AdmonitionStyle,
+    /// The syntax they are converted into.
+    pub target: AdmonitionStyle
+}
+
+/// Syntax of admonitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdmonitionStyle {
+    /// GitHub callouts, a block quote starting with a marker such as
+    /// `> [!NOTE]` on a line of its own.
+    GitHub,
+    /// mkdocs (and mdBook admonish) admonitions, such as `!!! note
+    /// "Title"`, followed by content indented by four spaces.
+    MkDocs,
+    /// A block quote starting with a bold label, such as `> **Note**` or
+    /// `> **Warning:**`, whose text is the kind of a common admonition.
+    BoldLabel
+}
+
+/// Handling of front matter at the start of a document, as used by static
+/// site generators: YAML between `---` lines (closed by `---` or `...`),
+/// or TOML between `+++` lines.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, FrontMatterPolicy, Options};
+/// let options = Options { front_matter: FrontMatterPolicy::Preserve, ..Options::default() };
+/// let output = prettify_with_options("---\ntitle:   Lorem\n---\nIpsum _dolor_", &options);
+/// assert_eq!(output, "---\ntitle:   Lorem\n---\n\nIpsum *dolor*");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrontMatterPolicy {
+    /// Parse front matter as Markdown, like the rest of the document.
+    #[default]
+    Markdown,
+    /// Copy front matter as it is.
+    Preserve,
+    /// Print YAML front matter with the keys of mappings sorted and with
+    /// canonical quoting of strings, removing comments.
+    ///
+    /// TOML front matter is copied as it is, as is YAML front matter that
+    /// cannot be parsed, which is reported as a `front-matter` diagnostic.
+    /// This requires the `yaml` feature; without it, YAML front matter is
+    /// copied as it is and a `front-matter` diagnostic is reported.
+    Canonical
+}
+
+/// Sanitization of HTML blocks and inline HTML, for printing untrusted
+/// documents.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, HtmlPolicy, Options};
+/// let options = Options {
+///     html: HtmlPolicy::Allowlist {
+///         tags: vec!["a".to_string()],
+///         attributes: vec!["href".to_string()]
+///     },
+///     ..Options::default()
+/// };
+/// let source = "Lorem <a href=\"x\" onclick=\"y()\"><b>ipsum</b></a>";
+/// let output = prettify_with_options(source, &options);
+/// assert_eq!(output, "Lorem <a href=\"x\">ipsum</a>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum HtmlPolicy {
+    /// Print all HTML as it is.
+    #[default]
+    Preserve,
+    /// Remove `<script>` and `<style>` elements including their content,
+    /// event handler attributes (such as `onclick`), and URLs with
+    /// schemes other than `http`, `https` and `mailto`, such as
+    /// `javascript:` URLs. Relative URLs are kept.
+    StripScripts,
+    /// Like `StripScripts`, but additionally remove all tags and
+    /// attributes that are not allowed. The content of removed tags is
+    /// kept.
+    Allowlist {
+        /// Names of allowed tags, such as `"a"` or `"img"`.
+        tags: Vec<String>,
+        /// Names of allowed attributes, such as `"href"`.
+        attributes: Vec<String>
+    }
+}
+
+/// Types of blocks that are left untouched by the printer.
+///
+/// Verbatim blocks are copied from the source document, which requires
+/// the printer to have access to it (see `PrettyPrinter::push_source`).
+/// Only blocks at the top level of the document are left untouched,
+/// nested blocks are always pretty printed.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_with_options, Options, VerbatimBlocks};
+/// let options = Options {
+///     verbatim: VerbatimBlocks { lists: true, ..VerbatimBlocks::default() },
+///     ..Options::default()
+/// };
+/// let output = prettify_with_options("Lorem\n_ipsum_\n\n* dolor\n* _sit_", &options);
+/// assert_eq!(output, "Lorem *ipsum*\n\n* dolor\n* _sit_");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VerbatimBlocks {
+    /// Leave paragraphs untouched.
+    pub paragraphs: bool,
+    /// Leave headings untouched.
+    pub headings: bool,
+    /// Leave block quotes untouched.
+    pub block_quotes: bool,
+    /// Leave lists untouched.
+    pub lists: bool,
+    /// Leave code blocks untouched, including their fences.
+    pub code_blocks: bool,
+    /// Leave footnote definitions untouched.
+    pub footnote_definitions: bool
+}
+
+impl VerbatimBlocks {
+    pub(crate) fn contains(&self, tag: &Tag) -> bool {
+        match *tag {
+            Tag::Paragraph => self.paragraphs,
+            Tag::Header(_) => self.headings,
+            Tag::BlockQuote => self.block_quotes,
+            Tag::List(_) => self.lists,
+            Tag::CodeBlock(_) => self.code_blocks,
+            Tag::FootnoteDefinition(_) => self.footnote_definitions,
+            _ => false
+        }
+    }
+}
+
+/// Handling of images without alt text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AltTextPolicy {
+    /// Print images without alt text as they are.
+    #[default]
+    Ignore,
+    /// Report an `image-alt` warning for each image without alt text.
+    Warn,
+    /// Report an `image-alt` error and fail printing when an image has
+    /// no alt text.
+    Require
+}
+
+/// Printing of emphasis, strong emphasis, code spans and links without
+/// any content, and of empty list items.
+///
+/// Empty inlines are printed as markup that does not parse back into
+/// the same construct, such as `**` for empty emphasis. Empty list items
+/// parse back, but usually remain from editing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyConstructPolicy {
+    /// Print empty constructs as they are.
+    #[default]
+    Preserve,
+    /// Leave out empty constructs.
+    Drop,
+    /// Print empty inlines as HTML elements, such as `<em></em>`, which
+    /// parse back. Empty list items are printed as they are.
+    Html,
+    /// Report an `empty-construct` error and fail printing when a
+    /// construct is empty.
+    Error
+}
+
+/// Numbering, placement and style of footnotes.
+///
+/// Renumbering, removing unreferenced definitions and converting between
+/// footnote styles requires the printer to have access to the source
+/// document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FootnoteOptions {
+    /// Number footnotes sequentially in order of first reference.
+    pub renumber: bool,
+    /// Where footnote definitions are placed.
+    pub placement: FootnotePlacement,
+    /// Remove definitions that are never referenced, reporting an
+    /// `unused-footnote` diagnostic for each.
+    pub remove_unreferenced: bool,
+    /// Remove definitions that repeat the label of an earlier definition.
+    /// Duplicates with different content are reported as
+    /// `duplicate-footnote` diagnostics either way.
+    pub remove_duplicates: bool,
+    /// Sort definitions alphabetically by label, instead of in order of
+    /// first reference. Only applies to definitions that are moved by
+    /// `placement`, and has no effect on renumbered footnotes.
+    pub sort: bool,
+    /// Whether footnotes are written as references or inline.
+    pub style: FootnoteStyle
+}
+
+/// Style of footnotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnoteStyle {
+    /// Leave footnotes as they are.
+    #[default]
+    Preserve,
+    /// Convert inline footnotes (`^[text]`) into references to numbered
+    /// definitions, which are added at the end of the document.
+    ///
+    /// Inline footnotes are not recognized by the parser, so they are
+    /// found in the text of the source document before it is parsed.
+    Reference,
+    /// Write the definitions of footnotes as inline footnotes (`^[text]`)
+    /// at their reference, if they consist of a single paragraph without
+    /// hard line breaks or unbalanced brackets, and are referenced
+    /// exactly once outside of footnote definitions.
+    Inline
+}
+
+/// Placement of footnote definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FootnotePlacement {
+    /// Leave definitions where they are.
+    #[default]
+    Preserve,
+    /// Place all definitions at the end of the document, in order of
+    /// first reference.
+    DocumentEnd,
+    /// Place definitions at the end of the section (delimited by
+    /// top-level headings) in which they are first referenced.
+    SectionEnd
+}
+
+/// Printing of reference links and link reference definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReferenceOptions {
+    /// How the labels of references are printed.
+    pub labels: ReferenceLabels,
+    /// Sort definitions alphabetically by label, instead of in order of
+    /// first use. Has no effect on numbered labels.
+    pub sort: bool,
+    /// Where definitions are placed.
+    pub placement: ReferencePlacement
+}
+
+/// Normalization of the labels of reference links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferenceLabels {
+    /// Use the label of the definition, as written in the source.
+    #[default]
+    Preserve,
+    /// Use the label of the definition in lowercase.
+    Lowercase,
+    /// Number labels sequentially in order of first use, such as `[1]`.
+    Numbered
+}
+
+/// Placement of link reference definitions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferencePlacement {
+    /// Place all definitions at the end of the document.
+    #[default]
+    DocumentEnd,
+    /// Place definitions at the end of the section (delimited by
+    /// top-level headings) in which they are first used.
+    SectionEnd
+}
+
+/// Style of the continuation lines of paragraphs inside block quotes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockQuoteStyle {
+    /// Start every line with a `>` marker.
+    #[default]
+    Explicit,
+    /// Only start the first line of a paragraph with a `>` marker, and
+    /// rely on lazy continuation for the following lines.
+    Lazy
+}
+
+/// Normalization of the titles of links and images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TitleOptions {
+    /// The quotes that titles are delimited with.
+    pub quote: TitleQuote,
+    /// Trim whitespace from both ends of titles, and collapse runs of
+    /// whitespace within them into a single space.
+    pub collapse_whitespace: bool,
+    /// Drop titles that are empty or consist of whitespace only.
+    pub drop_empty: bool,
+    /// Drop all titles, including those of link reference definitions.
+    pub drop_all: bool
+}
+
+/// Options for printing tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableOptions {
+    /// Length of the dashes in the separator row below the header.
+    pub separator: TableSeparator,
+    /// Pad cells with spaces to the width of their column, according to
+    /// the alignment of the column.
+    ///
+    /// Without padding, every cell is printed as is, which keeps diffs
+    /// small when the content of a single cell changes.
+    pub padding: bool,
+    /// The maximum width of the lines of a table, not counting the
+    /// indentation of the surrounding blocks.
+    ///
+    /// Tables with wider lines are printed according to `overflow`
+    /// instead. By default, tables may be arbitrarily wide.
+    pub max_width: Option<usize>,
+    /// How to print tables that are wider than `max_width`.
+    pub overflow: TableOverflow
+}
+
+impl Default for TableOptions {
+    fn default() -> TableOptions {
+        TableOptions {
+            separator: TableSeparator::Padded,
+            padding: true,
+            max_width: None,
+            overflow: TableOverflow::default()
+        }
+    }
+}
+
+/// Length of the dashes in the separator row of a table.
+///
+/// The alignment markers of each column (`:---`, `:---:` or `---:`) are
+/// preserved either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableSeparator {
+    /// Three dashes, such as `---` or `:---:`.
+    Minimal,
+    /// As many dashes as the column is wide.
+    #[default]
+    Padded
+}
+
+/// How to print a table whose lines would be wider than
+/// `TableOptions::max_width`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableOverflow {
+    /// Print the table without padding and with minimal separators. The
+    /// table may still be wider than the maximum width if its cells are.
+    #[default]
+    Minimal,
+    /// Print the table as an HTML block, with a line for each cell.
+    Html
+}
+
+/// How characters are escaped, for each class of characters that may
+/// need escaping.
+///
+/// Some renderers handle backslash escapes poorly, or strip them when
+/// converting documents, while entity references work everywhere outside
+/// of code.
+///
+/// # Examples
+///
+/// ```rust
+/// use prettify_cmark::{prettify_with_options, EscapeMethod, EscapeStyle, Options};
+///
+/// let options = Options {
+///     escapes: EscapeStyle { emphasis: EscapeMethod::Entity, ..EscapeStyle::default() },
+///     ..Options::default()
+/// };
+/// let output = prettify_with_options("2 \\* 3 = 6 \\`", &options);
+/// assert_eq!(output, "2 &#42; 3 = 6 \\`");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EscapeStyle {
+    /// `*` and `_`, which could start or end emphasis.
+    pub emphasis: EscapeMethod,
+    /// `` ` ``, which could start a code span.
+    pub code: EscapeMethod,
+    /// `[` and `]` in the text of links and images.
+    pub brackets: EscapeMethod,
+    /// `<` and `&`, which could start HTML or an entity reference.
+    pub html: EscapeMethod,
+    /// `\` itself, where it is followed by punctuation.
+    pub backslash: EscapeMethod,
+    /// Characters that would start a block at the beginning of a line,
+    /// such as `#`, `>`, `-` or the `.` of an ordered list marker, and
+    /// any other punctuation that is escaped in the source.
+    pub block_markers: EscapeMethod
+}
+
+impl EscapeStyle {
+    /// The method used to escape a character.
+    pub(crate) fn method(&self, c: char) -> EscapeMethod {
+        match c {
+            '*' | '_' => self.emphasis,
+            '`' => self.code,
+            '[' | ']' => self.brackets,
+            '<' | '&' => self.html,
+            '\\' => self.backslash,
+            _ => self.block_markers
+        }
+    }
+}
+
+/// A way of escaping a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EscapeMethod {
+    /// A backslash escape, such as `\*`.
+    #[default]
+    Backslash,
+    /// A numeric character reference, such as `&#42;`.
+    Entity
+}
+
+/// Printing of `<br>` elements in inline HTML.
+///
+/// Outside of headings and table cells, which cannot span multiple lines,
+/// the policies other than `Preserve` end the line at every `<br>`, so
+/// that the source reads like the rendered document. A line break that
+/// follows the element in the source is dropped, as is whitespace at the
+/// start of the next line. A `<br>` at the end of a paragraph is always
+/// kept as it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HtmlBreakPolicy {
+    /// Print `<br>` elements like any other inline HTML.
+    #[default]
+    Preserve,
+    /// Keep `<br>` elements, and start a new line after each of them.
+    BreakLine,
+    /// Replace `<br>` elements without attributes by hard line breaks
+    /// (`\` at the end of the line).
+    Convert
+}
+
+/// Printing of line breaks inside of headings and table cells, which
+/// cannot span multiple lines.
+///
+/// Such breaks can only be pushed into the printer as events, or come
+/// from text that contains line breaks, since the parser ends headings
+/// and table cells at the end of the line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeadingBreakStyle {
+    /// Replace all line breaks by spaces.
+    #[default]
+    Space,
+    /// Replace hard line breaks by `<br>` elements, and soft line breaks
+    /// by spaces.
+    Html
+}
+
+/// Markup used for links created from bare URLs and email addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutolinkStyle {
+    /// `<https://example.com>` or `<user@example.com>`
+    #[default]
+    Angle,
+    /// `[https://example.com](https://example.com)` or
+    /// `[user@example.com](mailto:user@example.com)`
+    Inline
+}
+
+/// Delimiters of link and image titles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TitleQuote {
+    /// `"title"`
+    #[default]
+    Double,
+    /// `'title'`
+    Single,
+    /// `(title)`
+    Parentheses
+}
+
+impl TitleOptions {
+    /// Render a title including its delimiters, or `None` if the title is
+    /// to be left out.
+    ///
+    /// Line breaks are always replaced by a single space, since the lines
+    /// that follow them would have to be indented like the surrounding
+    /// blocks, and could be mistaken for the start of a block.
+    pub(crate) fn render(&self, title: &str) -> Option<String> {
+        if self.drop_all {
+            return None;
+        }
+        let title = if self.collapse_whitespace {
+            Cow::Owned(title.split_ascii_whitespace().collect::<Vec<_>>().join(" "))
+        } else {
+            join_lines(title)
+        };
+        if title.is_empty() || self.drop_empty && title.trim().is_empty() {
+            return None;
+        }
+        let (open, close) = match self.quote {
+            TitleQuote::Double => ('"', '"'),
+            TitleQuote::Single => ('\'', '\''),
+            TitleQuote::Parentheses => ('(', ')')
+        };
+        let mut rendered = String::with_capacity(title.len() + 2);
+        rendered.push(open);
+        let mut chars = title.chars().peekable();
+        while let Some(c) = chars.next() {
+            let escaped_backslash = c == '\\' && chars.peek().is_none_or(|next| next.is_ascii_punctuation());
+            if c == open || c == close || escaped_backslash {
+                rendered.push('\\');
+            }
+            rendered.push(c);
+        }
+        rendered.push(close);
+        Some(rendered)
+    }
+}
+
+/// Join the lines of a title by single spaces, dropping the whitespace
+/// around line breaks and any blank lines.
+fn join_lines(title: &str) -> Cow<'_, str> {
+    if !title.contains(['\n', '\r']) {
+        return Cow::Borrowed(title);
+    }
+    let lines = title.split(['\n', '\r']).collect::<Vec<_>>();
+    let last = lines.len() - 1;
+    let mut joined = String::with_capacity(title.len());
+    for (index, line) in lines.into_iter().enumerate() {
+        let line = if index > 0 { line.trim_start() } else { line };
+        let line = if index < last { line.trim_end() } else { line };
+        if line.is_empty() {
+            continue;
+        }
+        if !joined.is_empty() {
+            joined.push(' ');
+        }
+        joined.push_str(line);
+    }
+    Cow::Owned(joined)
+}
@@ -0,0 +1,262 @@
+use std::mem;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use lints::{FirstH1, Lint, NoSkippedHeadingLevels, SingleH1};
+
+/// A heading in the outline of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// The text of the heading, with all markup removed.
+    pub text: String,
+    /// The level of the heading, from 1 to 6.
+    pub level: u8,
+    /// A GitHub-style anchor for the heading, such as `getting-started`.
+    ///
+    /// Anchors are unique within the document, duplicates are suffixed
+    /// with `-1`, `-2`, and so on.
+    pub slug: String,
+    /// The byte range of the heading in the source.
+    pub span: Range<usize>
+}
+
+/// Returns the headings of a CommonMark document up to the given depth.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::outline;
+/// let entries = outline("# Intro\n\n## Getting *started*\n\n### Details", 2);
+/// assert_eq!(entries.len(), 2);
+/// assert_eq!(entries[1].text, "Getting started");
+/// assert_eq!(entries[1].level, 2);
+/// assert_eq!(entries[1].slug, "getting-started");
+/// assert_eq!(entries[1].span, 9..29);
+/// ```
+pub fn outline(source: &str, max_depth: u8) -> Vec<OutlineEntry> {
+    let mut entries = Vec::new();
+    let mut slugger = Slugger::new();
+    let mut heading: Option<(usize, String)> = None;
+    let mut parser = Parser::new(source);
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => return entries
+        };
+        let end = parser.get_offset();
+        match event {
+            Event::Start(Tag::Header(_)) => {
+                let leading = source[start..end].len() - source[start..end].trim_start().len();
+                heading = Some((start + leading, String::new()));
+            },
+            Event::End(Tag::Header(level)) => {
+                if let Some((start, text)) = heading.take() {
+                    let slug = slugger.slug(&text);
+                    if level > i32::from(max_depth) {
+                        continue;
+                    }
+                    let end = start + source[start..end].trim_end_matches(['\r', '\n']).len();
+                    entries.push(OutlineEntry {
+                        slug,
+                        text,
+                        level: level as u8,
+                        span: start..end
+                    });
+                }
+            },
+            Event::Text(ref text) | Event::InlineHtml(ref text) => {
+                if let Some((_, ref mut heading)) = heading {
+                    heading.push_str(text);
+                }
+            },
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, ref mut heading)) = heading {
+                    heading.push(' ');
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Generator for unique GitHub-style heading anchors.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::Slugger;
+/// let mut slugger = Slugger::new();
+/// assert_eq!(slugger.slug("Getting Started!"), "getting-started");
+/// assert_eq!(slugger.slug("Getting started"), "getting-started-1");
+/// assert_eq!(slugger.slug("Getting started"), "getting-started-2");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Slugger {
+    seen: Vec<String>
+}
+
+impl Slugger {
+    /// Create a slugger that has not seen any headings yet.
+    pub fn new() -> Slugger {
+        Slugger::default()
+    }
+
+    /// Returns a unique anchor for the text of a heading.
+    pub fn slug(&mut self, text: &str) -> String {
+        let base = slug(text);
+        let mut unique = base.clone();
+        let mut suffix = 0;
+        while self.seen.contains(&unique) {
+            suffix += 1;
+            unique = format!("{}-{}", base, suffix);
+        }
+        self.seen.push(unique.clone());
+        unique
+    }
+
+    /// Check whether an anchor has been generated.
+    pub(crate) fn contains(&self, anchor: &str) -> bool {
+        self.seen.iter().any(|seen| seen == anchor)
+    }
+}
+
+/// The levels of the headings pushed into the printer so far, to check
+/// and repair their hierarchy as configured by `Options::heading_hierarchy`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct HeadingLevels {
+    skipped_levels: NoSkippedHeadingLevels,
+    single_h1: SingleH1,
+    first_h1: FirstH1,
+    /// The level of each enclosing heading in the source, along with the
+    /// level it is printed at, from the outermost one.
+    sections: Vec<(i32, i32)>,
+    /// The level that the heading that is being pushed is printed at.
+    current: Option<i32>
+}
+
+impl HeadingLevels {
+    /// The lints that check the levels of the headings in the source.
+    pub fn lints(&mut self) -> [&mut dyn Lint; 3] {
+        [&mut self.skipped_levels, &mut self.single_h1, &mut self.first_h1]
+    }
+
+    /// The level to print a heading that starts at.
+    ///
+    /// If `fix` is set, a level 1 heading that is not the first heading is
+    /// demoted to level 2, and a heading that skips levels is promoted to
+    /// the level after that of its parent heading, so that headings which
+    /// are siblings in the source stay siblings.
+    pub fn start(&mut self, level: i32, fix: bool) -> i32 {
+        let mut fixed = level;
+        if level == 1 && !self.sections.is_empty() {
+            fixed = 2;
+        }
+        while self.sections.last().is_some_and(|&(original, _)| original >= level) {
+            self.sections.pop();
+        }
+        if let Some(&(_, parent)) = self.sections.last() {
+            fixed = fixed.min(parent + 1);
+        }
+        self.sections.push((level, fixed));
+        let printed = if fix { fixed } else { level };
+        self.current = Some(printed);
+        printed
+    }
+
+    /// The level to print the end of a heading at.
+    pub fn end(&mut self, level: i32) -> i32 {
+        self.current.take().unwrap_or(level)
+    }
+}
+
+/// The path of headings of the section that the printer is in, tracked
+/// from the top-level headings that are pushed into it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct HeadingPath {
+    /// The level and text of each heading, from the outermost one.
+    headings: Vec<(i32, String)>,
+    /// The level and text of the heading that is being pushed.
+    current: Option<(i32, String)>,
+    /// Whether the path changed since it was last checked.
+    changed: bool
+}
+
+impl HeadingPath {
+    /// Keep track of a heading, given the depth of the blocks around the
+    /// event before it is pushed.
+    pub fn push_event(&mut self, event: &Event, depth: usize) {
+        match *event {
+            Event::Start(Tag::Header(level)) if depth == 0 => self.current = Some((level, String::new())),
+            Event::End(Tag::Header(_)) if depth == 1 => {
+                if let Some((level, text)) = self.current.take() {
+                    self.headings.retain(|&(outer, _)| outer < level);
+                    self.headings.push((level, text.trim().to_string()));
+                    self.changed = true;
+                }
+            },
+            Event::Text(ref text) | Event::InlineHtml(ref text) => {
+                if let Some((_, ref mut heading)) = self.current {
+                    heading.push_str(text);
+                }
+            },
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some((_, ref mut heading)) = self.current {
+                    heading.push(' ');
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Check whether the path changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        mem::replace(&mut self.changed, false)
+    }
+
+    /// The text of each heading, from the outermost one.
+    pub fn path(&self) -> Vec<&str> {
+        self.headings.iter().map(|(_, text)| text.as_str()).collect()
+    }
+}
+
+/// Check whether a selector (as described for `SectionProfile`) matches
+/// the heading path of a section or of one of its ancestors.
+pub(crate) fn matches_selector(selector: &str, path: &[&str]) -> bool {
+    let segments = selector.split('/').map(str::trim).filter(|segment| !segment.is_empty()).collect::<Vec<_>>();
+    !segments.is_empty() && (1..=path.len()).any(|length| matches_segments(&segments, &path[..length]))
+}
+
+fn matches_segments(segments: &[&str], path: &[&str]) -> bool {
+    match segments.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_segments(rest, &path[skip..])),
+        Some((&segment, rest)) => match path.split_first() {
+            Some((heading, path)) => {
+                (segment == "*" || segment.to_lowercase() == heading.to_lowercase()) && matches_segments(rest, path)
+            },
+            None => false
+        }
+    }
+}
+
+/// Derive a GitHub-style anchor from the text of a heading.
+pub(crate) fn slug(text: &str) -> String {
+    text.trim().chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c.to_lowercase().collect::<String>())
+            } else if c.is_whitespace() {
+                Some("-".to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
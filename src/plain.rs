@@ -0,0 +1,91 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+
+/// How `to_plain_text` prints links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PlainTextLinks {
+    /// Keep the text of links and drop their destinations.
+    #[default]
+    TextOnly,
+    /// Follow the text of links by their destination in parentheses,
+    /// unless the text already is the destination, as in autolinks.
+    WithUrl
+}
+
+/// Renders a CommonMark document as plain text, for search indexing and
+/// excerpts.
+///
+/// Like `render_html`, the document is pretty printed using the given
+/// options first, so that the text reflects the transforms applied by
+/// the pretty printer. All markup is stripped: blocks are separated by
+/// blank lines, soft line breaks become spaces, the text of links and
+/// the alternative text of images is kept, and HTML is dropped. The
+/// content of code blocks is kept verbatim.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{to_plain_text, Options, PlainTextLinks};
+/// let source = "# Lorem\n\n*Ipsum* [dolor][1]\n`sit` amet\n\n- consectetur\n\n[1]: http://example.com";
+/// let text = to_plain_text(source, PlainTextLinks::TextOnly, &Options::default());
+/// assert_eq!(text, "Lorem\n\nIpsum dolor sit amet\n\nconsectetur");
+///
+/// let text = to_plain_text(source, PlainTextLinks::WithUrl, &Options::default());
+/// assert_eq!(text, "Lorem\n\nIpsum dolor (http://example.com) sit amet\n\nconsectetur");
+/// ```
+pub fn to_plain_text(source: &str, links: PlainTextLinks, options: &Options) -> String {
+    let formatted = ::prettify_with_options(source, options);
+    let mut blocks = Vec::new();
+    let mut block = String::new();
+    let mut code = false;
+    // The start of the text of each open link in the current block.
+    let mut link_starts = Vec::new();
+    for event in Parser::new_ext(&formatted, options.parser_options()) {
+        match event {
+            Event::Start(Tag::Link(..)) => link_starts.push(block.len()),
+            Event::End(Tag::Link(destination, _)) => {
+                let start = link_starts.pop().unwrap_or(block.len()).min(block.len());
+                if links == PlainTextLinks::WithUrl && !destination.is_empty() && block[start..] != *destination {
+                    block.push_str(" (");
+                    block.push_str(&destination);
+                    block.push(')');
+                }
+            },
+            Event::Start(Tag::CodeBlock(_)) => {
+                flush(&mut blocks, &mut block, false);
+                code = true;
+            },
+            Event::End(Tag::CodeBlock(_)) => {
+                flush(&mut blocks, &mut block, true);
+                code = false;
+            },
+            Event::Start(tag) | Event::End(tag) => match tag {
+                Tag::Paragraph | Tag::Header(_) | Tag::BlockQuote | Tag::List(_) | Tag::Item |
+                Tag::FootnoteDefinition(_) | Tag::Table(_) | Tag::TableHead | Tag::TableRow => {
+                    flush(&mut blocks, &mut block, false);
+                },
+                Tag::TableCell if !block.is_empty() => block.push(' '),
+                _ => {}
+            },
+            Event::Text(text) => block.push_str(&text),
+            Event::SoftBreak if !code => block.push(' '),
+            Event::SoftBreak | Event::HardBreak => block.push('\n'),
+            Event::Html(_) | Event::InlineHtml(_) | Event::FootnoteReference(_) => {}
+        }
+    }
+    flush(&mut blocks, &mut block, false);
+    blocks.join("\n\n")
+}
+
+/// Add the text collected for a block to the blocks, unless it is empty.
+///
+/// Whitespace around the text is trimmed, except for the indentation of
+/// code blocks.
+fn flush(blocks: &mut Vec<String>, block: &mut String, code: bool) {
+    let text = if code { block.trim_end_matches('\n') } else { block.trim() };
+    if !text.is_empty() {
+        blocks.push(text.to_string());
+    }
+    block.clear();
+}
@@ -1,7 +1,41 @@
-use std::fmt::{Result, Write};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt::{self, Result, Write};
+use std::mem;
+use std::ops::Range;
 
-use pulldown_cmark::{Event, Tag};
+use pulldown_cmark::{Alignment, Event, Parser, Tag};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
 
+use admonitions;
+use backend::Backend;
+use buffer::{into_static, owned_tag};
+use budget::{Budget, CancellationToken, Usage};
+use checkpoint::Checkpoint;
+use comments::{self, CommentPrefix};
+use diagnostics::{Diagnostic, Message, Severity};
+use directives::{self, Directive, Overrides};
+use entities;
+use escape;
+use html;
+use info;
+use invisible;
+use lints::{LintContext, Lints};
+use inline::{self, AtomKind};
+use hooks::{AltTextProvider, Block, CodeBlockFormatter, Localizer, TextInspector, TextRun};
+use footnotes::{self, Footnotes};
+use front_matter::{self, FrontMatter};
+use outline::{self, HeadingLevels, HeadingPath, Slugger};
+use options::{AltTextPolicy, AutolinkStyle, BlockQuoteStyle, EmptyConstructPolicy, EntityPolicy, FootnotePlacement, FootnoteStyle, FrontMatterPolicy, HeadingBreakStyle, HtmlBreakPolicy, HtmlPolicy, InvisibleCharacterPolicy, NonBreakingSpaces, Normalization, Options};
+use options::{TableOptions, TableOverflow, TableSeparator};
+use references::{self, References};
+use shortcodes;
+use table;
+use validate;
+#[cfg(feature = "tracing")]
+use trace::Tracer;
 use writer::{Frame, Writer};
 
 /// Event-driven pretty printer for CommonMark documents.
@@ -28,10 +62,126 @@ use writer::{Frame, Writer};
 /// ```
 pub struct PrettyPrinter<W = String> {
     writer: Writer<W>,
-    needs_break: bool
+    options: Options,
+    needs_break: bool,
+    in_code: bool,
+    inline_buffer: Option<Vec<BufferedEvent>>,
+    link_text: Option<Vec<BufferedEvent>>,
+    table: Option<Vec<BufferedEvent>>,
+    /// Whether to escape each of the brackets in the link text that is
+    /// being printed, for each level of nested links and images.
+    brackets: Vec<VecDeque<bool>>,
+    blocks: Vec<Block>,
+    text_inspector: Option<Box<dyn TextInspector>>,
+    lints: Option<Lints>,
+    text_run: Option<(String, Option<usize>)>,
+    code_block_formatter: Option<Box<dyn CodeBlockFormatter>>,
+    code_block: Option<String>,
+    pending_text: Vec<(String, Option<OwnedSpan>)>,
+    quote_start: bool,
+    callout_line: Option<Vec<(String, Option<OwnedSpan>)>>,
+    /// A `<br>` element that ends the line, unless it ends the block.
+    pending_break: Option<String>,
+    /// Whether the printed block has to fit on a single line, as headings
+    /// and table cells do.
+    single_line: bool,
+    deferred: Option<BufferedEvent>,
+    /// The events of a heading that is demoted by `Options::heading_limit`,
+    /// which are printed once it ends.
+    demoted_heading: Option<Vec<BufferedEvent>>,
+    list_depth: usize,
+    references: References,
+    footnotes: Footnotes,
+    footnote_buffer: Option<Vec<BufferedEvent>>,
+    held_footnotes: Vec<(usize, Vec<BufferedEvent>)>,
+    /// Whether the next list is sorted, as it follows a sort directive.
+    sort_next: bool,
+    /// The events of the list that is being sorted, and the depth of the
+    /// block around it.
+    sorted_list: Option<Vec<BufferedEvent>>,
+    sort_depth: usize,
+    /// Options that are overridden by directives in the document.
+    overrides: Overrides,
+    headings: HeadingPath,
+    heading_levels: HeadingLevels,
+    /// The index of the section profile in effect, whose options replace
+    /// the options of the document, which are kept aside meanwhile.
+    profile: Option<usize>,
+    document_options: Option<Box<Options>>,
+    /// The tags of the blocks and inlines that have been started but not
+    /// ended yet, from the outermost one.
+    open_tags: Vec<Tag<'static>>,
+    section: usize,
+    alt_text_provider: Option<Box<dyn AltTextProvider>>,
+    localizer: Option<Box<dyn Localizer>>,
+    cancellation: Option<CancellationToken>,
+    budget: Budget,
+    usage: Usage,
+    #[cfg(feature = "tracing")]
+    tracer: Tracer,
+    empty_image: Option<Option<usize>>,
+    empty_start: Option<BufferedEvent>,
+    verbatim: Option<String>,
+    html_skip: Option<String>,
+    heading_text: Option<(String, Option<usize>)>,
+    slugger: Slugger,
+    /// The anchors of HTML elements, and the fragment, offset and section
+    /// of each link to an anchor, for `Options::broken_anchors`.
+    html_anchors: Vec<String>,
+    fragment_links: Vec<(String, Option<usize>, Vec<String>)>,
+    diagnostics: Vec<Diagnostic>
 }
 
-impl<W: Write> PrettyPrinter<W> {
+pub(crate) type BufferedEvent = (Event<'static>, Option<OwnedSpan>);
+
+/// A block that replaces a range of the events parsed from a source
+/// document, as used by `PrettyPrinter::push_source_replacing`.
+pub(crate) struct Replacement {
+    /// The indices of the replaced events, which may be empty to insert
+    /// the block before the event at its start.
+    pub range: Range<usize>,
+    pub block: ReplacementBlock
+}
+
+pub(crate) enum ReplacementBlock {
+    Events(Vec<Event<'static>>),
+    /// Events that are printed on a single line, without wrapping.
+    SingleLine(Vec<Event<'static>>),
+    /// Text that is printed as it is, like `PrettyPrinter::push_raw`.
+    Raw(String)
+}
+
+#[derive(Clone, Copy)]
+struct Span<'s> {
+    offset: usize,
+    source: &'s str
+}
+
+impl<'s> Span<'s> {
+    /// The offset of the first non-whitespace character of the span.
+    fn start(self) -> usize {
+        self.offset + self.source.len() - self.source.trim_start().len()
+    }
+
+    fn to_owned(self) -> OwnedSpan {
+        OwnedSpan { offset: self.offset, source: self.source.to_string() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct OwnedSpan {
+    offset: usize,
+    source: String
+}
+
+impl OwnedSpan {
+    fn as_span(&self) -> Span<'_> {
+        Span { offset: self.offset, source: &self.source }
+    }
+}
+
+impl<W: Backend> PrettyPrinter<W> {
     /// Create a new pretty printer that wraps around a writer.
     pub fn new(write: W) -> PrettyPrinter<W> {
         PrettyPrinter::new_with_prefix(write, "")
@@ -43,28 +193,1044 @@ impl<W: Write> PrettyPrinter<W> {
     /// The prefix will be applied to all lines that are produced by
     /// the printer.
     pub fn new_with_prefix(write: W, prefix: &str) -> PrettyPrinter<W> {
+        PrettyPrinter::new_with_options(write, prefix, Options::default())
+    }
+
+    /// Create a new pretty printer with a prefix and custom options
+    /// that wraps around a writer.
+    pub fn new_with_options(write: W, prefix: &str, options: Options) -> PrettyPrinter<W> {
+        PrettyPrinter {
+            writer: Writer::new(write, prefix.to_string())
+                .with_list_indent(options.list_indent.unwrap_or(0))
+                .with_strip_trailing(options.trim_trailing_whitespace)
+                .with_block_marker_escape(options.escapes.block_markers)
+                .with_width(options.wrap)
+                .with_tab_width(options.expand_tabs),
+            options,
+            needs_break: false,
+            in_code: false,
+            inline_buffer: None,
+            link_text: None,
+            table: None,
+            brackets: vec![],
+            blocks: vec![],
+            text_inspector: None,
+            lints: None,
+            text_run: None,
+            code_block_formatter: None,
+            code_block: None,
+            pending_text: vec![],
+            quote_start: false,
+            callout_line: None,
+            pending_break: None,
+            single_line: false,
+            deferred: None,
+            list_depth: 0,
+            references: References::default(),
+            footnotes: Footnotes::default(),
+            footnote_buffer: None,
+            demoted_heading: None,
+            held_footnotes: vec![],
+            sort_next: false,
+            sorted_list: None,
+            sort_depth: 0,
+            overrides: Overrides::default(),
+            headings: HeadingPath::default(),
+            heading_levels: HeadingLevels::default(),
+            profile: None,
+            document_options: None,
+            open_tags: vec![],
+            section: 0,
+            alt_text_provider: None,
+            localizer: None,
+            cancellation: None,
+            budget: Budget::default(),
+            usage: Usage::default(),
+            #[cfg(feature = "tracing")]
+            tracer: Tracer::default(),
+            empty_image: None,
+            empty_start: None,
+            verbatim: None,
+            html_skip: None,
+            heading_text: None,
+            slugger: Slugger::new(),
+            html_anchors: vec![],
+            fragment_links: vec![],
+            diagnostics: vec![]
+        }
+    }
+
+    /// Create a pretty printer that resumes from a checkpoint, writing the
+    /// remaining output to a new writer.
+    ///
+    /// Hooks such as text inspectors or code block formatters are not
+    /// part of the checkpoint, and have to be attached again.
+    pub fn resume(write: W, options: Options, checkpoint: Checkpoint) -> PrettyPrinter<W> {
+        let profile = checkpoint.profile.and_then(|index| options.profiles.get(index)).map(|profile| profile.options.clone());
+        let (options, document_options) = match profile {
+            Some(profile) => (profile, Some(Box::new(options))),
+            None => (options, None)
+        };
         PrettyPrinter {
-            writer: Writer::new(write, prefix.to_string()),
-            needs_break: false
+            writer: Writer::resume(write, checkpoint.writer)
+                .with_list_indent(options.list_indent.unwrap_or(0))
+                .with_strip_trailing(options.trim_trailing_whitespace)
+                .with_block_marker_escape(options.escapes.block_markers)
+                .with_width(checkpoint.overrides.wrap.unwrap_or(options.wrap))
+                .with_tab_width(options.expand_tabs),
+            options,
+            needs_break: checkpoint.needs_break,
+            in_code: checkpoint.in_code,
+            inline_buffer: checkpoint.inline_buffer,
+            link_text: checkpoint.link_text,
+            table: checkpoint.table,
+            brackets: vec![],
+            blocks: checkpoint.blocks,
+            text_inspector: None,
+            lints: None,
+            text_run: checkpoint.text_run,
+            code_block_formatter: None,
+            code_block: checkpoint.code_block,
+            pending_text: checkpoint.pending_text,
+            quote_start: checkpoint.quote_start,
+            callout_line: checkpoint.callout_line,
+            pending_break: checkpoint.pending_break,
+            single_line: checkpoint.single_line,
+            deferred: checkpoint.deferred,
+            list_depth: checkpoint.list_depth,
+            references: checkpoint.references,
+            footnotes: checkpoint.footnotes,
+            footnote_buffer: checkpoint.footnote_buffer,
+            demoted_heading: checkpoint.demoted_heading,
+            held_footnotes: checkpoint.held_footnotes,
+            sort_next: checkpoint.sort_next,
+            sorted_list: checkpoint.sorted_list,
+            sort_depth: checkpoint.sort_depth,
+            overrides: checkpoint.overrides,
+            headings: checkpoint.headings,
+            heading_levels: checkpoint.heading_levels,
+            profile: document_options.as_ref().and(checkpoint.profile),
+            document_options,
+            open_tags: checkpoint.open_tags,
+            section: checkpoint.section,
+            alt_text_provider: None,
+            localizer: None,
+            cancellation: None,
+            budget: Budget::default(),
+            usage: Usage::default(),
+            #[cfg(feature = "tracing")]
+            tracer: Tracer::default(),
+            empty_image: checkpoint.empty_image,
+            empty_start: checkpoint.empty_start,
+            verbatim: checkpoint.verbatim,
+            html_skip: checkpoint.html_skip,
+            heading_text: checkpoint.heading_text,
+            slugger: checkpoint.slugger,
+            html_anchors: checkpoint.html_anchors,
+            fragment_links: checkpoint.fragment_links,
+            diagnostics: vec![]
         }
     }
 
+    /// Capture the internal state of the printer, so that printing can be
+    /// resumed later using `PrettyPrinter::resume`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate pulldown_cmark;
+    /// # extern crate prettify_cmark;
+    /// use pulldown_cmark::Parser;
+    /// use prettify_cmark::{Options, PrettyPrinter};
+    ///
+    /// # fn main() {
+    /// let events = Parser::new("- Lorem\n- Ipsum").collect::<Vec<_>>();
+    /// let (first, second) = events.split_at(4);
+    ///
+    /// let mut printer = PrettyPrinter::default();
+    /// printer.push_events(first.iter().cloned()).unwrap();
+    /// let checkpoint = printer.checkpoint();
+    /// let first_output = printer.into_inner();
+    ///
+    /// let mut printer = PrettyPrinter::resume(String::new(), Options::default(), checkpoint);
+    /// printer.push_events(second.iter().cloned()).unwrap();
+    ///
+    /// assert_eq!(first_output + &printer.into_inner(), "- Lorem\n\n- Ipsum");
+    /// # }
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            writer: self.writer.state(),
+            needs_break: self.needs_break,
+            in_code: self.in_code,
+            inline_buffer: self.inline_buffer.clone(),
+            link_text: self.link_text.clone(),
+            table: self.table.clone(),
+            blocks: self.blocks.clone(),
+            text_run: self.text_run.clone(),
+            code_block: self.code_block.clone(),
+            pending_text: self.pending_text.clone(),
+            quote_start: self.quote_start,
+            callout_line: self.callout_line.clone(),
+            pending_break: self.pending_break.clone(),
+            single_line: self.single_line,
+            deferred: self.deferred.clone(),
+            list_depth: self.list_depth,
+            references: self.references.clone(),
+            footnotes: self.footnotes.clone(),
+            footnote_buffer: self.footnote_buffer.clone(),
+            demoted_heading: self.demoted_heading.clone(),
+            held_footnotes: self.held_footnotes.clone(),
+            sort_next: self.sort_next,
+            sorted_list: self.sorted_list.clone(),
+            sort_depth: self.sort_depth,
+            overrides: self.overrides,
+            headings: self.headings.clone(),
+            heading_levels: self.heading_levels.clone(),
+            profile: self.profile,
+            open_tags: self.open_tags.clone(),
+            section: self.section,
+            empty_image: self.empty_image,
+            empty_start: self.empty_start.clone(),
+            verbatim: self.verbatim.clone(),
+            html_skip: self.html_skip.clone(),
+            heading_text: self.heading_text.clone(),
+            slugger: self.slugger.clone(),
+            html_anchors: self.html_anchors.clone(),
+            fragment_links: self.fragment_links.clone()
+        }
+    }
+
+    /// Attach a formatter for the contents of fenced code blocks.
+    pub fn set_code_block_formatter<F: CodeBlockFormatter + 'static>(&mut self, formatter: F) {
+        self.code_block_formatter = Some(Box::new(formatter));
+    }
+
+    /// Attach a hook that receives every run of prose text that is printed.
+    pub fn set_text_inspector<T: TextInspector + 'static>(&mut self, inspector: T) {
+        self.text_inspector = Some(Box::new(inspector));
+    }
+
+    /// Attach lints that check the events of the document while it is
+    /// printed, reporting their findings as diagnostics.
+    pub fn set_lints(&mut self, lints: Lints) {
+        self.lints = Some(lints);
+    }
+
+    /// Attach a hook that supplies alt text for images that have none.
+    pub fn set_alt_text_provider<P: AltTextProvider + 'static>(&mut self, provider: P) {
+        self.alt_text_provider = Some(Box::new(provider));
+    }
+
+    /// Attach a hook that supplies the text generated by the printer,
+    /// such as the messages of diagnostics.
+    pub fn set_localizer<L: Localizer + 'static>(&mut self, localizer: L) {
+        self.localizer = Some(Box::new(localizer));
+    }
+
+    /// Attach a token that cancels printing at the start of the next
+    /// block once it is cancelled.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancellation = Some(token);
+    }
+
+    /// Limit the work the printer may do before it fails.
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
     /// Push a single event into the printer.
     ///
-    /// Events can be obtained using `pulldown_cmark::Parser`.
-    pub fn push_event<'a>(&mut self, event: Event<'a>) -> Result {
+    /// Events can be obtained using `pulldown_cmark::Parser`, or from an
+    /// `OwnedEvent`.
+    pub fn push_event<'a, E: Into<Event<'a>>>(&mut self, event: E) -> Result {
+        self.push_event_with_source(event.into(), None)
+    }
+
+    /// Parse a CommonMark document and push its events into the printer.
+    ///
+    /// In contrast to `push_events`, this gives the printer access to the
+    /// source text of each event, which is required by some options
+    /// such as `EntityPolicy::Preserve`.
+    pub fn push_source(&mut self, source: &str) -> Result {
+        let options = self.options.clone();
+        self.push_source_replacing(source, |source| {
+            let mut replacements = shortcodes::blocks(source, &options);
+            for replacement in admonitions::convert(source, &options) {
+                let range = &replacement.range;
+                if replacements.iter().all(|shortcode| shortcode.range.end <= range.start || range.end <= shortcode.range.start) {
+                    replacements.push(replacement);
+                }
+            }
+            replacements.sort_by_key(|replacement| replacement.range.start);
+            replacements
+        })
+    }
+
+    /// Push a CommonMark document like `push_source`, replacing ranges of
+    /// its events by other blocks.
+    ///
+    /// The replacements are determined from the source as it is parsed,
+    /// after any inline footnotes have been expanded, and are expected in
+    /// the order of their ranges, which must not overlap.
+    pub(crate) fn push_source_replacing<F>(&mut self, source: &str, replace: F) -> Result
+        where F: FnOnce(&str) -> Vec<Replacement>
+    {
+        let blanked;
+        let expanded;
+        let mut source = source;
+        if self.options.front_matter != FrontMatterPolicy::Markdown {
+            if let Some(front_matter) = front_matter::find(source) {
+                let printed = self.print_front_matter(&front_matter);
+                self.push_raw(&printed)?;
+                blanked = front_matter::blank(source, &front_matter);
+                source = &blanked;
+            }
+        }
+        let style = self.options.footnotes.map(|options| options.style);
+        if style == Some(FootnoteStyle::Reference) {
+            if let Some(text) = footnotes::expand_inline(source) {
+                expanded = text;
+                source = &expanded;
+            }
+        }
+        if let Some(ref options) = self.options.references {
+            self.references = references::scan(source, options, &self.options.titles, &mut self.diagnostics, self.localizer.as_deref());
+        }
+        if let Some(ref options) = self.options.footnotes {
+            self.footnotes = footnotes::scan(source, options, &mut self.diagnostics, self.localizer.as_deref());
+        }
+        if style == Some(FootnoteStyle::Inline) {
+            self.print_inline_footnotes(source)?;
+        }
+        let held = self.hold_section_footnotes(source);
+        #[cfg(feature = "tracing")]
+        let document = self.tracer.start_document(source.len());
+        let mut replacements = replace(source).into_iter().peekable();
+        let mut skipped = 0..0;
+        let mut parser = Parser::new_ext(source, self.options.parser_options());
+        let mut index = 0;
+        self.section = 0;
+        loop {
+            let start = parser.get_offset();
+            match parser.next() {
+                Some(event) => {
+                    let end = parser.get_offset();
+                    while let Some(replacement) = replacements.next_if(|replacement| replacement.range.start == index) {
+                        skipped = replacement.range.clone();
+                        self.push_replacement(replacement)?;
+                    }
+                    if !skipped.contains(&index) && !held.iter().any(|range| range.contains(&index)) {
+                        self.push_event_in_source(event, source, start..end)?;
+                    }
+                    index += 1;
+                },
+                None => {
+                    for replacement in replacements {
+                        self.push_replacement(replacement)?;
+                    }
+                    self.finish()?;
+                    self.check_fragment_links();
+                    #[cfg(feature = "tracing")]
+                    document.finish(&self.tracer);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Print front matter according to `Options::front_matter`.
+    fn print_front_matter(&mut self, front_matter: &FrontMatter) -> String {
+        if self.options.front_matter == FrontMatterPolicy::Canonical && front_matter.yaml {
+            #[cfg(feature = "yaml")]
+            match front_matter::canonicalize(front_matter) {
+                Ok(yaml) => return yaml,
+                Err(error) => {
+                    let offset = error.location().map(|location| front_matter.offset + location.index());
+                    self.report(Diagnostic::warning(
+                        Message::FrontMatter { error: &error.to_string() },
+                        self.localizer.as_deref(),
+                        offset));
+                }
+            }
+            #[cfg(not(feature = "yaml"))]
+            self.report(Diagnostic::warning(Message::FrontMatterFeature, self.localizer.as_deref(), Some(0)));
+        }
+        front_matter.source.to_string()
+    }
+
+    fn push_replacement(&mut self, replacement: Replacement) -> Result {
+        match replacement.block {
+            ReplacementBlock::Events(events) => self.push_events(events),
+            ReplacementBlock::SingleLine(events) => {
+                self.single_line = true;
+                let result = self.push_events(events);
+                self.single_line = false;
+                result
+            },
+            ReplacementBlock::Raw(text) => self.push_raw(&text)
+        }
+    }
+
+    /// Print the contents of the footnotes that are written inline, so
+    /// that they are available at their reference.
+    fn print_inline_footnotes(&mut self, source: &str) -> Result {
+        // The label and events of the definition that is being collected.
+        let mut label: Option<String> = None;
+        let mut events = Vec::new();
+        let mut parser = Parser::new_ext(source, self.options.parser_options());
+        loop {
+            let start = parser.get_offset();
+            let event = match parser.next() {
+                Some(event) => event,
+                None => return Ok(())
+            };
+            let end = parser.get_offset();
+            match event {
+                Event::Start(Tag::FootnoteDefinition(ref inline)) if self.footnotes.is_inline(inline) => {
+                    label = Some(inline.to_string());
+                },
+                Event::End(Tag::FootnoteDefinition(_)) => {
+                    if let Some(label) = label.take() {
+                        let mut printer = PrettyPrinter::new_with_options(String::new(), "", self.options.clone());
+                        printer.single_line = true;
+                        printer.footnotes = self.footnotes.clone();
+                        for (event, range) in events.drain(..) {
+                            printer.push_event_in_source(event, source, range)?;
+                        }
+                        let text = printer.into_inner().trim().replace('\n', " ");
+                        self.footnotes.set_inline(&label, text);
+                    }
+                },
+                event => {
+                    if label.is_some() {
+                        events.push((event, start..end));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Hold back the footnote definitions that are placed at the end of
+    /// the section they are first referenced in, before printing, so that
+    /// definitions after that section are placed there as well.
+    ///
+    /// Returns the ranges of the events of the definitions, which are
+    /// skipped while printing.
+    fn hold_section_footnotes(&mut self, source: &str) -> Vec<Range<usize>> {
+        let options = self.options.footnotes.unwrap_or_default();
+        let mut held = Vec::new();
+        if self.options.footnotes.is_none() || options.placement != FootnotePlacement::SectionEnd {
+            return held;
+        }
+        // The index of the first event and the events of the definition
+        // that is being collected.
+        let mut definition: Option<(usize, Vec<BufferedEvent>)> = None;
+        let mut depth = 0usize;
+        let mut parser = Parser::new_ext(source, self.options.parser_options());
+        let mut index = 0;
+        loop {
+            let start = parser.get_offset();
+            let event = match parser.next() {
+                Some(event) => event,
+                None => return held
+            };
+            let span = source.get(start..parser.get_offset()).map(|source| Span { offset: start, source });
+            if depth == 0 && matches!(event, Event::Start(Tag::FootnoteDefinition(_))) {
+                definition = Some((index, Vec::new()));
+            }
+            match event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            index += 1;
+            if let Some((_, ref mut events)) = definition {
+                events.push((into_static(event), span.map(Span::to_owned)));
+            }
+            if depth > 0 {
+                continue;
+            }
+            if let Some((first, events)) = definition.take() {
+                let (label, offset) = match events[0] {
+                    (Event::Start(Tag::FootnoteDefinition(ref label)), ref span) => {
+                        (label.to_string(), span.as_ref().map(|span| span.as_span().start()))
+                    },
+                    _ => continue
+                };
+                // Definitions that are removed are left to `hold_footnote`,
+                // which reports them where they are.
+                let removed = (options.remove_unreferenced && self.footnotes.is_unreferenced(&label)) ||
+                    (options.remove_duplicates && offset.is_some_and(|offset| self.footnotes.is_duplicate(offset))) ||
+                    self.footnotes.is_inline(&label);
+                if !removed {
+                    let section = self.footnotes.section(&label).unwrap_or(usize::MAX);
+                    self.held_footnotes.push((section, events));
+                    held.push(first..index);
+                }
+            }
+        }
+    }
+
+    /// Push an event that was parsed from the given range of a source
+    /// document.
+    pub(crate) fn push_event_in_source<'a>(&mut self, event: Event<'a>, source: &str, range: Range<usize>) -> Result {
+        let offset = range.start;
+        let span = source.get(range).map(|source| Span { offset, source });
+        self.push_event_with_source(event, span)
+    }
+
+    fn push_event_with_source<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        #[cfg(feature = "tracing")]
+        self.tracer.event(&event);
+        self.usage.count();
+        let starts_block = match event {
+            Event::Start(ref tag) => Block::from_tag(tag).is_some_and(|block| block != Block::TableCell),
+            Event::Html(_) => true,
+            _ => false
+        };
+        let mut checked = Ok(());
+        if starts_block {
+            checked = self.usage.check(&self.budget, self.cancellation.as_ref());
+        }
+        if checked.is_ok() {
+            let size = if self.is_buffering() { Some(buffered_size(&event, span)) } else { None };
+            checked = self.usage.buffer(&self.budget, size);
+        }
+        if let Err(message) = checked {
+            if let Some(message) = message {
+                self.report(Diagnostic::error(message, self.localizer.as_deref(), span.map(Span::start)));
+            }
+            return Err(fmt::Error);
+        }
+        if self.open_tags.is_empty() && self.headings.take_changed() {
+            self.select_profile()?;
+        }
+        self.headings.push_event(&event, self.open_tags.len());
+        if self.open_tags.is_empty() && matches!(event, Event::Start(Tag::Header(_))) {
+            self.flush_deferred()?;
+            let section = self.section;
+            self.write_section_end(section)?;
+            self.section += 1;
+        }
+        match event {
+            Event::Start(ref tag) => self.open_tags.push(owned_tag(tag.clone())),
+            Event::End(_) => {
+                self.open_tags.pop();
+            },
+            _ => {}
+        }
+        if let Some(mut verbatim) = self.verbatim.take() {
+            verbatim.push_str(span.map_or("", |span| span.source));
+            if !self.open_tags.is_empty() {
+                self.verbatim = Some(verbatim);
+                return Ok(());
+            }
+            return self.write_verbatim(&verbatim);
+        }
+        if let (Event::Start(ref tag), Some(span)) = (&event, span) {
+            if self.open_tags.len() == 1 && self.options.verbatim.contains(tag) {
+                self.verbatim = Some(span.source.to_string());
+                return Ok(());
+            }
+        }
+        let holds_footnotes = self.options.footnotes.as_ref().is_some_and(|options| {
+            options.placement != FootnotePlacement::Preserve || options.remove_unreferenced || options.remove_duplicates ||
+                options.style == FootnoteStyle::Inline
+        });
+        if holds_footnotes {
+            if let Some(mut buffer) = self.footnote_buffer.take() {
+                let ends_definition = matches!(event, Event::End(Tag::FootnoteDefinition(_)));
+                buffer.push((into_static(event), span.map(Span::to_owned)));
+                if ends_definition {
+                    return self.hold_footnote(buffer);
+                }
+                self.footnote_buffer = Some(buffer);
+                return Ok(());
+            }
+            if let Event::Start(Tag::FootnoteDefinition(_)) = event {
+                self.footnote_buffer = Some(vec![(into_static(event), span.map(Span::to_owned))]);
+                return Ok(());
+            }
+        }
+        if self.options.sort_lists {
+            if let Some(mut buffer) = self.sorted_list.take() {
+                let ends_list = self.open_tags.len() == self.sort_depth && matches!(event, Event::End(Tag::List(_)));
+                buffer.push((into_static(event), span.map(Span::to_owned)));
+                if ends_list {
+                    return self.replay(directives::sort_items(buffer));
+                }
+                self.sorted_list = Some(buffer);
+                return Ok(());
+            }
+            match event {
+                Event::Start(Tag::List(_)) if self.sort_next => {
+                    self.sort_next = false;
+                    self.sort_depth = self.open_tags.len() - 1;
+                    self.sorted_list = Some(vec![(into_static(event), span.map(Span::to_owned))]);
+                    return Ok(());
+                },
+                Event::Html(ref html) => self.sort_next = directives::parse(html) == Some(Directive::Sort),
+                _ => self.sort_next = false
+            }
+        }
+        self.push_block_event(event, span)
+    }
+
+    /// Whether any events are held back in buffers, to be printed later.
+    fn is_buffering(&self) -> bool {
+        self.inline_buffer.is_some() || self.link_text.is_some() || self.table.is_some() ||
+            self.footnote_buffer.is_some() || !self.held_footnotes.is_empty() || self.sorted_list.is_some() ||
+            self.verbatim.is_some() || self.code_block.is_some() || !self.pending_text.is_empty() ||
+            self.callout_line.is_some() || self.demoted_heading.is_some()
+    }
+
+    /// Remove, hold back or print a buffered footnote definition.
+    fn hold_footnote(&mut self, buffer: Vec<BufferedEvent>) -> Result {
+        let (label, offset) = match buffer[0] {
+            (Event::Start(Tag::FootnoteDefinition(ref label)), ref span) => {
+                (label.to_string(), span.as_ref().map(|span| span.as_span().start()))
+            },
+            _ => return Ok(())
+        };
+        let options = self.options.footnotes.unwrap_or_default();
+        if options.remove_unreferenced && self.footnotes.is_unreferenced(&label) {
+            self.report(Diagnostic::warning(
+                Message::UnusedFootnote { label: &label },
+                self.localizer.as_deref(),
+                offset));
+            return Ok(());
+        }
+        if options.remove_duplicates && offset.is_some_and(|offset| self.footnotes.is_duplicate(offset)) {
+            return Ok(());
+        }
+        if self.footnotes.is_inline(&label) {
+            return Ok(());
+        }
+        let section = match options.placement {
+            FootnotePlacement::Preserve => return self.replay(buffer),
+            FootnotePlacement::DocumentEnd => usize::MAX,
+            FootnotePlacement::SectionEnd => self.footnotes.section(&label).unwrap_or(usize::MAX)
+        };
+        self.held_footnotes.push((section, buffer));
+        Ok(())
+    }
+
+    /// Print the footnote and link reference definitions to be placed at
+    /// the end of the given section.
+    fn write_section_end(&mut self, section: usize) -> Result {
+        let (mut ready, held) = mem::take(&mut self.held_footnotes).into_iter()
+            .partition::<Vec<_>, _>(|&(at, _)| at <= section);
+        self.held_footnotes = held;
+        let options = self.options.footnotes.unwrap_or_default();
+        if options.sort && !options.renumber {
+            ready.sort_by_key(|(_, buffer)| match buffer[0].0 {
+                Event::Start(Tag::FootnoteDefinition(ref label)) => label.to_lowercase(),
+                _ => String::new()
+            });
+        } else {
+            ready.sort_by_key(|(_, buffer)| match buffer[0].0 {
+                Event::Start(Tag::FootnoteDefinition(ref label)) => self.footnotes.order(label),
+                _ => usize::MAX
+            });
+        }
+        for (_, buffer) in ready {
+            self.replay(buffer)?;
+        }
+        self.flush_deferred()?;
+        self.write_definitions(section)
+    }
+
+    fn replay(&mut self, buffer: Vec<BufferedEvent>) -> Result {
+        for (event, span) in buffer {
+            self.push_block_event(event, span.as_ref().map(OwnedSpan::as_span))?;
+        }
+        Ok(())
+    }
+
+    /// Report a diagnostic in the current section.
+    fn report(&mut self, mut diagnostic: Diagnostic) {
+        diagnostic.section = self.headings.path().into_iter().map(str::to_string).collect();
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Switch to the options of the first section profile that matches the
+    /// current heading path, or back to the options of the document.
+    fn select_profile(&mut self) -> Result {
+        let document = self.document_options.as_deref().unwrap_or(&self.options);
+        let path = self.headings.path();
+        let profile = document.profiles.iter().position(|profile| outline::matches_selector(&profile.selector, &path));
+        if profile == self.profile {
+            return Ok(());
+        }
+        let document = match self.document_options.take() {
+            Some(options) => *options,
+            None => mem::take(&mut self.options)
+        };
+        self.options = match profile {
+            Some(index) => {
+                let options = document.profiles[index].options.clone();
+                self.document_options = Some(Box::new(document));
+                options
+            },
+            None => document
+        };
+        self.profile = profile;
+        self.configure_writer()
+    }
+
+    /// Apply the options in effect to the writer.
+    fn configure_writer(&mut self) -> Result {
+        self.writer.set_list_indent(self.options.list_indent.unwrap_or(0));
+        self.writer.set_strip_trailing(self.options.trim_trailing_whitespace);
+        self.writer.set_block_marker_escape(self.options.escapes.block_markers);
+        self.writer.set_tab_width(self.options.expand_tabs);
+        self.writer.set_width(self.overrides.wrap.unwrap_or(self.options.wrap))
+    }
+
+    /// Override options as instructed by a directive.
+    fn apply_directive(&mut self, directive: Directive) -> Result {
+        match directive {
+            Directive::Set(settings) => self.overrides.apply(&settings),
+            Directive::Reset => self.overrides = Overrides::default(),
+            Directive::Sort => return Ok(())
+        }
+        self.configure_writer()
+    }
+
+    fn push_block_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        if let Event::Html(ref html) = event {
+            if let Some(directive) = directives::parse(html) {
+                self.apply_directive(directive)?;
+            }
+        }
+        if let Some(max_depth) = self.options.max_list_depth {
+            let max_depth = max_depth.max(1);
+            match event {
+                Event::Start(Tag::List(_)) => {
+                    self.list_depth += 1;
+                    if self.list_depth > max_depth {
+                        self.report(Diagnostic::warning(
+                            Message::ListDepth { max_depth },
+                            self.localizer.as_deref(),
+                            span.map(Span::start)));
+                        return Ok(());
+                    }
+                },
+                Event::End(Tag::List(_)) => {
+                    self.list_depth = self.list_depth.saturating_sub(1);
+                    if self.list_depth >= max_depth {
+                        return Ok(());
+                    }
+                },
+                _ => {}
+            }
+        }
+        self.push_normalized_event(event, span)
+    }
+
+    fn push_normalized_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        if !self.options.normalize_structure {
+            return self.process_event(event, span);
+        }
+        if let Some((deferred, deferred_span)) = self.deferred.take() {
+            let merged = match (&deferred, &event) {
+                (&Event::End(Tag::BlockQuote), &Event::Start(Tag::BlockQuote)) => true,
+                (&Event::End(Tag::List(a)), &Event::Start(Tag::List(b))) => a.is_some() == b.is_some(),
+                (&Event::Start(Tag::Item), &Event::End(Tag::Item)) => true,
+                _ => false
+            };
+            if merged {
+                return Ok(());
+            }
+            self.process_event(deferred, deferred_span.as_ref().map(OwnedSpan::as_span))?;
+        }
+        match event {
+            Event::End(Tag::BlockQuote) | Event::End(Tag::List(_)) | Event::Start(Tag::Item) => {
+                self.deferred = Some((into_static(event), span.map(Span::to_owned)));
+                Ok(())
+            },
+            _ => self.process_event(event, span)
+        }
+    }
+
+    fn flush_deferred(&mut self) -> Result {
+        match self.deferred.take() {
+            Some((event, span)) => self.process_event(event, span.as_ref().map(OwnedSpan::as_span)),
+            None => Ok(())
+        }
+    }
+
+    fn process_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        let event = match (event, self.options.heading_hierarchy) {
+            (Event::Start(Tag::Header(level)), Some(hierarchy)) => {
+                self.check_heading_hierarchy(level, span);
+                Event::Start(Tag::Header(self.heading_levels.start(level, hierarchy.fix)))
+            },
+            (Event::End(Tag::Header(level)), Some(_)) => Event::End(Tag::Header(self.heading_levels.end(level))),
+            (event, _) => event
+        };
+        if let Some(mut heading) = self.demoted_heading.take() {
+            if let Event::End(Tag::Header(_)) = event {
+                return self.print_demoted_heading(heading, span);
+            }
+            heading.push((into_static(event), span.map(Span::to_owned)));
+            self.demoted_heading = Some(heading);
+            return Ok(());
+        }
+        if let Some(limit) = self.options.heading_limit {
+            if let Event::Start(Tag::Header(level)) = event {
+                if level > limit.max_level {
+                    self.report(Diagnostic::warning(
+                        Message::HeadingDepth { level, max_level: limit.max_level },
+                        self.localizer.as_deref(),
+                        span.map(Span::start)));
+                    if limit.demote {
+                        self.demoted_heading = Some(vec![(Event::Start(Tag::Paragraph), span.map(Span::to_owned))]);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+        self.process_block_event(event, span)
+    }
+
+    /// Print a demoted heading as a paragraph of strong text.
+    ///
+    /// Headings without content are dropped, as an empty strong emphasis
+    /// would be read as a thematic break, and content that is strong as
+    /// a whole is not wrapped again.
+    fn print_demoted_heading(&mut self, mut heading: Vec<BufferedEvent>, span: Option<Span>) -> Result {
+        let (start, start_span) = heading.remove(0);
+        let empty = heading.iter().all(|(event, _)| match *event {
+            Event::Text(ref text) => text.trim().is_empty(),
+            Event::SoftBreak | Event::HardBreak => true,
+            _ => false
+        });
+        if empty {
+            return Ok(());
+        }
+        // The content is strong as a whole if the strong emphasis it
+        // starts with only ends with its last event.
+        let mut depth = 0usize;
+        let closes_early = heading[..heading.len() - 1].iter().any(|(event, _)| {
+            match *event {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            depth == 0
+        });
+        let strong = !closes_early && matches!(heading.first(), Some(&(Event::Start(Tag::Strong), _))) &&
+            matches!(heading.last(), Some(&(Event::End(Tag::Strong), _)));
+        self.process_event(start, start_span.as_ref().map(OwnedSpan::as_span))?;
+        if !strong {
+            self.process_event(Event::Start(Tag::Strong), None)?;
+        }
+        for (event, span) in heading {
+            self.process_event(event, span.as_ref().map(OwnedSpan::as_span))?;
+        }
+        if !strong {
+            self.process_event(Event::End(Tag::Strong), None)?;
+        }
+        self.process_event(Event::End(Tag::Paragraph), span)
+    }
+
+    fn process_block_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        if let Some(mut line) = self.callout_line.take() {
+            match event {
+                Event::Text(text) => {
+                    line.push((text.into_owned(), span.map(Span::to_owned)));
+                    self.callout_line = Some(line);
+                    return Ok(());
+                },
+                Event::SoftBreak | Event::End(Tag::Paragraph) if callout_marker(&line).is_some() => {
+                    let text = line.iter().map(|piece| piece.0.as_str()).collect::<String>();
+                    let mut rest = callout_marker(&line).unwrap_or(0);
+                    self.writer.write_text(&self.options.callout_case.apply(&text[..rest]))?;
+                    // The title after the marker is printed as text.
+                    for (text, span) in line {
+                        if rest >= text.len() {
+                            rest -= text.len();
+                            continue;
+                        }
+                        let span = if rest == 0 { span } else { None };
+                        let text = text[rest..].to_string();
+                        rest = 0;
+                        self.print_event(Event::Text(Cow::Owned(text)), span.as_ref().map(OwnedSpan::as_span))?;
+                    }
+                    if let Event::SoftBreak = event {
+                        self.writer.write_hard_break()?;
+                        return self.writer.write_indent();
+                    }
+                },
+                _ => {
+                    for (text, span) in line {
+                        self.print_event(Event::Text(Cow::Owned(text)), span.as_ref().map(OwnedSpan::as_span))?;
+                    }
+                }
+            }
+        }
+        let starts_callout = self.quote_start && event == Event::Start(Tag::Paragraph);
+        self.quote_start = event == Event::Start(Tag::BlockQuote);
+        if starts_callout {
+            self.print_event(event, span)?;
+            self.callout_line = Some(Vec::new());
+            return Ok(());
+        }
+        if let Some(mut buffer) = self.inline_buffer.take() {
+            if let Event::End(Tag::Header(_)) = event {
+                self.options.heading_case.apply(&mut buffer, self.options.attributes);
+                for (event, span) in buffer {
+                    self.print_event(event, span.as_ref().map(OwnedSpan::as_span))?;
+                }
+            } else {
+                buffer.push((into_static(event), span.map(Span::to_owned)));
+                self.inline_buffer = Some(buffer);
+                return Ok(());
+            }
+        }
+        let starts_heading = matches!(event, Event::Start(Tag::Header(_)));
+        self.print_event(event, span)?;
+        if starts_heading && !self.options.heading_case.is_preserve() {
+            self.inline_buffer = Some(Vec::new());
+        }
+        Ok(())
+    }
+
+    fn print_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        if let Some(mut buffer) = self.table.take() {
+            if let Event::End(Tag::Table(ref alignments)) = event {
+                return self.write_table(alignments, buffer);
+            }
+            if self.options.broken_anchors {
+                self.check_anchor(&event, span);
+            }
+            buffer.push((into_static(event), span.map(Span::to_owned)));
+            self.table = Some(buffer);
+            return Ok(());
+        }
+        if let Some(mut buffer) = self.link_text.take() {
+            let nested = buffer.iter().fold(0isize, |nested, (event, _)| match *event {
+                Event::Start(Tag::Link(_, _)) | Event::Start(Tag::Image(_, _)) => nested + 1,
+                Event::End(Tag::Link(_, _)) | Event::End(Tag::Image(_, _)) => nested - 1,
+                _ => nested
+            });
+            let ends_link = matches!(event, Event::End(Tag::Link(_, _)) | Event::End(Tag::Image(_, _)));
+            if !ends_link || nested > 0 {
+                buffer.push((into_static(event), span.map(Span::to_owned)));
+                self.link_text = Some(buffer);
+                return Ok(());
+            }
+            let empty_link = buffer.is_empty() && matches!(event, Event::End(Tag::Link(_, _))) &&
+                self.options.empty_constructs != EmptyConstructPolicy::Preserve;
+            if empty_link {
+                if let Event::End(tag) = event {
+                    return self.write_empty(tag, span);
+                }
+            }
+            if let Event::End(Tag::Link(ref url, ref title)) = event {
+                if let Some(autolink) = self.autolink(&buffer, url, title) {
+                    self.references.links.pop_front();
+                    return write!(self.writer, "<{}>", autolink);
+                }
+                self.writer.write_text("[")?;
+            }
+            self.brackets.push(escape::plan_link_text(&buffer));
+            for (event, span) in buffer {
+                self.print_event(event, span.as_ref().map(OwnedSpan::as_span))?;
+            }
+            let result = self.print_event(event, span);
+            self.brackets.pop();
+            return result;
+        }
+        if self.text_inspector.is_some() {
+            self.inspect_event(&event, span);
+        }
+        if self.lints.is_some() {
+            self.run_lints(&event, span);
+        }
+        if self.options.duplicate_anchors || self.options.broken_anchors {
+            self.check_anchor(&event, span);
+        }
+        if self.empty_image.is_some() {
+            match event {
+                Event::End(Tag::Image(ref url, ref title)) => self.check_alt_text(url, title)?,
+                Event::Text(ref text) if text.trim().is_empty() => {},
+                _ => self.empty_image = None
+            }
+        }
+        if !self.pending_text.is_empty() && !matches!(event, Event::Text(_)) {
+            let heading_end = matches!(event, Event::End(Tag::Header(_)));
+            self.flush_pending_text(heading_end)?;
+        }
+        if let Some(html) = self.pending_break.take() {
+            match event {
+                Event::SoftBreak => return self.write_html_break(&html),
+                Event::Text(ref text) if text.trim_start().is_empty() => {
+                    self.pending_break = Some(html);
+                    return Ok(());
+                },
+                // The line continues after the end of inline markup.
+                Event::End(Tag::Emphasis) | Event::End(Tag::Strong) |
+                Event::End(Tag::Link(_, _)) | Event::End(Tag::Image(_, _)) => {
+                    self.print_event(event, span)?;
+                    self.pending_break = Some(html);
+                    return Ok(());
+                },
+                Event::End(_) => self.writer.write_text(&html)?,
+                Event::Text(text) => {
+                    self.write_html_break(&html)?;
+                    let trimmed = text.trim_start();
+                    if trimmed.len() == text.len() {
+                        return self.print_event(Event::Text(text), span);
+                    }
+                    return self.print_event(Event::Text(trimmed.to_string().into()), None);
+                },
+                _ => self.write_html_break(&html)?
+            }
+        }
+        if let Some((start, start_span)) = self.empty_start.take() {
+            match (start, &event) {
+                (Event::Start(tag), Event::End(end)) if tag == *end => {
+                    return self.write_empty(tag, start_span.as_ref().map(OwnedSpan::as_span));
+                },
+                (start, _) => self.write_event(start, start_span.as_ref().map(OwnedSpan::as_span))?
+            }
+        }
+        let may_be_empty = matches!(event, Event::Start(Tag::Emphasis) | Event::Start(Tag::Strong) |
+                                           Event::Start(Tag::Code) | Event::Start(Tag::Item));
+        if may_be_empty && self.options.empty_constructs != EmptyConstructPolicy::Preserve {
+            self.empty_start = Some((into_static(event), span.map(Span::to_owned)));
+            return Ok(());
+        }
+        self.write_event(event, span)
+    }
+
+    fn write_event<'a>(&mut self, event: Event<'a>, span: Option<Span>) -> Result {
+        // Text held back for wrapping belongs to the current line, before
+        // any change of the layout, and single lines are never wrapped.
+        if let Event::Start(ref tag) | Event::End(ref tag) = event {
+            if !matches!(*tag, Tag::Emphasis | Tag::Strong | Tag::Code | Tag::Link(_, _) | Tag::Image(_, _)) {
+                self.writer.finish_word()?;
+            }
+        }
+        self.writer.set_wrap(!self.single_line)?;
         match event {
             Event::Start(tag) => {
                 match tag {
                     Tag::Paragraph => {
                         self.flush_break()?;
+                        self.start_lazy_continuation();
                     },
                     Tag::Rule => {
                         self.flush_break()?;
-                        self.writer.write_text("---")?;
+                        let rule = self.options.rule.render();
+                        self.writer.write_text(&rule)?;
                     },
                     Tag::Header(indent) => {
                         self.flush_break()?;
+                        self.single_line = true;
                         self.writer.write_text(&"#".repeat(indent as usize))?;
                         self.writer.write_non_breaking_space()?;
                     },
@@ -77,14 +1243,17 @@ impl<W: Write> PrettyPrinter<W> {
                             Some(Frame::ListItem(None)) => {
                                 self.flush_break()?;
                                 self.writer.write_text("-")?;
-                                self.writer.write_non_breaking_space()?;
+                                self.writer.write_marker_space(1)?;
                                 self.writer.push_frame(Frame::ListItem(None));
+                                self.start_lazy_continuation();
                             },
                             Some(Frame::ListItem(Some(index))) => {
                                 self.flush_break()?;
-                                write!(self.writer, "{}.", index)?;
-                                self.writer.write_non_breaking_space()?;
+                                let marker = format!("{}.", index);
+                                self.writer.write_text(&marker)?;
+                                self.writer.write_marker_space(marker.len())?;
                                 self.writer.push_frame(Frame::ListItem(Some(index + 1)));
+                                self.start_lazy_continuation();
                             },
                             _ => {}
                         }
@@ -95,11 +1264,27 @@ impl<W: Write> PrettyPrinter<W> {
                         self.writer.write_non_breaking_space()?;
                         self.writer.push_frame(Frame::BlockQuote);
                     },
-                    Tag::CodeBlock(note) => {
+                    Tag::CodeBlock(info) => {
                         self.flush_break()?;
-                        write!(self.writer, "```{}", note)?;
+                        let doctest = self.options.rustdoc && info::is_rustdoc(&info);
+                        if doctest {
+                            write!(self.writer, "```{}", info)?;
+                        } else {
+                            let info = info::normalize(&info, &self.options.info_string,
+                                                       self.options.attributes);
+                            if self.options.info_string.require_language && info::language(&info).is_empty() {
+                                self.report(Diagnostic::warning(
+                                    Message::CodeLanguage,
+                                    self.localizer.as_deref(),
+                                    span.map(Span::start)));
+                            }
+                            write!(self.writer, "```{}", info)?;
+                        }
                         self.writer.write_hard_break()?;
                         self.writer.write_indent()?;
+                        self.writer.set_preserve_whitespace(true);
+                        self.in_code = true;
+                        self.code_block = Some(String::new());
                     },
                     Tag::Emphasis => {
                         self.writer.write_text("*")?;
@@ -109,29 +1294,43 @@ impl<W: Write> PrettyPrinter<W> {
                     },
                     Tag::Code => {
                         self.writer.write_text("`")?;
+                        self.in_code = true;
                     },
                     Tag::Link(_, _) => {
-                        self.writer.write_text("[")?;
+                        // The opening bracket is written once the link text
+                        // is complete, as the link may become an autolink.
+                        self.link_text = Some(Vec::new());
                     },
                     Tag::Image(_, _) => {
                         self.writer.write_text("![")?;
+                        self.empty_image = Some(span.map(Span::start));
+                        self.link_text = Some(Vec::new());
                     },
-                    Tag::FootnoteDefinition(_) => { /* not supported for now */ },
-                    Tag::Table(_) => { /* not supported for now */ },
-                    Tag::TableHead => { /* not supported for now */ },
-                    Tag::TableRow => { /* not supported for now */ },
-                    Tag::TableCell => { /* not supported for now */ }
+                    Tag::FootnoteDefinition(label) => {
+                        self.flush_break()?;
+                        write!(self.writer, "[^{}]:", self.footnotes.label(&label))?;
+                        self.writer.write_non_breaking_space()?;
+                        self.writer.push_frame(Frame::FootnoteDefinition);
+                    },
+                    Tag::Table(_) => {
+                        self.flush_break()?;
+                        self.table = Some(Vec::new());
+                    },
+                    Tag::TableHead | Tag::TableRow | Tag::TableCell => { /* printed by write_table */ }
                 }
             },
             Event::End(tag) => {
                 match tag {
                     Tag::Paragraph => {
+                        self.writer.finish_line_head(None)?;
+                        self.writer.set_lazy(false);
                         self.needs_break = true;
                     },
                     Tag::Rule => {
                         self.needs_break = true;
                     },
                     Tag::Header(_) => {
+                        self.single_line = false;
                         self.needs_break = true;
                     },
                     Tag::List(_) => {
@@ -139,15 +1338,34 @@ impl<W: Write> PrettyPrinter<W> {
                         self.needs_break = true;
                     },
                     Tag::Item => {
+                        self.writer.finish_line_head(None)?;
+                        self.writer.set_lazy(false);
                         self.needs_break = true;
                     },
                     Tag::BlockQuote => {
                         self.writer.pop_frame();
                         self.needs_break = true;
                     },
-                    Tag::CodeBlock(_) => {
+                    Tag::CodeBlock(ref info) => {
+                        if let Some(code) = self.code_block.take() {
+                            let doctest = self.options.rustdoc && info::is_rustdoc(info);
+                            let info = info::normalize(info, &self.options.info_string,
+                                                       self.options.attributes);
+                            let lang = info::language(&info);
+                            let formatted = self.code_block_formatter.as_ref()
+                                .filter(|_| !doctest)
+                                .and_then(|formatter| formatter.format(lang, &code));
+                            // the closing fence has to start on a line of its own
+                            let mut code = formatted.unwrap_or(code);
+                            if !code.is_empty() && !code.ends_with('\n') {
+                                code.push('\n');
+                            }
+                            self.write_lines(&code)?;
+                        }
+                        self.writer.set_preserve_whitespace(false);
                         self.writer.write_text("```")?;
                         self.needs_break = true;
+                        self.in_code = false;
                     },
                     Tag::Emphasis => {
                         self.writer.write_text("*")?;
@@ -157,42 +1375,73 @@ impl<W: Write> PrettyPrinter<W> {
                     },
                     Tag::Code => {
                         self.writer.write_text("`")?;
+                        self.in_code = false;
                     },
                     Tag::Link(ref url, ref title) | Tag::Image(ref url, ref title) => {
-                        if title.is_empty() {
-                            write!(self.writer, "]({})", url)?;
+                        if let Some(Some(reference)) = self.references.links.pop_front() {
+                            self.writer.write_text(&reference)?;
+                        } else if let Some(title) = self.options.titles.render(title) {
+                            write!(self.writer, "]({} {})", url, title)?;
                         } else {
-                            write!(self.writer, "]({} \"{}\")", url, title)?;
+                            write!(self.writer, "]({})", url)?;
                         }
                     },
-                    Tag::FootnoteDefinition(_) => { /* not supported for now */ },
-                    Tag::Table(_) => { /* not supported for now */ },
-                    Tag::TableHead => { /* not supported for now */ },
-                    Tag::TableRow => { /* not supported for now */ },
-                    Tag::TableCell => { /* not supported for now */ }
+                    Tag::FootnoteDefinition(_) => {
+                        self.writer.pop_frame();
+                        self.needs_break = true;
+                    },
+                    Tag::Table(_) | Tag::TableHead | Tag::TableRow | Tag::TableCell => { /* printed by write_table */ }
                 }
             },
+            Event::Text(_) if self.html_skip.is_some() => {},
             Event::Text(text) => {
-                for (i, line) in text.split('\n').enumerate() {
-                    if i > 0 {
-                        self.writer.write_hard_break()?;
-                        self.writer.write_indent()?;
-                    }
-                    self.writer.write_text(line)?;
+                if let Some(code) = self.code_block.as_mut() {
+                    code.push_str(&text);
+                    return Ok(());
+                }
+                if !self.in_code && inline::has_recognizers(&self.options) {
+                    self.pending_text.push((text.into_owned(), span.map(Span::to_owned)));
+                    return Ok(());
+                }
+                let text = self.transform_text(text, span);
+                if self.in_code {
+                    self.write_lines(&text)?;
+                } else {
+                    self.write_content(&text)?;
                 }
             },
-            Event::Html(_html) => {
-                // not supported for now
+            Event::Html(html) => {
+                let html = self.sanitize_html(html);
+                if !html.trim().is_empty() {
+                    self.flush_break()?;
+                    self.write_lines(html.strip_suffix('\n').unwrap_or(&html))?;
+                    self.needs_break = true;
+                }
             },
             Event::InlineHtml(html) => {
-                self.writer.write_text(html.as_ref())?
+                let html = self.sanitize_html(html);
+                if self.options.html_breaks != HtmlBreakPolicy::Preserve && !self.single_line &&
+                    html::line_break(&html).is_some() {
+                    self.pending_break = Some(html.into_owned());
+                } else {
+                    self.writer.write_text(html.as_ref())?
+                }
             },
-            Event::FootnoteReference(_) => {
-                // not supported for now
+            Event::FootnoteReference(label) => {
+                match self.footnotes.inline(&label) {
+                    Some(text) => write!(self.writer, "^[{}]", text)?,
+                    None => write!(self.writer, "[^{}]", self.footnotes.label(&label))?
+                }
             },
             Event::SoftBreak => {
                 self.writer.write_soft_break()?
             },
+            Event::HardBreak if self.single_line => {
+                match self.options.heading_breaks {
+                    HeadingBreakStyle::Space => self.writer.write_soft_break()?,
+                    HeadingBreakStyle::Html => self.writer.write_text("<br>")?
+                }
+            },
             Event::HardBreak => {
                 self.writer.write_text("\\")?;
                 self.writer.write_hard_break()?;
@@ -203,11 +1452,45 @@ impl<W: Write> PrettyPrinter<W> {
         Ok(())
     }
 
+    /// Write raw text into the printer, bypassing any escaping and
+    /// normalization.
+    ///
+    /// Lines are still prefixed and indented according to the enclosing
+    /// blocks. When pushed between blocks, the text forms a block of its
+    /// own, otherwise it is written inline at the current position.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use prettify_cmark::PrettyPrinter;
+    ///
+    /// let mut printer = PrettyPrinter::default();
+    /// printer.push_source("# Title").unwrap();
+    /// printer.push_raw("[![CI](ci.svg)](ci)\n<br/>").unwrap();
+    /// printer.push_source("> Lorem").unwrap();
+    ///
+    /// assert_eq!(printer.into_inner(), "# Title\n\n[![CI](ci.svg)](ci)\n<br/>\n\n> Lorem");
+    /// ```
+    pub fn push_raw(&mut self, text: &str) -> Result {
+        self.flush_deferred()?;
+        if !self.pending_text.is_empty() {
+            self.flush_pending_text(false)?;
+        }
+        let block = self.needs_break || self.open_tags.is_empty();
+        if block {
+            self.flush_break()?;
+        }
+        self.write_lines(text)?;
+        self.needs_break = block;
+        Ok(())
+    }
+
     /// Push a series of events into the printer.
     ///
-    /// Events can be obtained using `pulldown_cmark::Parser`.
+    /// Events can be obtained using `pulldown_cmark::Parser`, or from a
+    /// series of `OwnedEvent`s.
     pub fn push_events<'a, I>(&mut self, events: I) -> Result
-        where I: IntoIterator<Item=Event<'a>>
+        where I: IntoIterator, I::Item: Into<Event<'a>>
     {
         for event in events {
             self.push_event(event)?;
@@ -215,13 +1498,610 @@ impl<W: Write> PrettyPrinter<W> {
         Ok(())
     }
 
+    /// Print the content that is held back until the end of the document,
+    /// such as footnote definitions that are moved there and link
+    /// reference definitions.
+    ///
+    /// `push_source` does this by itself. When pushing events, call it
+    /// after the last event of the document.
+    pub fn finish(&mut self) -> Result {
+        self.flush_deferred()?;
+        self.write_section_end(usize::MAX)
+    }
+
+    /// End the current document and separate it from the following one
+    /// by a thematic break.
+    ///
+    /// Any blocks and inlines that are still open are closed, printing the
+    /// content buffered for them such as the text of an unfinished link,
+    /// so that the following events start a fresh document. If nothing has
+    /// been printed yet, no thematic break is written.
+    pub fn push_document_break(&mut self) -> Result {
+        while let Some(tag) = self.open_tags.last().cloned() {
+            self.push_event_with_source(Event::End(tag), None)?;
+        }
+        self.flush_deferred()?;
+        if !self.pending_text.is_empty() {
+            self.flush_pending_text(false)?;
+        }
+        self.writer.clear_frames();
+        self.blocks.clear();
+        self.text_run = None;
+        self.inline_buffer = None;
+        self.link_text = None;
+        self.table = None;
+        self.callout_line = None;
+        if let Some(html) = self.pending_break.take() {
+            self.writer.write_text(&html)?;
+        }
+        self.single_line = false;
+        self.code_block = None;
+        self.in_code = false;
+        self.writer.set_preserve_whitespace(false);
+        self.writer.set_lazy(false);
+        self.quote_start = false;
+        self.footnote_buffer = None;
+        self.sort_next = false;
+        self.sorted_list = None;
+        self.verbatim = None;
+        self.headings = HeadingPath::default();
+        self.select_profile()?;
+        self.apply_directive(Directive::Reset)?;
+        if self.needs_break {
+            self.push_events(vec![Event::Start(Tag::Rule), Event::End(Tag::Rule)])?;
+        }
+        Ok(())
+    }
+
+    /// Returns the column (counted in characters, starting at zero) at
+    /// which the next text will be printed.
+    pub fn column(&self) -> usize {
+        self.writer.column()
+    }
+
+    /// Returns the text of the headings of the section that the printer
+    /// is in, from the outermost one.
+    ///
+    /// Only headings at the top level of the document are considered,
+    /// and a heading is part of the path once it has been pushed
+    /// completely.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use prettify_cmark::PrettyPrinter;
+    /// let mut printer = PrettyPrinter::default();
+    /// printer.push_source("# Guide\n\n## Setup\n\nLorem\n\n## *Install*").unwrap();
+    /// assert_eq!(printer.heading_path(), vec!["Guide", "Install"]);
+    /// ```
+    pub fn heading_path(&self) -> Vec<&str> {
+        self.headings.path()
+    }
+
+    /// Returns the diagnostics reported while printing so far.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Removes and returns the diagnostics reported while printing so far.
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        mem::take(&mut self.diagnostics)
+    }
+
     /// Unwrap the printer, returning the underlying writer.
     pub fn into_inner(self) -> W {
         self.writer.into_inner()
     }
 
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        self.writer.get_mut()
+    }
+
+    fn run_lints(&mut self, event: &Event, span: Option<Span>) {
+        let diagnostics = match self.lints.as_mut() {
+            Some(lints) => {
+                let source = span.map(|span| span.source.trim_start());
+                lints.check(event, span.map(Span::start), source, &self.headings.path(), self.localizer.as_deref())
+            },
+            None => return
+        };
+        for diagnostic in diagnostics {
+            self.report(diagnostic);
+        }
+    }
+
+    /// Run the lints of `Options::heading_hierarchy` on a heading as it is
+    /// in the source. Rules that are also registered with `set_lints` are
+    /// left to those lints, so that they are not reported twice.
+    fn check_heading_hierarchy(&mut self, level: i32, span: Option<Span>) {
+        let registered = self.lints.as_ref().map_or_else(Vec::new, Lints::rules);
+        let headings = self.headings.path();
+        let source = span.map(|span| span.source.trim_start());
+        let event = Event::Start(Tag::Header(level));
+        let mut diagnostics = Vec::new();
+        for lint in self.heading_levels.lints() {
+            if !registered.contains(&lint.rule()) {
+                let context = LintContext::new(span.map(Span::start), source, &headings, self.localizer.as_deref());
+                diagnostics.extend(context.run(lint, &event, Severity::Warning));
+            }
+        }
+        for diagnostic in diagnostics {
+            self.report(diagnostic);
+        }
+    }
+
+    fn inspect_event(&mut self, event: &Event, span: Option<Span>) {
+        match *event {
+            Event::Text(ref text) if !self.in_code => {
+                let run = self.text_run.get_or_insert_with(|| {
+                    (String::new(), span.map(|span| span.offset))
+                });
+                run.0.push_str(text);
+                return;
+            },
+            Event::SoftBreak => {
+                if let Some(run) = self.text_run.as_mut() {
+                    run.0.push('\n');
+                    return;
+                }
+            },
+            _ => {}
+        }
+
+        if let Some((text, offset)) = self.text_run.take() {
+            let headings = self.headings.path();
+            let run = TextRun { text: &text, offset, blocks: &self.blocks, headings: &headings };
+            if let Some(inspector) = self.text_inspector.as_mut() {
+                inspector.inspect(&run);
+            }
+        }
+
+        match *event {
+            Event::Start(ref tag) => {
+                self.blocks.extend(Block::from_tag(tag));
+            },
+            Event::End(ref tag) if Block::from_tag(tag).is_some() => {
+                self.blocks.pop();
+            },
+            _ => {}
+        }
+    }
+
+    fn transform_text<'a>(&mut self, text: Cow<'a, str>, span: Option<Span>) -> Cow<'a, str> {
+        if self.in_code {
+            return text;
+        }
+        match self.options.invisible_characters {
+            Some(InvisibleCharacterPolicy::Report) => {
+                self.report_invisible_characters(&text, span);
+                self.transform_prose(text, span)
+            },
+            Some(InvisibleCharacterPolicy::Strip) if text.contains(invisible::is_invisible) => {
+                self.transform_prose(invisible::strip(text), None)
+            },
+            Some(InvisibleCharacterPolicy::Escape) => {
+                let text = self.transform_prose(text, span);
+                invisible::escape(text)
+            },
+            _ => self.transform_prose(text, span)
+        }
+    }
+
+    /// Report every zero-width and bidirectional control character in
+    /// text, at its offset in the source if the text was written
+    /// literally.
+    fn report_invisible_characters(&mut self, text: &str, span: Option<Span>) {
+        for (index, character) in text.char_indices().filter(|&(_, c)| invisible::is_invisible(c)) {
+            let offset = span.map(|span| if span.source == text { span.offset + index } else { span.start() });
+            self.report(Diagnostic::warning(
+                Message::InvisibleCharacter { character },
+                self.localizer.as_deref(),
+                offset));
+        }
+    }
+
+    fn transform_prose<'a>(&mut self, text: Cow<'a, str>, span: Option<Span>) -> Cow<'a, str> {
+        // the parser replaces tabs by spaces up to its own tab stops, so
+        // they are taken from the source to be expanded by the writer
+        let text = match span {
+            Some(span) if self.options.expand_tabs.is_some() && span.source.contains('\t') &&
+                !text.is_empty() && text.bytes().all(|b| b == b' ') => Cow::Owned(span.source.to_string()),
+            _ => text
+        };
+        let text = match self.options.normalization {
+            Some(Normalization::Nfc) => Cow::Owned(text.nfc().collect()),
+            // compatibility decomposition would turn non-breaking spaces
+            // into regular ones, unless they are printed as configured
+            Some(Normalization::Nfkc) if self.options.non_breaking_spaces != NonBreakingSpaces::Entities => {
+                Cow::Owned(text.split('\u{a0}').map(|piece| piece.nfkc().collect::<String>()).collect::<Vec<_>>().join("\u{a0}"))
+            },
+            Some(Normalization::Nfkc) => Cow::Owned(text.nfkc().collect()),
+            None => text
+        };
+        // references to non-breaking spaces are printed as configured
+        // for them, regardless of the entity policy
+        let nbsp = text == "\u{a0}" && self.options.non_breaking_spaces != NonBreakingSpaces::Entities;
+        let preserved = span.is_some_and(|span| {
+            escape::is_escape(span.source) ||
+                self.options.entities == EntityPolicy::Preserve && entities::is_reference(span.source) && !nbsp
+        });
+        // text that was written literally in the source needs no escaping
+        // either, as long as it keeps its surroundings
+        let literal = span.is_some_and(|span| span.source == text);
+        let brackets = self.brackets.last_mut();
+        if preserved || literal {
+            if let Some(brackets) = brackets {
+                escape::skip_brackets(brackets, &text);
+            }
+            return match span {
+                Some(span) if preserved => escape::apply_style(Cow::Owned(span.source.to_string()), &self.options.escapes),
+                _ => self.encode_entities(text)
+            };
+        }
+        let text = escape::escape_inline(text, self.options.entities != EntityPolicy::Encode, brackets);
+        let text = self.encode_entities(text);
+        escape::apply_style(text, &self.options.escapes)
+    }
+
+    fn encode_entities<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        let encode = self.options.entities == EntityPolicy::Encode;
+        let nbsp = match self.options.non_breaking_spaces {
+            NonBreakingSpaces::Entities => encode,
+            NonBreakingSpaces::Character => false,
+            NonBreakingSpaces::Entity => true
+        };
+        if encode {
+            match entities::encode(&text, nbsp) {
+                Cow::Borrowed(_) => text,
+                Cow::Owned(encoded) => Cow::Owned(encoded)
+            }
+        } else if nbsp && text.contains('\u{a0}') {
+            Cow::Owned(text.replace('\u{a0}', "&nbsp;"))
+        } else {
+            text
+        }
+    }
+
+    /// The content of the autolink that a link is printed as, if bare
+    /// URLs are converted into autolinks and the link text is the plain
+    /// destination of the link.
+    fn autolink(&self, buffer: &[BufferedEvent], url: &str, title: &str) -> Option<String> {
+        if self.options.bare_urls != Some(AutolinkStyle::Angle) || !title.is_empty() ||
+           matches!(self.references.links.front(), Some(Some(_))) {
+            return None;
+        }
+        let mut text = String::new();
+        for (event, _) in buffer {
+            match *event {
+                Event::Text(ref piece) => text.push_str(piece),
+                _ => return None
+            }
+        }
+        let valid = !text.is_empty() && !text.contains(|c: char| c.is_whitespace() || c == '<' || c == '>');
+        let scheme = url.find(':').map(|colon| &url[..colon]);
+        let absolute = scheme.is_some_and(|scheme| {
+            scheme.len() >= 2 && scheme.starts_with(|c: char| c.is_ascii_alphabetic()) &&
+                scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        });
+        let email = url.strip_prefix("mailto:") == Some(&text) && text.contains('@');
+        if valid && (absolute && url == text || email) {
+            Some(text)
+        } else {
+            None
+        }
+    }
+
+    fn flush_pending_text(&mut self, heading_end: bool) -> Result {
+        let pieces = mem::take(&mut self.pending_text);
+        let text = pieces.iter().map(|piece| piece.0.as_str()).collect::<String>();
+        let mut pos = 0;
+        let linkify = self.brackets.is_empty();
+        for (atom, kind) in inline::atoms(&text, &self.options, heading_end, linkify) {
+            self.write_pieces(&pieces, pos..atom.start)?;
+            let token = &text[atom.clone()];
+            match (kind, self.options.bare_urls) {
+                (AtomKind::Url, Some(AutolinkStyle::Angle)) | (AtomKind::Email, Some(AutolinkStyle::Angle)) => {
+                    write!(self.writer, "<{}>", token)?
+                },
+                (AtomKind::Url, Some(AutolinkStyle::Inline)) | (AtomKind::Email, Some(AutolinkStyle::Inline)) => {
+                    let escaped = escape::escape_inline(Cow::Borrowed(token), true, None);
+                    let escaped = escape::apply_style(escaped, &self.options.escapes);
+                    let scheme = if kind == AtomKind::Email { "mailto:" } else { "" };
+                    write!(self.writer, "[{}]({}{})", escaped, scheme, token)?
+                },
+                _ => self.writer.write_text(token)?
+            }
+            pos = atom.end;
+        }
+        self.write_pieces(&pieces, pos..text.len())
+    }
+
+    fn write_pieces(&mut self, pieces: &[(String, Option<OwnedSpan>)], range: Range<usize>) -> Result {
+        let mut offset = 0;
+        for (piece, span) in pieces {
+            let start = range.start.max(offset);
+            let end = range.end.min(offset + piece.len());
+            if start < end {
+                let text = if start == offset && end == offset + piece.len() {
+                    self.transform_text(Cow::Borrowed(piece), span.as_ref().map(OwnedSpan::as_span))
+                } else {
+                    self.transform_text(Cow::Borrowed(&piece[start - offset..end - offset]), None)
+                };
+                self.write_content(&text)?;
+            }
+            offset += piece.len();
+        }
+        Ok(())
+    }
+
+    fn sanitize_html<'a>(&mut self, html: Cow<'a, str>) -> Cow<'a, str> {
+        if self.options.html == HtmlPolicy::Preserve {
+            return html;
+        }
+        Cow::Owned(html::sanitize(&html, &self.options.html, &mut self.html_skip))
+    }
+
+    /// Write the source of a block that is to be left untouched.
+    fn write_verbatim(&mut self, source: &str) -> Result {
+        let leading = source.len() - source.trim_start().len();
+        let start = source[..leading].rfind('\n').map_or(0, |newline| newline + 1);
+        self.flush_deferred()?;
+        self.flush_break()?;
+        self.writer.set_preserve_whitespace(true);
+        self.write_lines(source[start..].trim_end())?;
+        self.writer.set_preserve_whitespace(false);
+        self.needs_break = true;
+        Ok(())
+    }
+
+    /// Print a table once all of its events have been buffered. Each cell
+    /// is printed on its own, and the cells are then laid out together.
+    fn write_table(&mut self, alignments: &[Alignment], buffer: Vec<BufferedEvent>) -> Result {
+        let mut rows: Vec<Vec<Vec<BufferedEvent>>> = Vec::new();
+        let mut cell: Option<Vec<BufferedEvent>> = None;
+        for (event, span) in buffer {
+            match event {
+                Event::Start(Tag::TableHead) | Event::Start(Tag::TableRow) => rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => cell = Some(Vec::new()),
+                Event::End(Tag::TableCell) => {
+                    if let (Some(cell), Some(row)) = (cell.take(), rows.last_mut()) {
+                        row.push(cell);
+                    }
+                },
+                event => {
+                    if let Some(ref mut cell) = cell {
+                        cell.push((event, span));
+                    }
+                }
+            }
+        }
+
+        let mut printed = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let mut cells = Vec::with_capacity(row.len());
+            for cell in row {
+                let mut printer = PrettyPrinter::new_with_options(String::new(), "", self.options.clone());
+                printer.single_line = true;
+                for (index, (event, span)) in cell.iter().enumerate() {
+                    // The whitespace around the contents of a cell is not
+                    // part of them.
+                    match *event {
+                        Event::Text(ref text) if index == 0 && text.starts_with(|c: char| c.is_ascii_whitespace()) => {
+                            let text = text.trim_start_matches(|c: char| c.is_ascii_whitespace());
+                            if !text.is_empty() {
+                                printer.push_event_with_source(Event::Text(text.into()), None)?;
+                            }
+                        },
+                        ref event => {
+                            printer.push_event_with_source(event.clone(), span.as_ref().map(OwnedSpan::as_span))?;
+                        }
+                    }
+                }
+                cells.push(table::escape_pipes(printer.into_inner().trim_matches(|c: char| c.is_ascii_whitespace())).replace('\n', " "));
+            }
+            printed.push(cells);
+        }
+
+        let mut options = self.options.tables.unwrap_or_default();
+        if let Some(max_width) = self.overrides.max_width {
+            options.max_width = max_width;
+        }
+        let mut rendered = table::render(alignments, &printed, &options);
+        if options.max_width.is_some_and(|max_width| table::width(&rendered) > max_width) {
+            rendered = match options.overflow {
+                TableOverflow::Minimal => {
+                    let minimal = TableOptions { separator: TableSeparator::Minimal, padding: false, ..options };
+                    table::render(alignments, &printed, &minimal)
+                },
+                TableOverflow::Html => {
+                    let cells = rows.into_iter().map(|row| row.into_iter().map(|cell| {
+                        let mut html = String::new();
+                        pulldown_cmark::html::push_html(&mut html, cell.into_iter().map(|(event, _)| event));
+                        html
+                    }).collect()).collect::<Vec<Vec<String>>>();
+                    table::render_html(alignments, &cells)
+                }
+            };
+        }
+        self.write_lines(&rendered)?;
+        self.needs_break = true;
+        Ok(())
+    }
+
+    /// End the line after a `<br>` element, converting it into a hard
+    /// line break if requested.
+    fn write_html_break(&mut self, html: &str) -> Result {
+        let convert = self.options.html_breaks == HtmlBreakPolicy::Convert && html::line_break(html) == Some(false);
+        self.writer.write_text(if convert { "\\" } else { html })?;
+        self.writer.write_hard_break()?;
+        self.writer.write_indent()
+    }
+
+    fn write_lines(&mut self, text: &str) -> Result {
+        if self.single_line {
+            return self.writer.write_text(&text.replace('\n', " "));
+        }
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.writer.write_hard_break()?;
+                self.writer.write_indent()?;
+            }
+            self.writer.write_text(line)?;
+        }
+        Ok(())
+    }
+
+    /// Write lines of document content, which unlike other text is
+    /// escaped where needed at the start of a line.
+    fn write_content(&mut self, text: &str) -> Result {
+        if self.single_line {
+            return self.writer.write_content(&text.replace('\n', " "));
+        }
+        for (i, line) in text.split('\n').enumerate() {
+            if i > 0 {
+                self.writer.write_hard_break()?;
+                self.writer.write_indent()?;
+            }
+            self.writer.write_content(line)?;
+        }
+        Ok(())
+    }
+
+    /// Keep track of the anchors of headings and HTML elements, reporting
+    /// duplicates, and of the links to anchors.
+    fn check_anchor(&mut self, event: &Event, span: Option<Span>) {
+        match *event {
+            Event::Start(Tag::Header(_)) => {
+                self.heading_text = Some((String::new(), span.map(Span::start)));
+            },
+            Event::Text(ref text) => {
+                if let Some((ref mut heading, _)) = self.heading_text {
+                    heading.push_str(text);
+                }
+            },
+            Event::End(Tag::Header(_)) => {
+                if let Some((text, offset)) = self.heading_text.take() {
+                    let anchor = self.slugger.slug(&text);
+                    let base = outline::slug(&text);
+                    if anchor != base && self.options.duplicate_anchors {
+                        self.report(Diagnostic::warning(
+                            Message::DuplicateAnchor { heading: &text, anchor: &base },
+                            self.localizer.as_deref(),
+                            offset));
+                    }
+                }
+            },
+            Event::Start(Tag::Link(ref url, _)) if self.options.broken_anchors => {
+                if let Some(fragment) = url.strip_prefix('#').filter(|fragment| !fragment.is_empty()) {
+                    let section = self.headings.path().into_iter().map(str::to_string).collect();
+                    self.fragment_links.push((fragment.to_string(), span.map(Span::start), section));
+                }
+            },
+            Event::Html(ref html) | Event::InlineHtml(ref html) if self.options.broken_anchors => {
+                self.html_anchors.extend(html::anchors(html));
+            },
+            _ => {}
+        }
+    }
+
+    /// Report the links to anchors that do not exist in the document.
+    fn check_fragment_links(&mut self) {
+        for (fragment, offset, section) in mem::take(&mut self.fragment_links) {
+            if self.slugger.contains(&fragment.to_lowercase()) || self.html_anchors.contains(&fragment) {
+                continue;
+            }
+            let mut diagnostic = Diagnostic::warning(Message::BrokenAnchor { anchor: &fragment }, self.localizer.as_deref(), offset);
+            diagnostic.section = section;
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Print a construct without any content, according to the policy for
+    /// empty constructs.
+    fn write_empty(&mut self, tag: Tag, span: Option<Span>) -> Result {
+        if let Tag::Link(_, _) = tag {
+            self.references.links.pop_front();
+        }
+        match self.options.empty_constructs {
+            EmptyConstructPolicy::Preserve | EmptyConstructPolicy::Drop => Ok(()),
+            EmptyConstructPolicy::Html => match tag {
+                Tag::Emphasis => self.writer.write_text("<em></em>"),
+                Tag::Strong => self.writer.write_text("<strong></strong>"),
+                Tag::Code => self.writer.write_text("<code></code>"),
+                Tag::Link(url, title) => {
+                    write!(self.writer, "<a href=\"{}\"", html::escape_attribute(&url))?;
+                    if !title.is_empty() {
+                        write!(self.writer, " title=\"{}\"", html::escape_attribute(&title))?;
+                    }
+                    self.writer.write_text("></a>")
+                },
+                tag => {
+                    self.write_event(Event::Start(tag.clone()), span)?;
+                    self.write_event(Event::End(tag), None)
+                }
+            },
+            EmptyConstructPolicy::Error => {
+                self.report(Diagnostic::error(
+                    Message::EmptyConstruct { construct: validate::name(&tag) },
+                    self.localizer.as_deref(),
+                    span.map(Span::start)));
+                Err(fmt::Error)
+            }
+        }
+    }
+
+    /// Supply or report missing alt text for an image that is about to
+    /// be closed.
+    fn check_alt_text(&mut self, url: &str, title: &str) -> Result {
+        let offset = match self.empty_image.take() {
+            Some(offset) => offset,
+            None => return Ok(())
+        };
+        let alt = self.alt_text_provider.as_mut().and_then(|provider| provider.alt_text(url, title));
+        if let Some(alt) = alt {
+            // supplied alt text is escaped like the text of the document,
+            // so that it cannot end the image or add formatting
+            let alt = alt.replace('\n', " ");
+            self.brackets.push(escape::plan_link_text(&[(Event::Text(alt.clone().into()), None)]));
+            let alt = self.transform_text(Cow::Owned(alt), None);
+            self.brackets.pop();
+            return self.writer.write_content(&alt);
+        }
+        let message = Message::ImageAlt { url };
+        match self.options.image_alt {
+            AltTextPolicy::Ignore => Ok(()),
+            AltTextPolicy::Warn => {
+                self.report(Diagnostic::warning(message, self.localizer.as_deref(), offset));
+                Ok(())
+            },
+            AltTextPolicy::Require => {
+                self.report(Diagnostic::error(message, self.localizer.as_deref(), offset));
+                Err(fmt::Error)
+            }
+        }
+    }
+
+    fn write_definitions(&mut self, section: usize) -> Result {
+        let mut first = true;
+        while self.references.definitions.front().is_some_and(|&(at, _)| at <= section) {
+            let (_, definition) = self.references.definitions.pop_front().unwrap();
+            if first {
+                self.flush_break()?;
+                first = false;
+            } else {
+                self.writer.write_hard_break()?;
+                self.writer.write_indent()?;
+            }
+            self.writer.write_text(&definition)?;
+            self.needs_break = true;
+        }
+        Ok(())
+    }
+
     fn flush_break(&mut self) -> Result {
-        if self.needs_break {
+        self.writer.set_lazy(false);
+        if self.needs_break || self.writer.is_inside_item_text() {
             self.writer.write_hard_break()?;
             self.writer.write_indent()?;
             self.writer.write_hard_break()?;
@@ -230,6 +2110,67 @@ impl<W: Write> PrettyPrinter<W> {
         self.needs_break = false;
         Ok(())
     }
+
+    /// Leave out block quote markers from the continuation lines of the
+    /// paragraph or list item text that is about to be printed, if the
+    /// options ask for lazy block quotes.
+    fn start_lazy_continuation(&mut self) {
+        if self.options.block_quote_style == BlockQuoteStyle::Lazy {
+            self.writer.set_lazy(true);
+        }
+    }
+}
+
+impl PrettyPrinter<String> {
+    /// Detect the prefix shared by the lines of a comment that contains
+    /// Markdown, such as `///` or `//!` for Rust doc comments and `#` for
+    /// shell comments, along with its indentation.
+    ///
+    /// Returns `None` if the lines do not all start with the same prefix.
+    /// `prettify_comment` uses the detected prefix to format comments.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use prettify_cmark::PrettyPrinter;
+    /// let prefix = PrettyPrinter::detect_prefix(vec!["  # Lorem", "  #", "  # ipsum"]).unwrap();
+    /// assert_eq!(prefix.marker, "  #");
+    /// assert!(prefix.space && !prefix.trailing_space);
+    /// assert_eq!(PrettyPrinter::detect_prefix(vec!["/// Lorem", "ipsum"]), None);
+    /// ```
+    pub fn detect_prefix<'a, I: IntoIterator<Item=&'a str>>(lines: I) -> Option<CommentPrefix> {
+        comments::detect(lines)
+    }
+}
+
+/// Check whether the first line of a block quote starts with a callout
+/// marker such as `[!NOTE]`, optionally followed by a title, returning the
+/// length of the marker.
+fn callout_marker(line: &[(String, Option<OwnedSpan>)]) -> Option<usize> {
+    let text = line.iter().map(|piece| piece.0.as_str()).collect::<String>();
+    let end = text.strip_prefix("[!")?.find(']')? + 3;
+    let kind = &text[2..end - 1];
+    let title = &text[end..];
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) || !(title.is_empty() || title.starts_with(' ')) {
+        return None;
+    }
+    Some(end)
+}
+
+/// The approximate number of bytes taken up by an event when it is
+/// buffered, along with its span.
+fn buffered_size(event: &Event, span: Option<Span>) -> usize {
+    let text = match *event {
+        Event::Text(ref text) | Event::Html(ref text) | Event::InlineHtml(ref text) => text.len(),
+        Event::FootnoteReference(ref label) => label.len(),
+        Event::Start(ref tag) | Event::End(ref tag) => match *tag {
+            Tag::Link(ref url, ref title) | Tag::Image(ref url, ref title) => url.len() + title.len(),
+            Tag::CodeBlock(ref info) | Tag::FootnoteDefinition(ref info) => info.len(),
+            _ => 0
+        },
+        Event::SoftBreak | Event::HardBreak => 0
+    };
+    mem::size_of::<BufferedEvent>() + text + span.map_or(0, |span| span.source.len())
 }
 
 impl Default for PrettyPrinter {
@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use pulldown_cmark::{Event, Parser, Tag};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use diagnostics::{Diagnostic, Message};
+use hooks::Localizer;
+use options::{ReferenceLabels, ReferenceOptions, ReferencePlacement, TitleOptions};
+
+/// Reference links and definitions found in a source document, ready to
+/// be printed.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct References {
+    /// For each link or image in document order, the text to print in
+    /// place of the closing `](url)`, or `None` for inline links.
+    pub links: VecDeque<Option<String>>,
+    /// Printed definitions, along with the index of the section they
+    /// are to be placed at the end of.
+    pub definitions: VecDeque<(usize, String)>
+}
+
+struct Definition {
+    key: String,
+    label: String,
+    destination: String,
+    title: String,
+    first_use: Option<(usize, usize)>
+}
+
+enum Form {
+    Shortcut,
+    Collapsed,
+    Full
+}
+
+/// Scan a source document for reference links and definitions.
+///
+/// Only the first definition of each label is kept, as it is the one that
+/// links resolve to. Later definitions with a different destination or
+/// title are reported as `duplicate-definition` diagnostics.
+pub(crate) fn scan(source: &str, options: &ReferenceOptions, titles: &TitleOptions, diagnostics: &mut Vec<Diagnostic>,
+                   localizer: Option<&dyn Localizer>) -> References {
+    let mut definitions = Vec::new();
+    let mut links = Vec::new();
+    let mut text_ranges = Vec::new();
+    let mut starts = Vec::new();
+    let mut depth = 0usize;
+    let mut section = 0;
+    let mut parser = Parser::new(source);
+    loop {
+        let start = parser.get_offset();
+        let event = match parser.next() {
+            Some(event) => event,
+            None => break
+        };
+        let end = parser.get_offset();
+        match event {
+            Event::Start(Tag::Header(_)) if depth == 0 => {
+                section += 1;
+            },
+            Event::Start(Tag::Link(_, _)) | Event::Start(Tag::Image(_, _)) => {
+                starts.push(end);
+            },
+            Event::End(Tag::Link(_, _)) | Event::End(Tag::Image(_, _)) => {
+                let text = starts.pop().and_then(|text_start| source.get(text_start..start));
+                links.push((text.and_then(|text| reference_form(&source[start..end], text)), section));
+            },
+            Event::Text(_) | Event::Html(_) | Event::InlineHtml(_) => {
+                text_ranges.push(start..end);
+            },
+            _ => {}
+        }
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    let mut line_start = 0;
+    for line in source.split('\n') {
+        let range = line_start..line_start + line.len();
+        line_start = range.end + 1;
+        if text_ranges.iter().any(|text| overlaps(text, &range)) {
+            continue;
+        }
+        if let Some(definition) = parse_definition(line) {
+            match definitions.iter().find(|existing: &&Definition| existing.key == definition.key) {
+                Some(existing) => {
+                    if existing.destination != definition.destination || existing.title != definition.title {
+                        diagnostics.push(Diagnostic::warning(
+                            Message::DuplicateDefinition { label: &definition.label },
+                            localizer,
+                            Some(range.start + line.len() - line.trim_start().len())));
+                    }
+                },
+                None => definitions.push(definition)
+            }
+        }
+    }
+
+    // Record the first use of each definition, dropping references that
+    // the parser did not resolve through a definition we know about.
+    let mut resolved = Vec::new();
+    for (index, (form, section)) in links.into_iter().enumerate() {
+        let form = form.and_then(|(form, label)| {
+            let key = normalize_label(&label);
+            let definition = definitions.iter_mut().find(|definition| definition.key == key)?;
+            if definition.first_use.is_none() {
+                definition.first_use = Some((index, section));
+            }
+            Some((form, key))
+        });
+        resolved.push(form);
+    }
+
+    let last_section = section;
+    let mut order = (0..definitions.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| definitions[index].first_use.map_or(usize::MAX, |(first, _)| first));
+    let labels = order.iter().enumerate().map(|(number, &index)| {
+        let definition = &definitions[index];
+        let label = match options.labels {
+            ReferenceLabels::Preserve => definition.label.clone(),
+            ReferenceLabels::Lowercase => definition.label.to_lowercase(),
+            ReferenceLabels::Numbered => (number + 1).to_string()
+        };
+        (index, label)
+    }).collect::<Vec<_>>();
+    let label_of = |key: &str| {
+        labels.iter()
+            .find(|&&(index, _)| definitions[index].key == key)
+            .map(|(_, label)| label.clone())
+    };
+
+    let links = resolved.into_iter().map(|link| {
+        link.map(|(form, key)| {
+            let label = label_of(&key).unwrap_or(key);
+            match form {
+                Form::Shortcut if options.labels != ReferenceLabels::Numbered => "]".to_string(),
+                Form::Collapsed if options.labels != ReferenceLabels::Numbered => "][]".to_string(),
+                _ => format!("][{}]", label)
+            }
+        })
+    }).collect();
+
+    let mut printed = labels.iter().map(|(index, label)| {
+        let definition = &definitions[*index];
+        let section = match options.placement {
+            ReferencePlacement::DocumentEnd => last_section,
+            ReferencePlacement::SectionEnd => definition.first_use.map_or(last_section, |(_, section)| section)
+        };
+        let mut line = format!("[{}]: {}", label, definition.destination);
+        if !definition.title.is_empty() && !titles.drop_all {
+            line.push(' ');
+            line.push_str(&definition.title);
+        }
+        (section, line)
+    }).collect::<Vec<_>>();
+    if options.sort && options.labels != ReferenceLabels::Numbered {
+        printed.sort_by_key(|(section, line)| (*section, line.to_lowercase()));
+    } else {
+        printed.sort_by_key(|&(section, _)| section);
+    }
+
+    References { links, definitions: printed.into_iter().collect() }
+}
+
+/// Determine the form of a reference link from the source of its end
+/// event, such as `]`, `][]` or `][label]`.
+fn reference_form(end: &str, text: &str) -> Option<(Form, String)> {
+    if end == "]" {
+        Some((Form::Shortcut, text.to_string()))
+    } else if end == "][]" {
+        Some((Form::Collapsed, text.to_string()))
+    } else if end.starts_with("][") && end.ends_with(']') {
+        let label = &end[2..end.len() - 1];
+        Some((Form::Full, label.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Check whether a line is a single-line link reference definition.
+pub(crate) fn is_definition(line: &str) -> bool {
+    parse_definition(line).is_some()
+}
+
+/// Parse a single-line link reference definition, such as
+/// `[label]: <destination> "title"`.
+fn parse_definition(line: &str) -> Option<Definition> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 || !trimmed.starts_with('[') {
+        return None;
+    }
+    let close = trimmed.find("]:")?;
+    let label = &trimmed[1..close];
+    if label.trim().is_empty() || label.contains(['[', ']']) {
+        return None;
+    }
+    let rest = trimmed[close + 2..].trim();
+    let (destination, title) = match rest.find(char::is_whitespace) {
+        Some(split) => (&rest[..split], rest[split..].trim()),
+        None => (rest, "")
+    };
+    if destination.is_empty() {
+        return None;
+    }
+    Some(Definition {
+        key: normalize_label(label),
+        label: label.to_string(),
+        destination: destination.to_string(),
+        title: title.to_string(),
+        first_use: None
+    })
+}
+
+/// Normalize a label for matching, as described by the CommonMark spec.
+fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    a.start < b.end && b.start < a.end
+}
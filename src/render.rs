@@ -0,0 +1,48 @@
+use pulldown_cmark::{html, Event, Parser, Tag};
+
+use options::Options;
+use outline::Slugger;
+
+/// Renders a CommonMark document as HTML, for previews.
+///
+/// The document is pretty printed using the given options first, so that
+/// the rendering reflects the transforms applied by the pretty printer,
+/// and it is parsed with the same extensions. Headings get an `id`
+/// attribute with the anchor generated by `Slugger`, so that links to
+/// headings work in the preview.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{render_html, HeadingCase, Options};
+/// let options = Options { heading_case: HeadingCase::Sentence, ..Options::default() };
+/// let html = render_html("# Getting Started\n\nLorem __ipsum__", &options);
+/// assert_eq!(html, "<h1 id=\"getting-started\">Getting started</h1>\n<p>Lorem <strong>ipsum</strong></p>\n");
+/// ```
+pub fn render_html(source: &str, options: &Options) -> String {
+    let formatted = ::prettify_with_options(source, options);
+    let mut events = Parser::new_ext(&formatted, options.parser_options()).collect::<Vec<_>>();
+    let mut slugger = Slugger::new();
+    let mut heading = None;
+    let mut text = String::new();
+    for index in 0..events.len() {
+        match events[index] {
+            Event::Start(Tag::Header(_)) => {
+                heading = Some(index);
+                text.clear();
+            },
+            Event::Text(ref content) | Event::InlineHtml(ref content) if heading.is_some() => text.push_str(content),
+            Event::SoftBreak | Event::HardBreak if heading.is_some() => text.push(' '),
+            Event::End(Tag::Header(level)) => {
+                if let Some(start) = heading.take() {
+                    let slug = slugger.slug(&text);
+                    events[start] = Event::Html(format!("<h{} id=\"{}\">", level, slug).into());
+                }
+            },
+            _ => {}
+        }
+    }
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events.into_iter());
+    rendered
+}
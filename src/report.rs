@@ -0,0 +1,178 @@
+use std::fmt::{self, Display, Formatter};
+
+use pulldown_cmark::{Alignment, Event, Tag};
+
+use options::Options;
+use printer::PrettyPrinter;
+
+/// Builder of a document from headings, paragraphs, lists and tables,
+/// for generating reports such as benchmark results or audits without
+/// assembling events by hand.
+///
+/// All text is plain text, which the printer escapes as needed. The
+/// report is printed by `render`, or with the default options when it is
+/// formatted via `Display`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::Report;
+/// let results = vec![("parse", 12.5), ("print", 3.25)];
+/// let report = Report::new()
+///     .heading(1, "Benchmarks")
+///     .paragraph("Timings in *milliseconds*:")
+///     .table(vec!["name", "time"], results.iter().map(|&(name, time)| vec![name.to_string(), time.to_string()]))
+///     .list(vec!["2 benchmarks", "0 regressions"]);
+/// assert_eq!(report.to_string(), "# Benchmarks\n\n\
+///                                 Timings in \\*milliseconds\\*:\n\n\
+///                                 | name  | time |\n\
+///                                 | ----- | ---- |\n\
+///                                 | parse | 12.5 |\n\
+///                                 | print | 3.25 |\n\n\
+///                                 - 2 benchmarks\n\n\
+///                                 - 0 regressions");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    events: Vec<Event<'static>>
+}
+
+impl Report {
+    /// Start an empty report.
+    pub fn new() -> Report {
+        Report::default()
+    }
+
+    /// Add a heading of the given level, between 1 and 6.
+    pub fn heading<S: Into<String>>(mut self, level: i32, text: S) -> Report {
+        let level = level.clamp(1, 6);
+        self.events.push(Event::Start(Tag::Header(level)));
+        self.events.push(Event::Text(text.into().into()));
+        self.events.push(Event::End(Tag::Header(level)));
+        self
+    }
+
+    /// Add a paragraph of text.
+    pub fn paragraph<S: Into<String>>(mut self, text: S) -> Report {
+        self.events.push(Event::Start(Tag::Paragraph));
+        self.events.push(Event::Text(text.into().into()));
+        self.events.push(Event::End(Tag::Paragraph));
+        self
+    }
+
+    /// Add a bullet list with an item for each element.
+    pub fn list<I, T>(mut self, items: I) -> Report where I: IntoIterator<Item=T>, T: Display {
+        self.events.extend(list_from_iter(items, None));
+        self
+    }
+
+    /// Add an ordered list with an item for each element, numbered from
+    /// one.
+    pub fn ordered_list<I, T>(mut self, items: I) -> Report where I: IntoIterator<Item=T>, T: Display {
+        self.events.extend(list_from_iter(items, Some(1)));
+        self
+    }
+
+    /// Add a table with a header and a row for each element.
+    pub fn table<H, R, C>(mut self, headers: H, rows: R) -> Report
+        where H: IntoIterator, H::Item: Display, R: IntoIterator<Item=C>, C: IntoIterator, C::Item: Display {
+        self.events.extend(table_from_iter(headers, rows));
+        self
+    }
+
+    /// Add a fenced code block with the given language, which may be
+    /// empty.
+    pub fn code_block<S: Into<String>>(mut self, language: &str, code: S) -> Report {
+        let mut code = code.into();
+        if !code.ends_with('\n') {
+            code.push('\n');
+        }
+        self.events.push(Event::Start(Tag::CodeBlock(language.to_string().into())));
+        self.events.push(Event::Text(code.into()));
+        self.events.push(Event::End(Tag::CodeBlock(language.to_string().into())));
+        self
+    }
+
+    /// Add events, such as those of a parsed document or of `csv_table`.
+    pub fn events<I: IntoIterator<Item=Event<'static>>>(mut self, events: I) -> Report {
+        self.events.extend(events);
+        self
+    }
+
+    /// The events of the report.
+    pub fn into_events(self) -> Vec<Event<'static>> {
+        self.events
+    }
+
+    /// Pretty print the report using custom options.
+    pub fn render(&self, options: &Options) -> String {
+        let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+        printer.push_events(self.events.iter().cloned()).expect("failed to write to string");
+        printer.into_inner()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let mut printer = PrettyPrinter::new(fmt);
+        printer.push_events(self.events.iter().cloned())
+    }
+}
+
+/// Creates the events of a list with an item for each element, which is
+/// ordered and numbered from `start` if given.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{list_from_iter, PrettyPrinter};
+/// let mut printer = PrettyPrinter::default();
+/// printer.push_events(list_from_iter(1..4, Some(7))).unwrap();
+/// assert_eq!(printer.into_inner(), "7. 1\n\n8. 2\n\n9. 3");
+/// ```
+pub fn list_from_iter<I, T>(items: I, start: Option<usize>) -> Vec<Event<'static>>
+    where I: IntoIterator<Item=T>, T: Display {
+    let mut events = vec![Event::Start(Tag::List(start))];
+    for item in items {
+        events.push(Event::Start(Tag::Item));
+        events.push(Event::Text(item.to_string().into()));
+        events.push(Event::End(Tag::Item));
+    }
+    events.push(Event::End(Tag::List(start)));
+    events
+}
+
+/// Creates the events of a table from its header and rows, which are
+/// filled up with empty cells to the width of the widest row.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{table_from_iter, PrettyPrinter};
+/// let mut printer = PrettyPrinter::default();
+/// printer.push_events(table_from_iter(vec!["a", "b"], vec![vec![1], vec![2, 3]])).unwrap();
+/// assert_eq!(printer.into_inner(), "| a   | b   |\n| --- | --- |\n| 1   |     |\n| 2   | 3   |");
+/// ```
+pub fn table_from_iter<H, R, C>(headers: H, rows: R) -> Vec<Event<'static>>
+    where H: IntoIterator, H::Item: Display, R: IntoIterator<Item=C>, C: IntoIterator, C::Item: Display {
+    let mut records = vec![headers.into_iter().map(|cell| cell.to_string()).collect::<Vec<_>>()];
+    records.extend(rows.into_iter().map(|row| row.into_iter().map(|cell| cell.to_string()).collect::<Vec<_>>()));
+    let columns = records.iter().map(Vec::len).max().unwrap_or(0);
+    let alignments = vec![Alignment::None; columns];
+    let mut events = vec![Event::Start(Tag::Table(alignments.clone()))];
+    for (index, record) in records.into_iter().enumerate() {
+        let row = if index == 0 { Tag::TableHead } else { Tag::TableRow };
+        events.push(Event::Start(row.clone()));
+        let filled = record.len();
+        for cell in record.into_iter().chain((filled..columns).map(|_| String::new())) {
+            events.push(Event::Start(Tag::TableCell));
+            if !cell.is_empty() {
+                events.push(Event::Text(cell.into()));
+            }
+            events.push(Event::End(Tag::TableCell));
+        }
+        events.push(Event::End(row));
+    }
+    events.push(Event::End(Tag::Table(alignments)));
+    events
+}
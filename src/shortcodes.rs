@@ -0,0 +1,86 @@
+use minimal::top_blocks;
+use options::Options;
+use printer::{Replacement, ReplacementBlock};
+
+/// Find the blocks at the top level of a document that start with a
+/// shortcode of `Options::shortcodes`, and replace them by their source.
+///
+/// A block shortcode extends to its matching closing shortcode, if there
+/// is one, and may cover multiple blocks. Shortcodes that end in the
+/// middle of a block or a line are left to the inline recognizers.
+pub fn blocks(source: &str, options: &Options) -> Vec<Replacement> {
+    let mut replacements = Vec::new();
+    if options.shortcodes.is_empty() {
+        return replacements;
+    }
+    let blocks = top_blocks(source, options);
+    let mut index = 0;
+    while index < blocks.len() {
+        let start = blocks[index].range.start;
+        let end = match block_end(source, start, options) {
+            Some(end) => end,
+            None => {
+                index += 1;
+                continue;
+            }
+        };
+        let covered = blocks[index..].iter().take_while(|block| block.range.start < end).count();
+        let last = &blocks[index + covered - 1];
+        if last.range.end > end {
+            index += 1;
+            continue;
+        }
+        let text = source[start..end].to_string();
+        replacements.push(Replacement { range: blocks[index].events.start..last.events.end, block: ReplacementBlock::Raw(text) });
+        index += covered;
+    }
+    replacements
+}
+
+/// Find the end of the block shortcode starting at the given offset, if
+/// there is one.
+fn block_end(source: &str, start: usize, options: &Options) -> Option<usize> {
+    options.shortcodes.iter().find_map(|(open, close)| {
+        let (content, end) = shortcode(source, start, open, close)?;
+        let name = content.split_whitespace().next().unwrap_or_default();
+        // Closing and self-closing shortcodes do not have content.
+        if name.is_empty() || name.starts_with('/') || content.ends_with('/') {
+            return Some(end);
+        }
+        let mut offset = end;
+        for line in source[end..].split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
+            let indent = line.len() - line.trim_start().len();
+            let closing = match shortcode(source, line_start + indent, open, close) {
+                Some((closing, end)) if is_closing(closing, name) => end,
+                _ => continue
+            };
+            return Some(closing);
+        }
+        Some(end)
+    })
+}
+
+/// Parse a shortcode at the given offset that is followed by the end of
+/// its line, returning its content and its end.
+fn shortcode<'a>(source: &'a str, start: usize, open: &str, close: &str) -> Option<(&'a str, usize)> {
+    if open.is_empty() || close.is_empty() {
+        return None;
+    }
+    let text = source[start..].strip_prefix(open)?;
+    let inner = text.find(close)?;
+    let end = start + open.len() + inner + close.len();
+    let rest = source[end..].split('\n').next().unwrap_or_default();
+    if !rest.trim().is_empty() {
+        return None;
+    }
+    Some((text[..inner].trim(), end))
+}
+
+/// Check whether the content of a shortcode closes the shortcode of the
+/// given name, as in `{{< /name >}}` or `{% endname %}`.
+fn is_closing(closing: &str, name: &str) -> bool {
+    let closing = closing.split_whitespace().next().unwrap_or_default();
+    closing.strip_prefix('/').or_else(|| closing.strip_prefix("end")) == Some(name)
+}
@@ -0,0 +1,100 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+use options::Options;
+
+/// A file staged for commit, along with its staged contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedFile {
+    /// The path of the file, relative to the repository.
+    pub path: PathBuf,
+    /// The staged contents of the file.
+    pub content: String
+}
+
+/// Formatted contents that should replace the staged contents of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedReplacement {
+    /// The path of the file, relative to the repository.
+    pub path: PathBuf,
+    /// The formatted contents.
+    pub formatted: String
+}
+
+/// The result of formatting the files staged for a commit.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StagedSummary {
+    /// The number of Markdown files that were formatted.
+    pub checked: usize,
+    /// The number of files that were skipped because they are not
+    /// Markdown files.
+    pub skipped: usize,
+    /// Replacements for the files whose formatting changed.
+    pub replacements: Vec<StagedReplacement>
+}
+
+impl StagedSummary {
+    /// Whether all staged Markdown files are already formatted, in which
+    /// case a pre-commit hook should let the commit proceed.
+    pub fn is_clean(&self) -> bool {
+        self.replacements.is_empty()
+    }
+}
+
+impl Display for StagedSummary {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        if self.is_clean() {
+            return write!(fmt, "{} Markdown file(s) already formatted", self.checked);
+        }
+        write!(fmt, "{} of {} Markdown file(s) need formatting:", self.replacements.len(), self.checked)?;
+        for replacement in &self.replacements {
+            write!(fmt, "\n  {}", replacement.path.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the Markdown files among the files staged for a commit.
+///
+/// This is meant to be the core of a pre-commit hook: the caller lists
+/// the staged files and their staged contents (for example using
+/// `git diff --cached --name-only` and `git show :path`), and applies
+/// the returned replacements or rejects the commit based on the summary.
+/// Files are recognized as Markdown by their `.md` or `.markdown`
+/// extension; all other files are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::path::PathBuf;
+/// # use prettify_cmark::{format_staged, Options, StagedFile};
+/// let files = vec![
+///     StagedFile { path: PathBuf::from("README.md"), content: "Lorem __ipsum__".to_string() },
+///     StagedFile { path: PathBuf::from("src/lib.rs"), content: "fn main() {}".to_string() }
+/// ];
+/// let summary = format_staged(files, &Options::default());
+/// assert!(!summary.is_clean());
+/// assert_eq!(summary.replacements[0].formatted, "Lorem **ipsum**");
+/// assert_eq!(summary.to_string(), "1 of 1 Markdown file(s) need formatting:\n  README.md");
+/// ```
+pub fn format_staged<I: IntoIterator<Item=StagedFile>>(files: I, options: &Options) -> StagedSummary {
+    let mut summary = StagedSummary::default();
+    for file in files {
+        if !is_markdown(&file.path) {
+            summary.skipped += 1;
+            continue;
+        }
+        summary.checked += 1;
+        let formatted = ::prettify_with_options(&file.content, options);
+        if formatted != file.content {
+            summary.replacements.push(StagedReplacement { path: file.path, formatted });
+        }
+    }
+    summary
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("md") || extension.eq_ignore_ascii_case("markdown")
+    })
+}
@@ -0,0 +1,98 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+use plain::{to_plain_text, PlainTextLinks};
+use truncate::with_definitions;
+
+/// The summary of a document, as returned by `summary`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The summary, pretty printed as CommonMark.
+    pub markdown: String,
+    /// The summary as plain text, as rendered by `to_plain_text`.
+    pub text: String,
+    /// Whether the summary was cut at a `<!-- more -->` marker, rather
+    /// than being the first paragraph.
+    pub more: bool
+}
+
+/// Extracts the summary of a CommonMark document, for blog posts and
+/// other listings.
+///
+/// If the document contains a `<!-- more -->` comment, the summary is
+/// everything before it. Otherwise it is the first top-level paragraph.
+/// Link reference definitions from the rest of the document are kept, so
+/// that links in the summary still resolve. Returns `None` if the
+/// document has neither a marker nor a paragraph, or the summary is
+/// empty.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{summary, Options};
+/// let source = "# Lorem\n\n_Ipsum_ [dolor][1].\n\nSit amet.\n\n[1]: http://example.com";
+/// let first = summary(source, &Options::default()).unwrap();
+/// assert_eq!(first.markdown, "*Ipsum* [dolor](http://example.com).");
+/// assert_eq!(first.text, "Ipsum dolor.");
+///
+/// let more = summary("Lorem\n\nIpsum\n\n<!-- more -->\n\nDolor", &Options::default()).unwrap();
+/// assert_eq!(more.markdown, "Lorem\n\nIpsum");
+/// ```
+pub fn summary(source: &str, options: &Options) -> Option<Summary> {
+    let (kept, more) = match marker(source, options) {
+        Some(end) => (with_definitions(source[..end].trim_end(), &source[end..]), true),
+        None => {
+            let paragraph = first_paragraph(source, options)?;
+            let rest = format!("{}\n{}", &source[..paragraph.0], &source[paragraph.1..]);
+            (with_definitions(&source[paragraph.0..paragraph.1], &rest), false)
+        }
+    };
+    let markdown = ::prettify_with_options(&kept, options);
+    if markdown.is_empty() {
+        return None;
+    }
+    let text = to_plain_text(&markdown, PlainTextLinks::TextOnly, options);
+    Some(Summary { markdown, text, more })
+}
+
+/// Find the offset of the first `<!-- more -->` comment of a document.
+fn marker(source: &str, options: &Options) -> Option<usize> {
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    loop {
+        let offset = parser.get_offset();
+        match parser.next()? {
+            Event::Html(ref html) | Event::InlineHtml(ref html) if is_marker(html) => return Some(offset),
+            _ => {}
+        }
+    }
+}
+
+/// Whether an HTML comment is a `<!-- more -->` marker.
+fn is_marker(html: &str) -> bool {
+    let html = html.trim();
+    html.len() >= 7 && html.starts_with("<!--") && html.ends_with("-->") && html[4..html.len() - 3].trim().eq_ignore_ascii_case("more")
+}
+
+/// Find the source range of the first top-level paragraph of a document.
+fn first_paragraph(source: &str, options: &Options) -> Option<(usize, usize)> {
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    let mut depth = 0usize;
+    let mut start = None;
+    loop {
+        let offset = parser.get_offset();
+        match parser.next()? {
+            Event::Start(Tag::Paragraph) if depth == 0 => {
+                start = Some(offset);
+                depth += 1;
+            },
+            Event::Start(_) => depth += 1,
+            Event::End(_) => {
+                depth -= 1;
+                if let (0, Some(start)) = (depth, start) {
+                    return Some((start, parser.get_offset()));
+                }
+            },
+            _ => {}
+        }
+    }
+}
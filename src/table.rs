@@ -0,0 +1,130 @@
+use pulldown_cmark::Alignment;
+
+use options::{TableOptions, TableSeparator};
+
+/// Render a table from the printed contents of its cells.
+///
+/// The first row is the header. Rows with fewer cells than the widest
+/// row are filled up with empty cells.
+pub fn render(alignments: &[Alignment], rows: &[Vec<String>], options: &TableOptions) -> String {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(alignments.len());
+    let alignment = |column: usize| alignments.get(column).cloned().unwrap_or(Alignment::None);
+    let mut widths = (0..columns).map(|column| separator_width(alignment(column))).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut lines = Vec::with_capacity(rows.len() + 1);
+    for (index, row) in rows.iter().enumerate() {
+        let cells = (0..columns).map(|column| {
+            let cell = row.get(column).map_or("", String::as_str);
+            if options.padding {
+                pad(cell, widths[column], alignment(column))
+            } else {
+                cell.to_string()
+            }
+        }).collect::<Vec<_>>();
+        lines.push(format!("| {} |", cells.join(" | ")));
+        if index == 0 {
+            let separators = (0..columns).map(|column| {
+                let width = match options.separator {
+                    TableSeparator::Minimal => separator_width(alignment(column)),
+                    TableSeparator::Padded => widths[column]
+                };
+                separator(width, alignment(column))
+            }).collect::<Vec<_>>();
+            lines.push(format!("| {} |", separators.join(" | ")));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Render a table as an HTML block from the HTML contents of its cells.
+///
+/// Every cell is written on a line of its own, so that the block does not
+/// contain any blank lines that would end it early.
+pub fn render_html(alignments: &[Alignment], rows: &[Vec<String>]) -> String {
+    let mut lines = vec!["<table>".to_string()];
+    for (index, row) in rows.iter().enumerate() {
+        let element = if index == 0 { "th" } else { "td" };
+        if index == 0 {
+            lines.push("<thead>".to_string());
+        } else if index == 1 {
+            lines.push("<tbody>".to_string());
+        }
+        lines.push("<tr>".to_string());
+        for (column, cell) in row.iter().enumerate() {
+            let cell = cell.trim().replace('\n', " ");
+            lines.push(match alignments.get(column) {
+                Some(&Alignment::Left) => format!("<{0} align=\"left\">{1}</{0}>", element, cell),
+                Some(&Alignment::Center) => format!("<{0} align=\"center\">{1}</{0}>", element, cell),
+                Some(&Alignment::Right) => format!("<{0} align=\"right\">{1}</{0}>", element, cell),
+                _ => format!("<{0}>{1}</{0}>", element, cell)
+            });
+        }
+        lines.push("</tr>".to_string());
+        if index == 0 {
+            lines.push("</thead>".to_string());
+        }
+    }
+    if rows.len() > 1 {
+        lines.push("</tbody>".to_string());
+    }
+    lines.push("</table>".to_string());
+    lines.join("\n")
+}
+
+/// The width of the widest line of a rendered table, in characters.
+pub fn width(rendered: &str) -> usize {
+    rendered.lines().map(|line| line.chars().count()).max().unwrap_or(0)
+}
+
+/// Escape the pipes in the printed contents of a cell, so that they do not
+/// end the cell when the table is parsed again.
+///
+/// Table rows are split at every pipe that is not escaped by a backslash,
+/// even inside of code spans, so a pipe that is preceded by an even number
+/// of backslashes is escaped. Pipes that are already escaped are left
+/// alone, which also keeps escaped pipes in code spans as they are.
+pub fn escape_pipes(cell: &str) -> String {
+    let mut escaped = String::with_capacity(cell.len());
+    let mut backslashes = 0;
+    for c in cell.chars() {
+        if c == '|' && backslashes % 2 == 0 {
+            escaped.push('\\');
+        }
+        backslashes = if c == '\\' { backslashes + 1 } else { 0 };
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The width of the shortest separator for a column, with three dashes.
+fn separator_width(alignment: Alignment) -> usize {
+    match alignment {
+        Alignment::None => 3,
+        Alignment::Left | Alignment::Right => 4,
+        Alignment::Center => 5
+    }
+}
+
+fn separator(width: usize, alignment: Alignment) -> String {
+    match alignment {
+        Alignment::None => "-".repeat(width),
+        Alignment::Left => format!(":{}", "-".repeat(width - 1)),
+        Alignment::Right => format!("{}:", "-".repeat(width - 1)),
+        Alignment::Center => format!(":{}:", "-".repeat(width - 2))
+    }
+}
+
+fn pad(cell: &str, width: usize, alignment: Alignment) -> String {
+    let padding = width.saturating_sub(cell.chars().count());
+    let (before, after) = match alignment {
+        Alignment::None | Alignment::Left => (0, padding),
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2)
+    };
+    format!("{}{}{}", " ".repeat(before), cell, " ".repeat(after))
+}
@@ -0,0 +1,285 @@
+use std::cmp::Ordering;
+
+use pulldown_cmark::{Alignment, Event, Parser, Tag};
+
+use buffer::{into_static, EventBuffer};
+use options::Options;
+use printer::{PrettyPrinter, Replacement, ReplacementBlock};
+
+/// A cell of a `Table`, holding the events of its inline content.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cell {
+    events: Vec<Event<'static>>
+}
+
+impl Cell {
+    /// Create a cell containing plain text, which the printer escapes as
+    /// needed.
+    pub fn new<S: Into<String>>(text: S) -> Cell {
+        let text = text.into();
+        if text.is_empty() {
+            return Cell::default();
+        }
+        Cell { events: vec![Event::Text(text.into())] }
+    }
+
+    /// Create a cell from the events of its inline content.
+    pub fn from_events<'a, I: IntoIterator<Item=Event<'a>>>(events: I) -> Cell {
+        Cell { events: events.into_iter().map(into_static).collect() }
+    }
+
+    /// The events of the content of the cell.
+    pub fn events(&self) -> &[Event<'static>] {
+        &self.events
+    }
+
+    /// The text of the cell with all markup removed, without surrounding
+    /// whitespace.
+    pub fn text(&self) -> String {
+        let text = self.events.iter().filter_map(|event| match *event {
+            Event::Text(ref text) => Some(text.as_ref()),
+            Event::SoftBreak | Event::HardBreak => Some(" "),
+            _ => None
+        }).collect::<String>();
+        text.trim().to_string()
+    }
+}
+
+/// A table of a document, for sorting its rows and adding or removing
+/// columns programmatically, as used by `edit_tables`.
+///
+/// Rows may have fewer cells than the table has columns, in which case
+/// the remaining cells are empty.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate pulldown_cmark;
+/// # extern crate prettify_cmark;
+/// use pulldown_cmark::Alignment;
+/// use prettify_cmark::{edit_tables, Cell, Options, TableOptions};
+///
+/// # fn main() {
+/// let source = "| crate | version | license |\n| --- | --- | --- |\n\
+///               | serde | 1.0.1 | MIT |\n| csv | 1.3.0 | MIT |";
+/// let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+/// let output = edit_tables(source, &options, |table| {
+///     if let Some(license) = table.column("license") {
+///         table.remove_column(license);
+///     }
+///     table.insert_column(1, Cell::new("used"), Alignment::Right, vec![Cell::new("yes"), Cell::new("no")]);
+///     table.sort_by_column(0);
+/// });
+/// assert_eq!(output, "| crate | used | version |\n\
+///                     | ----- | ---: | ------- |\n\
+///                     | csv   |   no | 1.3.0   |\n\
+///                     | serde |  yes | 1.0.1   |");
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// The alignment of each column.
+    pub alignments: Vec<Alignment>,
+    /// The cells of the header row.
+    pub header: Vec<Cell>,
+    /// The cells of each row of the body.
+    pub rows: Vec<Vec<Cell>>
+}
+
+impl Table {
+    /// The index of the first column whose header has the given text
+    /// (as returned by `Cell::text`).
+    pub fn column(&self, header: &str) -> Option<usize> {
+        self.header.iter().position(|cell| cell.text() == header)
+    }
+
+    /// Sort the rows by the text of the cells in a column.
+    ///
+    /// If both cells contain finite numbers, they are compared
+    /// numerically, and otherwise alphabetically, ignoring case. The sort is stable, so
+    /// that rows can be sorted by multiple columns one after the other.
+    pub fn sort_by_column(&mut self, column: usize) {
+        let mut keyed = self.rows.drain(..)
+            .map(|row| (SortKey::new(row.get(column).map(Cell::text).unwrap_or_default()), row))
+            .collect::<Vec<_>>();
+        keyed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        self.rows = keyed.into_iter().map(|(_, row)| row).collect();
+    }
+
+    /// Sort the rows using a comparison function.
+    pub fn sort_rows_by<F: FnMut(&[Cell], &[Cell]) -> Ordering>(&mut self, mut compare: F) {
+        self.rows.sort_by(|a, b| compare(a, b));
+    }
+
+    /// Insert a column at the given index, with the cells of the column
+    /// for each row.
+    ///
+    /// An index past the last column appends the column. Rows that are
+    /// not given a cell get an empty one, and rows with fewer cells than
+    /// the index are filled up with empty cells.
+    pub fn insert_column<I>(&mut self, index: usize, header: Cell, alignment: Alignment, cells: I)
+        where I: IntoIterator<Item=Cell>
+    {
+        let index = index.min(self.columns());
+        self.alignments.resize(self.alignments.len().max(index), Alignment::None);
+        self.alignments.insert(index, alignment);
+        insert_cell(&mut self.header, index, header);
+        let mut cells = cells.into_iter();
+        for row in &mut self.rows {
+            insert_cell(row, index, cells.next().unwrap_or_default());
+        }
+    }
+
+    /// Remove the column at the given index, if there is one.
+    pub fn remove_column(&mut self, index: usize) {
+        let rows = Some(&mut self.header).into_iter().chain(&mut self.rows);
+        for row in rows {
+            if index < row.len() {
+                row.remove(index);
+            }
+        }
+        if index < self.alignments.len() {
+            self.alignments.remove(index);
+        }
+    }
+
+    /// The number of columns of the table.
+    pub fn columns(&self) -> usize {
+        self.rows.iter().map(Vec::len).chain(Some(self.header.len())).max().unwrap_or(0).max(self.alignments.len())
+    }
+
+    /// The events of the table.
+    pub fn to_events(&self) -> Vec<Event<'static>> {
+        let alignments = self.alignments.clone();
+        let mut events = vec![Event::Start(Tag::Table(alignments.clone()))];
+        let rows = Some((Tag::TableHead, &self.header)).into_iter()
+            .chain(self.rows.iter().map(|row| (Tag::TableRow, row)));
+        for (tag, row) in rows {
+            events.push(Event::Start(tag.clone()));
+            for cell in row {
+                events.push(Event::Start(Tag::TableCell));
+                events.extend(cell.events.iter().cloned());
+                events.push(Event::End(Tag::TableCell));
+            }
+            events.push(Event::End(tag));
+        }
+        events.push(Event::End(Tag::Table(alignments)));
+        events
+    }
+
+    /// Parse a table from its events, returning it along with the number
+    /// of events up to its end.
+    fn parse(events: &[Event]) -> Option<(Table, usize)> {
+        let alignments = match events.first() {
+            Some(&Event::Start(Tag::Table(ref alignments))) => alignments.clone(),
+            _ => return None
+        };
+        let mut table = Table { alignments, header: Vec::new(), rows: Vec::new() };
+        let mut cell: Option<Vec<Event<'static>>> = None;
+        for (index, event) in events.iter().enumerate().skip(1) {
+            match *event {
+                Event::Start(Tag::TableRow) => table.rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => cell = Some(Vec::new()),
+                Event::End(Tag::TableCell) => {
+                    let row = table.rows.last_mut().unwrap_or(&mut table.header);
+                    row.push(Cell { events: trim(cell.take()?) });
+                },
+                Event::End(Tag::Table(_)) => return Some((table, index + 1)),
+                ref event => {
+                    if let Some(ref mut cell) = cell {
+                        cell.push(into_static(event.clone()));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl EventBuffer {
+    /// Edit each table among the recorded events, replacing its events
+    /// by those of the edited table.
+    pub fn edit_tables<F: FnMut(&mut Table)>(&mut self, mut edit: F) {
+        let mut events = Vec::with_capacity(self.len());
+        let mut index = 0;
+        let recorded = self.events();
+        while index < recorded.len() {
+            match Table::parse(&recorded[index..]) {
+                Some((mut table, length)) => {
+                    edit(&mut table);
+                    events.extend(table.to_events());
+                    index += length;
+                },
+                None => {
+                    events.push(recorded[index].clone());
+                    index += 1;
+                }
+            }
+        }
+        *self.events_mut() = events;
+    }
+}
+
+/// Parses a CommonMark document, edits each of its tables, and returns
+/// the pretty printed document.
+///
+/// Tables are only recognized if `Options::tables` is set. See `Table`
+/// for an example.
+pub fn edit_tables<F: FnMut(&mut Table)>(source: &str, options: &Options, mut edit: F) -> String {
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.push_source_replacing(source, |source| {
+        let events = Parser::new_ext(source, options.parser_options()).collect::<Vec<_>>();
+        let mut replacements = Vec::new();
+        let mut index = 0;
+        while index < events.len() {
+            match Table::parse(&events[index..]) {
+                Some((mut table, length)) => {
+                    edit(&mut table);
+                    replacements.push(Replacement { range: index..index + length, block: ReplacementBlock::Events(table.to_events()) });
+                    index += length;
+                },
+                None => index += 1
+            }
+        }
+        replacements
+    }).expect("failed to write to string");
+    printer.into_inner()
+}
+
+/// Key for sorting cells numerically or alphabetically.
+#[derive(PartialEq, PartialOrd)]
+enum SortKey {
+    Number(f64),
+    Text(String)
+}
+
+impl SortKey {
+    fn new(text: String) -> SortKey {
+        // Only finite numbers are compared numerically, as `NaN` cannot be
+        // ordered.
+        match text.parse::<f64>() {
+            Ok(number) if number.is_finite() => SortKey::Number(number),
+            _ => SortKey::Text(text.to_lowercase())
+        }
+    }
+}
+
+/// Insert a cell into a row, filling the row up to the index first.
+fn insert_cell(row: &mut Vec<Cell>, index: usize, cell: Cell) {
+    if row.len() < index {
+        row.resize(index, Cell::default());
+    }
+    row.insert(index, cell);
+}
+
+/// Remove the whitespace that pads the content of a cell.
+fn trim(mut events: Vec<Event<'static>>) -> Vec<Event<'static>> {
+    if let Some(&mut Event::Text(ref mut text)) = events.first_mut() {
+        *text = text.trim_start().to_string().into();
+    }
+    if let Some(&mut Event::Text(ref mut text)) = events.last_mut() {
+        *text = text.trim_end().to_string().into();
+    }
+    events.retain(|event| !matches!(*event, Event::Text(ref text) if text.is_empty()));
+    events
+}
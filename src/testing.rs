@@ -0,0 +1,201 @@
+//! Helpers for testing the output of the pretty printer.
+//!
+//! This module is only available with the `testing` feature enabled.
+//! It is meant for crates that embed the pretty printer, to write
+//! regression tests against their own documents.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use pulldown_cmark::{html, Parser, OPTION_ENABLE_FOOTNOTES};
+
+use options::Options;
+
+/// Asserts that a CommonMark document is pretty printed as expected.
+///
+/// Options can be passed as an optional third argument.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[macro_use] extern crate prettify_cmark;
+/// # use prettify_cmark::{EntityPolicy, Options};
+/// # fn main() {
+/// assert_prettified!("Lorem __ipsum__", "Lorem **ipsum**");
+///
+/// let options = Options { entities: EntityPolicy::Preserve, ..Options::default() };
+/// assert_prettified!("Lorem&nbsp;ipsum", "Lorem&nbsp;ipsum", &options);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_prettified {
+    ($input:expr, $expected:expr) => {
+        assert_eq!($crate::prettify_with_options($input, &$crate::Options::default()), $expected,
+                   "unexpected pretty printed output")
+    };
+    ($input:expr, $expected:expr, $options:expr) => {
+        assert_eq!($crate::prettify_with_options($input, $options), $expected,
+                   "unexpected pretty printed output")
+    };
+}
+
+/// Environment variable that, when set, makes corpus runs write the
+/// expected output files instead of comparing against them.
+pub const BLESS_VAR: &str = "PRETTIFY_CMARK_BLESS";
+
+/// A document in a corpus whose output did not match expectations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorpusFailure {
+    /// The path of the input document.
+    pub path: PathBuf,
+    /// The expected output.
+    pub expected: String,
+    /// The actual output.
+    pub actual: String
+}
+
+impl fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}:", self.path.display())?;
+        writeln!(f, "--- expected")?;
+        writeln!(f, "{}", self.expected)?;
+        writeln!(f, "--- actual")?;
+        write!(f, "{}", self.actual)
+    }
+}
+
+/// Pretty prints every `.md` file in a directory, and compares the
+/// output against the corresponding `.expected.md` file.
+///
+/// If there is no expected output for a document, the output is checked
+/// to be stable instead, that is, pretty printing it again must not
+/// change it. If the `PRETTIFY_CMARK_BLESS` environment variable is set,
+/// the expected output files are written instead.
+pub fn run_corpus<P: AsRef<Path>>(dir: P, options: &Options) -> io::Result<Vec<CorpusFailure>> {
+    let bless = env::var_os(BLESS_VAR).is_some();
+    let mut inputs = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    inputs.retain(|path| {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or("");
+        name.ends_with(".md") && !name.ends_with(".expected.md")
+    });
+    inputs.sort();
+
+    let mut failures = Vec::new();
+    for path in inputs {
+        let source = fs::read_to_string(&path)?;
+        let actual = ::prettify_with_options(&source, options);
+        let expected_path = path.with_extension("expected.md");
+        if bless {
+            fs::write(&expected_path, &actual)?;
+            continue;
+        }
+        let expected = match fs::read_to_string(&expected_path) {
+            Ok(expected) => expected,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {
+                ::prettify_with_options(&actual, options)
+            },
+            Err(err) => return Err(err)
+        };
+        if actual != expected {
+            failures.push(CorpusFailure { path, expected, actual });
+        }
+    }
+    Ok(failures)
+}
+
+/// Runs `run_corpus` and panics if any document does not match its
+/// expected output.
+pub fn assert_corpus<P: AsRef<Path>>(dir: P, options: &Options) {
+    let failures = run_corpus(dir, options).expect("failed to read corpus");
+    if !failures.is_empty() {
+        let report = failures.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n\n");
+        panic!("{} document(s) did not match:\n\n{}", failures.len(), report);
+    }
+}
+
+/// A document in a corpus whose meaning was changed by pretty printing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoundTripFailure {
+    /// The path of the input document.
+    pub path: PathBuf,
+    /// The normalized HTML rendering of the input document.
+    pub expected: String,
+    /// The normalized HTML rendering of the pretty printed document.
+    pub actual: String
+}
+
+impl RoundTripFailure {
+    /// The byte offset at which the two renderings start to differ.
+    pub fn offset(&self) -> usize {
+        self.expected.bytes().zip(self.actual.bytes())
+            .position(|(a, b)| a != b)
+            .unwrap_or_else(|| self.expected.len().min(self.actual.len()))
+    }
+}
+
+impl fmt::Display for RoundTripFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let offset = self.offset();
+        let start = floor_char_boundary(&self.expected, offset.saturating_sub(20));
+        let excerpt = |html: &str| {
+            let end = floor_char_boundary(html, offset + 40);
+            html[start.min(end)..end].to_string()
+        };
+        write!(f, "{}\t{}\t{:?}\t{:?}", self.path.display(), offset,
+               excerpt(&self.expected), excerpt(&self.actual))
+    }
+}
+
+/// Pretty prints every `.md` file in a directory, and checks that the
+/// output renders to the same HTML as the input.
+///
+/// Differences in whitespace between and within HTML elements are not
+/// considered significant.
+pub fn run_round_trip<P: AsRef<Path>>(dir: P, options: &Options) -> io::Result<Vec<RoundTripFailure>> {
+    let mut inputs = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    inputs.retain(|path| path.extension().is_some_and(|ext| ext == "md"));
+    inputs.sort();
+
+    let mut failures = Vec::new();
+    for path in inputs {
+        let source = fs::read_to_string(&path)?;
+        let output = ::prettify_with_options(&source, options);
+        let expected = render_html(&source);
+        let actual = render_html(&output);
+        if actual != expected {
+            failures.push(RoundTripFailure { path, expected, actual });
+        }
+    }
+    Ok(failures)
+}
+
+/// Formats round trip failures as a report with one tab separated line
+/// per document, listing its path, the offset of the first difference and
+/// quoted excerpts of both renderings around it.
+pub fn round_trip_report(failures: &[RoundTripFailure]) -> String {
+    failures.iter().map(|failure| format!("{}\n", failure)).collect()
+}
+
+/// Render a document to HTML with all whitespace runs collapsed, and
+/// whitespace around tags removed.
+fn render_html(source: &str) -> String {
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(source, OPTION_ENABLE_FOOTNOTES));
+    let collapsed = rendered.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapsed.replace("> ", ">").replace(" <", "<")
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
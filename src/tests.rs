@@ -1,4 +1,20 @@
-use super::prettify;
+use std::cell::RefCell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use pulldown_cmark::{Alignment, Event, Parser, Tag, OPTION_ENABLE_FOOTNOTES, OPTION_ENABLE_TABLES};
+
+use super::{add_changelog_entries, adjust_headings, edit_badges, edit_tables, find_badges, PrettyDisplay, concat_documents, expand_includes, extract_section, outline, prettify, prettify_comment, table_from_iter, prettify_with_options, release_changelog, split_by_heading, validate_events, PrettyPrinter};
+use super::{SectionProfile, Badge, Block, Cell, Report, Budget, CancellationToken, Message, Checkpoint, CodeBlockFormatter, EventBuffer, OwnedEvent, IncludeError, IncludeLoader, NoopFormatter, TextRun};
+use super::{AdmonitionOptions, AdmonitionStyle, AltTextPolicy, AutolinkStyle, BlockQuoteStyle, CalloutCase, EmptyConstructPolicy, FrontMatterPolicy, HeadingBreakStyle, HeadingLimit, HeadingRange, DocumentSeparator, EntityPolicy, EscapeMethod, EscapeStyle, FootnoteOptions, FootnotePlacement, FootnoteStyle, HeadingCase};
+use super::{HtmlBreakPolicy, HtmlPolicy, InfoStringOptions, NestingError, Normalization, Options, VerbatimBlocks};
+use super::writer::{Frame, Writer};
+use super::{ReferenceLabels, ReferenceOptions, ReferencePlacement, RuleMarker, RuleStyle, Severity, TableOptions, TableOverflow, TableSeparator};
+use super::{TitleOptions, TitleQuote};
 
 #[test]
 fn simple_paragraph() {
@@ -161,3 +177,2143 @@ fn code_block_within_list_item() {
     let output = prettify("1. ```rust\n   extern crate prettify_cmark;\n   ```");
     assert_eq!(output, "1. ```rust\n   extern crate prettify_cmark;\n   ```");
 }
+
+#[test]
+fn entities_are_decoded_by_default() {
+    let output = prettify("Lorem&nbsp;ipsum &copy; dolor");
+    assert_eq!(output, "Lorem\u{a0}ipsum \u{a9} dolor");
+}
+
+#[test]
+fn entities_are_encoded() {
+    let options = Options { entities: EntityPolicy::Encode, ..Options::default() };
+    let output = prettify_with_options("Caf\u{e9} &amp;amp; \u{1f600} `&amp;`", &options);
+    assert_eq!(output, "Caf&eacute; &amp;amp; &#128512; `&amp;`");
+}
+
+#[test]
+fn entities_are_preserved() {
+    let options = Options { entities: EntityPolicy::Preserve, ..Options::default() };
+    let output = prettify_with_options("Lorem&nbsp;ipsum &#42; dolor &#x2014; sit \u{e9}", &options);
+    assert_eq!(output, "Lorem&nbsp;ipsum &#42; dolor &#x2014; sit \u{e9}");
+}
+
+#[test]
+fn text_is_normalized_to_nfc() {
+    let options = Options { normalization: Some(Normalization::Nfc), ..Options::default() };
+    let output = prettify_with_options("Cafe\u{301} `e\u{301}`", &options);
+    assert_eq!(output, "Caf\u{e9} `e\u{301}`");
+}
+
+#[test]
+fn text_is_normalized_to_nfkc() {
+    let options = Options { normalization: Some(Normalization::Nfkc), ..Options::default() };
+    let output = prettify_with_options("\u{fb01}ne", &options);
+    assert_eq!(output, "fine");
+}
+
+#[test]
+fn rule_with_custom_style() {
+    let rule = RuleStyle { marker: RuleMarker::Asterisk, length: 5, spaced: false };
+    let options = Options { rule, ..Options::default() };
+    let output = prettify_with_options("Lorem ipsum\n___\nDolor sit", &options);
+    assert_eq!(output, "Lorem ipsum\n\n*****\n\nDolor sit");
+}
+
+#[test]
+fn rule_with_spaced_style_and_minimum_length() {
+    let rule = RuleStyle { marker: RuleMarker::Underscore, length: 1, spaced: true };
+    let options = Options { rule, ..Options::default() };
+    let output = prettify_with_options("Lorem ipsum\n***\nDolor sit", &options);
+    assert_eq!(output, "Lorem ipsum\n\n_ _ _\n\nDolor sit");
+}
+
+#[test]
+fn headings_in_sentence_case() {
+    let options = Options { heading_case: HeadingCase::Sentence, ..Options::default() };
+    let output = prettify_with_options("# using the HTTP *Client* `API`\n\nLorem Ipsum", &options);
+    assert_eq!(output, "# Using the HTTP *client* `API`\n\nLorem Ipsum");
+}
+
+#[test]
+fn headings_in_title_case() {
+    let options = Options { heading_case: HeadingCase::Title, ..Options::default() };
+    let output = prettify_with_options("## the state of *the art* in rust", &options);
+    assert_eq!(output, "## The State of *the Art* in Rust");
+}
+
+#[test]
+fn headings_with_custom_case() {
+    fn shout(text: &str) -> String {
+        text.replace("ss", "\u{df}").to_uppercase()
+    }
+    let options = Options { heading_case: HeadingCase::Custom(shout), ..Options::default() };
+    let output = prettify_with_options("# kiss *me*\n\nkiss", &options);
+    assert_eq!(output, "# KISS *ME*\n\nkiss");
+}
+
+#[test]
+fn text_inspector_receives_runs_with_context() {
+    let runs = Rc::new(RefCell::new(Vec::new()));
+    let collected = runs.clone();
+    let mut printer = PrettyPrinter::default();
+    printer.set_text_inspector(move |run: &TextRun| {
+        collected.borrow_mut().push((run.text.to_string(), run.offset, run.blocks.to_vec()));
+    });
+    printer.push_source("# Foo\n\n> Lorem &amp;\n> ipsum `sit`\n\n```\ncode\n```").unwrap();
+    assert_eq!(*runs.borrow(), vec![
+        ("Foo".to_string(), Some(2), vec![Block::Heading(1)]),
+        ("Lorem &\nipsum ".to_string(), Some(9), vec![Block::BlockQuote, Block::Paragraph])
+    ]);
+}
+
+struct Reindent;
+
+impl CodeBlockFormatter for Reindent {
+    fn format(&self, lang: &str, code: &str) -> Option<String> {
+        match lang {
+            "rust" => Some(code.lines().map(|line| line.trim()).collect::<Vec<_>>().join("\n")),
+            _ => None
+        }
+    }
+}
+
+#[test]
+fn code_blocks_with_formatter() {
+    let mut printer = PrettyPrinter::default();
+    printer.set_code_block_formatter(Reindent);
+    printer.push_source("> ```rust,ignore\n>   let x = 1;\n> ```\n\n```text\n  foo\n```").unwrap();
+    assert_eq!(printer.into_inner(), "> ```rust,ignore\n> let x = 1;\n> ```\n\n```text\n  foo\n```");
+}
+
+#[test]
+fn code_blocks_with_noop_formatter() {
+    let mut printer = PrettyPrinter::default();
+    printer.set_code_block_formatter(NoopFormatter);
+    printer.push_source("```rust\n  let x = 1;\n\n```").unwrap();
+    assert_eq!(printer.into_inner(), "```rust\n  let x = 1;\n\n```");
+}
+
+#[test]
+fn info_string_is_preserved_by_default() {
+    let output = prettify("```rs,no_run,ignore,no_run\nfoo\n```");
+    assert_eq!(output, "```rs,no_run,ignore,no_run\nfoo\n```");
+}
+
+#[test]
+fn info_string_attributes_are_normalized() {
+    let info_string = InfoStringOptions {
+        sort_attributes: true,
+        dedupe_attributes: true,
+        aliases: vec![("rs".to_string(), "rust".to_string())],
+        ..InfoStringOptions::default()
+    };
+    let options = Options { info_string, ..Options::default() };
+    let output = prettify_with_options("```rs,no_run,ignore,no_run\nfoo\n```\n\n```sh  b a\nbar\n```", &options);
+    assert_eq!(output, "```rust,ignore,no_run\nfoo\n```\n\n```sh a b\nbar\n```");
+}
+
+#[test]
+fn doctests_are_preserved_in_rustdoc_mode() {
+    let info_string = InfoStringOptions {
+        sort_attributes: true,
+        aliases: vec![("text".to_string(), "txt".to_string())],
+        ..InfoStringOptions::default()
+    };
+    let options = Options { rustdoc: true, info_string, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "///", options);
+    printer.set_code_block_formatter(Reindent);
+    printer.push_source("```should_panic,edition2021\n# fn main() {\n  panic!();\n\n# }\n```\n\n```text\n  foo\n```").unwrap();
+    assert_eq!(printer.into_inner(), "/// ```should_panic,edition2021\n/// # fn main() {\n///   panic!();\n///\n/// # }\n/// ```\n///\n/// ```txt\n///   foo\n/// ```");
+}
+
+#[test]
+fn wiki_links_are_preserved() {
+    let options = Options { wiki_links: true, entities: EntityPolicy::Encode, ..Options::default() };
+    let output = prettify_with_options("See [[Caf\u{e9}|alias]] and ![[img.png]] or [[]] caf\u{e9}", &options);
+    assert_eq!(output, "See [[Caf\u{e9}|alias]] and ![[img.png]] or [[]] caf&eacute;");
+}
+
+#[test]
+fn callout_marker_is_kept_on_its_own_line() {
+    let output = prettify("> [!NOTE]\n> Lorem ipsum\n> dolor sit\n\n> [!warning]\n\n> [!NOTE]Lorem\n> ipsum");
+    assert_eq!(output, "> [!NOTE]\n> Lorem ipsum dolor sit\n\n> [!warning]\n\n> [!NOTE]Lorem ipsum");
+}
+
+#[test]
+fn titled_callout_marker_is_kept_on_its_own_line() {
+    let options = Options { callout_case: CalloutCase::Upper, ..Options::default() };
+    let output = prettify_with_options("> [!note] Lorem \\*ipsum\\*\n> dolor\n> sit", &options);
+    assert_eq!(output, "> [!NOTE] Lorem \\*ipsum\\*\n> dolor sit");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[test]
+fn callout_marker_case_is_normalized() {
+    let options = Options { callout_case: CalloutCase::Upper, ..Options::default() };
+    let output = prettify_with_options("- > [!Tip]\n  > Lorem ipsum", &options);
+    assert_eq!(output, "- > [!TIP]\n  > Lorem ipsum");
+}
+
+#[test]
+fn heading_attributes_are_preserved() {
+    let info_string = InfoStringOptions { sort_attributes: true, ..InfoStringOptions::default() };
+    let options = Options {
+        attributes: true,
+        heading_case: HeadingCase::Title,
+        info_string,
+        ..Options::default()
+    };
+    let output = prettify_with_options("# the *first* heading {#first .big}\n\n```rust z a {.numberLines startFrom=5}\nfoo\n```", &options);
+    assert_eq!(output, "# The *First* Heading {#first .big}\n\n```rust a z {.numberLines startFrom=5}\nfoo\n```");
+}
+
+#[test]
+fn document_break_closes_open_blocks() {
+    let mut printer = PrettyPrinter::default();
+    printer.push_source("- > Lorem").unwrap();
+    printer.push_document_break().unwrap();
+    printer.push_source("Ipsum").unwrap();
+    assert_eq!(printer.into_inner(), "- > Lorem\n\n---\n\nIpsum");
+
+    let mut printer = PrettyPrinter::default();
+    let events = Parser::new("> Lorem [ipsum](http://example.com) dolor").take(5).collect::<Vec<_>>();
+    assert_eq!(events.last(), Some(&Event::Text("ipsum".into())));
+    printer.push_events(events).unwrap();
+    printer.push_document_break().unwrap();
+    printer.push_source("Dolor").unwrap();
+    assert_eq!(printer.into_inner(), "> Lorem [ipsum](http://example.com)\n\n---\n\nDolor");
+}
+
+#[test]
+fn concat_documents_with_rules() {
+    let options = Options { rule: RuleStyle { length: 5, ..RuleStyle::default() }, ..Options::default() };
+    let output = concat_documents(vec!["# Foo", "- Bar", "Baz"], DocumentSeparator::Rule, &options);
+    assert_eq!(output, "# Foo\n\n-----\n\n- Bar\n\n-----\n\nBaz");
+}
+
+#[test]
+fn concat_documents_with_headings() {
+    let documents = vec![("Foo", "Lorem"), ("Bar", "> Ipsum")];
+    let output = concat_documents(documents, DocumentSeparator::Heading(3), &Options::default());
+    assert_eq!(output, "### Foo\n\nLorem\n\n### Bar\n\n> Ipsum");
+}
+
+#[test]
+fn template_placeholders_are_preserved() {
+    let placeholders = vec![
+        ("{{".to_string(), "}}".to_string()),
+        ("{%".to_string(), "%}".to_string()),
+        ("${".to_string(), "}".to_string())
+    ];
+    let options = Options { placeholders, entities: EntityPolicy::Encode, ..Options::default() };
+    let output = prettify_with_options("Hi {{ user.name | \"<b>\" }}, {% if x > 1 %}${caf\u{e9}} > caf\u{e9}{% endif %}", &options);
+    assert_eq!(output, "Hi {{ user.name | \"<b>\" }}, {% if x > 1 %}${caf\u{e9}} &gt; caf&eacute;{% endif %}");
+}
+
+#[test]
+fn html_blocks_are_preserved() {
+    let output = prettify("Foo\n\n<!-- comment -->\n\n<div>\n<b>x</b>\n</div>\n\n- > <!-- c -->\n  > x");
+    assert_eq!(output, "Foo\n\n<!-- comment -->\n\n<div>\n<b>x</b>\n</div>\n\n- > <!-- c -->\n  > x");
+}
+
+struct Files(Vec<(&'static str, &'static str)>);
+
+impl IncludeLoader for Files {
+    fn load(&self, path: &Path) -> io::Result<String> {
+        self.0.iter()
+            .find(|&&(name, _)| Path::new(name) == path)
+            .map(|&(_, content)| content.to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "not found"))
+    }
+}
+
+#[test]
+fn include_directives_are_expanded() {
+    let files = Files(vec![
+        ("docs/intro.md", "Lorem *ipsum*\n\n<!-- include: parts/more.md -->\n"),
+        ("docs/parts/more.md", "Dolor\nsit")
+    ]);
+    let source = "# Foo\n\n<!-- include: intro.md -->\n\n> {{#include parts/more.md}}\n";
+    let expanded = expand_includes(source, Path::new("docs/index.md"), &files).unwrap();
+    assert_eq!(expanded, "# Foo\n\nLorem *ipsum*\n\nDolor\nsit\n\n> Dolor\n> sit\n");
+    assert_eq!(prettify(&expanded), "# Foo\n\nLorem *ipsum*\n\nDolor sit\n\n> Dolor sit");
+}
+
+#[test]
+fn include_directives_are_preserved() {
+    let output = prettify("# Foo\n\n<!-- include: intro.md -->\n\n{{#include intro.md}}");
+    assert_eq!(output, "# Foo\n\n<!-- include: intro.md -->\n\n{{#include intro.md}}");
+}
+
+#[test]
+fn include_cycles_are_detected() {
+    let files = Files(vec![
+        ("a.md", "<!-- include: b.md -->"),
+        ("b.md", "<!-- include: a.md -->")
+    ]);
+    match expand_includes("<!-- include: a.md -->", Path::new("index.md"), &files) {
+        Err(IncludeError::Cycle(chain)) => {
+            let expected = vec!["index.md", "a.md", "b.md", "a.md"];
+            assert_eq!(chain, expected.into_iter().map(PathBuf::from).collect::<Vec<_>>());
+        },
+        other => panic!("unexpected result: {:?}", other)
+    }
+
+    let files = Files(vec![("docs/a.md", "<!-- include: ../docs/a.md -->")]);
+    match expand_includes("<!-- include: docs/./a.md -->", Path::new("index.md"), &files) {
+        Err(IncludeError::Cycle(chain)) => {
+            let expected = vec!["index.md", "docs/a.md", "docs/a.md"];
+            assert_eq!(chain, expected.into_iter().map(PathBuf::from).collect::<Vec<_>>());
+        },
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn include_depth_is_limited() {
+    struct Nested;
+
+    impl IncludeLoader for Nested {
+        fn load(&self, _: &Path) -> io::Result<String> {
+            Ok("<!-- include: next/a.md -->".to_string())
+        }
+    }
+
+    match expand_includes("<!-- include: a.md -->", Path::new("index.md"), &Nested) {
+        Err(IncludeError::TooDeep(chain)) => assert_eq!(chain.len(), 34),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn include_directives_in_code_blocks_are_preserved() {
+    let files = Files(vec![("intro.md", "Lorem")]);
+    let source = "````md\n<!-- include: intro.md -->\n```\n````\n\n> ~~~\n> {{#include intro.md}}\n> ~~~\n<!-- include: intro.md -->\n";
+    let expanded = expand_includes(source, Path::new("index.md"), &files).unwrap();
+    assert_eq!(expanded, "````md\n<!-- include: intro.md -->\n```\n````\n\n> ~~~\n> {{#include intro.md}}\n> ~~~\nLorem\n");
+}
+
+#[test]
+fn event_buffer_records_and_replays() {
+    let buffer = {
+        let source = String::from("# Foo\n\n- Lorem &amp; `ipsum`");
+        EventBuffer::from_source(&source)
+    };
+    assert_eq!(buffer.len(), 13);
+    for _ in 0..2 {
+        let mut printer = PrettyPrinter::default();
+        buffer.replay(&mut printer).unwrap();
+        assert_eq!(printer.into_inner(), "# Foo\n\n- Lorem & `ipsum`");
+    }
+}
+
+#[test]
+fn owned_events_are_printed_on_another_thread() {
+    let (sender, receiver) = mpsc::channel();
+    let parser = thread::spawn(move || {
+        let source = String::from("> Lorem &amp; `ipsum`\n\n1. Dolor");
+        for event in Parser::new(&source) {
+            sender.send(OwnedEvent::from(event)).unwrap();
+        }
+    });
+    let mut printer = PrettyPrinter::default();
+    printer.push_events(receiver).unwrap();
+    parser.join().unwrap();
+    assert_eq!(printer.into_inner(), "> Lorem & `ipsum`\n\n1. Dolor");
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn async_printer_writes_complete_blocks() {
+    use super::AsyncPrettyPrinter;
+
+    let runtime = ::tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut printer = AsyncPrettyPrinter::new(Vec::new());
+    let mut written = Vec::new();
+    for event in Parser::new("# Foo\n\n- Lorem\n- Ipsum\n\n<!-- x -->") {
+        runtime.block_on(printer.push_event(event)).unwrap();
+        written.push(String::from_utf8(printer.get_ref().clone()).unwrap());
+    }
+    runtime.block_on(printer.finish()).unwrap();
+    written.dedup();
+    assert_eq!(written, vec!["", "# Foo", "# Foo\n\n- Lorem\n\n- Ipsum", "# Foo\n\n- Lorem\n\n- Ipsum\n\n<!-- x -->"]);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn async_printer_finishes_held_footnotes() {
+    use super::AsyncPrettyPrinter;
+
+    let options = Options {
+        footnotes: Some(FootnoteOptions { placement: FootnotePlacement::DocumentEnd, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    let source = "[^a]: Ay\n\nLorem[^a]\n\n# Ipsum";
+    let runtime = ::tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut printer = AsyncPrettyPrinter::new_with_options(Vec::new(), "", options.clone());
+    let mut sync = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    for event in Parser::new_ext(source, options.parser_options()) {
+        sync.push_event(event.clone()).unwrap();
+        runtime.block_on(printer.push_event(event)).unwrap();
+    }
+    sync.finish().unwrap();
+    runtime.block_on(printer.finish()).unwrap();
+    let output = String::from_utf8(printer.into_inner()).unwrap();
+    assert_eq!(output, sync.into_inner());
+    assert_eq!(output, prettify_with_options(source, &options));
+    assert_eq!(output, "Lorem[^a]\n\n# Ipsum\n\n[^a]: Ay");
+}
+
+fn print_with_checkpoints<F>(source: &str, options: &Options, transfer: F) -> String
+    where F: Fn(Checkpoint) -> Checkpoint
+{
+    let events = EventBuffer::from_source(source);
+    let mut output = String::new();
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "//!", options.clone());
+    for event in events {
+        printer.push_event(event).unwrap();
+        let checkpoint = transfer(printer.checkpoint());
+        output.push_str(&printer.into_inner());
+        printer = PrettyPrinter::resume(String::new(), options.clone(), checkpoint);
+    }
+    output + &printer.into_inner()
+}
+
+#[test]
+fn printing_resumes_from_checkpoints() {
+    let source = "# the *heading*\n\n- > [!NOTE]\n  > Lorem [[ipsum]]\n\n1. ```rust\n   foo\n   ```";
+    let options = Options { heading_case: HeadingCase::Title, wiki_links: true, ..Options::default() };
+    let expected = "//! # The *Heading*\n//!\n//! - > [!NOTE]\n//!   > Lorem [[ipsum]]\n//!\n//! 1. ```rust\n//!    foo\n//!    ```";
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "//!", options.clone());
+    printer.push_source(source).unwrap();
+    assert_eq!(printer.into_inner(), expected);
+    let output = print_with_checkpoints(source, &options, |checkpoint| {
+        thread::spawn(move || checkpoint).join().unwrap()
+    });
+    assert_eq!(output, expected);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn checkpoints_are_serializable() {
+    let source = "# the *heading*\n\n- > [!NOTE]\n  > Lorem [[ipsum]]\n\n1. ```rust\n   foo\n   ```";
+    let options = Options { heading_case: HeadingCase::Title, wiki_links: true, ..Options::default() };
+    let output = print_with_checkpoints(source, &options, |checkpoint| {
+        let json = ::serde_json::to_string(&checkpoint).unwrap();
+        ::serde_json::from_str(&json).unwrap()
+    });
+    assert_eq!(output, "//! # The *Heading*\n//!\n//! - > [!NOTE]\n//!   > Lorem [[ipsum]]\n//!\n//! 1. ```rust\n//!    foo\n//!    ```");
+}
+
+#[test]
+fn outline_lists_headings_up_to_depth() {
+    let source = "Intro\n\nSetext `code`\n===\n\n> ## What's new? ##\n\n### Hidden";
+    let entries = outline(source, 2);
+    let summary = entries.iter()
+        .map(|entry| (entry.text.as_str(), entry.level, entry.slug.as_str(), &source[entry.span.clone()]))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, vec![
+        ("Setext code", 1, "setext-code", "Setext `code`\n==="),
+        ("What's new?", 2, "whats-new", "## What's new? ##")
+    ]);
+}
+
+#[test]
+fn split_by_heading_ignores_nested_and_deeper_headings() {
+    let source = "# Install\n\n> # Note\n\n### Cargo\n\nUse [cargo].\n\n## Usage\n\n[cargo]: https://crates.io";
+    let sections = split_by_heading(source, 2, &Options::default());
+    let summary = sections.iter()
+        .map(|section| (section.title.as_deref(), section.source.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, vec![
+        (Some("Install"), "# Install\n\n> # Note\n\n### Cargo\n\nUse [cargo](https://crates.io)."),
+        (Some("Usage"), "## Usage")
+    ]);
+
+    let sections = split_by_heading("# *Setup* <b>now</b>", 1, &Options::default());
+    assert_eq!(sections[0].title.as_deref(), Some("Setup now"));
+
+    let options = Options {
+        tables: Some(TableOptions::default()),
+        references: Some(ReferenceOptions::default()),
+        ..Options::default()
+    };
+    let source = "# A\n\n| a | b |\n|---|---|\n| c | d |\n\n# B\n\nSee [y][2].\n\n[1]: http://a\n[2]: http://b";
+    let sections = split_by_heading(source, 1, &options);
+    assert_eq!(sections[0].source, "# A\n\n| a   | b   |\n| --- | --- |\n| c   | d   |\n\n[1]: http://a\n[2]: http://b");
+    assert_eq!(sections[1].source, "# B\n\nSee [y][2].\n\n[2]: http://b\n[1]: http://a");
+    assert_eq!(extract_section(source, &["B"], false, &options).as_deref(), Some(sections[1].source.as_str()));
+    assert_eq!(adjust_headings(source, 1, HeadingRange::All, &options),
+               "## A\n\n| a   | b   |\n| --- | --- |\n| c   | d   |\n\n## B\n\nSee [y][2].\n\n[2]: http://b\n[1]: http://a");
+}
+
+#[test]
+fn structure_is_normalized() {
+    let options = Options { normalize_structure: true, ..Options::default() };
+    let output = prettify_with_options("> Lorem\n\n> ipsum\n\n- a\n-\n* b\n\n1. c\n2) d\n\n- e", &options);
+    assert_eq!(output, "> Lorem\n>\n> ipsum\n\n- a\n\n- b\n\n1. c\n\n2. d\n\n- e");
+}
+
+#[test]
+fn lists_use_canonical_indent() {
+    let options = Options { list_indent: Some(4), ..Options::default() };
+    let output = prettify_with_options("- a\n   1. b\n\n      c", &options);
+    assert_eq!(output, "-   a\n\n    1.  b\n\n        c");
+}
+
+#[test]
+fn deeply_nested_lists_are_flattened() {
+    let options = Options { max_list_depth: Some(2), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("- a\n  - b\n    - c\n      - d\n  - e").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "- a\n\n  - b\n\n  - c\n\n  - d\n\n  - e");
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].rule, "list-depth");
+    assert_eq!(diagnostics[0].offset, Some(14));
+}
+
+#[test]
+fn reference_links_are_preserved() {
+    let source = "See [Foo][Bar], [bar] and ![x][].\n\n[X]: /x.png\n[bar]: http://a \"T\"\n[unused]: /u";
+    let options = Options { references: Some(ReferenceOptions::default()), ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "See [Foo][bar], [bar] and ![x][].\n\n[bar]: http://a \"T\"\n[X]: /x.png\n[unused]: /u");
+}
+
+#[test]
+fn reference_links_are_numbered_per_section() {
+    let source = "# A\n\n[one] and [two](/2).\n\n# B\n\n[Three][] [one]\n\n[three]: /3\n[one]: /1";
+    let options = Options {
+        references: Some(ReferenceOptions {
+            labels: ReferenceLabels::Numbered,
+            placement: ReferencePlacement::SectionEnd,
+            ..ReferenceOptions::default()
+        }),
+        ..Options::default()
+    };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "# A\n\n[one][1] and [two](/2).\n\n[1]: /1\n\n# B\n\n[Three][2] [one][1]\n\n[2]: /3");
+}
+
+#[test]
+fn footnotes_are_printed() {
+    let options = Options { footnotes: Some(FootnoteOptions::default()), ..Options::default() };
+    let output = prettify_with_options("Lorem[^a].\n\n[^a]: Ipsum\\\n    dolor\nsit", &options);
+    assert_eq!(output, "Lorem[^a].\n\n[^a]: Ipsum\\\n    dolor sit");
+}
+
+#[test]
+fn footnotes_are_renumbered_and_moved() {
+    let source = "[^z]: Unused\n\n[^b]: Bee\n\n# A\n\nA[^b] and[^a].\n\n[^a]: Ay\n\n# B\n\nB[^b]";
+    let options = Options {
+        footnotes: Some(FootnoteOptions {
+            renumber: true,
+            placement: FootnotePlacement::SectionEnd,
+            remove_unreferenced: true,
+            ..FootnoteOptions::default()
+        }),
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source(source).unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "# A\n\nA[^1] and[^2].\n\n[^1]: Bee\n\n[^2]: Ay\n\n# B\n\nB[^1]");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].rule, "unused-footnote");
+    assert_eq!(diagnostics[0].offset, Some(0));
+
+    let source = "# S1\n\nText[^b].\n\n# S2\n\nMore[^a].\n\n[^a]: A.\n\n[^b]: B.";
+    let options = Options {
+        footnotes: Some(FootnoteOptions { placement: FootnotePlacement::SectionEnd, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    assert_eq!(prettify_with_options(source, &options), "# S1\n\nText[^b].\n\n[^b]: B.\n\n# S2\n\nMore[^a].\n\n[^a]: A.");
+}
+
+#[test]
+fn duplicate_definitions_are_removed_and_sorted() {
+    let options = Options {
+        footnotes: Some(FootnoteOptions {
+            placement: FootnotePlacement::DocumentEnd,
+            remove_duplicates: true,
+            sort: true,
+            ..FootnoteOptions::default()
+        }),
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("A[^b][^a]\n\n[^b]: Bee\n\n[^a]: Ay\n\n[^b]:  Bee\n\n[^a]: Other").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "A[^b][^a]\n\n[^a]: Ay\n\n[^b]: Bee");
+    let reported = diagnostics.iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(reported, vec![("duplicate-footnote", Some(44))]);
+
+    let options = Options { references: Some(ReferenceOptions { sort: true, ..ReferenceOptions::default() }), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("[y] and [x]\n\n[y]: /y\n[x]: /x\n[y]: /y\n [X]: /z").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "[y] and [x]\n\n[x]: /x\n[y]: /y");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].to_string(), "warning[duplicate-definition] at byte 38: conflicting definition of `X` was removed");
+}
+
+#[test]
+fn images_without_alt_text_are_reported() {
+    let options = Options { image_alt: AltTextPolicy::Warn, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("![](a.png) ![ok](b.png) ![ ](c.png)").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "![](a.png) ![ok](b.png) ![ ](c.png)");
+    let reported = diagnostics.iter().map(|diagnostic| diagnostic.offset).collect::<Vec<_>>();
+    assert_eq!(reported, vec![Some(0), Some(24)]);
+
+    let options = Options { image_alt: AltTextPolicy::Require, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    assert!(printer.push_source("Lorem ![](a.png)").is_err());
+    assert_eq!(printer.diagnostics()[0].severity, Severity::Error);
+
+    let mut printer = PrettyPrinter::default();
+    printer.set_alt_text_provider(|_: &str, _: &str| Some("a] *b* `c`\nd".to_string()));
+    printer.push_source("![](a.png)").unwrap();
+    assert_eq!(printer.into_inner(), "![a\\] \\*b\\* \\`c\\` d](a.png)");
+}
+
+#[test]
+fn code_blocks_without_language_are_reported() {
+    let options = Options {
+        info_string: InfoStringOptions { require_language: true, ..InfoStringOptions::default() },
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("```\nfoo\n```\n\n```Rust\nbar\n```\n\n    baz").unwrap();
+    let reported = printer.diagnostics().iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(reported, vec![("code-language", Some(0)), ("code-language", Some(34))]);
+}
+
+#[test]
+fn verbatim_blocks_are_copied_from_source() {
+    let options = Options {
+        verbatim: VerbatimBlocks { code_blocks: true, block_quotes: true, ..VerbatimBlocks::default() },
+        ..Options::default()
+    };
+    let source = "Lorem\n\n\n    fn main() {}  \n\n~~~ rust\nlet x;\n~~~\n\n>   __quoted__\n> text\n\n- > __nested__";
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "Lorem\n\n    fn main() {}\n\n~~~ rust\nlet x;\n~~~\n\n>   __quoted__\n> text\n\n- > **nested**");
+}
+
+const SNAPSHOT_SOURCE: &str = "Title\n=====\n\nLorem _ipsum_ __dolor__\nsit `amet`.\n\n* one\n* two\n   1) three\n\n> quote\n\n***\n\n    code\n\n~~~rust\nfn main() {}\n~~~";
+
+#[test]
+fn default_output_snapshot() {
+    let output = prettify(SNAPSHOT_SOURCE);
+    assert_eq!(output, "# Title\n\nLorem *ipsum* **dolor** sit `amet`.\n\n- one\n\n- two\n\n  1. three\n\n> quote\n\n---\n\n```\ncode\n```\n\n```rust\nfn main() {}\n```");
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn corpus_runner_compares_expected_output() {
+    use std::fs;
+    use super::testing::run_corpus;
+
+    let dir = ::std::env::temp_dir().join(format!("prettify-cmark-corpus-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("a.md"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("a.expected.md"), "Lorem **ipsum**").unwrap();
+    fs::write(dir.join("b.md"), "* dolor").unwrap();
+    fs::write(dir.join("b.expected.md"), "* dolor").unwrap();
+    fs::write(dir.join("c.md"), "Sit _amet_").unwrap();
+    let failures = run_corpus(&dir, &Options::default()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].path, dir.join("b.md"));
+    assert_eq!(failures[0].actual, "- dolor");
+    assert_prettified!("Sit _amet_", "Sit *amet*");
+}
+
+#[test]
+fn writer_tracks_columns_under_nested_frames() {
+    let mut writer = Writer::new(String::new(), "//!".to_string());
+    assert_eq!(writer.column(), 4);
+    writer.write_text("Grüße").unwrap();
+    assert_eq!(writer.column(), 9);
+
+    writer.push_frame(Frame::BlockQuote);
+    writer.push_frame(Frame::ListItem(Some(10)));
+    writer.write_hard_break().unwrap();
+    writer.write_indent().unwrap();
+    assert_eq!(writer.column(), 10);
+    writer.write_text("日本").unwrap();
+    writer.write_soft_break().unwrap();
+    assert_eq!(writer.column(), 13);
+    writer.write_text("a\nbc").unwrap();
+    assert_eq!(writer.column(), 2);
+    assert_eq!(writer.into_inner(), "//! Grüße\n//! >     日本 a\nbc");
+}
+
+#[test]
+fn printer_exposes_column() {
+    let mut printer = PrettyPrinter::new_with_prefix(String::new(), "///");
+    printer.push_source("> - Lorem").unwrap();
+    assert_eq!(printer.column(), 13);
+}
+
+#[test]
+fn raw_text_is_indented_but_not_escaped() {
+    let mut printer = PrettyPrinter::new_with_prefix(String::new(), "//!");
+    printer.push_source("- Lorem").unwrap();
+    printer.push_raw("<b>*raw*</b>").unwrap();
+    printer.push_events(Parser::new("Ipsum\n\n- dolor\n\n  sit").take(8)).unwrap();
+    printer.push_raw("*a*\n*b*").unwrap();
+    assert_eq!(printer.into_inner(), "//! - Lorem\n//!\n//! <b>*raw*</b>\n//!\n//! Ipsum\n//!\n//! - dolor\n//!\n//!   *a*\n//!   *b*");
+}
+
+#[test]
+fn scripts_are_stripped_from_html() {
+    let options = Options { html: HtmlPolicy::StripScripts, ..Options::default() };
+    let source = "<div onclick='x()' class=\"a\">\n<script>\nalert(1)\n</script>\n<a href=\" JavaScript:y()\">z</a>\n</div>\n\n<style>p {}</style>\n\nLorem <script>alert(2)</script> <img src=\"a.png\"/>";
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "<div class=\"a\">\n<a>z</a>\n</div>\n\nLorem  <img src=\"a.png\" />");
+}
+
+#[test]
+fn obfuscated_scripts_are_stripped_from_html() {
+    let options = Options { html: HtmlPolicy::StripScripts, ..Options::default() };
+    let output = prettify_with_options("<a href=\"&#106;avascript:alert(1)\">a</a>", &options);
+    assert_eq!(output, "<a>a</a>");
+    let output = prettify_with_options("<a href=\"&#x6A;ava&Tab;script&colon;alert(1)\">a</a>", &options);
+    assert_eq!(output, "<a>a</a>");
+    let output = prettify_with_options("<a href=\"java\tscript:alert(1)\">a</a>", &options);
+    assert_eq!(output, "<a>a</a>");
+    let output = prettify_with_options("<a href=\"data:text/html,x\">a</a>", &options);
+    assert_eq!(output, "<a>a</a>");
+    let output = prettify_with_options("<div>\n<img/onerror=alert(1) src=x>\n</div>", &options);
+    assert_eq!(output, "<div>\n<img src=x>\n</div>");
+
+    let source = "<a href=\"https://example.com/a:b\">a</a> <a href=\"MAILTO:x@example.com\">b</a> <a href=\"docs/a:b\">c</a>";
+    assert_eq!(prettify_with_options(source, &options), source);
+}
+
+#[test]
+fn duplicate_heading_anchors_are_reported() {
+    let source = "# Usage\n\n## Install\n\n# Usage!\n\n## `install`";
+    let slugs = outline(source, 6).into_iter().map(|entry| entry.slug).collect::<Vec<_>>();
+    assert_eq!(slugs, vec!["usage", "install", "usage-1", "install-1"]);
+
+    let options = Options { duplicate_anchors: true, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source(source).unwrap();
+    let reported = printer.diagnostics().iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(reported, vec![("duplicate-anchor", Some(21)), ("duplicate-anchor", Some(31))]);
+}
+
+#[test]
+fn deep_headings_are_reported() {
+    let options = Options {
+        heading_limit: Some(HeadingLimit { max_level: 2, demote: false }),
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("## Lorem\n\n### Ipsum\n\n- #### Dolor").unwrap();
+    let reported = printer.diagnostics().iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.offset))
+        .collect::<Vec<_>>();
+    assert_eq!(printer.into_inner(), "## Lorem\n\n### Ipsum\n\n- #### Dolor");
+    assert_eq!(reported, vec![("heading-depth", Some(10)), ("heading-depth", Some(23))]);
+}
+
+#[test]
+fn demoted_headings_are_idempotent() {
+    let options = Options {
+        heading_limit: Some(HeadingLimit { max_level: 2, demote: true }),
+        ..Options::default()
+    };
+    let output = prettify_with_options("## Lorem\n\n###\n\nIpsum\n\n### **dolor**\n\n### Sit *amet*", &options);
+    assert_eq!(output, "## Lorem\n\nIpsum\n\n**dolor**\n\n**Sit *amet***");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[test]
+fn block_syntax_is_escaped_at_start_of_line() {
+    let source = "\\- foo\n\n1\\. bar\n\n\\# baz\n\n\\> qux\n\na\\\n\\- b\n\n- \\+ c\n\n> 10\\) d\n\n\\*\\*\\*\n\n2024 was \\<b> - fine";
+    let output = prettify(source);
+    assert_eq!(output, "\\- foo\n\n1\\. bar\n\n\\# baz\n\n\\> qux\n\na\\\n\\- b\n\n- \\+ c\n\n> 10\\) d\n\n\\*\\*\\*\n\n2024 was \\<b> - fine");
+    assert_eq!(prettify(&output), output);
+}
+
+#[cfg(feature = "testing")]
+#[test]
+#[ignore]
+fn readme_corpus_round_trips() {
+    use super::testing::{round_trip_report, run_round_trip};
+
+    let corpus = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+    let failures = run_round_trip(corpus, &Options::default()).expect("failed to read corpus");
+    assert!(failures.is_empty(), "{} document(s) changed meaning, see tests/corpus_gaps.md:\n{}",
+            failures.len(), round_trip_report(&failures));
+}
+
+#[test]
+fn parsed_events_are_well_formed() {
+    for entry in ::std::fs::read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus")).unwrap() {
+        let source = ::std::fs::read_to_string(entry.unwrap().path()).unwrap();
+        let source = source + "\n| a | b |\n|---|---|\n| `c` | [^d] |\n\n[^d]: > e";
+        let events = Parser::new_ext(&source, OPTION_ENABLE_FOOTNOTES | OPTION_ENABLE_TABLES);
+        assert_eq!(validate_events(events), Ok(()));
+    }
+}
+
+#[test]
+fn malformed_events_are_rejected() {
+    let unclosed = vec![Event::Start(Tag::Paragraph), Event::Start(Tag::Strong), Event::End(Tag::Strong)];
+    assert_eq!(validate_events(unclosed), Err(NestingError::Unclosed { index: 0, tag: Tag::Paragraph }));
+
+    let mismatched = vec![Event::Start(Tag::Paragraph), Event::End(Tag::Emphasis)];
+    assert_eq!(validate_events(mismatched), Err(NestingError::MismatchedEnd {
+        index: 1,
+        expected: Tag::Paragraph,
+        found: Tag::Emphasis
+    }));
+
+    let stray = vec![Event::Start(Tag::Item), Event::End(Tag::Item)];
+    let err = validate_events(stray).unwrap_err();
+    assert_eq!(err.to_string(), "event 0: list item is not allowed at the top level");
+
+    let text = vec![Event::Start(Tag::List(None)), Event::Text("Lorem".into()), Event::End(Tag::List(None))];
+    let err = validate_events(text).unwrap_err();
+    assert_eq!(err.to_string(), "event 1: inline content is not allowed inside list");
+}
+
+#[test]
+fn code_block_content_is_preserved_exactly() {
+    let options = Options { trim_trailing_whitespace: true, ..Options::default() };
+    let source = "<div>  \n<p>Lorem</p>\t\n</div>  \n\n```\nipsum  \n\n  \n\n```\n\n- ```\n  dolor \n\n\n  ```";
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "<div>\n<p>Lorem</p>\n</div>\n\n```\nipsum  \n\n  \n\n```\n\n- ```\n  dolor \n\n\n  ```");
+
+    let mut printer = PrettyPrinter::default();
+    printer.push_events(vec![
+        Event::Start(Tag::CodeBlock("rust".into())),
+        Event::Text("let x = 1;  \n\nx".into()),
+        Event::End(Tag::CodeBlock("rust".into()))
+    ]).unwrap();
+    assert_eq!(printer.into_inner(), "```rust\nlet x = 1;  \n\nx\n```");
+}
+
+#[test]
+fn trailing_whitespace_is_stripped_from_output_lines() {
+    let options = Options {
+        trim_trailing_whitespace: true,
+        verbatim: VerbatimBlocks { paragraphs: true, ..VerbatimBlocks::default() },
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "//!", options.clone());
+    printer.push_source("> <div>  \n>   </div>\t\n\nLorem  \nipsum").unwrap();
+    printer.push_raw("dolor  \n \nsit  ").unwrap();
+    let checkpoint = printer.checkpoint();
+    let first = printer.into_inner();
+
+    let mut printer = PrettyPrinter::resume(String::new(), options, checkpoint);
+    printer.push_source("```\namet  \n```").unwrap();
+    assert_eq!(first + &printer.into_inner(),
+               "//! > <div>\n//! >   </div>\n//!\n//! Lorem  \n//! ipsum\n//!\n//! dolor\n//!\n//! sit\n//!\n//! ```\n//! amet  \n//! ```");
+}
+
+#[test]
+fn block_quotes_with_lazy_continuation() {
+    let source = "> Lorem\nipsum\\\ndolor\n>\n> - sit\\\namet\n>\n> ```\n> a\n> ```";
+    assert_eq!(prettify(source), "> Lorem ipsum\\\n> dolor\n>\n> - sit\\\n>   amet\n>\n> ```\n> a\n> ```");
+
+    let options = Options { block_quote_style: BlockQuoteStyle::Lazy, ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "> Lorem ipsum\\\ndolor\n>\n> - sit\\\n  amet\n>\n> ```\n> a\n> ```");
+    assert_eq!(prettify(&output), prettify(source));
+}
+
+#[test]
+fn nested_inline_constructs_in_link_text() {
+    let cases = [
+        ("[a [b] c](u)", "[a [b] c](u)"),
+        ("[`]` and `[`](u)", "[`]` and `[`](u)"),
+        ("[![i [j]](p.png) k](u)", "[![i [j]](p.png) k](u)"),
+        ("*[e](u)* and **[f *g*](u)**", "*[e](u)* and **[f *g*](u)**"),
+        ("[**h** `i` <b>j</b>](u \"t\")", "[**h** `i` <b>j</b>](u \"t\")"),
+        ("![*k* `l`](p.png)", "![*k* `l`](p.png)")
+    ];
+    for &(source, expected) in &cases {
+        assert_eq!(prettify(source), expected);
+    }
+
+    let mut printer = PrettyPrinter::default();
+    printer.push_events(vec![
+        Event::Start(Tag::Paragraph),
+        Event::Start(Tag::Link("u".into(), "".into())),
+        Event::Text("a] [b".into()),
+        Event::Start(Tag::Image("p.png".into(), "".into())),
+        Event::Text("c[".into()),
+        Event::End(Tag::Image("p.png".into(), "".into())),
+        Event::Start(Tag::Code),
+        Event::Text("]".into()),
+        Event::End(Tag::Code),
+        Event::Text("[d]]".into()),
+        Event::End(Tag::Link("u".into(), "".into())),
+        Event::End(Tag::Paragraph)
+    ]).unwrap();
+    assert_eq!(printer.into_inner(), "[a\\] [b![c\\[](p.png)`]`[d]]](u)");
+}
+
+#[test]
+fn inline_markup_in_text_is_escaped() {
+    let mut printer = PrettyPrinter::default();
+    printer.push_events(vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("*not* _emphasis_, a * b, snake_case, `tick`, <b>, 1 < 2, &amp; & C:\\path\\".into()),
+        Event::SoftBreak,
+        Event::Start(Tag::Image("p.png".into(), "".into())),
+        Event::Text("[x] ]y[".into()),
+        Event::End(Tag::Image("p.png".into(), "".into())),
+        Event::End(Tag::Paragraph)
+    ]).unwrap();
+    let output = printer.into_inner();
+    assert_eq!(output, "\\*not\\* \\_emphasis\\_, a * b, snake_case, \\`tick\\`, \\<b>, 1 < 2, \\&amp; & C:\\path\\\\ \
+                        ![[x] \\]y\\[](p.png)");
+    assert_eq!(prettify(&output), output);
+
+    let options = Options { entities: EntityPolicy::Encode, ..Options::default() };
+    let source = "\\<b> \\&amp; [a\\]b](u) \\_";
+    assert_eq!(prettify_with_options(source, &options), "\\<b&gt; \\&amp; [a\\]b](u) \\_");
+}
+
+#[test]
+fn link_titles_are_normalized() {
+    let source = "[a](u \"Lorem  \\\"ipsum\\\"\n dolor\") ![b](p.png ' ') [c](v (sit))";
+    assert_eq!(prettify(source), "[a](u \"Lorem  \\\"ipsum\\\" dolor\") ![b](p.png \" \") [c](v \"sit\")");
+
+    let options = Options {
+        titles: TitleOptions { quote: TitleQuote::Single, collapse_whitespace: true, drop_empty: true, drop_all: false },
+        ..Options::default()
+    };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "[a](u 'Lorem \"ipsum\" dolor') ![b](p.png) [c](v 'sit')");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[test]
+fn bare_urls_are_converted_to_links() {
+    let source = "See https://example.com/a_(b). Mail me@example.org, [http://x.com](http://x.com) or [y](http://y.com).";
+    assert_eq!(prettify(source), source);
+
+    let options = Options { bare_urls: Some(AutolinkStyle::Angle), ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "See <https://example.com/a_(b)>. Mail <me@example.org>, <http://x.com> or [y](http://y.com).");
+    assert_eq!(prettify_with_options(&output, &options), output);
+
+    let options = Options { bare_urls: Some(AutolinkStyle::Inline), ..Options::default() };
+    assert_eq!(prettify_with_options("Visit http://x.com, mail a.b@c.org.", &options),
+               "Visit [http://x.com](http://x.com), mail [a.b@c.org](mailto:a.b@c.org).");
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn directory_tree_is_formatted_with_ignore_rules() {
+    use std::fs;
+    use super::{format_tree, FileOutcome, TreeOptions};
+
+    let dir = ::std::env::temp_dir().join(format!("prettify-cmark-tree-{}", ::std::process::id()));
+    fs::create_dir_all(dir.join("docs/generated")).unwrap();
+    fs::create_dir_all(dir.join("vendor")).unwrap();
+    fs::write(dir.join(".gitignore"), "vendor/\n*.draft.md\n").unwrap();
+    fs::write(dir.join("README.md"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("notes.draft.md"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("notes.txt"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("vendor/a.md"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("docs/.gitignore"), "/generated/**\n!/generated/keep.md\n").unwrap();
+    fs::write(dir.join("docs/guide.md"), "Dolor *sit*").unwrap();
+    fs::write(dir.join("docs/generated/api.md"), "Lorem __ipsum__").unwrap();
+    fs::write(dir.join("docs/generated/keep.md"), "Lorem __ipsum__").unwrap();
+
+    let options = TreeOptions { exclude: vec!["docs/guide.md".to_string()], write: true, ..TreeOptions::default() };
+    let reports = format_tree(&dir, &options).unwrap();
+    let written = fs::read_to_string(dir.join("README.md")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let paths = reports.iter().map(|report| report.path.strip_prefix(&dir).unwrap().to_path_buf()).collect::<Vec<_>>();
+    assert_eq!(paths, vec![PathBuf::from("README.md"), PathBuf::from("docs/generated/keep.md")]);
+    assert!(reports.iter().all(|report| matches!(report.outcome, FileOutcome::Changed)));
+    assert_eq!(written, "Lorem **ipsum**");
+}
+
+#[cfg(all(feature = "fs", unix))]
+#[test]
+fn file_is_formatted_in_place() {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use super::format_file_in_place;
+
+    let dir = ::std::env::temp_dir().join(format!("prettify-cmark-in-place-{}", ::std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("README.md");
+    fs::write(&path, "Lorem __ipsum__").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+    let changed = format_file_in_place(&path, &Options::default(), true).unwrap();
+    let unchanged = format_file_in_place(&path, &Options::default(), true).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    let backup = fs::read_to_string(dir.join("README.md.bak")).unwrap();
+    let mode = fs::metadata(&path).unwrap().permissions().mode();
+    let mut files = fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name()).collect::<Vec<_>>();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(changed);
+    assert!(!unchanged);
+    assert_eq!(contents, "Lorem **ipsum**");
+    assert_eq!(backup, "Lorem __ipsum__");
+    assert_eq!(mode & 0o777, 0o640);
+    files.sort();
+    assert_eq!(files, vec!["README.md", "README.md.bak"]);
+}
+
+#[test]
+fn staged_markdown_files_are_formatted() {
+    use super::{format_staged, StagedFile};
+
+    let file = |path: &str, content: &str| StagedFile { path: PathBuf::from(path), content: content.to_string() };
+    let summary = format_staged(vec![
+        file("README.md", "Lorem *ipsum*"),
+        file("docs/Guide.MARKDOWN", "* dolor"),
+        file("Cargo.toml", "[package]")
+    ], &Options::default());
+    assert_eq!((summary.checked, summary.skipped), (2, 1));
+    assert_eq!(summary.replacements.len(), 1);
+    assert_eq!(summary.replacements[0].path, PathBuf::from("docs/Guide.MARKDOWN"));
+    assert_eq!(summary.replacements[0].formatted, "- dolor");
+
+    let summary = format_staged(vec![file("README.md", "Lorem *ipsum*")], &Options::default());
+    assert!(summary.is_clean());
+    assert_eq!(summary.to_string(), "1 Markdown file(s) already formatted");
+}
+
+#[test]
+fn minimal_formatting_preserves_formatted_blocks() {
+    use super::prettify_minimal;
+
+    let source = "Title\n=====\n\n\n> Lorem *ipsum*\n\n[sit]: /amet\n   \n1) a\n2) b\n\nDolor\nsit  \n";
+    assert_eq!(prettify_minimal(source, &Options::default()),
+               "# Title\n\n\n> Lorem *ipsum*\n\n[sit]: /amet\n   \n1. a\n\n2. b\n\nDolor sit  \n");
+
+    let formatted = prettify(source);
+    assert_eq!(prettify_minimal(&formatted, &Options::default()), formatted);
+
+    let options = Options { normalize_structure: true, ..Options::default() };
+    assert_eq!(prettify_minimal("> a\n\n\n> b\n", &options), prettify_with_options("> a\n\n\n> b\n", &options));
+}
+
+#[test]
+fn minimal_formatting_parses_with_extensions() {
+    use super::prettify_minimal;
+
+    let options = Options { footnotes: Some(FootnoteOptions::default()), ..Options::default() };
+    let source = "Text[^a].\n\n[^a]: Note\n    more *x*";
+    assert_eq!(prettify_minimal(source, &options), prettify_with_options(source, &options));
+    assert!(prettify_minimal(source, &options).contains("more *x*"));
+
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    let source = "Lorem\n\n|a|b|\n|-|-|\n|c|d|";
+    assert_eq!(prettify_minimal(source, &options), prettify_with_options(source, &options));
+    assert_eq!(prettify_minimal(source, &options), "Lorem\n\n| a   | b   |\n| --- | --- |\n| c   | d   |");
+}
+
+#[test]
+fn incremental_formatting_matches_full_formatting() {
+    use super::{IncrementalFormatter, TextEdit};
+
+    let source = "# Lorem\n\nIpsum __dolor__\n\n* sit\n* amet\n\n[a]: /b\n\n> consectetur\n\n```rust\nfn main() {}\n```\n";
+    let mut formatter = IncrementalFormatter::new(source, Options::default());
+    assert_eq!(formatter.output(), prettify(source));
+
+    let end = usize::MAX..usize::MAX;
+    let edits = vec![
+        (9..14, "_Ipsum_"),
+        (0..0, "Intro\n\n"),
+        (17..17, "\n\n"),
+        (17..17, "\n===\n"),
+        (end, "\n\nEnd [a]"),
+        (0..7, ""),
+        (10..10, "[a]: /c\n\n"),
+        (5..5, "```\n")
+    ];
+    for (range, replacement) in edits {
+        let len = formatter.source().len();
+        let range = range.start.min(len)..range.end.min(len);
+        let before = formatter.output().to_string();
+        let edit = formatter.apply(TextEdit { range, replacement: replacement.to_string() });
+        let expected = prettify(formatter.source());
+        assert_eq!(formatter.output(), expected, "after inserting {:?}", replacement);
+
+        let mut patched = before;
+        patched.replace_range(edit.range, &edit.replacement);
+        assert_eq!(patched, expected);
+    }
+}
+
+#[test]
+fn incremental_formatting_matches_full_formatting_with_document_options() {
+    use super::{HeadingHierarchy, IncrementalFormatter, TextEdit};
+
+    let all = vec![
+        Options { footnotes: Some(FootnoteOptions::default()), ..Options::default() },
+        Options { tables: Some(TableOptions::default()), ..Options::default() },
+        Options { heading_hierarchy: Some(HeadingHierarchy { fix: true }), ..Options::default() },
+        Options { heading_limit: Some(HeadingLimit { max_level: 2, demote: true }), ..Options::default() },
+        Options { duplicate_anchors: true, ..Options::default() }
+    ];
+    let source = "# A\n\nText[^a].\n\n### B\n\n|a|b|\n|-|-|\n|c|d|\n\n[^a]: Note\n    more *x*";
+    for options in all {
+        let mut formatter = IncrementalFormatter::new(source, options.clone());
+        assert_eq!(formatter.output(), prettify_with_options(source, &options));
+
+        let before = formatter.output().to_string();
+        let edit = formatter.apply(TextEdit { range: 0..0, replacement: "## C\n\n".to_string() });
+        let expected = prettify_with_options(formatter.source(), &options);
+        assert_eq!(formatter.output(), expected);
+
+        let mut patched = before;
+        patched.replace_range(edit.range, &edit.replacement);
+        assert_eq!(patched, expected);
+    }
+}
+
+#[test]
+fn html_rendering_uses_parser_extensions_and_anchors() {
+    use super::render_html;
+
+    let options = Options { footnotes: Some(FootnoteOptions::default()), ..Options::default() };
+    let html = render_html("## Usage\n\nLorem[^1]\n\n## *Usage*\n\n[^1]: Ipsum", &options);
+    assert!(html.starts_with("<h2 id=\"usage\">Usage</h2>\n<p>Lorem<sup class=\"footnote-reference\">"), "{}", html);
+    assert!(html.contains("<h2 id=\"usage-1\"><em>Usage</em></h2>"), "{}", html);
+    assert!(html.contains("<div class=\"footnote-definition\" id=\"1\">"), "{}", html);
+
+    assert_eq!(render_html("Lorem[^1]\n\n[^1]: Ipsum", &Options::default()), "<p>Lorem<a href=\"Ipsum\">^1</a></p>\n");
+}
+
+#[test]
+fn documents_are_compared_semantically() {
+    use super::{first_difference, semantically_equal};
+
+    let source = "Lorem\n=====\n\n* ipsum\n* __dolor__   sit\n  amet\n\n    code  block\n";
+    assert!(semantically_equal(source, &prettify(source), &Options::default()));
+    assert!(!semantically_equal("```\na  b\n```", "```\na b\n```", &Options::default()));
+
+    let difference = first_difference("Lorem\n\n- ipsum", "Lorem\n\n- ipsum\n- dolor", &Options::default()).unwrap();
+    assert_eq!(difference.right, Some(OwnedEvent::from(Event::Start(Tag::Item))));
+    assert_eq!(difference.to_string(), "event 7 differs: end of list at byte 14 != start of list item at byte 15");
+}
+
+#[test]
+fn event_diff_lists_inserted_and_deleted_events() {
+    use super::{diff_events, ChangeKind};
+
+    let a = "# Lorem\n\nIpsum dolor\n\n- sit\n- amet";
+    assert!(diff_events(a, &prettify(a), &Options::default()).is_empty());
+
+    let diff = diff_events(a, "# Lorem\n\nIpsum dolor\n\n> Quote\n\n- sit", &Options::default());
+    let kinds = diff.changes.iter().map(|change| change.kind).collect::<Vec<_>>();
+    assert_eq!(kinds, vec![ChangeKind::Inserted; 5].into_iter().chain(vec![ChangeKind::Deleted; 3]).collect::<Vec<_>>());
+    assert_eq!(diff.to_string().lines().next(), Some("+ start of block quote at byte 22"));
+    assert_eq!(diff.to_string().lines().last(), Some("- text \"amet\" at byte 30"));
+}
+
+#[test]
+fn tables_preserve_alignment() {
+    let source = "| a | b __c__ | d |\n|:-|:-:|-:|\n| 1 | long cell | 3 |\nmore | text\n";
+    assert_eq!(prettify(source), "| a | b **c** | d | |:-|:-:|-:| | 1 | long cell | 3 | more | text");
+
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "| a    |  b **c**  |    d |\n\
+                        | :--- | :-------: | ---: |\n\
+                        | 1    | long cell |    3 |\n\
+                        | more |   text    |      |");
+    assert_eq!(prettify_with_options(&output, &options), output);
+
+    let options = Options {
+        tables: Some(TableOptions { separator: TableSeparator::Minimal, padding: false, ..TableOptions::default() }),
+        ..Options::default()
+    };
+    assert_eq!(prettify_with_options(source, &options), "| a | b **c** | d |\n\
+                                                         | :--- | :---: | ---: |\n\
+                                                         | 1 | long cell | 3 |\n\
+                                                         | more | text |  |");
+}
+
+#[test]
+fn table_cells_escape_pipes() {
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    let source = "| a \\| b | `c \\| d` |\n| --- | --- |\n| &#124; | x |\n";
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "| a \\| b | `c \\| d` |\n\
+                        | ------ | -------- |\n\
+                        | \\|     | x        |");
+    assert_eq!(prettify_with_options(&output, &options), output);
+
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_events(vec![
+        Event::Start(Tag::Table(vec![Alignment::None])),
+        Event::Start(Tag::TableHead),
+        Event::Start(Tag::TableCell),
+        Event::Start(Tag::Code),
+        Event::Text("e | f".into()),
+        Event::End(Tag::Code),
+        Event::End(Tag::TableCell),
+        Event::End(Tag::TableHead),
+        Event::End(Tag::Table(vec![Alignment::None]))
+    ]).unwrap();
+    assert_eq!(printer.into_inner(), "| `e \\| f` |\n| -------- |");
+}
+
+#[test]
+fn wide_tables_fall_back_to_narrower_output() {
+    let source = "| a | b |\n|:-|-:|\n| a *long* cell | x<y |\n| 2 |\n";
+    let tables = TableOptions { max_width: Some(20), ..TableOptions::default() };
+    let options = Options { tables: Some(tables), ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "| a | b |\n\
+                                                        | :--- | ---: |\n\
+                                                        | a *long* cell | x<y |\n\
+                                                        | 2 |  |");
+
+    let options = Options { tables: Some(TableOptions { max_width: Some(40), ..tables }), ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "| a             |    b |\n\
+                                                        | :------------ | ---: |\n\
+                                                        | a *long* cell |  x<y |\n\
+                                                        | 2             |      |");
+
+    let options = Options { tables: Some(TableOptions { overflow: TableOverflow::Html, ..tables }), ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "<table>\n<thead>\n<tr>\n<th align=\"left\">a</th>\n<th align=\"right\">b</th>\n</tr>\n</thead>\n\
+                        <tbody>\n<tr>\n<td align=\"left\">a <em>long</em> cell</td>\n<td align=\"right\">x&lt;y</td>\n</tr>\n\
+                        <tr>\n<td align=\"left\">2</td>\n</tr>\n</tbody>\n</table>");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv_and_tsv_data_is_printed_as_table() {
+    use super::{csv_table, CsvError};
+
+    let mut printer = PrettyPrinter::default();
+    printer.push_source("# Report").unwrap();
+    printer.push_events(csv_table("a\tb|c\r\n\r\n1\t\"multi\nline\"\r\n2\n", '\t').unwrap()).unwrap();
+    assert_eq!(printer.into_inner(), "# Report\n\n\
+                                      | a   | b\\|c       |\n\
+                                      | --- | ---------- |\n\
+                                      | 1   | multi line |\n\
+                                      | 2   |            |");
+
+    assert_eq!(csv_table("", ',').unwrap(), vec![]);
+    let err = csv_table("a,b\n1,\"2\n", ',').unwrap_err();
+    assert_eq!(err, CsvError { line: 2 });
+    assert_eq!(err.to_string(), "unterminated quoted field starting on line 2");
+}
+
+#[test]
+fn escapes_use_configured_style() {
+    let source = "\\# Lorem \\*ipsum\\* [a \\[b\\]](u) 2 * 3\n\n1\\. dolor `sit` \\`amet\\`";
+    let escapes = EscapeStyle {
+        emphasis: EscapeMethod::Entity,
+        brackets: EscapeMethod::Entity,
+        block_markers: EscapeMethod::Entity,
+        ..EscapeStyle::default()
+    };
+    let options = Options { escapes, entities: EntityPolicy::Preserve, ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "&#35; Lorem &#42;ipsum&#42; [a &#91;b&#93;](u) 2 * 3\n\n1&#46; dolor `sit` \\`amet\\`");
+    assert_eq!(prettify_with_options(&output, &options), output);
+
+    let options = Options { escapes, ..Options::default() };
+    let output = prettify_with_options("Lorem _ipsum_ \\__dolor", &options);
+    assert_eq!(output, "Lorem *ipsum* &#95;_dolor");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[test]
+fn leading_whitespace_in_text_is_preserved() {
+    use super::semantically_equal;
+
+    for &(source, expected) in &[
+        ("# &#32;Lorem", "# &#32;Lorem"),
+        ("- &#32;ipsum", "- &#32;ipsum"),
+        ("> &#32; dolor", "> &#32; dolor"),
+        ("&#32;&#32;&#32;&#32;sit", "&#32;   sit"),
+        ("a <b>&#32; amet</b>", "a <b>  amet</b>")
+    ] {
+        let output = prettify(source);
+        assert_eq!(output, expected);
+        assert_eq!(prettify(&output), output);
+        assert!(semantically_equal(source, &output, &Options::default()));
+    }
+
+    let mut printer = PrettyPrinter::new_with_prefix(String::new(), "//!");
+    printer.push_events(vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("  Lorem".into()),
+        Event::InlineHtml("<br>".into()),
+        Event::Text("  ipsum ".into()),
+        Event::SoftBreak,
+        Event::Text(" dolor".into()),
+        Event::End(Tag::Paragraph)
+    ]).unwrap();
+    assert_eq!(printer.into_inner(), "//! &#32; Lorem<br>  ipsum   dolor");
+}
+
+#[cfg(feature = "unstable-writer")]
+#[test]
+fn writer_is_usable_for_custom_printers() {
+    use std::fmt::Write;
+
+    let mut writer = super::Writer::new(String::new(), "#".to_string()).with_list_indent(4);
+    writer.write_text(">").unwrap();
+    writer.write_non_breaking_space().unwrap();
+    writer.push_frame(super::Frame::BlockQuote);
+    writer.write_text("-").unwrap();
+    writer.write_marker_space(1).unwrap();
+    writer.push_frame(super::Frame::ListItem(None));
+    write!(writer, "Lorem {}", 1).unwrap();
+    writer.write_hard_break().unwrap();
+    writer.write_indent().unwrap();
+    writer.write_content("# ipsum").unwrap();
+    assert_eq!(writer.into_inner(), "# > -   Lorem 1\n# >     \\# ipsum");
+}
+
+#[test]
+fn html_line_breaks_end_lines() {
+    let source = "Lorem<br>ipsum <BR/> *dolor<br>* sit<br class=\"x\">\namet <br>\n\n# Heading<br>text";
+    assert_eq!(prettify(source), "Lorem<br>ipsum <BR/> *dolor<br>* sit<br class=\"x\"> amet <br>\n\n# Heading<br>text");
+
+    let options = Options { html_breaks: HtmlBreakPolicy::BreakLine, ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "Lorem<br>\nipsum <BR/>\n*dolor*<br>\nsit<br class=\"x\">\namet <br>\n\n# Heading<br>text");
+    assert_eq!(prettify_with_options(&output, &options), output);
+
+    let options = Options { html_breaks: HtmlBreakPolicy::Convert, ..Options::default() };
+    let output = prettify_with_options(source, &options);
+    assert_eq!(output, "Lorem\\\nipsum \\\n*dolor*\\\nsit<br class=\"x\">\namet <br>\n\n# Heading<br>text");
+    assert_eq!(prettify_with_options(&output, &options), output);
+}
+
+#[test]
+fn adjust_headings_shifts_levels_within_range() {
+    let source = "# One\n\n> ## Two\n\nThree\n=====\n\n###### Four";
+    let options = Options::default();
+    assert_eq!(adjust_headings(source, 2, HeadingRange::Headings(1..3), &options),
+               "# One\n\n> #### Two\n\n### Three\n\n###### Four");
+    assert_eq!(adjust_headings(source, 3, HeadingRange::Bytes(17..source.len()), &options),
+               "# One\n\n> ## Two\n\n#### Three\n\n###### Four");
+    assert_eq!(adjust_headings(source, -9, HeadingRange::All, &options),
+               "# One\n\n> # Two\n\n# Three\n\n# Four");
+    assert_eq!(adjust_headings(source, i32::MAX, HeadingRange::All, &options),
+               "###### One\n\n> ###### Two\n\n###### Three\n\n###### Four");
+    assert_eq!(adjust_headings(source, i32::MIN, HeadingRange::All, &options),
+               "# One\n\n> # Two\n\n# Three\n\n# Four");
+}
+
+#[test]
+fn extract_section_follows_heading_path() {
+    let source = "Intro\n\n# Guide\n\n## *Setup* <b>now</b>\n\nLorem\n\n> ## Nested\n\n#### Deep\n\n# Other\n\n## Nested";
+    let options = Options::default();
+    assert_eq!(extract_section(source, &["Guide", "Setup now"], true, &options).as_deref(),
+               Some("# *Setup* <b>now</b>\n\nLorem\n\n> # Nested\n\n### Deep"));
+    assert_eq!(extract_section(source, &["Other", "Nested"], false, &options).as_deref(), Some("## Nested"));
+    assert_eq!(extract_section(source, &["Guide", "Nested"], false, &options), None);
+    assert_eq!(extract_section(source, &[], false, &options), None);
+}
+
+#[test]
+fn footnotes_convert_between_reference_and_inline_style() {
+    let source = "Lorem[^a] ipsum[^b] dolor[^b].\n\n[^a]: Simple _note_\n    [continued].\n\n[^b]: Shared";
+    let inline = Options {
+        footnotes: Some(FootnoteOptions { style: FootnoteStyle::Inline, renumber: true, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    let output = prettify_with_options(source, &inline);
+    assert_eq!(output, "Lorem^[Simple *note* [continued].] ipsum[^1] dolor[^1].\n\n[^1]: Shared");
+
+    let reference = Options {
+        footnotes: Some(FootnoteOptions { style: FootnoteStyle::Reference, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    assert_eq!(prettify_with_options(&output, &reference),
+               "Lorem[^2] ipsum[^1] dolor[^1].\n\n[^1]: Shared\n\n[^2]: Simple *note* [continued].");
+    assert_eq!(prettify_with_options("`^[code]` \\^[escaped] ^[open", &reference), "`^[code]` \\^[escaped] ^[open");
+}
+
+#[test]
+fn localizer_supplies_diagnostic_messages() {
+    let options = Options {
+        footnotes: Some(FootnoteOptions { remove_unreferenced: true, ..FootnoteOptions::default() }),
+        image_alt: AltTextPolicy::Warn,
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.set_localizer(|message: &Message| match *message {
+        Message::UnusedFootnote { label } => Some(format!("note inutilisée `{}` supprimée", label)),
+        _ => None
+    });
+    printer.push_source("![](a.png)\n\n[^x]: Lorem").unwrap();
+    let messages = printer.diagnostics().iter().map(|diagnostic| (diagnostic.rule, diagnostic.message.as_str())).collect::<Vec<_>>();
+    assert_eq!(messages, vec![
+        ("image-alt", "image `a.png` has no alt text"),
+        ("unused-footnote", "note inutilisée `x` supprimée")
+    ]);
+}
+
+#[test]
+fn output_is_deterministic_across_threads() {
+    let source = "# Intro\n\nLorem [b][] ipsum [a][] dolor[^z] sit[^y].\n\n\
+                  [^y]: Why\n\n[^z]: Zed\n\n[b]: http://b.example\n[a]: http://a.example\n\n\
+                  | x | y |\n|---|:-:|\n| 1 | 2 |\n\n# Intro\n\n- [B][]\n- [A][]\n\n[B]: http://b.example";
+    let options = Options {
+        references: Some(ReferenceOptions { sort: true, ..ReferenceOptions::default() }),
+        footnotes: Some(FootnoteOptions { sort: true, placement: FootnotePlacement::DocumentEnd, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    let expected = prettify_with_options(source, &options);
+    let threads = (0..8).map(|_| {
+        let options = options.clone();
+        thread::spawn(move || (0..25).map(|_| prettify_with_options(source, &options)).collect::<Vec<_>>())
+    }).collect::<Vec<_>>();
+    for thread in threads {
+        for output in thread.join().unwrap() {
+            assert_eq!(output, expected);
+        }
+    }
+}
+
+#[test]
+fn budget_aborts_printing_at_block_boundaries() {
+    let source = "Lorem *ipsum*\n\n- dolor\n- sit\n\namet";
+    let mut printer = PrettyPrinter::default();
+    printer.set_budget(Budget { events: Some(8), ..Budget::default() });
+    assert!(printer.push_source(source).is_err());
+    assert_eq!(printer.diagnostics().len(), 1);
+    assert_eq!(printer.diagnostics()[0].to_string(), "error[event-budget] at byte 23: document has more than 8 events");
+    assert!(printer.push_source(source).is_err());
+    assert_eq!(printer.diagnostics().len(), 1);
+
+    let mut printer = PrettyPrinter::default();
+    printer.set_budget(Budget { time: Some(Duration::from_millis(20)), ..Budget::default() });
+    printer.push_event(Event::Start(Tag::Paragraph)).unwrap();
+    thread::sleep(Duration::from_millis(30));
+    printer.push_event(Event::End(Tag::Paragraph)).unwrap();
+    assert!(printer.push_event(Event::Start(Tag::Paragraph)).is_err());
+    assert_eq!(printer.diagnostics()[0].rule, "time-budget");
+
+    let token = CancellationToken::new();
+    let mut printer = PrettyPrinter::default();
+    printer.set_cancellation_token(token.clone());
+    thread::spawn(move || token.cancel()).join().unwrap();
+    assert!(printer.push_source(source).is_err());
+    assert_eq!(printer.into_inner(), "");
+}
+
+#[test]
+fn buffer_limit_caps_held_back_events() {
+    let table = format!("| a | b |\n| --- | --- |\n{}\nLorem", "| ipsum | x |\n".repeat(50));
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    let budget = Budget { buffer: Some(4096), ..Budget::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.set_budget(budget);
+    assert!(printer.push_source(&table).is_err());
+    assert_eq!(printer.diagnostics()[0].rule, "buffer-limit");
+
+    // Buffers are released once their events are printed.
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.set_budget(budget);
+    printer.push_source(&"| a | b |\n| --- | --- |\n| ipsum | x |\n\n".repeat(50)).unwrap();
+
+    let notes = (0..100).map(|index| format!("[^{0}]: Note {0}\n", index)).collect::<String>();
+    let options = Options {
+        footnotes: Some(FootnoteOptions { placement: FootnotePlacement::DocumentEnd, ..FootnoteOptions::default() }),
+        ..Options::default()
+    };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.set_budget(budget);
+    assert!(printer.push_source(&notes).is_err());
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn printing_is_traced() {
+    use std::sync::{Arc, Mutex};
+    use tracing::{span, Event, Metadata, Subscriber};
+
+    /// Collects the messages of all events.
+    struct Messages(Arc<Mutex<Vec<String>>>);
+
+    impl Subscriber for Messages {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn new_span(&self, _span: &span::Attributes) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _span: &span::Id, _values: &span::Record) {}
+
+        fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+        fn event(&self, event: &Event) {
+            let mut message = format!("{} {}", event.metadata().level(), event.metadata().name());
+            for field in event.fields() {
+                message.push(' ');
+                message.push_str(field.name());
+            }
+            self.0.lock().unwrap().push(message);
+        }
+
+        fn enter(&self, _span: &span::Id) {}
+
+        fn exit(&self, _span: &span::Id) {}
+    }
+
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let output = tracing::subscriber::with_default(Messages(messages.clone()), || prettify("Lorem\n\n- ipsum"));
+    assert_eq!(output, "Lorem\n\n- ipsum");
+    let messages = messages.lock().unwrap();
+    assert_eq!(messages.iter().filter(|message| message.starts_with("TRACE")).count(), 8);
+    let debug = messages.iter().filter(|message| message.starts_with("DEBUG")).collect::<Vec<_>>();
+    assert_eq!(debug.len(), 4);
+    assert!(debug[0].ends_with("message block events elapsed_us"));
+    assert!(debug[3].ends_with("message events elapsed_us"));
+}
+
+#[test]
+fn alternate_display_prints_a_single_paragraph() {
+    let source = "# Title\n\n> Lorem\n> ipsum\\\n> dolor\n\n```rust\nfn main() {}\nlet x = 1;\n```\n\n<div>\nhtml\n</div>\n\n---\n\n1. sit\n2. *amet*\n\n| a | b |\n| --- | --- |\n| c | d |";
+    assert_eq!(format!("{:#}", PrettyDisplay(source)), "Title Lorem ipsum dolor `fn main() {} let x = 1;` sit *amet* | a | b | | --- | --- | | c | d |");
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    assert_eq!(format!("{:#}", PrettyDisplay::with(source, options.clone())),
+               "Title Lorem ipsum dolor `fn main() {} let x = 1;` sit *amet* a b c d");
+    assert_eq!(format!("{:#}", PrettyDisplay::with("\\# not a heading\n\n| `a  b` | c |\n| --- | --- |", options)),
+               "\\# not a heading `a b` c");
+}
+
+#[test]
+fn display_width_wraps_prose() {
+    let source = "# A heading that is longer than the width\n\n\
+                  > Lorem ipsum dolor sit amet, `consectetur adipiscing` elit sed\n\
+                  > eiusmod 1. tempor <span class=\"a b\">incididunt</span>\n\n\
+                  1. incididunt ut labore et dolore magna aliqua\n\n\
+                  ```\ncode that is longer than the width\n```";
+    let expected = "# A heading that is longer than the width\n\n\
+                    > Lorem ipsum dolor\n\
+                    > sit amet,\n\
+                    > `consectetur adipiscing`\n\
+                    > elit sed eiusmod\n\
+                    > 1\\. tempor\n\
+                    > <span class=\"a b\">incididunt</span>\n\n\
+                    1. incididunt ut\n   labore et dolore\n   magna aliqua\n\n\
+                    ```\ncode that is longer than the width\n```";
+    let output = format!("{:20}", PrettyDisplay(source));
+    assert_eq!(output, expected);
+    let options = Options { wrap: Some(20), ..Options::default() };
+    assert_eq!(prettify_with_options(&output, &options), expected);
+}
+
+#[test]
+fn comments_keep_their_exact_prefix() {
+    let prefix = PrettyPrinter::detect_prefix("//!Lorem\n//!\n//!* ipsum".lines()).unwrap();
+    assert_eq!((prefix.marker.as_str(), prefix.space, prefix.trailing_space), ("//!", false, false));
+    assert_eq!(prettify_comment("//!Lorem\n//!\n//!* ipsum", &Options::default()).unwrap(), "//!Lorem\n//!\n//!- ipsum");
+
+    let options = Options { wrap: Some(20), ..Options::default() };
+    let comment = "\t# Lorem __ipsum__ dolor sit amet\n\t# \n\t# 1) consectetur\n";
+    assert_eq!(prettify_comment(comment, &options).unwrap(),
+               "\t# Lorem **ipsum**\n\t# dolor sit amet\n\t# \n\t# 1. consectetur\n");
+    assert_eq!(prettify_comment("/// Lorem\n// ipsum", &options), None);
+}
+
+#[test]
+fn breaks_in_headings_stay_on_one_line() {
+    let events = vec![
+        Event::Start(Tag::Header(2)),
+        Event::Text("Lorem".into()),
+        Event::SoftBreak,
+        Event::Text("ipsum\ndolor".into()),
+        Event::HardBreak,
+        Event::Start(Tag::Code),
+        Event::Text("sit\namet".into()),
+        Event::End(Tag::Code),
+        Event::End(Tag::Header(2)),
+        Event::Start(Tag::Paragraph),
+        Event::Text("consectetur".into()),
+        Event::End(Tag::Paragraph)
+    ];
+    let mut printer = PrettyPrinter::default();
+    printer.push_events(events.clone()).unwrap();
+    assert_eq!(printer.into_inner(), "## Lorem ipsum dolor `sit amet`\n\nconsectetur");
+
+    let options = Options { heading_breaks: HeadingBreakStyle::Html, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_events(events).unwrap();
+    assert_eq!(printer.into_inner(), "## Lorem ipsum dolor<br>`sit amet`\n\nconsectetur");
+}
+
+#[test]
+fn link_titles_stay_inside_their_block() {
+    let source = "- [a](u \"Lorem\n  ipsum \n    dolor\") ![b][c]\n\n[c]: p.png 'sit'";
+    assert_eq!(prettify(source), "- [a](u \"Lorem ipsum dolor\") ![b](p.png \"sit\")");
+
+    let options = Options {
+        references: Some(ReferenceOptions::default()),
+        titles: TitleOptions { drop_all: true, ..TitleOptions::default() },
+        ..Options::default()
+    };
+    assert_eq!(prettify_with_options(source, &options), "- [a](u) ![b][c]\n\n[c]: p.png");
+}
+
+#[test]
+fn empty_constructs_follow_their_policy() {
+    let link = || Tag::Link("u&v".into(), "".into());
+    let events = vec![
+        Event::Start(Tag::Paragraph),
+        Event::Text("a ".into()),
+        Event::Start(Tag::Strong),
+        Event::End(Tag::Strong),
+        Event::Text(" b ".into()),
+        Event::Start(link()),
+        Event::End(link()),
+        Event::Start(Tag::Emphasis),
+        Event::Text("c".into()),
+        Event::End(Tag::Emphasis),
+        Event::End(Tag::Paragraph),
+        Event::Start(Tag::List(Some(1))),
+        Event::Start(Tag::Item),
+        Event::End(Tag::Item),
+        Event::Start(Tag::Item),
+        Event::Start(Tag::Code),
+        Event::End(Tag::Code),
+        Event::Text("d".into()),
+        Event::End(Tag::Item),
+        Event::End(Tag::List(Some(1)))
+    ];
+    let print = |empty_constructs| {
+        let options = Options { empty_constructs, ..Options::default() };
+        let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+        let result = printer.push_events(events.clone());
+        let rules = printer.diagnostics().iter().map(|diagnostic| diagnostic.rule).collect::<Vec<_>>();
+        (result.is_ok(), rules, printer.into_inner())
+    };
+    assert_eq!(print(EmptyConstructPolicy::Preserve), (true, vec![], "a **** b [](u&v)*c*\n\n1.\n\n2. ``d".to_string()));
+    assert_eq!(print(EmptyConstructPolicy::Drop), (true, vec![], "a  b *c*\n\n1. d".to_string()));
+    assert_eq!(print(EmptyConstructPolicy::Html),
+               (true, vec![], "a <strong></strong> b <a href=\"u&amp;v\"></a>*c*\n\n1.\n\n2. <code></code>d".to_string()));
+    assert_eq!(print(EmptyConstructPolicy::Error), (false, vec!["empty-construct"], "a ".to_string()));
+}
+
+#[test]
+fn reports_are_assembled_from_iterators() {
+    let findings = [("unsafe block", 3), ("unwrap", 12)];
+    let report = Report::new()
+        .heading(9, "Audit | 2024")
+        .ordered_list(findings.iter().map(|&(name, _)| name))
+        .table(vec!["finding", "count", "note"], findings.iter().map(|&(name, count)| vec![name.to_string(), count.to_string()]))
+        .code_block("sh", "cargo audit");
+    let options = Options { tables: Some(TableOptions { padding: false, ..TableOptions::default() }), ..Options::default() };
+    assert_eq!(report.render(&options), "###### Audit | 2024\n\n\
+                                         1. unsafe block\n\n\
+                                         2. unwrap\n\n\
+                                         | finding | count | note |\n\
+                                         | ------------ | ----- | ---- |\n\
+                                         | unsafe block | 3 |  |\n\
+                                         | unwrap | 12 |  |\n\n\
+                                         ```sh\ncargo audit\n```");
+    assert_eq!(table_from_iter(Vec::<String>::new(), Vec::<Vec<String>>::new()).len(), 4);
+}
+
+#[test]
+fn changelog_entries_are_inserted_under_unreleased() {
+    let changelog = "# Changelog\n\n## [Unreleased]\n\n### Fixed\n\n- Lorem\n\n### Added\n\n- Ipsum\n\n## [1.0.0]\n\n### Fixed\n\n- Dolor\n";
+    let options = Options::default();
+    let fixed = add_changelog_entries(changelog, "fixed", &["Sit\namet"], &options);
+    assert_eq!(fixed, "# Changelog\n\n## [Unreleased]\n\n### Fixed\n\n- Lorem\n\n- Sit amet\n\n\
+                       ### Added\n\n- Ipsum\n\n## [1.0.0]\n\n### Fixed\n\n- Dolor");
+    let removed = add_changelog_entries(changelog, "Removed", &["Consectetur"], &options);
+    assert!(removed.contains("- Ipsum\n\n### Removed\n\n- Consectetur\n\n## [1.0.0]"));
+    assert_eq!(add_changelog_entries("# Changelog\n", "Added", &["Lorem"], &options),
+               "# Changelog\n\n## [Unreleased]\n\n### Added\n\n- Lorem");
+    assert_eq!(release_changelog("# Changelog\n\n## 1.0.0\n", "1.1.0", None, &options),
+               "# Changelog\n\n## Unreleased\n\n## 1.1.0\n\n## 1.0.0");
+}
+
+#[test]
+fn badges_are_edited_on_a_single_line() {
+    let options = Options { wrap: Some(10), ..Options::default() };
+    let add = |badges: &mut Vec<Badge>| badges.push(Badge::new("CI", "ci.svg", Some("ci")));
+    assert_eq!(edit_badges("# Lorem", &options, add), "# Lorem\n\n[![CI](ci.svg)](ci)");
+    assert_eq!(edit_badges("# Lorem\n\nIpsum dolor sit", &options, add),
+               "# Lorem\n\n[![CI](ci.svg)](ci)\n\nIpsum\ndolor sit");
+    assert_eq!(edit_badges("<p>Lorem</p>\n", &Options::default(), add), "[![CI](ci.svg)](ci)\n\n<p>Lorem</p>");
+
+    let readme = "# Lorem\n\n[![CI](ci.svg)](ci) ![Docs](docs.svg)\n\n## Ipsum\n\n![Dolor](dolor.svg)";
+    assert_eq!(find_badges(readme, &options).len(), 2);
+    assert_eq!(edit_badges(readme, &options, |badges| badges.retain(|badge| badge.link.is_none())),
+               "# Lorem\n\n![Docs](docs.svg)\n\n## Ipsum\n\n![Dolor](dolor.svg)");
+    assert_eq!(edit_badges(readme, &options, Vec::clear), "# Lorem\n\n## Ipsum\n\n![Dolor](dolor.svg)");
+
+    assert!(find_badges("![Lorem](lorem.svg) ipsum", &options).is_empty());
+    assert!(find_badges("[![Lorem](lorem.svg) ipsum](dolor)", &options).is_empty());
+}
+
+#[test]
+fn lists_after_sort_directives_are_sorted() {
+    let source = "<!-- prettify: sort -->\n\n- zeta\n  - nested\n- [Alpha](a)\n\n  More\n- beta\n\n\
+                  <!-- prettify: sort -->\n\nLorem\n\n- d\n- c";
+    let options = Options { sort_lists: true, ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options),
+               "<!-- prettify: sort -->\n\n- [Alpha](a)\n\n  More\n\n- beta\n\n- zeta\n\n  - nested\n\n\
+                <!-- prettify: sort -->\n\nLorem\n\n- d\n\n- c");
+    assert!(prettify(source).starts_with("<!-- prettify: sort -->\n\n- zeta"));
+}
+
+#[test]
+fn tables_are_sorted_and_edited() {
+    let options = Options { tables: Some(TableOptions { padding: false, ..TableOptions::default() }), ..Options::default() };
+    let source = "- Sizes:\n\n  | name | size |\n  | --- | --- |\n  | *b* | 10 |\n  | a | 9 |\n  | c |\n\nLorem [ipsum]\n\n[ipsum]: http://example.com";
+    let output = edit_tables(source, &options, |table| {
+        table.sort_by_column(1);
+        table.insert_column(3, Cell::new("note"), Alignment::Center, Some(Cell::new("first")));
+    });
+    assert_eq!(output, "- Sizes:\n\n  | name | size | note |\n  | ---- | ---- | :---: |\n  \
+                        | a | 9 | first |\n  | *b* | 10 |  |\n  | c |  |  |\n\nLorem [ipsum](http://example.com)");
+
+    let source = "| n | x |\n| - | - |\n| 2 | a |\n| NaN | b |\n| 10 | c |\n| inf | d |\n| 1 | e |";
+    let output = edit_tables(source, &options, |table| table.sort_by_column(0));
+    assert_eq!(output, "| n | x |\n| --- | --- |\n| 1 | e |\n| 2 | a |\n| 10 | c |\n| inf | d |\n| NaN | b |");
+
+    let mut buffer = Parser::new_ext("| a | b |\n| - | - |\n| 1 | 2 |\n| 3 | 4 |", OPTION_ENABLE_TABLES).collect::<EventBuffer>();
+    buffer.edit_tables(|table| {
+        table.remove_column(0);
+        table.remove_column(5);
+        table.sort_rows_by(|a, b| b[0].text().cmp(&a[0].text()));
+    });
+    let mut printer = PrettyPrinter::default();
+    buffer.replay(&mut printer).unwrap();
+    assert_eq!(printer.into_inner(), "| b   |\n| --- |\n| 4   |\n| 2   |");
+}
+
+#[test]
+fn admonitions_are_converted_between_syntaxes() {
+    let convert = |source: AdmonitionStyle, target: AdmonitionStyle, text: &str| {
+        let options = Options { admonitions: Some(AdmonitionOptions { source, target }), wrap: Some(20), ..Options::default() };
+        let output = prettify_with_options(text, &options);
+        assert_eq!(prettify_with_options(&output, &options), output);
+        output
+    };
+    let mkdocs = "!!! danger \"Data loss\"\n    Lorem [ipsum] dolor sit amet.\n\n    - consectetur\n\nAfter\n\n[ipsum]: http://example.com";
+    assert_eq!(convert(AdmonitionStyle::MkDocs, AdmonitionStyle::BoldLabel, mkdocs),
+               "> **Danger** **Data\n> loss**\n>\n> Lorem\n> [ipsum](http://example.com)\n> dolor sit amet.\n>\n> - consectetur\n\nAfter");
+    assert_eq!(convert(AdmonitionStyle::GitHub, AdmonitionStyle::MkDocs, "> [!Note]\n> Lorem ipsum dolor sit amet.\n\n> [!]\n> Lorem"),
+               "!!! note\n    Lorem ipsum\n    dolor sit amet.\n\n> [!] Lorem");
+    assert_eq!(convert(AdmonitionStyle::BoldLabel, AdmonitionStyle::GitHub, "> **Tip:** Lorem\n\n> **Lorem** ipsum"),
+               "> [!TIP]\n> Lorem\n\n> **Lorem** ipsum");
+    // Content that is not indented after a lazy continuation line belongs
+    // to the paragraph, which is left alone.
+    assert_eq!(convert(AdmonitionStyle::MkDocs, AdmonitionStyle::GitHub, "!!! note\n    Lorem\nipsum"), "!!! note Lorem ipsum");
+}
+
+#[test]
+fn front_matter_is_preserved() {
+    let options = Options { front_matter: FrontMatterPolicy::Preserve, ..Options::default() };
+    assert_eq!(prettify_with_options("---\ntitle: *Lorem*\ntags: [a, b]\n...\n# Ipsum", &options),
+               "---\ntitle: *Lorem*\ntags: [a, b]\n...\n\n# Ipsum");
+    assert_eq!(prettify_with_options("+++\ntitle = \"Lorem\"\n+++\n\nIpsum _dolor_", &options),
+               "+++\ntitle = \"Lorem\"\n+++\n\nIpsum *dolor*");
+    assert_eq!(prettify_with_options("---\nLorem", &options), "---\n\nLorem");
+    assert_eq!(prettify("---\ntitle: Lorem\n---\nIpsum"), "---\n\n## title: Lorem\n\nIpsum");
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn yaml_front_matter_is_canonicalized() {
+    let options = Options { front_matter: FrontMatterPolicy::Canonical, ..Options::default() };
+    let source = "---\n# The title\ntitle:   'Lorem'\nauthor: {name: Ipsum, email: \"ipsum@example.com\"}\nid: \"007\"\n---\n\nDolor";
+    assert_eq!(prettify_with_options(source, &options),
+               "---\nauthor:\n  email: ipsum@example.com\n  name: Ipsum\nid: '007'\ntitle: Lorem\n---\n\nDolor");
+    assert_eq!(prettify_with_options("+++\nb = 1\na = 2\n+++", &options), "+++\nb = 1\na = 2\n+++");
+
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("---\ntitle: [Lorem\n---\nIpsum").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "---\ntitle: [Lorem\n---\n\nIpsum");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].rule, "front-matter");
+    assert!(diagnostics[0].offset.unwrap() > 4);
+}
+
+#[cfg(not(feature = "yaml"))]
+#[test]
+fn canonical_front_matter_is_reported_without_yaml() {
+    let options = Options { front_matter: FrontMatterPolicy::Canonical, ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source("---\ntitle:   Lorem\n---\nIpsum").unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), "---\ntitle:   Lorem\n---\n\nIpsum");
+    assert_eq!(diagnostics[0].to_string(),
+               "warning[front-matter] at byte 0: front matter can only be canonicalized with the `yaml` feature");
+}
+
+#[test]
+fn shortcodes_are_preserved() {
+    let shortcodes = vec![
+        ("{{<".to_string(), ">}}".to_string()),
+        ("{{%".to_string(), "%}}".to_string()),
+        ("{%".to_string(), "%}".to_string())
+    ];
+    let options = Options { shortcodes, wrap: Some(20), ..Options::default() };
+    let source = "{{< figure\n    src=\"a.png\"  title=\"*A*\" />}}\n\nLorem  _ipsum_ {{< ref \"dolor-sit.md\" >}}\n\n\
+                  {% raw %}\n{{ x }}  *y*\n\n- z\n{% endraw %}\n\n{{% note %}}\n  *Dolor*\n{{% /note %}}\n\n\
+                  {{< youtube id >}}\n* sit\n\n{{< tip >}} amet";
+    assert_eq!(prettify_with_options(source, &options),
+               "{{< figure\n    src=\"a.png\"  title=\"*A*\" />}}\n\nLorem  *ipsum*\n{{< ref \"dolor-sit.md\" >}}\n\n\
+                {% raw %}\n{{ x }}  *y*\n\n- z\n{% endraw %}\n\n{{% note %}}\n  *Dolor*\n{{% /note %}}\n\n\
+                {{< youtube id >}}\n\n- sit\n\n{{< tip >}} amet");
+    assert_eq!(prettify("{% raw %}\n{{ x }}  *y*\n{% endraw %}"), "{% raw %} {{ x }}  *y* {% endraw %}");
+}
+
+#[test]
+fn directives_override_options() {
+    let options = Options { tables: Some(TableOptions { max_width: Some(12), ..TableOptions::default() }), ..Options::default() };
+    let source = "<!-- prettify: wrap=6 max-width=off -->\n\n| a | b |\n| - | - |\n| lorem | ipsum |\n\nLorem ipsum\n\n\
+                  <!-- prettify: wrap=off -->\n\n| a | b |\n| - | - |\n| lorem | ipsum |\n\nLorem ipsum\n\n\
+                  <!-- prettify: wrap=lorem -->\n\nLorem ipsum";
+    assert_eq!(prettify_with_options(source, &options),
+               "<!-- prettify: wrap=6 max-width=off -->\n\n| a     | b     |\n| ----- | ----- |\n| lorem | ipsum |\n\nLorem\nipsum\n\n\
+                <!-- prettify: wrap=off -->\n\n| a     | b     |\n| ----- | ----- |\n| lorem | ipsum |\n\nLorem ipsum\n\n\
+                <!-- prettify: wrap=lorem -->\n\nLorem ipsum");
+
+    let documents = vec![("", "<!-- prettify: max-width=off -->\n\n| a | b |\n| - | - |\n| lorem | ipsum |"),
+                         ("", "| a | b |\n| - | - |\n| lorem | ipsum |")];
+    let output = concat_documents(documents, DocumentSeparator::Rule, &options);
+    assert!(output.ends_with("---\n\n| a | b |\n| --- | --- |\n| lorem | ipsum |"));
+}
+
+#[test]
+fn sections_are_printed_with_their_profiles() {
+    let options = Options {
+        wrap: Some(12),
+        profiles: vec![
+            SectionProfile { selector: "Guide/*".to_string(), options: Options { list_indent: Some(4), ..Options::default() } },
+            SectionProfile { selector: "reference".to_string(), options: Options { wrap: Some(6), ..Options::default() } }
+        ],
+        ..Options::default()
+    };
+    let source = "Lorem ipsum dolor\n\n# Guide\n\nLorem ipsum dolor\n\n## Install\n\n- Lorem ipsum dolor\n\n\
+                  # Reference\n\nLorem ipsum\n\n## Nested\n\nLorem ipsum\n\n# Lorem\n\nLorem ipsum dolor";
+    assert_eq!(prettify_with_options(source, &options),
+               "Lorem ipsum\ndolor\n\n# Guide\n\nLorem ipsum\ndolor\n\n## Install\n\n-   Lorem ipsum dolor\n\n\
+                # Reference\n\nLorem\nipsum\n\n## Nested\n\nLorem\nipsum\n\n# Lorem\n\nLorem ipsum\ndolor");
+
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options.clone());
+    printer.push_source("# Reference\n\nLorem ipsum").unwrap();
+    let checkpoint = printer.checkpoint();
+    let first = printer.into_inner();
+    let mut printer = PrettyPrinter::resume(String::new(), options, checkpoint);
+    printer.push_source("Dolor sit").unwrap();
+    assert_eq!(first + &printer.into_inner(), "# Reference\n\nLorem\nipsum\n\nDolor\nsit");
+}
+
+#[test]
+fn heading_path_is_tracked() {
+    let runs = Rc::new(RefCell::new(Vec::new()));
+    let collected = runs.clone();
+    let options = Options { max_list_depth: Some(1), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.set_text_inspector(move |run: &TextRun| {
+        collected.borrow_mut().push((run.text.to_string(), run.headings.join("/")));
+    });
+    printer.push_source("Lorem\n\n# Guide\n\n> # Quoted\n\n## Install\n\n- ipsum\n  - dolor\n\n# Reference").unwrap();
+    assert_eq!(printer.heading_path(), vec!["Reference"]);
+    assert_eq!(printer.diagnostics()[0].section, vec!["Guide", "Install"]);
+    assert_eq!(printer.diagnostics()[0].to_string(),
+               "warning[list-depth] at byte 50 in Guide > Install: list nested deeper than 1 levels was flattened");
+    assert_eq!(*runs.borrow(), vec![
+        ("Lorem".to_string(), "".to_string()),
+        ("Guide".to_string(), "Guide".to_string()),
+        ("Quoted".to_string(), "Guide".to_string()),
+        ("Install".to_string(), "Guide/Install".to_string()),
+        ("ipsum".to_string(), "Guide/Install".to_string()),
+        ("dolor".to_string(), "Guide/Install".to_string()),
+        ("Reference".to_string(), "Reference".to_string())
+    ]);
+}
+
+#[test]
+fn broken_anchors_are_reported() {
+    let options = Options { broken_anchors: true, tables: Some(TableOptions::default()), ..Options::default() };
+    let source = "# Getting *started*\n\n[a](#getting-started) [b](#Getting-Started) [c](#usage) [d](#install-1)\n\n\
+                  ## Install\n\n<a name=\"usage\"></a>\n\n| [e](#top) | [f](#) |\n| - | - |\n\n[g](#install) [h][top]\n\n\
+                  [top]: #top";
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source(source).unwrap();
+    let diagnostics = printer.take_diagnostics();
+    let broken = diagnostics.iter().map(|diagnostic| (diagnostic.message.as_str(), diagnostic.section.join("/"))).collect::<Vec<_>>();
+    assert_eq!(broken, vec![
+        ("link to `#install-1` matches no heading or anchor", "Getting started".to_string()),
+        ("link to `#top` matches no heading or anchor", "Getting started/Install".to_string()),
+        ("link to `#top` matches no heading or anchor", "Getting started/Install".to_string())
+    ]);
+    assert_eq!(diagnostics[0].offset, Some(77));
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn link_graph_is_collected_from_directory_tree() {
+    use std::fs;
+    use super::{format_tree_with_links, TreeOptions};
+
+    let dir = ::std::env::temp_dir().join(format!("prettify-cmark-links-{}", ::std::process::id()));
+    fs::create_dir_all(dir.join("docs/images")).unwrap();
+    fs::write(dir.join("README.md"), "[Guide](docs/guide.md#install) [Setup](./docs/guide.md#setup)\n\n[Docs](docs/) [Home](#top)").unwrap();
+    fs::write(dir.join("docs/guide.md"), "# Install\n\n![Logo](images/logo%20dark.png) [Up](../README.md?plain)\n\n\
+                                          [Away](../../other.md) <https://example.com> [Mail](mailto:a@example.com)").unwrap();
+    fs::write(dir.join("docs/images/logo dark.png"), "").unwrap();
+
+    let (reports, graph) = format_tree_with_links(&dir, &TreeOptions::default()).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(reports.len(), 2);
+    assert_eq!(graph.files.keys().collect::<Vec<_>>(), vec!["README.md", "docs/guide.md"]);
+    assert_eq!(graph.assets.iter().collect::<Vec<_>>(), vec!["docs/images/logo dark.png"]);
+    let guide = &graph.files["docs/guide.md"];
+    assert_eq!(guide.anchors, vec!["install"]);
+    let targets = guide.links.iter().map(|link| link.target.as_str()).collect::<Vec<_>>();
+    assert_eq!(targets, vec!["docs/images/logo dark.png", "README.md", "../other.md"]);
+    let dead = graph.dead_links().into_iter().map(|(path, link)| (path, link.destination.as_str(), link.offset)).collect::<Vec<_>>();
+    assert_eq!(dead, vec![("README.md", "./docs/guide.md#setup", 31), ("docs/guide.md", "../../other.md", 69)]);
+}
+
+#[test]
+fn documents_are_truncated() {
+    use super::{prettify_truncated, Truncation};
+
+    let source = "# Title\n\n> Lorem **ipsum**\n> dolor\n>\n> sit amet\n\n```\ncode\n```\n\n[x]: http://example.com";
+    let truncation = Truncation { ellipsis: "[More](/post)".to_string(), ..Truncation::characters(12) };
+    assert_eq!(prettify_truncated(source, &truncation, &Options::default()),
+               "# Title\n\n> Lorem **ipsum** dolor\n\n[More](/post)");
+    assert_eq!(prettify_truncated(source, &Truncation::words(1), &Options::default()), "# Title\n\n…");
+    assert_eq!(prettify_truncated("Lorem [ipsum][x]\n\n[x]: http://example.com", &Truncation::words(1), &Options::default()),
+               prettify("Lorem [ipsum][x]\n\n[x]: http://example.com"));
+}
+
+#[test]
+fn documents_are_rendered_as_plain_text() {
+    use super::{to_plain_text, PlainTextLinks};
+
+    let source = "> Lorem <b>ipsum</b>\\\n> ![dolor](sit.png)\n\n```\n  code\n```\n\nSee <http://example.com> and [amet](http://amet.com)[^1].\n\n[^1]: Note";
+    let options = Options { footnotes: Some(Default::default()), ..Options::default() };
+    assert_eq!(to_plain_text(source, PlainTextLinks::TextOnly, &options),
+               "Lorem ipsum\ndolor\n\n  code\n\nSee http://example.com and amet.\n\nNote");
+    assert_eq!(to_plain_text(source, PlainTextLinks::WithUrl, &options),
+               "Lorem ipsum\ndolor\n\n  code\n\nSee http://example.com and amet (http://amet.com).\n\nNote");
+}
+
+#[test]
+fn summaries_are_extracted() {
+    use super::{summary, Summary};
+
+    let source = "---\n\n> Quote\n\nLorem __ipsum__\ndolor.\n\nSit <!--  MORE  --> amet.";
+    assert_eq!(summary(source, &Options::default()),
+               Some(Summary { markdown: "---\n\n> Quote\n\nLorem **ipsum** dolor.\n\nSit".to_string(),
+                              text: "Quote\n\nLorem ipsum dolor.\n\nSit".to_string(), more: true }));
+    assert_eq!(summary("# Lorem\n\n> Ipsum\n\nDolor", &Options::default()).map(|summary| summary.markdown), Some("Dolor".to_string()));
+    assert_eq!(summary("# Lorem\n\n<!-- more -->", &Options::default()).map(|summary| summary.more), Some(true));
+    assert_eq!(summary("<!-- more -->\n\nLorem", &Options::default()), None);
+    assert_eq!(summary("# Lorem", &Options::default()), None);
+}
+
+#[test]
+fn non_breaking_spaces_are_printed_as_configured() {
+    use super::{NonBreakingSpaces, Normalization};
+
+    let source = "10&nbsp;kg and 5&#160;m and 3\u{a0}s";
+    let options = Options { non_breaking_spaces: NonBreakingSpaces::Entity, entities: EntityPolicy::Preserve, ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "10&nbsp;kg and 5&nbsp;m and 3&nbsp;s");
+    let options = Options { non_breaking_spaces: NonBreakingSpaces::Character, entities: EntityPolicy::Encode, ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "10\u{a0}kg and 5\u{a0}m and 3\u{a0}s");
+    let options = Options {
+        non_breaking_spaces: NonBreakingSpaces::Character,
+        normalization: Some(Normalization::Nfkc),
+        wrap: Some(4),
+        ..Options::default()
+    };
+    assert_eq!(prettify_with_options("Lorem 10&nbsp;kg", &options), "Lorem\n10\u{a0}kg");
+
+    let options = Options { tables: Some(TableOptions::default()), ..Options::default() };
+    assert_eq!(prettify_with_options("| a | b |\n|---|---|\n| &nbsp; | c |", &options), "| a   | b   |\n| --- | --- |\n| \u{a0}   | c   |");
+}
+
+#[test]
+fn tabs_are_expanded_in_prose() {
+    let options = Options { expand_tabs: Some(4), ..Options::default() };
+    assert_eq!(prettify_with_options("a\tb\tc\n\n```\nd\te\n```", &options), "a   b   c\n\n```\nd\te\n```");
+    let options = Options { expand_tabs: Some(8), wrap: Some(10), ..Options::default() };
+    assert_eq!(prettify_with_options("ab\tcd ef", &options), "ab      cd\nef");
+
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", Options { wrap: Some(6), ..Options::default() });
+    printer.push_events(Parser::new("a b c").map(|event| match event {
+        Event::Text(text) => Event::Text(text.replacen(' ', "\t", 1).into()),
+        event => event
+    })).unwrap();
+    assert_eq!(printer.into_inner(), "a\tb\nc");
+}
+
+#[test]
+fn invisible_characters_are_handled() {
+    use super::InvisibleCharacterPolicy;
+
+    let source = "# Lorem\n\nip\u{200b}sum &#x202e;dolor `sit\u{200b}`";
+    let options = Options { invisible_characters: Some(InvisibleCharacterPolicy::Strip), ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "# Lorem\n\nipsum dolor `sit\u{200b}`");
+    let options = Options { invisible_characters: Some(InvisibleCharacterPolicy::Escape), ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "# Lorem\n\nip&#8203;sum &#8238;dolor `sit\u{200b}`");
+
+    let options = Options { invisible_characters: Some(InvisibleCharacterPolicy::Report), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source(source).unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), source.replace("&#x202e;", "\u{202e}"));
+    let reported = diagnostics.iter().map(|diagnostic| (diagnostic.rule, diagnostic.message.as_str(), diagnostic.offset)).collect::<Vec<_>>();
+    assert_eq!(reported, vec![
+        ("invisible-character", "text contains invisible character U+200B", Some(11)),
+        ("invisible-character", "text contains invisible character U+202E", Some(18))
+    ]);
+}
+
+#[test]
+fn documents_are_checked_without_formatting() {
+    use super::{check, HeadingLimit};
+
+    let options = Options {
+        wrap: Some(12),
+        heading_limit: Some(HeadingLimit { max_level: 1, demote: true }),
+        tables: Some(TableOptions::default()),
+        ..Options::default()
+    };
+    let source = "## Lorem ipsum dolor\n\n- Lorem ipsum dolor\n  http://example.com/lorem\n\n\
+                  | Lorem ipsum | dolor sit |\n| - | - |\n\n<p>Lorem ipsum dolor</p>\n\n    Lorem ipsum dolor";
+    let reported = check(source, &options).into_iter().map(|diagnostic| (diagnostic.rule, diagnostic.offset)).collect::<Vec<_>>();
+    assert_eq!(reported, vec![("heading-depth", Some(0)), ("line-width", Some(22))]);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn diagnostics_are_serialized_to_sarif() {
+    use super::{check, diagnostics_to_sarif, Diagnostic, FileDiagnostics, HeadingLimit};
+
+    let options = Options { heading_limit: Some(HeadingLimit { max_level: 1, demote: false }), ..Options::default() };
+    let source = "# Guide\n\n## Install";
+    let files = vec![
+        FileDiagnostics { path: PathBuf::from("docs").join("guide.md"), source: source.into(), diagnostics: check(source, &options) },
+        FileDiagnostics { path: PathBuf::from("README.md"), source: String::new(), diagnostics: Vec::new() }
+    ];
+    let sarif = serde_json::from_str::<serde_json::Value>(&diagnostics_to_sarif(&files)).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    assert_eq!(sarif["runs"][0]["tool"]["driver"]["rules"], serde_json::json!([{ "id": "heading-depth" }]));
+    assert_eq!(sarif["runs"][0]["results"], serde_json::json!([{
+        "ruleId": "heading-depth",
+        "level": "warning",
+        "message": { "text": "heading of level 2 is deeper than the maximum of 1" },
+        "locations": [{ "physicalLocation": { "artifactLocation": { "uri": "docs/guide.md" }, "region": { "byteOffset": 9, "startLine": 3, "startColumn": 1 } } }],
+        "properties": { "section": ["Guide"] }
+    }]));
+
+    let diagnostic = Diagnostic { severity: Severity::Error, rule: "no-bare-urls", message: "Lorem".into(), offset: Some(14), section: Vec::new() };
+    let files = vec![FileDiagnostics { path: PathBuf::from("README.md"), source: "Ünïcode\n\nab cd".into(), diagnostics: vec![diagnostic] }];
+    let sarif = serde_json::from_str::<serde_json::Value>(&diagnostics_to_sarif(&files)).unwrap();
+    let region = &sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"];
+    assert_eq!(*region, serde_json::json!({ "byteOffset": 14, "startLine": 3, "startColumn": 4 }));
+}
+
+#[test]
+fn lints_report_during_printing() {
+    use super::{Lints, Severity};
+
+    let mut lints = Lints::builtin();
+    lints.set_severity("single-h1", Severity::Error);
+    lints.remove("no-skipped-heading-levels");
+    assert_eq!(lints.rules(), vec!["no-bare-urls", "single-h1", "first-h1"]);
+
+    let source = "# Lorem\n\n### Ipsum\n\nSee  http://example.com, <http://example.org>\nand `http://example.net`.\n\n# Dolor";
+    let mut printer = PrettyPrinter::default();
+    printer.set_lints(lints);
+    printer.push_source(source).unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), prettify(source));
+    let reported = diagnostics.iter().map(|diagnostic| (diagnostic.severity, diagnostic.rule, diagnostic.offset)).collect::<Vec<_>>();
+    assert_eq!(reported, vec![(Severity::Warning, "no-bare-urls", Some(25)), (Severity::Error, "single-h1", Some(93))]);
+
+    let mut printer = PrettyPrinter::default();
+    printer.set_lints(Lints::builtin());
+    printer.push_source("## Lorem\n\n#### Ipsum\n\n### Dolor").unwrap();
+    let messages = printer.take_diagnostics().into_iter().map(|diagnostic| diagnostic.message).collect::<Vec<_>>();
+    assert_eq!(messages, vec!["heading of level 4 follows a heading of level 2"]);
+
+    let mut printer = PrettyPrinter::default();
+    printer.set_lints(Lints::builtin());
+    printer.set_localizer(|message: &Message| match *message {
+        Message::BareUrl { url } => Some(format!("URL `{}` ohne Link", url)),
+        _ => None
+    });
+    printer.push_source("See http://example.com").unwrap();
+    assert_eq!(printer.take_diagnostics()[0].message, "URL `http://example.com` ohne Link");
+}
+
+#[test]
+fn heading_hierarchy_is_checked_and_fixed() {
+    use super::{HeadingHierarchy, Lints};
+
+    let source = "## Lorem\n\n# Ipsum\n\n#### Dolor\n\n> # Sit\n\n###### Amet";
+    let options = Options { heading_hierarchy: Some(HeadingHierarchy::default()), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_source(source).unwrap();
+    let diagnostics = printer.take_diagnostics();
+    assert_eq!(printer.into_inner(), source);
+    let reported = diagnostics.iter().map(|diagnostic| (diagnostic.rule, diagnostic.offset)).collect::<Vec<_>>();
+    assert_eq!(reported, vec![("first-h1", Some(10)), ("no-skipped-heading-levels", Some(19)), ("single-h1", Some(33)),
+                              ("no-skipped-heading-levels", Some(40))]);
+
+    let options = Options { heading_hierarchy: Some(HeadingHierarchy { fix: true }), ..Options::default() };
+    assert_eq!(prettify_with_options(source, &options), "## Lorem\n\n## Ipsum\n\n### Dolor\n\n> ## Sit\n\n### Amet");
+    assert_eq!(prettify_with_options("# A\n\n### B\n\n### C\n\n#### D\n\n## E", &options), "# A\n\n## B\n\n## C\n\n### D\n\n## E");
+
+    let options = Options { heading_hierarchy: Some(HeadingHierarchy::default()), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.set_lints(Lints::builtin());
+    printer.push_source(source).unwrap();
+    let rules = printer.take_diagnostics().into_iter().map(|diagnostic| diagnostic.rule).collect::<Vec<_>>();
+    assert_eq!(rules, vec!["first-h1", "no-skipped-heading-levels", "single-h1", "no-skipped-heading-levels"]);
+}
+
+#[test]
+fn unbalanced_end_events_do_not_panic() {
+    let mut printer = PrettyPrinter::default();
+    printer.push_event(Event::End(Tag::Paragraph)).unwrap();
+    printer.push_event(Event::Text("Lorem".into())).unwrap();
+    assert_eq!(printer.into_inner(), "Lorem");
+
+    let options = Options { max_list_depth: Some(1), ..Options::default() };
+    let mut printer = PrettyPrinter::new_with_options(String::new(), "", options);
+    printer.push_event(Event::End(Tag::List(None))).unwrap();
+    printer.push_event(Event::Text("Lorem".into())).unwrap();
+    assert_eq!(printer.into_inner(), "Lorem");
+}
@@ -0,0 +1,71 @@
+//! Instrumentation of the printer using `tracing`.
+//!
+//! This module is only available with the `tracing` feature enabled.
+//! Documents printed from source are wrapped in a `document` span, and
+//! counts and timings are reported as `DEBUG` events for each document
+//! and block, and as `TRACE` events for each event pushed into the
+//! printer.
+
+use std::time::Instant;
+
+use pulldown_cmark::Event;
+use tracing::span::EnteredSpan;
+
+use hooks::Block;
+
+/// Counts and timings of the events and blocks printed so far.
+#[derive(Debug, Default)]
+pub(crate) struct Tracer {
+    events: usize,
+    /// The start time and event count at the start of each open block.
+    blocks: Vec<(Instant, usize)>
+}
+
+impl Tracer {
+    /// Trace an event that is pushed into the printer.
+    pub fn event(&mut self, event: &Event) {
+        self.events += 1;
+        tracing::trace!(event = ?event, "pushing event");
+        match *event {
+            Event::Start(ref tag) if Block::from_tag(tag).is_some() => {
+                self.blocks.push((Instant::now(), self.events));
+            },
+            Event::End(ref tag) => {
+                if let (Some(block), Some((started, events))) = (Block::from_tag(tag), self.blocks.pop()) {
+                    tracing::debug!(
+                        block = ?block,
+                        events = self.events - events + 1,
+                        elapsed_us = started.elapsed().as_micros() as u64,
+                        "printed block");
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Enter the span of a document that is printed from source.
+    pub fn start_document(&self, bytes: usize) -> DocumentTrace {
+        DocumentTrace {
+            _span: tracing::debug_span!("document", bytes).entered(),
+            started: Instant::now(),
+            events: self.events
+        }
+    }
+}
+
+/// A document that is being printed from source.
+pub(crate) struct DocumentTrace {
+    _span: EnteredSpan,
+    started: Instant,
+    events: usize
+}
+
+impl DocumentTrace {
+    /// Report the events and time it took to print the document.
+    pub fn finish(self, tracer: &Tracer) {
+        tracing::debug!(
+            events = tracer.events - self.events,
+            elapsed_us = self.started.elapsed().as_micros() as u64,
+            "printed document");
+    }
+}
@@ -0,0 +1,327 @@
+//! Formatting of files and of all Markdown files in a directory tree.
+//!
+//! This module is only available with the `fs` feature enabled.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use links::LinkGraph;
+use options::Options;
+
+/// Options for formatting a directory tree.
+#[derive(Debug, Clone)]
+pub struct TreeOptions {
+    /// Options for formatting each file.
+    pub format: Options,
+    /// Extensions of the files that are formatted, without the leading dot.
+    pub extensions: Vec<String>,
+    /// Names of the ignore files that are read in every directory, such
+    /// as `.gitignore`. Their patterns apply to the directory they are
+    /// found in and to everything below it.
+    pub ignore_files: Vec<String>,
+    /// Additional patterns of files and directories to exclude, using the
+    /// same syntax as ignore files, relative to the root directory.
+    pub exclude: Vec<String>,
+    /// Write the formatted output back to files that changed, instead of
+    /// only reporting them.
+    pub write: bool,
+    /// Keep a copy of the original contents of every written file, with
+    /// `.bak` appended to its name.
+    pub backup: bool
+}
+
+impl Default for TreeOptions {
+    fn default() -> TreeOptions {
+        TreeOptions {
+            format: Options::default(),
+            extensions: vec!["md".to_string(), "markdown".to_string()],
+            ignore_files: vec![".gitignore".to_string()],
+            exclude: Vec::new(),
+            write: false,
+            backup: false
+        }
+    }
+}
+
+/// The result of formatting a single file in a directory tree.
+#[derive(Debug)]
+pub struct FileReport {
+    /// The path of the file, including the root directory.
+    pub path: PathBuf,
+    /// What happened to the file.
+    pub outcome: FileOutcome
+}
+
+/// What happened to a file when formatting a directory tree.
+#[derive(Debug)]
+pub enum FileOutcome {
+    /// The file is already formatted.
+    Unchanged,
+    /// Formatting changed the file. It has been written back if
+    /// `TreeOptions::write` is set.
+    Changed,
+    /// The file could not be read or written.
+    Failed(io::Error)
+}
+
+/// Formats all Markdown files in a directory tree, and reports for each
+/// of them whether it changed.
+///
+/// Directories are walked in sorted order. Files and directories that
+/// match a pattern in an ignore file or in `TreeOptions::exclude` are
+/// skipped, as are `.git` directories and symbolic links. Patterns follow
+/// the `.gitignore` syntax: `*`, `?`, `[...]` and `**` wildcards, `!`
+/// to negate a pattern, a trailing `/` to only match directories, and a
+/// leading or inner `/` to match relative to the directory of the ignore
+/// file instead of at any depth.
+///
+/// Errors for individual files are reported in their `FileReport`, while
+/// an error is only returned if a directory cannot be read.
+pub fn format_tree<P: AsRef<Path>>(root: P, options: &TreeOptions) -> io::Result<Vec<FileReport>> {
+    let mut reports = Vec::new();
+    walk_root(root.as_ref(), options, &mut reports, None)?;
+    Ok(reports)
+}
+
+/// Formats all Markdown files in a directory tree like `format_tree`, and
+/// collects the graph of relative links between them.
+///
+/// The graph contains the Markdown files that were read, and the other
+/// files of the tree that are not ignored as assets, so that dead links
+/// can be found using `LinkGraph::dead_links`.
+pub fn format_tree_with_links<P: AsRef<Path>>(root: P, options: &TreeOptions) -> io::Result<(Vec<FileReport>, LinkGraph)> {
+    let mut reports = Vec::new();
+    let mut graph = LinkGraph::new();
+    walk_root(root.as_ref(), options, &mut reports, Some(&mut graph))?;
+    Ok((reports, graph))
+}
+
+fn walk_root(root: &Path, options: &TreeOptions, reports: &mut Vec<FileReport>, graph: Option<&mut LinkGraph>) -> io::Result<()> {
+    let mut rules = options.exclude.iter().filter_map(|line| Rule::parse(line, &[])).collect();
+    walk(root, &mut Vec::new(), &mut rules, options, reports, graph)
+}
+
+fn walk(dir: &Path, base: &mut Vec<String>, rules: &mut Vec<Rule>, options: &TreeOptions,
+        reports: &mut Vec<FileReport>, mut graph: Option<&mut LinkGraph>) -> io::Result<()> {
+    let inherited = rules.len();
+    for name in &options.ignore_files {
+        match fs::read_to_string(dir.join(name)) {
+            Ok(content) => rules.extend(content.lines().filter_map(|line| Rule::parse(line, base))),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => {},
+            Err(err) => return Err(err)
+        }
+    }
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let file_type = entry.file_type()?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let is_dir = file_type.is_dir();
+        if file_type.is_symlink() || is_dir && name == ".git" {
+            continue;
+        }
+        base.push(name);
+        if !is_ignored(rules, base, is_dir) {
+            if is_dir {
+                walk(&entry.path(), base, rules, options, reports, graph.as_deref_mut())?;
+            } else if has_extension(&entry.path(), &options.extensions) {
+                let path = entry.path();
+                let outcome = match fs::read_to_string(&path) {
+                    Ok(source) => {
+                        if let Some(ref mut graph) = graph {
+                            graph.add_file(&base.join("/"), &source, &options.format);
+                        }
+                        format_file(&path, &source, options)
+                    },
+                    Err(err) => FileOutcome::Failed(err)
+                };
+                reports.push(FileReport { path, outcome });
+            } else if let Some(ref mut graph) = graph {
+                graph.assets.insert(base.join("/"));
+            }
+        }
+        base.pop();
+    }
+    rules.truncate(inherited);
+    Ok(())
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension().is_some_and(|extension| {
+        extensions.iter().any(|allowed| extension.eq_ignore_ascii_case(allowed.as_str()))
+    })
+}
+
+fn format_file(path: &Path, source: &str, options: &TreeOptions) -> FileOutcome {
+    let formatted = ::prettify_with_options(source, &options.format);
+    if formatted == source {
+        return FileOutcome::Unchanged;
+    }
+    if options.write {
+        if let Err(err) = replace_file(path, &formatted, options.backup) {
+            return FileOutcome::Failed(err);
+        }
+    }
+    FileOutcome::Changed
+}
+
+/// Formats a file and replaces its contents if they changed, returning
+/// whether they did.
+///
+/// The formatted output is written to a temporary file next to the
+/// original, which then atomically replaces it, so that the file is
+/// never left partially written. The permissions of the file are
+/// preserved. If `backup` is set, a copy of the original contents is
+/// kept in a file with `.bak` appended to its name.
+pub fn format_file_in_place<P: AsRef<Path>>(path: P, options: &Options, backup: bool) -> io::Result<bool> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let formatted = ::prettify_with_options(&source, options);
+    if formatted == source {
+        return Ok(false);
+    }
+    replace_file(path, &formatted, backup)?;
+    Ok(true)
+}
+
+fn replace_file(path: &Path, contents: &str, backup: bool) -> io::Result<()> {
+    let permissions = fs::metadata(path)?.permissions();
+    if backup {
+        fs::copy(path, with_suffix(path, ".bak"))?;
+    }
+    let temp = with_suffix(path, &format!(".{}.tmp", ::std::process::id()));
+    let written = fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.set_permissions(permissions)?;
+        file.sync_all()
+    });
+    match written.and_then(|()| fs::rename(&temp, path)) {
+        Ok(()) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp);
+            Err(err)
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Check whether a path, given as its components relative to the root,
+/// is ignored. The last matching rule wins.
+fn is_ignored(rules: &[Rule], path: &[String], is_dir: bool) -> bool {
+    rules.iter().rev()
+        .find(|rule| rule.matches(path, is_dir))
+        .is_some_and(|rule| !rule.negated)
+}
+
+/// A single pattern of an ignore file.
+#[derive(Debug)]
+struct Rule {
+    /// The directory of the ignore file, relative to the root.
+    base: Vec<String>,
+    /// The components of the pattern.
+    pattern: Vec<String>,
+    negated: bool,
+    dir_only: bool,
+    /// Whether the pattern matches relative to `base`, rather than the
+    /// last component of a path at any depth.
+    anchored: bool
+}
+
+impl Rule {
+    fn parse(line: &str, base: &[String]) -> Option<Rule> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line))
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line)
+        };
+        let anchored = line.contains('/');
+        let pattern = line.trim_start_matches('/').split('/').map(str::to_string).collect::<Vec<_>>();
+        if pattern.iter().all(String::is_empty) {
+            return None;
+        }
+        Some(Rule { base: base.to_vec(), pattern, negated, dir_only, anchored })
+    }
+
+    fn matches(&self, path: &[String], is_dir: bool) -> bool {
+        if self.dir_only && !is_dir || !path.starts_with(&self.base) {
+            return false;
+        }
+        let relative = &path[self.base.len()..];
+        if self.anchored {
+            match_components(&self.pattern, relative)
+        } else {
+            relative.last().is_some_and(|name| match_component(self.pattern[0].as_bytes(), name.as_bytes()))
+        }
+    }
+}
+
+fn match_components(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        // A trailing `**` matches everything inside, but not the
+        // directory itself, so that its contents can be re-included.
+        Some((first, rest)) if first == "**" && rest.is_empty() => !path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| match_components(rest, &path[skip..]))
+        },
+        Some((first, rest)) => {
+            path.split_first().is_some_and(|(name, path)| {
+                match_component(first.as_bytes(), name.as_bytes()) && match_components(rest, path)
+            })
+        }
+    }
+}
+
+/// Match a single path component against a glob with `*`, `?` and
+/// `[...]` wildcards.
+fn match_component(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| match_component(rest, &name[skip..])),
+        Some((b'?', rest)) => !name.is_empty() && match_component(rest, &name[1..]),
+        Some((b'[', rest)) => match (rest.iter().position(|&b| b == b']'), name.split_first()) {
+            (Some(end), Some((&c, name))) if end > 0 => {
+                match_class(&rest[..end], c) && match_component(&rest[end + 1..], name)
+            },
+            (None, Some((&c, name))) => c == b'[' && match_component(rest, name),
+            _ => false
+        },
+        Some((b'\\', rest)) if !rest.is_empty() => {
+            name.first() == Some(&rest[0]) && match_component(&rest[1..], &name[1..])
+        },
+        Some((&b, rest)) => name.first() == Some(&b) && match_component(rest, &name[1..])
+    }
+}
+
+fn match_class(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some((b'!', rest)) | Some((b'^', rest)) if !rest.is_empty() => (true, rest),
+        _ => (false, class)
+    };
+    let mut i = 0;
+    let mut matched = false;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            matched |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            matched |= class[i] == c;
+            i += 1;
+        }
+    }
+    matched != negated
+}
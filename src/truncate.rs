@@ -0,0 +1,106 @@
+use pulldown_cmark::{Event, Parser, Tag};
+
+use options::Options;
+use references;
+
+/// The amount of text that `prettify_truncated` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncationLimit {
+    /// Keep about this many words, counted as runs of non-whitespace.
+    Words(usize),
+    /// Keep about this many characters of text, not counting markup.
+    Characters(usize)
+}
+
+/// How `prettify_truncated` shortens a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Truncation {
+    /// The amount of text to keep.
+    pub limit: TruncationLimit,
+    /// Markdown that is appended as a separate block if the document was
+    /// truncated, such as `…` or `[Read more](/post)`.
+    pub ellipsis: String
+}
+
+impl Truncation {
+    /// Keep about this many words, followed by `…`.
+    pub fn words(limit: usize) -> Truncation {
+        Truncation { limit: TruncationLimit::Words(limit), ellipsis: "…".to_string() }
+    }
+
+    /// Keep about this many characters of text, followed by `…`.
+    pub fn characters(limit: usize) -> Truncation {
+        Truncation { limit: TruncationLimit::Characters(limit), ellipsis: "…".to_string() }
+    }
+}
+
+/// Parses a CommonMark document and returns the pretty printed start of
+/// it, up to about the given number of words or characters, for
+/// previews and summaries.
+///
+/// The document is cut at the end of the first block (such as a
+/// paragraph, heading, code block or list item) in which the limit is reached, so
+/// that no formatting is left open, and lists and block quotes around
+/// the block are closed. Link reference definitions from the rest of the
+/// document are kept, so that the remaining links still resolve. If the
+/// document was truncated, the ellipsis is printed after it.
+///
+/// # Examples
+///
+/// ```rust
+/// # use prettify_cmark::{prettify_truncated, Options, Truncation};
+/// let source = "Lorem *ipsum*\n\n- dolor [sit][1]\n- amet\n\nconsectetur\n\n[1]: http://example.com";
+/// let output = prettify_truncated(source, &Truncation::words(3), &Options::default());
+/// assert_eq!(output, "Lorem *ipsum*\n\n- dolor [sit](http://example.com)\n\n…");
+/// ```
+pub fn prettify_truncated(source: &str, truncation: &Truncation, options: &Options) -> String {
+    let end = match cut(source, truncation.limit, options) {
+        Some(end) => end,
+        None => return ::prettify_with_options(source, options)
+    };
+    let mut output = ::prettify_with_options(&with_definitions(&source[..end], &source[end..]), options);
+    if !output.is_empty() {
+        output.push_str("\n\n");
+    }
+    output.push_str(&truncation.ellipsis);
+    output
+}
+
+/// Append the link reference definitions found in `rest` to `kept`, so
+/// that the links in the kept part of a document still resolve.
+pub(crate) fn with_definitions(kept: &str, rest: &str) -> String {
+    let mut kept = kept.to_string();
+    for line in rest.lines().filter(|line| references::is_definition(line)) {
+        kept.push_str("\n\n");
+        kept.push_str(line);
+    }
+    kept
+}
+
+/// Find the offset at which a document is cut, if there is any content
+/// after it.
+fn cut(source: &str, limit: TruncationLimit, options: &Options) -> Option<usize> {
+    let mut count = 0;
+    let mut parser = Parser::new_ext(source, options.parser_options());
+    while let Some(event) = parser.next() {
+        match event {
+            Event::Text(ref text) | Event::Html(ref text) => count += match limit {
+                TruncationLimit::Words(_) => text.split_whitespace().count(),
+                TruncationLimit::Characters(_) => text.chars().count()
+            },
+            Event::End(Tag::Paragraph) | Event::End(Tag::Header(_)) | Event::End(Tag::CodeBlock(_)) |
+            Event::End(Tag::Table(_)) | Event::End(Tag::Rule) | Event::End(Tag::Item) => {
+                let limit = match limit {
+                    TruncationLimit::Words(limit) | TruncationLimit::Characters(limit) => limit
+                };
+                if count >= limit {
+                    let end = parser.get_offset();
+                    let rest = source[end..].lines().any(|line| !line.trim().is_empty() && !references::is_definition(line));
+                    return if rest { Some(end) } else { None };
+                }
+            },
+            _ => {}
+        }
+    }
+    None
+}
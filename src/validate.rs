@@ -0,0 +1,190 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+
+use pulldown_cmark::{Event, Tag};
+
+use buffer::owned_tag;
+
+/// Error in the structure of a stream of events.
+///
+/// The index refers to the position of the offending event in the
+/// stream, counting from zero.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NestingError {
+    /// An end tag was found without a matching start tag.
+    UnexpectedEnd {
+        index: usize,
+        tag: Tag<'static>
+    },
+    /// An end tag was found that does not match the innermost open tag.
+    MismatchedEnd {
+        index: usize,
+        expected: Tag<'static>,
+        found: Tag<'static>
+    },
+    /// A tag was opened but never closed.
+    Unclosed {
+        index: usize,
+        tag: Tag<'static>
+    },
+    /// A tag or event appears inside a tag that cannot contain it, such
+    /// as a paragraph inside of emphasis, or a list item outside of a list.
+    IllegalNesting {
+        index: usize,
+        parent: Option<Tag<'static>>,
+        child: String
+    }
+}
+
+impl Display for NestingError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match *self {
+            NestingError::UnexpectedEnd { index, ref tag } => {
+                write!(fmt, "event {}: end of {} without a start", index, name(tag))
+            },
+            NestingError::MismatchedEnd { index, ref expected, ref found } => {
+                write!(fmt, "event {}: expected end of {}, found end of {}", index, name(expected), name(found))
+            },
+            NestingError::Unclosed { index, ref tag } => {
+                write!(fmt, "event {}: {} is never closed", index, name(tag))
+            },
+            NestingError::IllegalNesting { index, parent: Some(ref parent), ref child } => {
+                write!(fmt, "event {}: {} is not allowed inside {}", index, child, name(parent))
+            },
+            NestingError::IllegalNesting { index, parent: None, ref child } => {
+                write!(fmt, "event {}: {} is not allowed at the top level", index, child)
+            }
+        }
+    }
+}
+
+impl Error for NestingError {}
+
+/// Checks that a stream of events is well-formed before printing it.
+///
+/// Every start tag has to be closed by a matching end tag, and tags have
+/// to be nested the way a CommonMark parser would produce them: list items
+/// only occur directly inside lists, table rows and cells only inside
+/// tables, and blocks never occur inside inline content. Events produced
+/// by `pulldown_cmark::Parser` always pass this check, so it is mostly
+/// useful for events that are constructed programmatically.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate pulldown_cmark;
+/// # extern crate prettify_cmark;
+/// use pulldown_cmark::{Event, Parser, Tag};
+/// use prettify_cmark::{validate_events, NestingError};
+///
+/// # fn main() {
+/// assert_eq!(validate_events(Parser::new("Lorem _ipsum_")), Ok(()));
+///
+/// let events = vec![
+///     Event::Start(Tag::Paragraph),
+///     Event::Start(Tag::Emphasis),
+///     Event::Start(Tag::Paragraph)
+/// ];
+/// assert_eq!(validate_events(events), Err(NestingError::IllegalNesting {
+///     index: 2,
+///     parent: Some(Tag::Emphasis),
+///     child: "paragraph".to_string()
+/// }));
+/// # }
+/// ```
+pub fn validate_events<'a, I: IntoIterator<Item=Event<'a>>>(events: I) -> Result<(), NestingError> {
+    let mut open: Vec<(usize, Tag<'static>)> = vec![];
+    for (index, event) in events.into_iter().enumerate() {
+        let parent = open.last().map(|(_, tag)| tag);
+        match event {
+            Event::Start(tag) => {
+                let nested_link = is_link(&tag) && open.iter().any(|(_, tag)| is_link(tag));
+                if nested_link || !may_contain(parent, &tag) {
+                    return Err(illegal(index, parent, name(&tag)));
+                }
+                open.push((index, owned_tag(tag)));
+            },
+            Event::End(tag) => {
+                match open.pop() {
+                    Some((_, ref expected)) if mem::discriminant(expected) == mem::discriminant(&tag) => {},
+                    Some((_, expected)) => {
+                        return Err(NestingError::MismatchedEnd { index, expected, found: owned_tag(tag) });
+                    },
+                    None => return Err(NestingError::UnexpectedEnd { index, tag: owned_tag(tag) })
+                }
+            },
+            Event::Html(_) => {
+                if !allows_blocks(parent) {
+                    return Err(illegal(index, parent, "HTML block"));
+                }
+            },
+            _ => {
+                if !allows_inline(parent) {
+                    return Err(illegal(index, parent, "inline content"));
+                }
+            }
+        }
+    }
+    match open.pop() {
+        Some((index, tag)) => Err(NestingError::Unclosed { index, tag }),
+        None => Ok(())
+    }
+}
+
+fn illegal(index: usize, parent: Option<&Tag>, child: &str) -> NestingError {
+    NestingError::IllegalNesting { index, parent: parent.cloned().map(owned_tag), child: child.to_string() }
+}
+
+/// Check whether a tag may occur directly inside another one, or at the
+/// top level if there is no parent.
+fn may_contain(parent: Option<&Tag>, child: &Tag) -> bool {
+    match *child {
+        Tag::Item => matches!(parent, Some(&Tag::List(_))),
+        Tag::TableHead | Tag::TableRow => matches!(parent, Some(&Tag::Table(_))),
+        Tag::TableCell => matches!(parent, Some(&Tag::TableHead) | Some(&Tag::TableRow)),
+        Tag::Emphasis | Tag::Strong | Tag::Code | Tag::Link(_, _) | Tag::Image(_, _) => allows_inline(parent),
+        _ => allows_blocks(parent)
+    }
+}
+
+fn allows_blocks(parent: Option<&Tag>) -> bool {
+    match parent {
+        None => true,
+        Some(tag) => matches!(*tag, Tag::BlockQuote | Tag::Item | Tag::FootnoteDefinition(_))
+    }
+}
+
+fn allows_inline(parent: Option<&Tag>) -> bool {
+    match parent {
+        None => false,
+        Some(tag) => !matches!(*tag, Tag::Rule | Tag::BlockQuote | Tag::List(_) | Tag::FootnoteDefinition(_) |
+                                     Tag::Table(_) | Tag::TableHead | Tag::TableRow)
+    }
+}
+
+fn is_link(tag: &Tag) -> bool {
+    matches!(*tag, Tag::Link(_, _))
+}
+
+pub(crate) fn name(tag: &Tag) -> &'static str {
+    match *tag {
+        Tag::Paragraph => "paragraph",
+        Tag::Rule => "thematic break",
+        Tag::Header(_) => "heading",
+        Tag::BlockQuote => "block quote",
+        Tag::CodeBlock(_) => "code block",
+        Tag::List(_) => "list",
+        Tag::Item => "list item",
+        Tag::FootnoteDefinition(_) => "footnote definition",
+        Tag::Table(_) => "table",
+        Tag::TableHead => "table head",
+        Tag::TableRow => "table row",
+        Tag::TableCell => "table cell",
+        Tag::Emphasis => "emphasis",
+        Tag::Strong => "strong emphasis",
+        Tag::Code => "code span",
+        Tag::Link(_, _) => "link",
+        Tag::Image(_, _) => "image"
+    }
+}
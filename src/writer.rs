@@ -1,97 +1,566 @@
+//! The low-level writer that the printer is built on.
+//!
+//! It is public with the `unstable-writer` feature enabled, so that other
+//! printers can reuse its handling of prefixes, container blocks and
+//! indentation. Its API may change in any release.
+
 use std::fmt::{Result, Write};
+use std::iter;
+
+use backend::{Backend, Instruction};
+use escape;
+use options::EscapeMethod;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq)]
+/// The columns at which tabs stop when measuring lines, as in CommonMark.
+const TAB_STOP: usize = 4;
+
+/// A container block that the lines written by a `Writer` are nested in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Frame {
+    /// A list item, indented by the width of its marker: two columns for
+    /// bullet lists, or that of the given number of an ordered list (such
+    /// as `10.` and a space). The number is that of the next item.
     ListItem(Option<usize>),
+    /// The definition of a footnote, indented by four columns.
+    FootnoteDefinition,
+    /// A block quote, indented by a `>` marker and a space.
     BlockQuote
 }
 
+/// The state of a writer that is captured by a checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WriterState {
+    pub prefix: String,
+    pub frames: Vec<Frame>,
+    pub line_head: Option<String>,
+    pub lazy: bool,
+    pub trailing: String,
+    pub preserve: bool,
+    pub needs_space: usize,
+    pub column: usize,
+    pub wrap: bool,
+    pub word: Option<Vec<(String, bool)>>
+}
+
 struct Output<W> {
     inner: W,
-    needs_space: usize
+    needs_space: usize,
+    column: usize,
+    /// Whitespace at the end of the text written so far, which is held
+    /// back so that it can be stripped if the line ends.
+    trailing: String,
+    /// Whether to strip trailing whitespace from lines.
+    strip_trailing: bool,
+    /// Whether lines are currently written exactly, as in code blocks.
+    preserve: bool
 }
 
-impl<W: Write> Output<W> {
+impl<W: Backend> Output<W> {
     pub fn write_text(&mut self, text: &str) -> Result {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.advance(text);
+        if !self.strip_trailing || self.preserve {
+            self.flush_space()?;
+            return self.inner.emit(Instruction::Text(text));
+        }
+        let body = text.trim_end_matches([' ', '\t']);
+        if body.is_empty() {
+            self.column += self.needs_space;
+            self.trailing.extend(iter::repeat_n(' ', self.needs_space));
+            self.trailing.push_str(text);
+            self.needs_space = 0;
+            return Ok(());
+        }
+        self.flush_space()?;
+        self.inner.emit(Instruction::Text(body))?;
+        self.trailing.push_str(&text[body.len()..]);
+        Ok(())
+    }
+
+    pub fn write_indent_text(&mut self, text: &str) -> Result {
+        self.flush_space()?;
+        self.advance(text);
+        self.inner.emit(Instruction::Indent(text))
+    }
+
+    fn flush_space(&mut self) -> Result {
+        if !self.trailing.is_empty() {
+            self.inner.emit(Instruction::Text(&self.trailing))?;
+            self.trailing.clear();
+        }
         if self.needs_space > 0 {
-            let space = " ".repeat(self.needs_space);
-            self.inner.write_str(&space)?;
+            self.inner.emit(Instruction::Space(self.needs_space))?;
+            self.column += self.needs_space;
             self.needs_space = 0;
         }
-        self.inner.write_str(text)
+        Ok(())
     }
 
-    pub fn write_hard_break(&mut self) -> Result {
+    /// Keep track of the current column after writing text.
+    fn advance(&mut self, text: &str) {
+        match text.rfind('\n') {
+            Some(newline) => self.column = advance_column(0, &text[newline + 1..]),
+            None => self.column = advance_column(self.column, text)
+        }
+    }
+
+    /// Finish the current line, dropping any trailing whitespace that is
+    /// to be stripped.
+    fn finish_line(&mut self) -> Result {
         self.needs_space = 0;
-        self.inner.write_str("\n")
+        if self.strip_trailing && !self.preserve {
+            self.trailing.clear();
+        }
+        self.flush_space()
+    }
+
+    pub fn write_hard_break(&mut self) -> Result {
+        self.finish_line()?;
+        self.column = 0;
+        self.inner.emit(Instruction::Break)
     }
 
     pub fn write_soft_break(&mut self) -> Result {
         self.needs_space = 0;
+        self.flush_space()?;
         // we'll deal with line wrapping later
-        self.inner.write_str(" ")
+        self.column += 1;
+        self.inner.emit(Instruction::Space(1))
     }
 }
 
+/// Writer of CommonMark-like markup that takes care of the indentation of
+/// nested blocks.
+///
+/// Each line starts with the prefix of the writer, followed by the
+/// indentation of each frame, from the outermost to the innermost. Text
+/// is written as is, except for `write_content`, which escapes text that
+/// would be mistaken for block syntax at the start of a line. Spaces for
+/// indentation and after markers are only written once text follows them
+/// on the same line, so that lines never end in indentation.
+///
+/// Writers start out at the beginning of the first line, with its
+/// indentation written. Frames that are pushed or popped take effect on
+/// the next call to `write_indent`, which starts a new line after
+/// `write_hard_break`.
 pub struct Writer<W> {
     prefix: String,
     frames: Vec<Frame>,
+    list_indent: usize,
+    /// Content written at the start of a line, which is held back until
+    /// it is known whether it would be mistaken for block syntax.
+    line_head: Option<String>,
+    /// Whether block quote markers are left out of the following lines,
+    /// relying on lazy continuation.
+    lazy: bool,
+    /// How characters that would start a block are escaped.
+    block_marker_escape: EscapeMethod,
+    /// The width at which lines are wrapped, if any.
+    width: Option<usize>,
+    /// The width of the tab stops that tabs in content are expanded to,
+    /// if any.
+    tab_width: Option<usize>,
+    /// Whether lines are currently wrapped.
+    wrap: bool,
+    /// Text written since the last space at which the line may be broken,
+    /// which is held back until it is known whether it fits on the line.
+    /// Each piece is marked with whether it is content.
+    word: Option<Vec<(String, bool)>>,
     output: Output<W>
 }
 
-impl<W: Write> Writer<W> {
+impl<W: Backend> Writer<W> {
+    /// Create a writer with a prefix for every line, such as `//!`, which
+    /// may be empty.
     pub fn new(output: W, prefix: String) -> Writer<W> {
         let mut writer = Writer {
-            prefix: prefix,
+            prefix,
             frames: vec![],
-            output: Output { inner: output, needs_space: 0 }
+            list_indent: 0,
+            line_head: None,
+            lazy: false,
+            block_marker_escape: EscapeMethod::Backslash,
+            width: None,
+            tab_width: None,
+            wrap: true,
+            word: None,
+            output: Output {
+                inner: output,
+                needs_space: 0,
+                column: 0,
+                trailing: String::new(),
+                strip_trailing: false,
+                preserve: false
+            }
         };
         writer.write_indent()
             .expect("failed to initialise output");
         writer
     }
 
+    pub(crate) fn resume(output: W, state: WriterState) -> Writer<W> {
+        Writer {
+            prefix: state.prefix,
+            frames: state.frames,
+            list_indent: 0,
+            line_head: state.line_head,
+            lazy: state.lazy,
+            block_marker_escape: EscapeMethod::Backslash,
+            width: None,
+            tab_width: None,
+            wrap: state.wrap,
+            word: state.word,
+            output: Output {
+                inner: output,
+                needs_space: state.needs_space,
+                column: state.column,
+                trailing: state.trailing,
+                strip_trailing: false,
+                preserve: state.preserve
+            }
+        }
+    }
+
+    /// Indent list items by at least this number of columns, instead of
+    /// the width of their marker.
+    pub fn with_list_indent(mut self, list_indent: usize) -> Writer<W> {
+        self.list_indent = list_indent;
+        self
+    }
+
+    /// Strip trailing whitespace from every line, except while whitespace
+    /// is preserved.
+    pub fn with_strip_trailing(mut self, strip_trailing: bool) -> Writer<W> {
+        self.output.strip_trailing = strip_trailing;
+        self
+    }
+
+    /// Escape characters that would start a block using this method in
+    /// `write_content`, instead of a backslash.
+    pub fn with_block_marker_escape(mut self, method: EscapeMethod) -> Writer<W> {
+        self.block_marker_escape = method;
+        self
+    }
+
+    /// Wrap lines at this width in columns, breaking them at the spaces in
+    /// content and at soft breaks.
+    pub fn with_width(mut self, width: Option<usize>) -> Writer<W> {
+        self.width = width;
+        self
+    }
+
+    /// Expand tabs in content to spaces, up to the next multiple of this
+    /// width in columns.
+    pub fn with_tab_width(mut self, tab_width: Option<usize>) -> Writer<W> {
+        self.tab_width = tab_width;
+        self
+    }
+
+    /// Change the minimum indentation of list items from now on.
+    pub fn set_list_indent(&mut self, list_indent: usize) {
+        self.list_indent = list_indent;
+    }
+
+    /// Change whether trailing whitespace is stripped from now on.
+    pub fn set_strip_trailing(&mut self, strip_trailing: bool) {
+        self.output.strip_trailing = strip_trailing;
+    }
+
+    /// Change how characters that would start a block are escaped from
+    /// now on.
+    pub fn set_block_marker_escape(&mut self, method: EscapeMethod) {
+        self.block_marker_escape = method;
+    }
+
+    /// Change the width at which lines are wrapped from now on.
+    pub fn set_width(&mut self, width: Option<usize>) -> Result {
+        self.finish_word()?;
+        self.width = width;
+        Ok(())
+    }
+
+    /// Change the width of the tab stops that tabs in content are
+    /// expanded to from now on.
+    pub fn set_tab_width(&mut self, tab_width: Option<usize>) {
+        self.tab_width = tab_width;
+    }
+
+    pub(crate) fn state(&self) -> WriterState {
+        WriterState {
+            prefix: self.prefix.clone(),
+            frames: self.frames.clone(),
+            line_head: self.line_head.clone(),
+            lazy: self.lazy,
+            trailing: self.output.trailing.clone(),
+            preserve: self.output.preserve,
+            needs_space: self.output.needs_space,
+            column: self.output.column,
+            wrap: self.wrap,
+            word: self.word.clone()
+        }
+    }
+
+    /// Leave out block quote markers from the following lines, as is
+    /// possible for the continuation lines of a paragraph.
+    pub fn set_lazy(&mut self, lazy: bool) {
+        self.lazy = lazy;
+    }
+
+    /// Write lines exactly as given from now on, without stripping
+    /// trailing whitespace, as needed for the contents of code blocks.
+    pub fn set_preserve_whitespace(&mut self, preserve: bool) {
+        self.output.preserve = preserve;
+    }
+
+    /// Suspend or resume the wrapping of lines, as needed for text that
+    /// has to stay on a single line.
+    pub fn set_wrap(&mut self, wrap: bool) -> Result {
+        if !wrap {
+            self.finish_word()?;
+        }
+        self.wrap = wrap;
+        Ok(())
+    }
+
+    /// The column (in characters) at which the next text will be written,
+    /// including pending indentation and spaces, and text that is held
+    /// back at the start of the line or for wrapping.
+    pub fn column(&self) -> usize {
+        let column = self.output.column + self.output.needs_space;
+        let column = self.line_head.as_ref().map_or(column, |head| advance_column(column, head));
+        self.word.as_ref().map_or(column, |word| word_end(column + 1, word))
+    }
+
+    /// Check whether the current line of a list item already has content,
+    /// as is the case for a tight item followed by a nested block.
+    pub fn is_inside_item_text(&self) -> bool {
+        matches!(self.frames.last(), Some(&Frame::ListItem(_))) && self.output.needs_space == 0
+    }
+
+    /// Nest the following lines in a container block, after its marker has
+    /// been written.
     pub fn push_frame(&mut self, frame: Frame) {
         self.frames.push(frame);
+        self.line_head = Some(String::new());
     }
 
+    /// End the innermost container block, returning it.
     pub fn pop_frame(&mut self) -> Option<Frame> {
         self.frames.pop()
     }
 
+    /// End all container blocks.
+    pub fn clear_frames(&mut self) {
+        self.frames.clear();
+    }
+
+    /// Write markup or other text exactly as given.
     pub fn write_text(&mut self, text: &str) -> Result {
+        match self.word {
+            Some(ref mut word) => {
+                word.push((text.to_string(), false));
+                Ok(())
+            },
+            None => self.write_markup(text)
+        }
+    }
+
+    fn write_markup(&mut self, text: &str) -> Result {
+        self.finish_line_head(text.chars().next())?;
         self.output.write_text(text)
     }
 
+    /// Write document content, escaping it where it would otherwise be
+    /// mistaken for block syntax at the start of a line.
+    ///
+    /// While lines are wrapped, the line may be broken at every space.
+    pub fn write_content(&mut self, text: &str) -> Result {
+        if let Some(tab_width) = self.tab_width.filter(|_| text.contains('\t')) {
+            let expanded = expand_tabs(text, self.column(), tab_width);
+            return self.write_expanded_content(&expanded);
+        }
+        self.write_expanded_content(text)
+    }
+
+    fn write_expanded_content(&mut self, text: &str) -> Result {
+        if !self.is_wrapping() {
+            return self.write_unwrapped_content(text);
+        }
+        for (i, piece) in text.split(' ').enumerate() {
+            if i > 0 {
+                self.hold_word()?;
+            }
+            match self.word {
+                Some(ref mut word) => word.push((piece.to_string(), true)),
+                None => self.write_unwrapped_content(piece)?
+            }
+        }
+        Ok(())
+    }
+
+    fn write_unwrapped_content(&mut self, text: &str) -> Result {
+        // Whitespace that directly follows indentation or a block marker
+        // would be taken as part of it when parsing, so the first space
+        // is written as a character reference to keep it in the text.
+        let after_layout = match self.line_head {
+            Some(ref head) => head.is_empty(),
+            None => self.output.needs_space > 0
+        };
+        if after_layout && !self.output.preserve {
+            if let Some(rest) = text.strip_prefix(' ') {
+                self.write_content_head("&#32;")?;
+                return self.write_content_head(rest);
+            }
+            if let Some(rest) = text.strip_prefix('\t') {
+                self.write_content_head("&#9;")?;
+                return self.write_content_head(rest);
+            }
+        }
+        self.write_content_head(text)
+    }
+
+    fn write_content_head(&mut self, text: &str) -> Result {
+        match self.line_head {
+            Some(ref mut head) => {
+                head.push_str(text);
+                if !head.chars().all(is_block_syntax) {
+                    self.finish_line_head(None)?;
+                }
+                Ok(())
+            },
+            None => self.output.write_text(text)
+        }
+    }
+
+    /// Write out content held back at the start of the line, given the
+    /// character that follows it.
+    pub fn finish_line_head(&mut self, next: Option<char>) -> Result {
+        let head = match self.line_head.take() {
+            Some(head) => head,
+            None => return Ok(())
+        };
+        let mut line = head.clone();
+        line.extend(next);
+        match escape_position(&line) {
+            Some(pos) if pos < head.len() => {
+                let mut escaped = head[..pos].to_string();
+                let c = head[pos..].chars().next().unwrap_or_default();
+                escape::push_escaped(&mut escaped, c, self.block_marker_escape);
+                escaped.push_str(&head[pos + c.len_utf8()..]);
+                self.output.write_text(&escaped)
+            },
+            _ => self.output.write_text(&head)
+        }
+    }
+
+    fn is_wrapping(&self) -> bool {
+        self.wrap && self.width.is_some() && !self.output.preserve
+    }
+
+    /// Write out the word held back before the last space, and hold back
+    /// the text that follows the space.
+    fn hold_word(&mut self) -> Result {
+        self.finish_word()?;
+        self.word = Some(Vec::new());
+        Ok(())
+    }
+
+    /// Write out the text held back for wrapping, preceded by a space, or
+    /// by a line break if it does not fit on the current line.
+    pub fn finish_word(&mut self) -> Result {
+        let word = match self.word.take() {
+            Some(word) => word,
+            None => return Ok(())
+        };
+        let overflows = self.width.is_some_and(|width| word_end(self.column() + 1, &word) > width);
+        let line_is_empty = self.line_head.as_ref().is_some_and(String::is_empty);
+        if overflows && !line_is_empty {
+            self.write_hard_break()?;
+            self.write_indent()?;
+        } else {
+            self.write_unwrapped_content(" ")?;
+        }
+        for (piece, content) in word {
+            if content {
+                self.write_unwrapped_content(&piece)?;
+            } else {
+                self.write_markup(&piece)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// End the current line. The next line has to be started with
+    /// `write_indent`.
     pub fn write_hard_break(&mut self) -> Result {
+        self.finish_word()?;
+        self.finish_line_head(None)?;
         self.output.write_hard_break()
     }
 
+    /// Write a space between words, where the line may be broken.
     pub fn write_soft_break(&mut self) -> Result {
+        if self.is_wrapping() {
+            return self.hold_word();
+        }
+        self.finish_line_head(Some(' '))?;
         self.output.write_soft_break()
     }
 
+    /// Write a space after a marker, such as that of a heading, which is
+    /// only written once text follows it.
     pub fn write_non_breaking_space(&mut self) -> Result {
+        self.finish_word()?;
+        self.finish_line_head(Some(' '))?;
         self.output.needs_space += 1;
         Ok(())
     }
 
+    /// Write the spaces after a list marker of the given width, filling up
+    /// the list indent.
+    pub fn write_marker_space(&mut self, marker_width: usize) -> Result {
+        self.finish_word()?;
+        self.finish_line_head(Some(' '))?;
+        self.output.needs_space += self.list_indent.saturating_sub(marker_width).max(1);
+        Ok(())
+    }
+
+    /// Start a new line with the prefix and the indentation of all frames.
     pub fn write_indent(&mut self) -> Result {
+        self.finish_word()?;
+        self.finish_line_head(None)?;
+        self.write_frame_indent()?;
+        self.line_head = Some(String::new());
+        Ok(())
+    }
+
+    fn write_frame_indent(&mut self) -> Result {
         if !self.prefix.is_empty() {
-            self.output.inner.write_str(&self.prefix)?;
+            self.output.write_indent_text(&self.prefix)?;
             self.output.needs_space += 1;
         }
         for frame in &self.frames[..] {
-            match frame {
-                &Frame::ListItem(None) => {
-                    self.output.needs_space += 2;
+            match *frame {
+                Frame::ListItem(None) => {
+                    self.output.needs_space += self.list_indent.max(2);
                 },
-                &Frame::ListItem(Some(index)) => {
+                Frame::ListItem(Some(index)) => {
                     let indent = (index / 10) + 3;
-                    self.output.needs_space += indent;
+                    self.output.needs_space += self.list_indent.max(indent);
+                },
+                Frame::FootnoteDefinition => {
+                    self.output.needs_space += 4;
                 },
-                &Frame::BlockQuote => {
-                    self.output.write_text(">")?;
+                Frame::BlockQuote if self.lazy => {},
+                Frame::BlockQuote => {
+                    self.output.write_indent_text(">")?;
                     self.output.needs_space += 1;
                 }
             }
@@ -99,13 +568,103 @@ impl<W: Write> Writer<W> {
         Ok(())
     }
 
-    pub fn into_inner(self) -> W {
+    /// Finish the last line and return the underlying output.
+    pub fn into_inner(mut self) -> W {
+        self.finish_word()
+            .and_then(|()| self.finish_line_head(None))
+            .and_then(|()| self.output.finish_line())
+            .expect("failed to finish output");
         self.output.inner
     }
+
+    /// Access the underlying output.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.output.inner
+    }
 }
 
-impl<W: Write> Write for Writer<W> {
+impl<W: Backend> Write for Writer<W> {
     fn write_str(&mut self, s: &str) -> Result {
-        self.output.write_text(s)
+        self.write_text(s)
+    }
+}
+
+/// The column after a word that starts at the given column.
+fn word_end(column: usize, word: &[(String, bool)]) -> usize {
+    word.iter().fold(column, |column, (piece, _)| advance_column(column, piece))
+}
+
+/// The column after text on a single line that starts at the given
+/// column, where tabs advance to the next tab stop.
+fn advance_column(column: usize, text: &str) -> usize {
+    text.chars().fold(column, |column, c| if c == '\t' { (column / TAB_STOP + 1) * TAB_STOP } else { column + 1 })
+}
+
+/// Replace tabs by spaces up to the next multiple of the tab width, for
+/// text that starts at the given column.
+fn expand_tabs(text: &str, mut column: usize, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut expanded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' => {
+                let spaces = tab_width - column % tab_width;
+                expanded.extend(iter::repeat_n(' ', spaces));
+                column += spaces;
+            },
+            '\n' => {
+                expanded.push(c);
+                column = 0;
+            },
+            c => {
+                expanded.push(c);
+                column += 1;
+            }
+        }
     }
+    expanded
+}
+
+/// Check whether a character may be part of block syntax at the start
+/// of a line, in which case more content is needed to make a decision.
+fn is_block_syntax(c: char) -> bool {
+    "#>-+*_=`~<.)0123456789 \t".contains(c)
+}
+
+/// Find the position at which a line needs a backslash escape so that it
+/// is not parsed as the start of a block, such as a heading, a list item,
+/// a block quote, a thematic break, a setext underline or a code fence.
+fn escape_position(line: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let line = &line[indent..];
+    let rest = |pos: usize| line[pos..].is_empty() || line[pos..].starts_with([' ', '\t']);
+    let first = line.chars().next()?;
+    let position = match first {
+        '>' => Some(0),
+        '#' => {
+            let hashes = line.len() - line.trim_start_matches('#').len();
+            if hashes <= 6 && rest(hashes) { Some(0) } else { None }
+        },
+        '-' | '+' | '*' if rest(1) => Some(0),
+        '`' | '~' if line.starts_with(&first.to_string().repeat(3)) => Some(0),
+        '<' => {
+            match line[1..].chars().next() {
+                Some(c) if c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?' => Some(0),
+                _ => None
+            }
+        },
+        '0'..='9' => {
+            let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let delimiter = line[digits..].starts_with(['.', ')']);
+            if digits <= 9 && delimiter && rest(digits + 1) { Some(digits) } else { None }
+        },
+        _ => None
+    };
+    position.or_else(|| {
+        let marks = line.trim_end().chars().filter(|&c| c != ' ' && c != '\t').collect::<String>();
+        let repeated = marks.chars().all(|c| c == first);
+        let thematic = "-*_".contains(first) && marks.len() >= 3;
+        let setext = first == '=' || first == '-';
+        if repeated && (thematic || (setext && !line.trim_end().contains(' '))) { Some(0) } else { None }
+    }).map(|position| indent + position)
 }